@@ -1,7 +1,11 @@
-use crate::edit_plan::{ActionType, EditPlan};
-use crate::preferences::PreferenceManager;
-use crate::timeline::{TimelineEngine, TimelineState};
-use tauri::{AppHandle, Emitter, State};
+use crate::edit_plan::{ActionParameters, ActionType, EditAction, EditPlan};
+use crate::ffmpeg::FFmpegEngine;
+use crate::llm::{log_artifact, ArtifactType};
+use crate::preferences::{EditingPreferences, GeneralPreferences, PreferenceManager};
+use crate::timeline::{ChapterMarker, Clip, TimelineEngine, TimelineState, MIN_LOOP_REGION_SECS};
+use crate::validator::{check_speed_multiplier, MAX_VOLUME_DB, MIN_VOLUME_DB};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
 use thiserror::Error;
 use uuid::Uuid;
 
@@ -15,8 +19,39 @@ pub enum RouterError {
     LockError,
     #[error("Invalid action parameters: {0}")]
     InvalidParameters(String),
-    #[error("Post-mutation invariant violated: {0}")]
-    InvariantViolation(String),
+    /// `diff` summarizes what the rejected mutation changed, when known.
+    #[error("Post-mutation invariant violated: {reason}{}", diff_suffix(.diff))]
+    InvariantViolation { reason: String, diff: String },
+    #[error("Clip {0} is on locked track {1}")]
+    TrackLocked(String, String),
+}
+
+impl RouterError {
+    pub fn violation(reason: String) -> Self {
+        RouterError::InvariantViolation {
+            reason,
+            diff: String::new(),
+        }
+    }
+
+    /// Attach the attempted changes to an invariant violation.
+    pub fn with_diff(self, changes: String) -> Self {
+        match self {
+            RouterError::InvariantViolation { reason, .. } => RouterError::InvariantViolation {
+                reason,
+                diff: changes,
+            },
+            other => other,
+        }
+    }
+}
+
+fn diff_suffix(diff: &str) -> String {
+    if diff.is_empty() {
+        String::new()
+    } else {
+        format!(" (attempted: {})", diff)
+    }
 }
 
 /// AUTHORITATIVE LIST OF TIMELINE INVARIANTS
@@ -27,15 +62,43 @@ pub enum RouterError {
 /// 3. No overlapping clips on the same track
 /// 4. Timeline duration = max(start + duration) across all clips (or 0 if empty)
 /// 5. playhead_time ∈ [0, duration]
+/// 6. loop_region, when set, lies within [0, duration]
+/// 7. Clip count <= `max_timeline_clips`, when configured
+/// 8. Every clip's track_id names a track in `tracks` (when tracks are listed)
+/// 9. Markers lie within [0, duration] and have unique IDs
+/// 10. Audio-only clips are only placed on audio tracks
+/// 11. With `require_no_gaps`, clips on a track are back to back
+///     (`validate_no_gaps`, checked by the preference-aware edit paths)
 ///
 /// If ANY invariant fails, the mutation MUST be rolled back.
 pub fn validate_state_invariants(state: &TimelineState) -> Result<(), RouterError> {
-    // Invariant 1: All clips must have positive duration
+    validate_state_invariants_with(state, &EditingPreferences::default())
+}
+
+/// `validate_state_invariants` against the user's editing preferences.
+pub fn validate_state_invariants_with(
+    state: &TimelineState,
+    editing: &EditingPreferences,
+) -> Result<(), RouterError> {
+    // Invariant 1: All clips must have positive duration, and virtual trim
+    // handles must lie inside the source
     for clip in &state.clips {
-        if clip.duration <= 0.0 {
-            return Err(RouterError::InvariantViolation(format!(
+        if clip.effective_duration() <= 0.0 {
+            return Err(RouterError::violation(format!(
                 "Clip '{}' has invalid duration: {:.2}s (must be > 0)",
-                clip.id, clip.duration
+                clip.id,
+                clip.effective_duration()
+            )));
+        }
+        if clip.source_in() < clip.source_offset - 0.001
+            || clip.source_out() > clip.duration + 0.001
+        {
+            return Err(RouterError::violation(format!(
+                "Clip '{}' edit range [{:.2}, {:.2}] is outside its {:.2}s source",
+                clip.id,
+                clip.source_in(),
+                clip.source_out(),
+                clip.duration
             )));
         }
     }
@@ -43,7 +106,7 @@ pub fn validate_state_invariants(state: &TimelineState) -> Result<(), RouterErro
     // Invariant 2: All clips must have non-negative start time
     for clip in &state.clips {
         if clip.start < 0.0 {
-            return Err(RouterError::InvariantViolation(format!(
+            return Err(RouterError::violation(format!(
                 "Clip '{}' has negative start time: {:.2}s",
                 clip.id, clip.start
             )));
@@ -57,7 +120,7 @@ pub fn validate_state_invariants(state: &TimelineState) -> Result<(), RouterErro
         clips_by_track
             .entry(clip.track_id.clone())
             .or_default()
-            .push((clip.id.clone(), clip.start, clip.start + clip.duration));
+            .push((clip.id.clone(), clip.start, clip.end()));
     }
 
     for (track_id, mut clips) in clips_by_track {
@@ -65,9 +128,13 @@ pub fn validate_state_invariants(state: &TimelineState) -> Result<(), RouterErro
         for i in 1..clips.len() {
             let prev_end = clips[i - 1].2;
             let curr_start = clips[i].1;
+            // Crossfading pairs overlap on purpose, by up to the transition length
+            let allowed_overlap = state
+                .transition_between(&clips[i - 1].0, &clips[i].0)
+                .map_or(0.0, |t| t.duration);
             // Allow tiny gaps due to floating point precision
-            if prev_end > curr_start + 0.001 {
-                return Err(RouterError::InvariantViolation(format!(
+            if prev_end > curr_start + allowed_overlap + 0.001 {
+                return Err(RouterError::violation(format!(
                     "Clips '{}' and '{}' overlap on track '{}' (prev ends at {:.2}s, next starts at {:.2}s)",
                     clips[i - 1].0, clips[i].0, track_id, prev_end, curr_start
                 )));
@@ -76,13 +143,9 @@ pub fn validate_state_invariants(state: &TimelineState) -> Result<(), RouterErro
     }
 
     // Invariant 4: Duration must equal max(start + duration) or 0 if empty
-    let calculated_duration = state
-        .clips
-        .iter()
-        .map(|c| c.start + c.duration)
-        .fold(0.0, f64::max);
+    let calculated_duration = state.clips.iter().map(|c| c.end()).fold(0.0, f64::max);
     if (state.duration - calculated_duration).abs() > 0.001 {
-        return Err(RouterError::InvariantViolation(format!(
+        return Err(RouterError::violation(format!(
             "Duration mismatch: stored={:.2}s, calculated={:.2}s",
             state.duration, calculated_duration
         )));
@@ -90,21 +153,137 @@ pub fn validate_state_invariants(state: &TimelineState) -> Result<(), RouterErro
 
     // Invariant 5: Playhead must be within valid range [0, duration]
     if state.playhead_time < 0.0 || state.playhead_time > state.duration + 0.001 {
-        return Err(RouterError::InvariantViolation(format!(
+        return Err(RouterError::violation(format!(
             "Playhead {:.2}s is outside valid range [0, {:.2}]",
             state.playhead_time, state.duration
         )));
     }
 
+    // Invariant 6: Loop region must be an ordered range within [0, duration]
+    if let Some((start, end)) = state.loop_region {
+        if start < 0.0 || end > state.duration + 0.001 || start >= end {
+            return Err(RouterError::violation(format!(
+                "Loop region [{:.2}, {:.2}] is outside valid range [0, {:.2}]",
+                start, end, state.duration
+            )));
+        }
+    }
+
+    // Invariant 7: Clip count within the configured limit
+    if let Some(max) = editing.max_timeline_clips {
+        if state.clips.len() > max {
+            return Err(RouterError::violation(format!(
+                "Timeline has {} clips, more than the limit of {}",
+                state.clips.len(),
+                max
+            )));
+        }
+    }
+
+    // Invariant 8: Clips reference existing tracks; legacy timelines without a
+    // track list are exempt
+    if !state.tracks.is_empty() {
+        if let Some(clip) = state
+            .clips
+            .iter()
+            .find(|c| state.track(&c.track_id).is_none())
+        {
+            return Err(RouterError::violation(format!(
+                "Clip '{}' is on unknown track '{}'",
+                clip.id, clip.track_id
+            )));
+        }
+    }
+
+    // Invariant 9: Markers within the timeline, each ID used once
+    let mut marker_ids = std::collections::HashSet::new();
+    for marker in &state.markers {
+        if marker.time_s < 0.0 || marker.time_s > state.duration + 0.001 {
+            return Err(RouterError::violation(format!(
+                "Marker '{}' at {:.2}s is outside valid range [0, {:.2}]",
+                marker.id, marker.time_s, state.duration
+            )));
+        }
+        if !marker_ids.insert(marker.id.as_str()) {
+            return Err(RouterError::violation(format!(
+                "Duplicate marker ID '{}'",
+                marker.id
+            )));
+        }
+    }
+
+    // Invariant 10: Audio-only clips stay off video and overlay tracks
+    if let Some(clip) = state
+        .clips
+        .iter()
+        .find(|c| c.is_audio_only() && !state.is_audio_track(&c.track_id))
+    {
+        return Err(RouterError::violation(format!(
+            "Audio-only clip '{}' is on non-audio track '{}'",
+            clip.id, clip.track_id
+        )));
+    }
+
     Ok(())
 }
 
+/// Empty stretch between two consecutive clips on a track.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GapInfo {
+    pub track_id: String,
+    pub gap_start: f64,
+    pub gap_end: f64,
+}
+
+/// Every gap between consecutive clips on each track, ordered by track then time.
+/// Space before a track's first clip is not a gap.
+pub fn find_gaps(state: &TimelineState) -> Vec<GapInfo> {
+    let mut by_track: std::collections::BTreeMap<&str, Vec<(f64, f64)>> =
+        std::collections::BTreeMap::new();
+    for clip in &state.clips {
+        by_track
+            .entry(clip.track_id.as_str())
+            .or_default()
+            .push((clip.start, clip.end()));
+    }
+
+    let mut gaps = Vec::new();
+    for (track_id, mut spans) in by_track {
+        spans.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        for pair in spans.windows(2) {
+            let (prev_end, next_start) = (pair[0].1, pair[1].0);
+            if next_start > prev_end + 0.001 {
+                gaps.push(GapInfo {
+                    track_id: track_id.to_string(),
+                    gap_start: prev_end,
+                    gap_end: next_start,
+                });
+            }
+        }
+    }
+    gaps
+}
+
+/// Invariant 11, for users who set `require_no_gaps`.
+pub fn validate_no_gaps(state: &TimelineState) -> Result<(), RouterError> {
+    match find_gaps(state).first() {
+        Some(gap) => Err(RouterError::violation(format!(
+            "Gap on track '{}' from {:.2}s to {:.2}s",
+            gap.track_id, gap.gap_start, gap.gap_end
+        ))),
+        None => Ok(()),
+    }
+}
+
 pub fn run_edit_plan(
     engine: &State<'_, TimelineEngine>,
     app_handle: &AppHandle,
     prefs: &State<'_, PreferenceManager>,
-    plan: EditPlan,
+    mut plan: EditPlan,
 ) -> Result<TimelineState, String> {
+    if !prefs.get_preferences().general.auto_ripple_edits {
+        downgrade_ripple_deletes(&mut plan);
+    }
     println!(
         "🚀 [Backend] Action Router: Executing Edit Plan with {} actions",
         plan.actions.len()
@@ -125,16 +304,532 @@ pub fn run_edit_plan(
 
     // STEP 3 FIX: Snapshot state BEFORE mutations for rollback capability
     let snapshot = state.clone();
+    let user_prefs = prefs.get_preferences();
+
+    let applied = apply_plan_to_state_with(&mut state, &plan, &user_prefs.editing)
+        .and_then(|skipped| enforce_gap_policy(&state, &user_prefs.general).map(|_| skipped));
+    let skipped = match applied {
+        Ok(skipped) => skipped,
+        Err(e) => {
+            println!("❌ [Router] {}. ROLLING BACK.", e);
+            log_artifact(
+                app_handle,
+                ArtifactType::Error,
+                &format!("Edit plan rolled back: {}", e),
+            );
+            // Restore snapshot - atomicity enforced
+            *state = snapshot;
+            return Err(e);
+        }
+    };
+
+    if !skipped.is_empty() {
+        let skipped_actions: Vec<&EditAction> = skipped.iter().map(|&i| &plan.actions[i]).collect();
+        log_artifact(
+            app_handle,
+            ArtifactType::ApplyEditPlan {
+                plan: serde_json::to_string_pretty(&skipped_actions).unwrap_or_default(),
+                result: "SKIPPED_NOOP".to_string(),
+            },
+            &format!(
+                "{} of {} actions were no-ops",
+                skipped.len(),
+                plan.actions.len()
+            ),
+        );
+    }
+
+    // Nothing changed: keep the version and don't wake the UI
+    if skipped.len() == plan.actions.len() {
+        println!("⏭️ [Router] Every action was a no-op; state unchanged");
+        *state = snapshot;
+        return Ok(state.clone());
+    }
+
+    // 6. Increment version counter and make the edit undoable
+    state.version += 1;
+    engine.push_undo_snapshot(&mut state, snapshot);
+
+    // Cached export segments containing the touched clips are now stale
+    if let Some(ffmpeg) = app_handle.try_state::<FFmpegEngine>() {
+        for action in &plan.actions {
+            ffmpeg.invalidate_render_cache_for_clip(&action.target_clip_id);
+        }
+    }
+
+    println!(
+        "📊 [Backend] State AFTER execution: {} clips, {:.2}s, version {}",
+        state.clips.len(),
+        state.duration,
+        state.version
+    );
+
+    // 7. Emit Update
+    let _ = app_handle.emit("STATE_UPDATE", &*state);
+
+    // 8. Log Interaction
+    let details = serde_json::json!({
+        "plan": plan,
+        "resulting_duration": state.duration
+    });
+    prefs.log_interaction("AI_EDIT_APPLIED", details);
+
+    Ok(state.clone())
+}
+
+// Trim with the deltas rounded to whole frames at `fps`, counting the result in
+// frames so the seconds come out exact.
+fn trim_by_frames(
+    clip: &mut Clip,
+    params: &ActionParameters,
+    editing: &EditingPreferences,
+    fps: f64,
+) {
+    clip.sync_frames();
+    let speed = clip.speed_multiplier;
+    let frames = |delta: Option<f64>| {
+        delta
+            .filter(|d| d.abs() >= editing.min_trim_delta_secs)
+            .map_or(0, |d| (d * fps).round() as i64)
+    };
+    // Frames of source to either side of the edit range, in timeline frames
+    let head = ((clip.source_in() - clip.source_offset) / speed * fps).round() as i64;
+    let tail = ((clip.duration - clip.source_out()) / speed * fps).round() as i64;
+    let length = clip.duration_frames as i64;
+
+    // Both ends relative to the current in point
+    let mut first = frames(params.trim_start_delta).clamp(-head, length + tail);
+    let mut last = (length + frames(params.trim_end_delta)).clamp(-head, length + tail);
+
+    // Enforce minimum duration
+    let min_frames = ((editing.min_clip_duration_secs * fps).ceil() as i64).max(1);
+    if last - first < min_frames {
+        last = (first + min_frames).min(length + tail);
+        first = (last - min_frames).max(-head);
+    }
+
+    // Keep the remaining frames where they were on the timeline,
+    // enforcing non-negative start
+    let in_point =
+        (clip.source_in() + first as f64 / fps * speed).clamp(clip.source_offset, clip.duration);
+    clip.set_start_frames((clip.start_frames as i64 + first).max(0) as u64);
+    clip.edit_in = Some(in_point);
+    clip.set_duration_frames((last - first).max(0) as u64);
+}
+
+// A SetLoopRegion action loops over its target clip unless it gives explicit bounds.
+fn loop_region_for(action: &EditAction, clip_start: f64, clip_end: f64) -> (f64, f64) {
+    let params = action.parameters.as_ref();
+    (
+        params.and_then(|p| p.loop_start).unwrap_or(clip_start),
+        params.and_then(|p| p.loop_end).unwrap_or(clip_end),
+    )
+}
+
+/// Turn RippleDelete actions into plain deletes, for users who disabled
+/// `auto_ripple_edits`.
+pub fn downgrade_ripple_deletes(plan: &mut EditPlan) {
+    for action in &mut plan.actions {
+        if action.action_type == ActionType::RippleDelete {
+            action.action_type = ActionType::Delete;
+        }
+    }
+}
+
+/// Shift both trim handles of `clip_id` by `slip_delta` timeline seconds, so a
+/// different stretch of the source plays in the same place on the timeline.
+/// `edit_in`/`edit_out` are the slipped range; they never go before
+/// `source_offset` or past `duration`.
+pub fn slip_clip(state: &mut TimelineState, clip_id: &str, slip_delta: f64) -> Result<(), String> {
+    let clip = state
+        .clips
+        .iter_mut()
+        .find(|c| c.id == clip_id)
+        .ok_or_else(|| RouterError::ClipNotFound(clip_id.to_string()).to_string())?;
+    let shift = slip_delta * clip.speed_multiplier;
+    let (edit_in, edit_out) = (clip.source_in() + shift, clip.source_out() + shift);
+    if edit_in < clip.source_offset - 1e-6 || edit_out > clip.duration + 1e-6 {
+        return Err(RouterError::InvalidParameters(format!(
+            "slip_delta {:.2}s runs past the source media of {}",
+            slip_delta, clip_id
+        ))
+        .to_string());
+    }
+    clip.edit_in = Some(edit_in.max(clip.source_offset));
+    clip.edit_out = Some(edit_out.min(clip.duration));
+    Ok(())
+}
+
+/// Move the cut between `clip_id` and the next clip on its track by `roll_delta`
+/// timeline seconds: positive lengthens the target and shortens the next clip,
+/// so the track's total length doesn't change.
+pub fn rolling_trim(
+    state: &mut TimelineState,
+    clip_id: &str,
+    roll_delta: f64,
+    editing: &EditingPreferences,
+) -> Result<(), String> {
+    let a = clip_index(state, clip_id)?;
+    let b = track_neighbor(state, a, true).ok_or_else(|| {
+        RouterError::InvalidParameters(format!(
+            "ROLLING_TRIM needs a clip after {} on its track",
+            clip_id
+        ))
+        .to_string()
+    })?;
+    roll_between(state, a, b, roll_delta, "roll_delta", editing)
+}
+
+/// Move `clip_id` by `slide_delta` timeline seconds, lengthening the clip before
+/// it and shortening the one after it (or the reverse), so nothing else moves.
+pub fn slide_clip(
+    state: &mut TimelineState,
+    clip_id: &str,
+    slide_delta: f64,
+    editing: &EditingPreferences,
+) -> Result<(), String> {
+    let index = clip_index(state, clip_id)?;
+    let (Some(prev), Some(next)) = (
+        track_neighbor(state, index, false),
+        track_neighbor(state, index, true),
+    ) else {
+        return Err(RouterError::InvalidParameters(format!(
+            "SLIDE_CLIP needs clips before and after {} on its track",
+            clip_id
+        ))
+        .to_string());
+    };
+    roll_between(state, prev, next, slide_delta, "slide_delta", editing)?;
+    state.clips[index].start += slide_delta;
+    Ok(())
+}
+
+fn clip_index(state: &TimelineState, clip_id: &str) -> Result<usize, String> {
+    state
+        .clips
+        .iter()
+        .position(|c| c.id == clip_id)
+        .ok_or_else(|| RouterError::ClipNotFound(clip_id.to_string()).to_string())
+}
+
+// Closest clip on the same track starting after (or before) clips[index]
+fn track_neighbor(state: &TimelineState, index: usize, after: bool) -> Option<usize> {
+    let clip = &state.clips[index];
+    let candidates = state.clips.iter().enumerate().filter(|(_, c)| {
+        c.track_id == clip.track_id
+            && if after {
+                c.start > clip.start
+            } else {
+                c.start < clip.start
+            }
+    });
+    let by_start = |(_, x): &(usize, &Clip), (_, y): &(usize, &Clip)| x.start.total_cmp(&y.start);
+    if after {
+        candidates.min_by(by_start)
+    } else {
+        candidates.max_by(by_start)
+    }
+    .map(|(i, _)| i)
+}
+
+// Extend the tail of clips[first] by `delta` and cut as much off the head of
+// clips[second], which moves with it. `name` is the parameter, for errors.
+fn roll_between(
+    state: &mut TimelineState,
+    first: usize,
+    second: usize,
+    delta: f64,
+    name: &str,
+    editing: &EditingPreferences,
+) -> Result<(), String> {
+    // Handles are source seconds, so each side scales the delta by its own speed
+    let (a, b) = (&state.clips[first], &state.clips[second]);
+    let a_out = a.source_out() + delta * a.speed_multiplier;
+    let b_in = b.source_in() + delta * b.speed_multiplier;
+    if a_out > a.duration + 1e-6 || b_in < b.source_offset - 1e-6 {
+        return Err(RouterError::InvalidParameters(format!(
+            "{} {:.2}s runs past the source media of {} or {}",
+            name, delta, a.id, b.id
+        ))
+        .to_string());
+    }
+    let a_len = (a_out - a.source_in()) / a.speed_multiplier;
+    let b_len = (b.source_out() - b_in) / b.speed_multiplier;
+    if a_len < editing.min_clip_duration_secs || b_len < editing.min_clip_duration_secs {
+        return Err(RouterError::InvalidParameters(format!(
+            "{} {:.2}s would leave a clip shorter than {}s",
+            name, delta, editing.min_clip_duration_secs
+        ))
+        .to_string());
+    }
+
+    let a = &mut state.clips[first];
+    a.edit_in = Some(a.source_in());
+    a.edit_out = Some(a_out.min(a.duration));
+    let b = &mut state.clips[second];
+    b.edit_out = Some(b.source_out());
+    b.edit_in = Some(b_in.max(b.source_offset));
+    b.start += delta;
+    Ok(())
+}
+
+/// Move `clip_id` to `target_index` among the clips on its track (ordered by start).
+///
+/// Without `preserve_gaps` the track is repacked back to back from its first
+/// start. With it, only the clips between the old and new positions move, each
+/// by the moved clip's length, so every other gap stays as it was.
+pub fn reorder_clip(
+    state: &mut TimelineState,
+    clip_id: &str,
+    target_index: usize,
+    preserve_gaps: bool,
+) -> Result<(), String> {
+    let track_id = state
+        .clips
+        .iter()
+        .find(|c| c.id == clip_id)
+        .map(|c| c.track_id.clone())
+        .ok_or_else(|| RouterError::ClipNotFound(clip_id.to_string()).to_string())?;
+
+    // Indices into state.clips for this track, in timeline order
+    let mut order: Vec<usize> = (0..state.clips.len())
+        .filter(|&i| state.clips[i].track_id == track_id)
+        .collect();
+    order.sort_by(|&a, &b| {
+        state.clips[a]
+            .start
+            .partial_cmp(&state.clips[b].start)
+            .unwrap()
+    });
+
+    let from = order
+        .iter()
+        .position(|&i| state.clips[i].id == clip_id)
+        .unwrap();
+    if target_index >= order.len() {
+        return Err(format!(
+            "Target index {} is out of range (track has {} clips)",
+            target_index,
+            order.len()
+        ));
+    }
+    if from == target_index {
+        return Ok(());
+    }
+
+    if !preserve_gaps {
+        let mut cursor = state.clips[order[0]].start;
+        let moved = order.remove(from);
+        order.insert(target_index, moved);
+        for &i in &order {
+            state.clips[i].start = cursor;
+            cursor = state.clips[i].end();
+        }
+        return Ok(());
+    }
+
+    let moved = order[from];
+    let length = state.clips[moved].effective_duration();
+    if from < target_index {
+        // Later clips slide back into the hole; the moved clip ends where the last of them did
+        let new_end = state.clips[order[target_index]].end();
+        for &i in &order[from + 1..=target_index] {
+            state.clips[i].start -= length;
+        }
+        state.clips[moved].start = new_end - length;
+    } else {
+        // Earlier clips slide forward; the moved clip takes the first one's start
+        let new_start = state.clips[order[target_index]].start;
+        for &i in &order[target_index..from] {
+            state.clips[i].start += length;
+        }
+        state.clips[moved].start = new_start;
+    }
+    Ok(())
+}
+
+/// Clear `[start_time, end_time]` on `track_id`, or on every unlocked track when
+/// `None`. Clips inside the range are removed and clips crossing its edges are
+/// trimmed back to it through their edit points; a clip spanning the whole
+/// range is split around it. With `ripple`, everything after the range on the
+/// affected tracks moves left to close it. Returns how many clips were removed.
+pub fn delete_clips_in_range(
+    state: &mut TimelineState,
+    start_time: f64,
+    end_time: f64,
+    track_id: Option<&str>,
+    ripple: bool,
+) -> Result<usize, String> {
+    if start_time < 0.0 || end_time <= start_time {
+        return Err(format!(
+            "Invalid range [{:.2}, {:.2}]",
+            start_time, end_time
+        ));
+    }
+    if let Some(track_id) = track_id {
+        if state.track(track_id).is_some_and(|t| t.locked) {
+            return Err(format!("Track {} is locked", track_id));
+        }
+    }
+    let locked: Vec<String> = state
+        .tracks
+        .iter()
+        .filter(|t| t.locked)
+        .map(|t| t.id.clone())
+        .collect();
+    let affects = |clip: &Clip| match track_id {
+        Some(id) => clip.track_id == id,
+        None => !locked.contains(&clip.track_id),
+    };
+
+    let mut removed = 0;
+    let mut kept = Vec::with_capacity(state.clips.len());
+    for mut clip in std::mem::take(&mut state.clips) {
+        let (clip_start, clip_end) = (clip.start, clip.end());
+        if !affects(&clip) || clip_end <= start_time + 0.001 || clip_start >= end_time - 0.001 {
+            kept.push(clip);
+            continue;
+        }
+        // Source positions of the range edges within this clip
+        let source_at = |t: f64| clip.source_in() + (t - clip_start) * clip.speed_multiplier;
+        let (cut_in, cut_out, source_out) = (
+            source_at(start_time),
+            source_at(end_time),
+            clip.source_out(),
+        );
+
+        let keeps_head = clip_start < start_time - 0.001;
+        let keeps_tail = clip_end > end_time + 0.001;
+        if keeps_tail {
+            let mut tail = clip.clone();
+            if keeps_head {
+                tail.id = Uuid::new_v4().to_string();
+            }
+            tail.start = end_time;
+            tail.edit_in = Some(cut_out);
+            tail.edit_out = Some(source_out);
+            kept.push(tail);
+        }
+        if keeps_head {
+            clip.edit_in = Some(clip.source_in());
+            clip.edit_out = Some(cut_in);
+            kept.push(clip);
+        } else if !keeps_tail {
+            removed += 1;
+        }
+    }
+    state.clips = kept;
+
+    if ripple {
+        let span = end_time - start_time;
+        for clip in state
+            .clips
+            .iter_mut()
+            .filter(|c| affects(c) && c.start >= end_time - 0.001)
+        {
+            clip.start = (clip.start - span).max(0.0);
+        }
+    }
+    state.recalculate_duration();
+    Ok(removed)
+}
+
+/// True when `action` would leave `state` unchanged:
+/// - a move to the clip's current start, or a reorder without a target
+/// - a trim, roll, slip, slide or audio offset by zero
+/// - the speed, volume or loop region the clip already has
+///
+/// Missing clips are a validation error, not a no-op.
+pub fn is_noop_action(action: &EditAction, state: &TimelineState) -> bool {
+    const EPSILON: f64 = 1e-6;
+    let Some(clip) = state.clips.iter().find(|c| c.id == action.target_clip_id) else {
+        return false;
+    };
+    let params = action.parameters.as_ref();
+    match action.action_type {
+        ActionType::Move => match params.and_then(|p| p.new_start_time) {
+            Some(new_start) => (new_start.max(0.0) - clip.start).abs() < EPSILON,
+            None => true,
+        },
+        ActionType::Trim => {
+            let zero = |delta: Option<f64>| delta.is_none_or(|d| d.abs() < EPSILON);
+            params.is_none_or(|p| zero(p.trim_start_delta) && zero(p.trim_end_delta))
+        }
+        ActionType::Reorder => params.and_then(|p| p.target_index).is_none(),
+        ActionType::SetLoopRegion => {
+            state.loop_region == Some(loop_region_for(action, clip.start, clip.end()))
+        }
+        ActionType::SetSpeed => params
+            .and_then(|p| p.speed_multiplier)
+            .is_none_or(|s| (s - clip.speed_multiplier).abs() < EPSILON),
+        ActionType::SetVolume => params.and_then(|p| p.volume_db).is_none_or(|db| {
+            (db.clamp(MIN_VOLUME_DB, MAX_VOLUME_DB) - clip.volume_db).abs() < EPSILON
+        }),
+        ActionType::RollingTrim => params
+            .and_then(|p| p.roll_delta)
+            .is_none_or(|d| d.abs() < EPSILON),
+        ActionType::SlipClip => params
+            .and_then(|p| p.slip_delta)
+            .is_none_or(|d| d.abs() < EPSILON),
+        ActionType::SlideClip => params
+            .and_then(|p| p.slide_delta)
+            .is_none_or(|d| d.abs() < EPSILON),
+        ActionType::AudioOffset => params
+            .and_then(|p| p.audio_offset_delta)
+            .is_none_or(|d| d.abs() < EPSILON),
+        ActionType::Delete
+        | ActionType::RippleDelete
+        | ActionType::Split
+        | ActionType::AddMarker => false,
+    }
+}
+
+/// Apply every action in `plan` to `state`, then recalculate duration, clamp the
+/// playhead and check invariants. No-op actions are skipped and their indices
+/// returned. On error `state` may be partially mutated, so callers must keep a
+/// snapshot to roll back to.
+pub fn apply_plan_to_state(
+    state: &mut TimelineState,
+    plan: &EditPlan,
+) -> Result<Vec<usize>, String> {
+    apply_plan_to_state_with(state, plan, &EditingPreferences::default())
+}
+
+/// `apply_plan_to_state` with trim limits and invariants from `editing`.
+pub fn apply_plan_to_state_with(
+    state: &mut TimelineState,
+    plan: &EditPlan,
+    editing: &EditingPreferences,
+) -> Result<Vec<usize>, String> {
+    // Kept to describe the attempted changes if the result is rejected
+    let before = state.clone();
 
     // 2. Pre-Validation Pass: Check target clips exist
     for action in &plan.actions {
-        if !state.clips.iter().any(|c| c.id == action.target_clip_id) {
+        let Some(clip) = state.clips.iter().find(|c| c.id == action.target_clip_id) else {
             return Err(RouterError::ClipNotFound(action.target_clip_id.clone()).to_string());
+        };
+        // Markers belong to the timeline, not the target clip's track
+        if action.action_type != ActionType::AddMarker
+            && state.track(&clip.track_id).is_some_and(|t| t.locked)
+        {
+            return Err(
+                RouterError::TrackLocked(clip.id.clone(), clip.track_id.clone()).to_string(),
+            );
         }
     }
 
     // 3. Execution Pass
-    for action in &plan.actions {
+    let mut skipped = Vec::new();
+    for (action_index, action) in plan.actions.iter().enumerate() {
+        if is_noop_action(action, state) {
+            println!(
+                "⏭️ [Router] Skipping no-op {:?} on clip {}",
+                action.action_type, action.target_clip_id
+            );
+            skipped.push(action_index);
+            continue;
+        }
         println!(
             "▶️ [Router] Executing {:?} on clip {}",
             action.action_type, action.target_clip_id
@@ -151,22 +846,57 @@ pub fn run_edit_plan(
                     println!("  ✓ Deleted clip: {}", removed.id);
                 }
             }
-            ActionType::Move => {
-                if let Some(clip) = state
+            ActionType::RippleDelete => {
+                if let Some(index) = state
                     .clips
-                    .iter_mut()
-                    .find(|c| c.id == action.target_clip_id)
+                    .iter()
+                    .position(|c| c.id == action.target_clip_id)
                 {
-                    if let Some(params) = &action.parameters {
-                        if let Some(new_start) = params.new_start_time {
-                            let old_start = clip.start;
-                            // Enforce non-negative start time
-                            clip.start = new_start.max(0.0);
-                            println!(
-                                "  ✓ Moved clip from {:.2}s to {:.2}s",
-                                old_start, clip.start
-                            );
+                    let removed = state.clips.remove(index);
+                    let (gap_end, gap) = (removed.end(), removed.effective_duration());
+                    // Clips butting up against the deleted one count as after it
+                    for clip in state
+                        .clips
+                        .iter_mut()
+                        .filter(|c| c.track_id == removed.track_id && c.start >= gap_end - 0.001)
+                    {
+                        clip.start = (clip.start - gap).max(0.0);
+                    }
+                    println!(
+                        "  ✓ Ripple deleted clip: {} ({:.2}s closed)",
+                        removed.id, gap
+                    );
+                }
+            }
+            ActionType::Move => {
+                let new_start = action.parameters.as_ref().and_then(|p| p.new_start_time);
+                if let Some(new_start) = new_start {
+                    // Enforce non-negative start time, then land on a nearby clip
+                    // edge rather than leave a sliver of gap
+                    let requested = new_start.max(0.0);
+                    let new_start = state
+                        .snap_to_clip_edge(
+                            requested,
+                            editing.snap_threshold_s,
+                            Some(&action.target_clip_id),
+                        )
+                        .max(0.0);
+                    let snapped_free = new_start == requested;
+                    if let Some(clip) = state
+                        .clips
+                        .iter_mut()
+                        .find(|c| c.id == action.target_clip_id)
+                    {
+                        let old_start = clip.start;
+                        clip.start = new_start;
+                        // Off any clip edge, land on a whole frame
+                        if let (Some(fps), true) = (clip.frame_rate(), snapped_free) {
+                            clip.set_start_frames((new_start * fps).round() as u64);
                         }
+                        println!(
+                            "  ✓ Moved clip from {:.2}s to {:.2}s",
+                            old_start, clip.start
+                        );
                     }
                 }
             }
@@ -176,34 +906,56 @@ pub fn run_edit_plan(
                     .iter_mut()
                     .find(|c| c.id == action.target_clip_id)
                 {
-                    if let Some(params) = &action.parameters {
-                        let original_duration = clip.duration;
+                    if let (Some(params), Some(fps)) = (&action.parameters, clip.frame_rate()) {
+                        let original_duration = clip.effective_duration();
+                        trim_by_frames(clip, params, editing, fps);
+                        println!(
+                            "  ✓ Trimmed clip: {:.2}s -> {:.2}s ({} frames)",
+                            original_duration,
+                            clip.effective_duration(),
+                            clip.duration_frames
+                        );
+                    } else if let Some(params) = &action.parameters {
+                        let original_duration = clip.effective_duration();
+                        // Non-destructive: move the virtual trim handles, keep `duration`
+                        let mut edit_in = clip.source_in();
+                        let mut edit_out = clip.source_out();
+                        // Deltas and limits are timeline seconds; handles are source seconds
+                        let speed = clip.speed_multiplier;
+                        let significant = |delta: Option<f64>| {
+                            delta
+                                .filter(|d| d.abs() >= editing.min_trim_delta_secs)
+                                .map(|d| d * speed)
+                        };
 
                         // Trim Start
-                        if let Some(delta) = params.trim_start_delta {
-                            clip.start += delta;
-                            clip.duration -= delta;
+                        if let Some(delta) = significant(params.trim_start_delta) {
+                            edit_in = (edit_in + delta).clamp(clip.source_offset, clip.duration);
                         }
 
                         // Trim End
-                        if let Some(delta) = params.trim_end_delta {
-                            clip.duration += delta; // Delta is usually negative for shortening
+                        if let Some(delta) = significant(params.trim_end_delta) {
+                            edit_out = (edit_out + delta).clamp(clip.source_offset, clip.duration);
+                            // Delta is usually negative for shortening
                         }
 
-                        // Enforce minimum duration (0.1s)
-                        const MIN_DURATION: f64 = 0.1;
-                        if clip.duration < MIN_DURATION {
-                            clip.duration = MIN_DURATION;
+                        // Enforce minimum duration
+                        let min_duration = editing.min_clip_duration_secs * speed;
+                        if edit_out - edit_in < min_duration {
+                            edit_out = (edit_in + min_duration).min(clip.duration);
+                            edit_in = (edit_out - min_duration).max(clip.source_offset);
                         }
 
-                        // Enforce non-negative start
-                        if clip.start < 0.0 {
-                            clip.start = 0.0;
-                        }
+                        // Keep the remaining frames where they were on the timeline,
+                        // enforcing non-negative start
+                        clip.start = (clip.start + (edit_in - clip.source_in()) / speed).max(0.0);
+                        clip.edit_in = Some(edit_in);
+                        clip.edit_out = Some(edit_out);
 
                         println!(
                             "  ✓ Trimmed clip: {:.2}s -> {:.2}s",
-                            original_duration, clip.duration
+                            original_duration,
+                            clip.effective_duration()
                         );
                     }
                 }
@@ -217,24 +969,44 @@ pub fn run_edit_plan(
                     if let Some(params) = &action.parameters {
                         if let Some(split_time) = params.split_time {
                             let original_clip = &mut state.clips[index];
+                            original_clip.sync_frames();
 
-                            // Calculate relative split point
+                            // Calculate relative split point, on a whole frame when
+                            // the clip's frame rate is known
+                            let fps = original_clip.frame_rate();
                             let relative_split = split_time - original_clip.start;
+                            let relative_split = fps
+                                .map_or(relative_split, |fps| (relative_split * fps).round() / fps);
+
+                            if relative_split > 0.0
+                                && relative_split < original_clip.effective_duration()
+                            {
+                                // Both halves keep the full source and split the edit range
+                                let split_point = original_clip.source_in()
+                                    + relative_split * original_clip.speed_multiplier;
 
-                            if relative_split > 0.0 && relative_split < original_clip.duration {
                                 // Create new clip (second half)
-                                let new_duration = original_clip.duration - relative_split;
                                 let mut new_clip = original_clip.clone();
                                 new_clip.id = Uuid::new_v4().to_string();
-                                new_clip.start = split_time;
-                                new_clip.duration = new_duration;
+                                new_clip.start = original_clip.start + relative_split;
+                                new_clip.edit_in = Some(split_point);
+                                new_clip.edit_out = Some(original_clip.source_out());
 
                                 // Modify original (first half)
-                                original_clip.duration = relative_split;
+                                original_clip.edit_in = Some(original_clip.source_in());
+                                original_clip.edit_out = Some(split_point);
+
+                                if let Some(fps) = fps {
+                                    let frames = (relative_split * fps).round() as u64;
+                                    new_clip.set_start_frames(original_clip.start_frames + frames);
+                                    new_clip.duration_frames =
+                                        original_clip.duration_frames.saturating_sub(frames);
+                                    original_clip.set_duration_frames(frames);
+                                }
 
                                 println!(
                                     "  ✓ Split clip at {:.2}s, new clip: {}",
-                                    split_time, new_clip.id
+                                    new_clip.start, new_clip.id
                                 );
 
                                 // Insert new clip after original
@@ -244,15 +1016,143 @@ pub fn run_edit_plan(
                     }
                 }
             }
+            ActionType::SetLoopRegion => {
+                if let Some(clip) = state.clips.iter().find(|c| c.id == action.target_clip_id) {
+                    let (start, end) = loop_region_for(action, clip.start, clip.end());
+                    if start < 0.0 || end - start < MIN_LOOP_REGION_SECS {
+                        return Err(format!(
+                            "Invalid loop region [{:.2}, {:.2}] (must be >= {}s)",
+                            start, end, MIN_LOOP_REGION_SECS
+                        ));
+                    }
+                    state.loop_region = Some((start, end));
+                    println!("  ✓ Loop region set to [{:.2}s, {:.2}s]", start, end);
+                }
+            }
+            ActionType::AddMarker => {
+                if let Some(clip) = state.clips.iter().find(|c| c.id == action.target_clip_id) {
+                    let params = action.parameters.as_ref();
+                    let marker = ChapterMarker {
+                        id: Uuid::new_v4().to_string(),
+                        time_s: params.and_then(|p| p.marker_time).unwrap_or(clip.start),
+                        label: params
+                            .and_then(|p| p.marker_label.clone())
+                            .unwrap_or_else(|| format!("Chapter {}", state.markers.len() + 1)),
+                        color_hex: params.and_then(|p| p.marker_color.clone()),
+                    };
+                    println!(
+                        "  ✓ Added marker '{}' at {:.2}s",
+                        marker.label, marker.time_s
+                    );
+                    state.markers.push(marker);
+                    state.sort_markers();
+                }
+            }
+            ActionType::SetSpeed => {
+                let speed = action.parameters.as_ref().and_then(|p| p.speed_multiplier);
+                if let (Some(speed), Some(clip)) = (
+                    speed,
+                    state
+                        .clips
+                        .iter_mut()
+                        .find(|c| c.id == action.target_clip_id),
+                ) {
+                    check_speed_multiplier(speed)?;
+                    let old_end = clip.end();
+                    clip.speed_multiplier = speed;
+                    let (track_id, new_end) = (clip.track_id.clone(), clip.end());
+                    // Clips butting up against this one keep butting up against it
+                    for other in state.clips.iter_mut().filter(|c| {
+                        c.track_id == track_id
+                            && c.id != action.target_clip_id
+                            && c.start >= old_end - 0.001
+                    }) {
+                        other.start = (other.start + new_end - old_end).max(0.0);
+                    }
+                    println!(
+                        "  ✓ Set clip speed to {:.2}x ({:.2}s -> {:.2}s end)",
+                        speed, old_end, new_end
+                    );
+                }
+            }
+            ActionType::SetVolume => {
+                let volume_db = action.parameters.as_ref().and_then(|p| p.volume_db);
+                if let (Some(volume_db), Some(clip)) = (
+                    volume_db,
+                    state
+                        .clips
+                        .iter_mut()
+                        .find(|c| c.id == action.target_clip_id),
+                ) {
+                    clip.volume_db = volume_db.clamp(MIN_VOLUME_DB, MAX_VOLUME_DB);
+                    println!("  ✓ Set clip volume to {:+.1} dB", clip.volume_db);
+                }
+            }
+            ActionType::RollingTrim => {
+                if let Some(delta) = action.parameters.as_ref().and_then(|p| p.roll_delta) {
+                    rolling_trim(state, &action.target_clip_id, delta, editing)?;
+                    println!("  ✓ Rolled edit point by {:+.2}s", delta);
+                }
+            }
+            ActionType::SlipClip => {
+                if let Some(delta) = action.parameters.as_ref().and_then(|p| p.slip_delta) {
+                    slip_clip(state, &action.target_clip_id, delta)?;
+                    println!("  ✓ Slipped clip source by {:+.2}s", delta);
+                }
+            }
+            ActionType::SlideClip => {
+                if let Some(delta) = action.parameters.as_ref().and_then(|p| p.slide_delta) {
+                    slide_clip(state, &action.target_clip_id, delta, editing)?;
+                    println!("  ✓ Slid clip by {:+.2}s", delta);
+                }
+            }
+            ActionType::AudioOffset => {
+                let delta = action
+                    .parameters
+                    .as_ref()
+                    .and_then(|p| p.audio_offset_delta);
+                if let (Some(delta), Some(clip)) = (
+                    delta,
+                    state
+                        .clips
+                        .iter_mut()
+                        .find(|c| c.id == action.target_clip_id),
+                ) {
+                    let offset = clip.audio_offset_s + delta;
+                    // Some of the audio has to stay under the clip's picture
+                    if offset.abs() >= clip.effective_duration() {
+                        return Err(RouterError::InvalidParameters(format!(
+                            "audio offset {:.2}s moves all of clip {}'s audio outside its {:.2}s",
+                            offset,
+                            clip.id,
+                            clip.effective_duration()
+                        ))
+                        .to_string());
+                    }
+                    clip.audio_offset_s = offset;
+                    println!("  ✓ Set clip audio offset to {:+.2}s", offset);
+                }
+            }
+            ActionType::Reorder => {
+                if let Some(params) = &action.parameters {
+                    if let Some(target_index) = params.target_index {
+                        let preserve_gaps = params.preserve_gaps.unwrap_or(false);
+                        reorder_clip(state, &action.target_clip_id, target_index, preserve_gaps)?;
+                        println!(
+                            "  ✓ Reordered clip to index {} (preserve gaps: {})",
+                            target_index, preserve_gaps
+                        );
+                    }
+                }
+            }
         }
     }
 
-    // 4. Recalculate Duration
-    state.duration = state
-        .clips
-        .iter()
-        .map(|c| c.start + c.duration)
-        .fold(0.0, f64::max);
+    // 4. Recalculate Duration, and frame counts for clips moved in seconds
+    for clip in &mut state.clips {
+        clip.sync_frames();
+    }
+    state.duration = state.clips.iter().map(|c| c.end()).fold(0.0, f64::max);
 
     // STEP 5 FIX: Clamp playhead to valid range after mutations
     // Invariant: playhead_time ∈ [0, duration] always
@@ -265,37 +1165,151 @@ pub fn run_edit_plan(
         );
     }
 
-    // STEP 3 FIX: Post-Mutation Validation with ROLLBACK
+    state.clamp_loop_region();
+    state.clamp_markers();
+
+    // STEP 3 FIX: Post-Mutation Validation
     // Invalid state CANNOT persist - this is a hard reject
-    if let Err(e) = validate_state_invariants(&state) {
-        println!(
-            "❌ [Router] Invariant violation detected: {}. ROLLING BACK.",
-            e
-        );
-        // Restore snapshot - atomicity enforced
-        *state = snapshot;
-        return Err(format!("Mutation rejected - invariant violated: {}", e));
-    }
+    validate_state_invariants_with(state, editing).map_err(|e| {
+        let diff = describe_rejected_changes(&before, state, plan);
+        format!(
+            "Mutation rejected - invariant violated: {}",
+            e.with_diff(diff)
+        )
+    })?;
+    Ok(skipped)
+}
 
-    // 6. Increment version counter
-    state.version += 1;
+// `describe_state_changes`, with the plan actions that targeted each clip
+fn describe_rejected_changes(
+    before: &TimelineState,
+    after: &TimelineState,
+    plan: &EditPlan,
+) -> String {
+    describe_state_changes(before, after)
+        .into_iter()
+        .map(|change| {
+            let actions: Vec<String> = plan
+                .actions
+                .iter()
+                .filter(|a| change.starts_with(&format!("clip {}:", a.target_clip_id)))
+                .filter_map(|a| serde_json::to_value(&a.action_type).ok())
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect();
+            if actions.is_empty() {
+                change
+            } else {
+                format!("{} via {}", change, actions.join(", "))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
 
-    println!(
-        "📊 [Backend] State AFTER execution: {} clips, {:.2}s, version {}",
-        state.clips.len(),
-        state.duration,
-        state.version
-    );
+/// `validate_no_gaps` when `general.require_no_gaps` is set.
+pub fn enforce_gap_policy(
+    state: &TimelineState,
+    general: &GeneralPreferences,
+) -> Result<(), String> {
+    if general.require_no_gaps {
+        validate_no_gaps(state)
+            .map_err(|e| format!("Mutation rejected - invariant violated: {}", e))?;
+    }
+    Ok(())
+}
 
-    // 7. Emit Update
-    let _ = app_handle.emit("STATE_UPDATE", &*state);
+/// Dry run: the state `plan` would produce, leaving `state` untouched.
+pub fn preview_edit_plan(
+    state: &TimelineState,
+    plan: &EditPlan,
+    editing: &EditingPreferences,
+) -> Result<TimelineState, String> {
+    let mut preview = state.clone();
+    apply_plan_to_state_with(&mut preview, plan, editing)?;
+    Ok(preview)
+}
 
-    // 8. Log Interaction
-    let details = serde_json::json!({
-        "plan": plan,
-        "resulting_duration": state.duration
-    });
-    prefs.log_interaction("AI_EDIT_APPLIED", details);
+/// `preview_edit_plan` against the engine's current state, with the user's
+/// ripple and editing preferences. The state lock is only held long enough
+/// to copy the timeline, so the real state is never mutated.
+pub fn run_edit_plan_dry_run(
+    engine: &State<'_, TimelineEngine>,
+    prefs: &State<'_, PreferenceManager>,
+    mut plan: EditPlan,
+) -> Result<(TimelineState, Vec<String>), String> {
+    let user_prefs = prefs.get_preferences();
+    if !user_prefs.general.auto_ripple_edits {
+        downgrade_ripple_deletes(&mut plan);
+    }
+    let current = engine
+        .state
+        .lock()
+        .map_err(|_| "Failed to acquire state lock".to_string())?
+        .clone();
+    let preview = preview_edit_plan(&current, &plan, &user_prefs.editing)?;
+    enforce_gap_policy(&preview, &user_prefs.general)?;
+    let changes = describe_state_changes(&current, &preview);
+    Ok((preview, changes))
+}
 
-    Ok(state.clone())
+/// One line per clip that was added, removed or changed between `before`
+/// and `after`, e.g. "clip abc: duration 10.0 → 8.0s".
+pub fn describe_state_changes(before: &TimelineState, after: &TimelineState) -> Vec<String> {
+    let mut changes = Vec::new();
+    for old in &before.clips {
+        let Some(new) = after.clips.iter().find(|c| c.id == old.id) else {
+            changes.push(format!("clip {}: removed", old.id));
+            continue;
+        };
+        let mut fields = Vec::new();
+        if (old.effective_duration() - new.effective_duration()).abs() > 0.001 {
+            fields.push(format!(
+                "duration {:.1} → {:.1}s",
+                old.effective_duration(),
+                new.effective_duration()
+            ));
+        }
+        if (old.start - new.start).abs() > 0.001 {
+            fields.push(format!("start {:.1} → {:.1}s", old.start, new.start));
+        }
+        if old.track_id != new.track_id {
+            fields.push(format!("track {} → {}", old.track_id, new.track_id));
+        }
+        if (old.speed_multiplier - new.speed_multiplier).abs() > 0.001 {
+            fields.push(format!(
+                "speed {}x → {}x",
+                old.speed_multiplier, new.speed_multiplier
+            ));
+        }
+        if (old.volume_db - new.volume_db).abs() > 0.001 {
+            fields.push(format!("volume {} → {} dB", old.volume_db, new.volume_db));
+        }
+        if !fields.is_empty() {
+            changes.push(format!("clip {}: {}", old.id, fields.join(", ")));
+        }
+    }
+    for new in &after.clips {
+        if !before.clips.iter().any(|c| c.id == new.id) {
+            changes.push(format!(
+                "clip {}: added at {:.1}s ({:.1}s long)",
+                new.id,
+                new.start,
+                new.effective_duration()
+            ));
+        }
+    }
+    if before.markers.len() != after.markers.len() {
+        changes.push(format!(
+            "markers: {} → {}",
+            before.markers.len(),
+            after.markers.len()
+        ));
+    }
+    if (before.duration - after.duration).abs() > 0.001 {
+        changes.push(format!(
+            "timeline: duration {:.1} → {:.1}s",
+            before.duration, after.duration
+        ));
+    }
+    changes
 }
@@ -0,0 +1,311 @@
+// src-tauri/src/analyzer.rs
+// Lint-style checks over the timeline. Lints are advisory: they never block edits,
+// but some carry an auto-fix that `apply_lint_fix` can perform.
+use crate::commands::consolidate_duplicates_logic;
+use crate::timeline::TimelineState;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Clips shorter than this are flagged as possibly accidental.
+const SHORT_CLIP_THRESHOLD: f64 = 0.5;
+/// Speed factors above this are flagged as likely to look choppy.
+const HIGH_SPEED_THRESHOLD: f64 = 4.0;
+/// Same tolerance used by the router invariants.
+const EPSILON: f64 = 0.001;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LintSeverity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TimelineLint {
+    /// Stable identifier derived from the offending clip(s), valid until the state changes.
+    pub id: String,
+    pub severity: LintSeverity,
+    pub clip_id: Option<String>,
+    pub message: String,
+    /// Name of the fix `apply_lint_fix` will run, if the lint is auto-fixable.
+    pub auto_fix_command: Option<String>,
+}
+
+pub fn analyze_state(state: &TimelineState) -> Vec<TimelineLint> {
+    let mut lints = Vec::new();
+
+    // 1. Very short clips
+    for clip in &state.clips {
+        if clip.effective_duration() < SHORT_CLIP_THRESHOLD {
+            lints.push(TimelineLint {
+                id: format!("short_clip:{}", clip.id),
+                severity: LintSeverity::Warning,
+                clip_id: Some(clip.id.clone()),
+                message: format!(
+                    "Clip is {:.2}s long and may be too short (< {:.1}s)",
+                    clip.effective_duration(),
+                    SHORT_CLIP_THRESHOLD
+                ),
+                auto_fix_command: None,
+            });
+        }
+    }
+
+    // 2. Gaps between clips on the same track
+    let mut clips_by_track: BTreeMap<&str, Vec<_>> = BTreeMap::new();
+    for clip in &state.clips {
+        clips_by_track.entry(&clip.track_id).or_default().push(clip);
+    }
+    for (track_id, mut clips) in clips_by_track {
+        clips.sort_by(|a, b| a.start.partial_cmp(&b.start).unwrap());
+        for pair in clips.windows(2) {
+            let prev_end = pair[0].end();
+            let gap = pair[1].start - prev_end;
+            if gap > EPSILON {
+                lints.push(TimelineLint {
+                    id: format!("gap:{}", pair[1].id),
+                    severity: LintSeverity::Info,
+                    clip_id: Some(pair[1].id.clone()),
+                    message: format!(
+                        "{:.2}s gap before this clip on track '{}', consider compacting",
+                        gap, track_id
+                    ),
+                    auto_fix_command: Some("close_gap".to_string()),
+                });
+            }
+        }
+    }
+
+    // 3. Stored duration out of sync with the clips
+    let calculated_duration = state.clips.iter().map(|c| c.end()).fold(0.0, f64::max);
+    if (state.duration - calculated_duration).abs() > EPSILON {
+        lints.push(TimelineLint {
+            id: "duration_mismatch".to_string(),
+            severity: LintSeverity::Error,
+            clip_id: None,
+            message: format!(
+                "Stored duration doesn't match calculated (stored={:.2}s, calculated={:.2}s)",
+                state.duration, calculated_duration
+            ),
+            auto_fix_command: Some("recalculate_duration".to_string()),
+        });
+    }
+
+    // 4. Orphaned clips
+    for clip in &state.clips {
+        if !std::path::Path::new(&clip.source_file).exists() {
+            lints.push(TimelineLint {
+                id: format!("missing_source:{}", clip.id),
+                severity: LintSeverity::Error,
+                clip_id: Some(clip.id.clone()),
+                message: format!("Source file missing: {}", clip.source_file),
+                auto_fix_command: None,
+            });
+        }
+    }
+
+    // 5. Same media imported more than once into separate files
+    let mut first_by_hash: BTreeMap<&str, &crate::timeline::Clip> = BTreeMap::new();
+    for clip in &state.clips {
+        let Some(hash) = clip.source_hash.as_deref() else {
+            continue;
+        };
+        match first_by_hash.get(hash) {
+            Some(primary) if primary.source_file != clip.source_file => {
+                lints.push(TimelineLint {
+                    id: format!("duplicate_source:{}", clip.id),
+                    severity: LintSeverity::Info,
+                    clip_id: Some(clip.id.clone()),
+                    message: format!(
+                        "Identical source to clip {} stored as a separate file",
+                        primary.id
+                    ),
+                    auto_fix_command: Some("consolidate_duplicates".to_string()),
+                });
+            }
+            Some(_) => {}
+            None => {
+                first_by_hash.insert(hash, clip);
+            }
+        }
+    }
+
+    // 6. Very high speed factors
+    for clip in &state.clips {
+        if clip.speed_multiplier > HIGH_SPEED_THRESHOLD {
+            lints.push(TimelineLint {
+                id: format!("high_speed:{}", clip.id),
+                severity: LintSeverity::Warning,
+                clip_id: Some(clip.id.clone()),
+                message: format!(
+                    "Clip plays at {:.1}x and may look choppy (> {:.0}x)",
+                    clip.speed_multiplier, HIGH_SPEED_THRESHOLD
+                ),
+                auto_fix_command: None,
+            });
+        }
+    }
+
+    // 7. The same footage used twice. Split halves share a file but not a range.
+    for (i, clip) in state.clips.iter().enumerate() {
+        let earlier = state.clips[..i].iter().find(|other| {
+            other.source_file == clip.source_file
+                && other.source_in() < clip.source_out() - EPSILON
+                && clip.source_in() < other.source_out() - EPSILON
+        });
+        if let Some(other) = earlier {
+            lints.push(TimelineLint {
+                id: format!("repeated_footage:{}", clip.id),
+                severity: LintSeverity::Info,
+                clip_id: Some(clip.id.clone()),
+                message: format!(
+                    "Reuses footage from {} that clip {} already plays",
+                    clip.source_file, other.id
+                ),
+                auto_fix_command: None,
+            });
+        }
+    }
+
+    lints
+}
+
+/// Apply the auto-fix for a lint previously returned by `analyze_state` on this state.
+pub fn apply_fix(state: &mut TimelineState, lint: &TimelineLint) -> Result<(), String> {
+    match lint.auto_fix_command.as_deref() {
+        Some("close_gap") => {
+            let clip_id = lint.clip_id.as_deref().ok_or("Lint has no clip")?;
+            let clip = state
+                .clips
+                .iter()
+                .find(|c| c.id == clip_id)
+                .ok_or(format!("Clip {} not found", clip_id))?;
+            let track_id = clip.track_id.clone();
+            let clip_start = clip.start;
+
+            let prev_end = state
+                .clips
+                .iter()
+                .filter(|c| c.track_id == track_id && c.start < clip_start)
+                .map(|c| c.end())
+                .fold(0.0, f64::max);
+            let gap = clip_start - prev_end;
+
+            // Shift this clip and everything after it on the track
+            for c in state.clips.iter_mut() {
+                if c.track_id == track_id && c.start >= clip_start {
+                    c.start -= gap;
+                }
+            }
+            state.recalculate_duration();
+            Ok(())
+        }
+        Some("recalculate_duration") => {
+            state.recalculate_duration();
+            Ok(())
+        }
+        Some("consolidate_duplicates") => {
+            let clip_id = lint.clip_id.as_deref().ok_or("Lint has no clip")?;
+            let hash = state
+                .clips
+                .iter()
+                .find(|c| c.id == clip_id)
+                .and_then(|c| c.source_hash.clone())
+                .ok_or(format!("Clip {} has no source hash", clip_id))?;
+            let primary_id = state
+                .clips
+                .iter()
+                .find(|c| c.source_hash.as_deref() == Some(hash.as_str()))
+                .map(|c| c.id.clone())
+                .ok_or("Primary clip not found")?;
+            consolidate_duplicates_logic(state, &primary_id, &[clip_id.to_string()]).map(|_| ())
+        }
+        Some(other) => Err(format!("Unknown auto-fix '{}'", other)),
+        None => Err(format!("Lint '{}' has no automatic fix", lint.id)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timeline::Clip;
+
+    fn clip(id: &str, start: f64, duration: f64) -> Clip {
+        Clip {
+            id: id.to_string(),
+            track_id: "v1".to_string(),
+            start,
+            duration,
+            source_file: format!("/missing/{}.mp4", id),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_analyze_flags_short_clip_and_gap() {
+        let state = TimelineState {
+            clips: vec![clip("a", 0.0, 0.2), clip("b", 3.0, 5.0)],
+            duration: 8.0,
+            ..Default::default()
+        };
+
+        let lints = analyze_state(&state);
+        assert!(lints.iter().any(|l| l.id == "short_clip:a"));
+        assert!(lints.iter().any(|l| l.id == "gap:b"));
+        assert!(lints.iter().any(|l| l.id == "missing_source:a"));
+        assert!(!lints.iter().any(|l| l.id == "duration_mismatch"));
+    }
+
+    #[test]
+    fn test_analyze_flags_high_speed_and_repeated_footage() {
+        let halves = |id: &str, start: f64, edit_in: f64| Clip {
+            source_file: "/missing/shared.mp4".to_string(),
+            edit_in: Some(edit_in),
+            edit_out: Some(edit_in + 2.0),
+            ..clip(id, start, 10.0)
+        };
+        let state = TimelineState {
+            clips: vec![
+                halves("a", 0.0, 0.0),
+                halves("b", 2.0, 2.0),
+                halves("c", 4.0, 1.0),
+                Clip {
+                    speed_multiplier: 8.0,
+                    ..clip("d", 6.0, 8.0)
+                },
+            ],
+            duration: 7.0,
+            ..Default::default()
+        };
+
+        let ids: Vec<_> = analyze_state(&state).into_iter().map(|l| l.id).collect();
+        assert!(!ids.contains(&"repeated_footage:b".to_string()));
+        assert!(ids.contains(&"repeated_footage:c".to_string()));
+        assert!(ids.contains(&"high_speed:d".to_string()));
+        assert!(!ids.contains(&"high_speed:a".to_string()));
+    }
+
+    #[test]
+    fn test_apply_close_gap_fix() {
+        let mut state = TimelineState {
+            clips: vec![
+                clip("a", 0.0, 2.0),
+                clip("b", 3.0, 5.0),
+                clip("c", 8.0, 1.0),
+            ],
+            duration: 9.0,
+            ..Default::default()
+        };
+
+        let lint = analyze_state(&state)
+            .into_iter()
+            .find(|l| l.id == "gap:b")
+            .unwrap();
+        apply_fix(&mut state, &lint).unwrap();
+
+        assert_eq!(state.clips[1].start, 2.0);
+        assert_eq!(state.clips[2].start, 7.0);
+        assert_eq!(state.duration, 8.0);
+    }
+}
@@ -0,0 +1,78 @@
+// src-tauri/src/app_state.rs
+// The managed state every command works from, bundled so command logic can run
+// without a Tauri AppHandle. The app builds it from the already-managed engines,
+// which share their data with these handles; tests build a fresh in-memory one.
+use crate::edit_plan::EditPlan;
+use crate::ffmpeg::FFmpegEngine;
+use crate::preferences::PreferenceManager;
+use crate::timeline::TimelineEngine;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+use tokio::sync::Mutex;
+
+// Item 7: Active Requests State
+#[derive(Clone, Default)]
+pub struct ActiveRequests(pub Arc<Mutex<HashMap<String, tokio::task::JoinHandle<()>>>>);
+
+impl ActiveRequests {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// AI edit plans captured between `start_macro_recording` and `stop_macro_recording`.
+#[derive(Default)]
+pub struct MacroRecorder {
+    /// `None` while not recording.
+    recording_buffer: std::sync::Mutex<Option<Vec<EditPlan>>>,
+}
+
+impl MacroRecorder {
+    /// Begin a new recording, discarding any unsaved one.
+    pub fn start(&self) {
+        *self.recording_buffer.lock().unwrap() = Some(Vec::new());
+    }
+
+    /// Add an applied plan to the recording, if one is running.
+    pub fn record(&self, plan: &EditPlan) {
+        if let Some(buffer) = self.recording_buffer.lock().unwrap().as_mut() {
+            buffer.push(plan.clone());
+        }
+    }
+
+    /// End the recording and return its plans, or `None` if none was running.
+    pub fn stop(&self) -> Option<Vec<EditPlan>> {
+        self.recording_buffer.lock().unwrap().take()
+    }
+}
+
+#[derive(Clone)]
+pub struct AppState {
+    pub timeline: Arc<TimelineEngine>,
+    pub prefs: Arc<PreferenceManager>,
+    pub ffmpeg: Arc<FFmpegEngine>,
+    pub requests: Arc<ActiveRequests>,
+}
+
+impl AppState {
+    /// Handles onto the state already managed by `app`.
+    pub fn from_app(app: &AppHandle) -> Self {
+        Self {
+            timeline: Arc::new((*app.state::<TimelineEngine>()).clone()),
+            prefs: Arc::new((*app.state::<PreferenceManager>()).clone()),
+            ffmpeg: Arc::new((*app.state::<FFmpegEngine>()).clone()),
+            requests: Arc::new((*app.state::<ActiveRequests>()).clone()),
+        }
+    }
+
+    /// Empty timeline and default preferences that are never written to disk.
+    pub fn new_for_testing() -> Self {
+        Self {
+            timeline: Arc::new(TimelineEngine::new()),
+            prefs: Arc::new(PreferenceManager::new_in_memory()),
+            ffmpeg: Arc::new(FFmpegEngine::new()),
+            requests: Arc::new(ActiveRequests::new()),
+        }
+    }
+}
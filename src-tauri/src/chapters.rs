@@ -0,0 +1,126 @@
+// src-tauri/src/chapters.rs
+// Chapter marker export in the formats players and hosting sites read.
+use crate::timeline::{ChapterMarker, TimelineState};
+
+/// Render the timeline's markers as `format`: "ffmetadata", "youtube" or "podlove".
+pub fn export_chapter_markers(state: &TimelineState, format: &str) -> Result<String, String> {
+    match format {
+        "ffmetadata" => Ok(to_ffmetadata(&state.markers, state.duration)),
+        "youtube" => Ok(to_youtube(&state.markers, state.duration)),
+        "podlove" => Ok(to_podlove(&state.markers)),
+        other => Err(format!(
+            "Unknown chapter format '{}' (expected ffmetadata, youtube or podlove)",
+            other
+        )),
+    }
+}
+
+// FFmpeg metadata file, for `-i chapters.txt -map_metadata 1`. Each chapter
+// runs until the next marker, the last one until the end of the timeline.
+fn to_ffmetadata(markers: &[ChapterMarker], duration: f64) -> String {
+    let ms = |secs: f64| (secs * 1000.0).round() as u64;
+    let mut out = String::from(";FFMETADATA1\n");
+    for (i, marker) in markers.iter().enumerate() {
+        let end = markers.get(i + 1).map_or(duration, |next| next.time_s);
+        out.push_str(&format!(
+            "[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+            ms(marker.time_s),
+            ms(end),
+            escape_ffmetadata(&marker.label)
+        ));
+    }
+    out
+}
+
+fn escape_ffmetadata(value: &str) -> String {
+    value
+        .chars()
+        .fold(String::with_capacity(value.len()), |mut out, c| {
+            if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+                out.push('\\');
+            }
+            out.push(c);
+            out
+        })
+}
+
+// "01:30 Label" per line, with hours only on timelines an hour or longer.
+// YouTube only shows chapters when the first one starts at 00:00.
+fn to_youtube(markers: &[ChapterMarker], duration: f64) -> String {
+    markers
+        .iter()
+        .map(|m| {
+            let total = m.time_s.floor() as u64;
+            let (h, min, s) = (total / 3600, total % 3600 / 60, total % 60);
+            let stamp = if duration >= 3600.0 {
+                format!("{}:{:02}:{:02}", h, min, s)
+            } else {
+                format!("{:02}:{:02}", min, s)
+            };
+            format!("{} {}\n", stamp, m.label)
+        })
+        .collect()
+}
+
+// Podlove Simple Chapters XML, with normal play time stamps
+fn to_podlove(markers: &[ChapterMarker]) -> String {
+    let mut out = String::from(
+        "<psc:chapters version=\"1.2\" xmlns:psc=\"http://podlove.org/simple-chapters\">\n",
+    );
+    for marker in markers {
+        let ms = (marker.time_s * 1000.0).round() as u64;
+        out.push_str(&format!(
+            "  <psc:chapter start=\"{:02}:{:02}:{:02}.{:03}\" title=\"{}\"/>\n",
+            ms / 3_600_000,
+            ms % 3_600_000 / 60_000,
+            ms % 60_000 / 1000,
+            ms % 1000,
+            escape_xml(&marker.label)
+        ));
+    }
+    out.push_str("</psc:chapters>\n");
+    out
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_chapter_markers_formats() {
+        let marker = |time_s: f64, label: &str| ChapterMarker {
+            id: label.to_string(),
+            time_s,
+            label: label.to_string(),
+            color_hex: None,
+        };
+        let state = TimelineState {
+            duration: 90.0,
+            markers: vec![marker(0.0, "Intro"), marker(65.5, "Q&A <live>")],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            export_chapter_markers(&state, "ffmetadata").unwrap(),
+            ";FFMETADATA1\n\
+             [CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=65500\ntitle=Intro\n\
+             [CHAPTER]\nTIMEBASE=1/1000\nSTART=65500\nEND=90000\ntitle=Q&A <live>\n"
+        );
+        assert_eq!(
+            export_chapter_markers(&state, "youtube").unwrap(),
+            "00:00 Intro\n01:05 Q&A <live>\n"
+        );
+        let podlove = export_chapter_markers(&state, "podlove").unwrap();
+        assert!(podlove
+            .contains("<psc:chapter start=\"00:01:05.500\" title=\"Q&amp;A &lt;live&gt;\"/>"));
+        assert!(export_chapter_markers(&state, "srt").is_err());
+    }
+}
@@ -1,17 +1,77 @@
 // src-tauri/src/commands.rs
-use crate::timeline::{Clip, TimelineEngine, TimelineState};
-use tauri::{AppHandle, Emitter, State};
+use crate::action_router::{
+    self, apply_plan_to_state_with, enforce_gap_policy, reorder_clip, validate_state_invariants,
+    validate_state_invariants_with,
+};
+use crate::analyzer::{analyze_state, apply_fix, TimelineLint};
+use crate::app_state::AppState;
+use crate::chapters;
+use crate::edit_plan::{
+    generate_plan_for_move, generate_plan_for_split, generate_plan_for_trim, EditPlan,
+};
+use crate::ffmpeg::{parse_ffmpeg_progress_line, FFmpegEngine, VideoCodecInfo};
+use crate::handlers::{self, check_clip_limit};
+use crate::llm::{log_artifact, ArtifactType};
+use crate::preferences::{EditingPreferences, PreferenceManager};
+use crate::project::{
+    detect_project_version, read_project_file_info, upgrade_project, ProjectFile, ProjectFileInfo,
+    ProjectMetadata, ProjectMetadataStore, CURRENT_PROJECT_VERSION,
+};
+use crate::timeline::{
+    ChapterMarker, Clip, SubtitleTrack, TimelineEngine, TimelineState, TimelineStatistics,
+    TimelineSummary,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tauri::{AppHandle, Emitter, Manager, State};
 // We use uuid to generate unique IDs for new clips
 use uuid::Uuid;
 
+// Apply `edit` as one undoable step (see `TimelineEngine::apply_edit`) and
+// push the result to the frontend.
+pub(crate) fn edit_timeline(
+    app: &AppHandle,
+    engine: &TimelineEngine,
+    edit: impl FnOnce(&mut TimelineState) -> Result<(), String>,
+) -> Result<TimelineState, String> {
+    let state = engine.apply_edit(edit)?;
+    app.emit("STATE_UPDATE", &state)
+        .map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
 // --- COMMAND 1: Get Current State ---
 // The frontend calls this to know what to draw.
 #[tauri::command]
-pub fn get_timeline_state(engine: State<'_, TimelineEngine>) -> Result<TimelineState, String> {
-    // Lock the state to read it safely
-    let state = engine.state.lock().map_err(|_| "Failed to lock state")?;
-    // Return a copy of the state to the UI
-    Ok(state.clone())
+pub fn get_timeline_state(app: State<'_, AppState>) -> Result<TimelineState, String> {
+    handlers::get_timeline_state(&app)
+}
+
+/// The full state only when it moved past `known_version`, so pollers skip serializing
+/// an unchanged timeline.
+#[tauri::command]
+pub fn get_timeline_state_if_changed(
+    app: State<'_, AppState>,
+    known_version: u64,
+) -> Result<Option<TimelineState>, String> {
+    handlers::get_timeline_state_if_changed(&app, known_version)
+}
+
+/// Version, counts and times only - cheap enough to poll every animation frame.
+#[tauri::command]
+pub fn get_timeline_summary(app: State<'_, AppState>) -> Result<TimelineSummary, String> {
+    handlers::get_timeline_summary(&app)
+}
+
+/// Clip counts, gaps and clip lengths. Read-only, so no STATE_UPDATE.
+#[tauri::command]
+pub fn get_timeline_statistics(app: State<'_, AppState>) -> Result<TimelineStatistics, String> {
+    handlers::get_timeline_statistics(&app)
 }
 
 // --- COMMAND 2: Add a Clip (Simulated for now) ---
@@ -19,63 +79,129 @@ pub fn get_timeline_state(engine: State<'_, TimelineEngine>) -> Result<TimelineS
 // In the future, this will involve FFmpeg to get real duration.
 #[tauri::command]
 pub fn add_clip(
-    engine: State<'_, TimelineEngine>,
+    app: State<'_, AppState>,
     file_path: String,
     duration: f64, // Frontend tells us duration for now
 ) -> Result<TimelineState, String> {
-    println!("➡️ Received Add Clip Command for: {}", file_path);
-
-    // Lock the state to modify it
-    let mut state = engine.state.lock().map_err(|_| "Failed to lock state")?;
+    handlers::add_clip(&app, file_path, duration)
+}
 
-    // Create the new clip struct
-    let new_clip = Clip {
-        id: Uuid::new_v4().to_string(),        // Generate a unique ID
-        track_id: "video_track_1".to_string(), // Hardcoded for V1
-        start: state.duration,                 // Append to the end
-        duration: duration,
-        source_file: file_path,
-    };
+// --- COMMAND 3: Add Test Clips (Fixture) ---
+// Generates synthetic clips for testing purposes.
 
-    // Add clip to state
-    state.clips.push(new_clip);
-    // Update total duration
-    state.duration += duration;
+/// lavfi source used for generated test clips.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum TestPattern {
+    TestSrc,
+    ColorBars,
+    Smptehdbars,
+    Noise,
+}
 
-    println!("✅ Clip Added. New State Duration: {:.2}s", state.duration);
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct GenerateClipOptions {
+    pub pattern: TestPattern,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub duration: f64,
+    pub include_audio: bool,
+    /// Sine tone pitch; 440Hz when unset.
+    pub audio_frequency_hz: Option<u32>,
+}
 
-    // Return the updated state so UI can redraw instantly
-    Ok(state.clone())
+impl Default for GenerateClipOptions {
+    fn default() -> Self {
+        Self {
+            pattern: TestPattern::TestSrc,
+            width: 1280,
+            height: 720,
+            fps: 30,
+            duration: 5.0,
+            include_audio: false,
+            audio_frequency_hz: None,
+        }
+    }
 }
 
-// --- COMMAND 3: Add Test Clips (Fixture) ---
-// Generates synthetic clips for testing purposes.
 #[tauri::command]
 pub fn add_test_clips(
     _app: AppHandle,
     engine: State<'_, TimelineEngine>,
     count: usize,
+    options: Option<GenerateClipOptions>,
 ) -> Result<TimelineState, String> {
     println!("🧪 Generating {} test clips...", count);
+    let options = options.unwrap_or_default();
+    if options.width == 0 || options.height == 0 || options.fps == 0 || options.duration <= 0.0 {
+        return Err(format!("Invalid test clip options: {:?}", options));
+    }
 
-    let mut state = engine.state.lock().map_err(|_| "Failed to lock state")?;
-
-    // Call the helper logic
-    add_test_clips_logic(&mut state, count);
-
-    // Emit update
-    _app.emit("STATE_UPDATE", &*state)
-        .map_err(|e| e.to_string())?;
+    // Call the helper logic; edit_timeline emits the update
+    let state = edit_timeline(&_app, &engine, |state| {
+        add_test_clips_logic(state, count, &options);
+        Ok(())
+    })?;
 
     println!(
         "✅ Added {} test clips. Total duration: {:.2}s",
         count, state.duration
     );
-    Ok(state.clone())
+    Ok(state)
+}
+
+// FFmpeg arguments that synthesize one test clip at `output`.
+fn test_clip_args(options: &GenerateClipOptions, output: &str) -> Vec<String> {
+    let video = format!(
+        "duration={}:size={}x{}:rate={}",
+        options.duration, options.width, options.height, options.fps
+    );
+    let source = match options.pattern {
+        TestPattern::TestSrc => format!("testsrc={}", video),
+        TestPattern::ColorBars => format!("smptebars={}", video),
+        TestPattern::Smptehdbars => format!("smptehdbars={}", video),
+        // No noise source in lavfi; run the noise filter over flat gray
+        TestPattern::Noise => format!("color=c=gray:{},noise=alls=60:allf=t+u", video),
+    };
+
+    let mut args: Vec<String> = vec![
+        "-y".into(),
+        "-f".into(),
+        "lavfi".into(),
+        "-i".into(),
+        source,
+    ];
+    if options.include_audio {
+        let tone = format!(
+            "sine=frequency={}:duration={}",
+            options.audio_frequency_hz.unwrap_or(440),
+            options.duration
+        );
+        args.extend(["-f", "lavfi", "-i"].map(String::from));
+        args.push(tone);
+        args.extend(
+            [
+                "-filter_complex",
+                "[1:a]amix=inputs=1:duration=first[aout]",
+                "-map",
+                "0:v",
+                "-map",
+                "[aout]",
+                "-c:a",
+                "aac",
+            ]
+            .map(String::from),
+        );
+    }
+    // yuv420p pixel format for maximum compatibility
+    args.extend(["-c:v", "libx264", "-pix_fmt", "yuv420p"].map(String::from));
+    args.push(output.to_string());
+    args
 }
 
 // Helper function for testing logic without Tauri types
-fn add_test_clips_logic(state: &mut TimelineState, count: usize) {
+fn add_test_clips_logic(state: &mut TimelineState, count: usize, options: &GenerateClipOptions) {
     // Determine uploads dir (hacky for this helper, but works for now)
     let current_dir = std::env::current_dir().expect("failed to get current dir");
     let videos_dir = if current_dir.ends_with("src-tauri") {
@@ -94,21 +220,8 @@ fn add_test_clips_logic(state: &mut TimelineState, count: usize) {
         let file_path_str = file_path.to_string_lossy().to_string();
 
         // Generate video using FFmpeg
-        // testsrc: 5 seconds, 720p, 30fps
-        // yuv420p pixel format for maximum compatibility
         let status = std::process::Command::new("ffmpeg")
-            .args(&[
-                "-y",
-                "-f",
-                "lavfi",
-                "-i",
-                "testsrc=duration=5:size=1280x720:rate=30",
-                "-c:v",
-                "libx264",
-                "-pix_fmt",
-                "yuv420p",
-                &file_path_str,
-            ])
+            .args(test_clip_args(options, &file_path_str))
             .output()
             .expect("Failed to execute ffmpeg");
 
@@ -118,11 +231,13 @@ fn add_test_clips_logic(state: &mut TimelineState, count: usize) {
                 id: Uuid::new_v4().to_string(),
                 track_id: "video_track_1".to_string(),
                 start: state.duration,
-                duration: 5.0,
+                duration: options.duration,
                 source_file: file_path_str,
+                ..Default::default()
             };
+            state.ensure_track(&new_clip.track_id);
             state.clips.push(new_clip);
-            state.duration += 5.0;
+            state.duration += options.duration;
         } else {
             println!(
                 "❌ Failed to generate test clip: {}",
@@ -133,7 +248,7 @@ fn add_test_clips_logic(state: &mut TimelineState, count: usize) {
 }
 
 // Helper to get video directories
-fn get_video_dirs(_app: &AppHandle) -> (std::path::PathBuf, std::path::PathBuf) {
+pub(crate) fn get_video_dirs(_app: &AppHandle) -> (std::path::PathBuf, std::path::PathBuf) {
     // Use current working directory to keep videos inside the project folder during dev
     let current_dir = std::env::current_dir().expect("failed to get current dir");
 
@@ -165,16 +280,154 @@ fn get_video_dirs(_app: &AppHandle) -> (std::path::PathBuf, std::path::PathBuf)
 pub fn import_video(
     app: AppHandle,
     engine: State<'_, TimelineEngine>,
+    ffmpeg: State<'_, FFmpegEngine>,
+    prefs: State<'_, PreferenceManager>,
     file_path: String,
 ) -> Result<TimelineState, String> {
+    let editing = prefs.get_preferences().editing;
+    let result = import_video_logic(&app, &engine, &ffmpeg, &editing, &file_path);
+    if let Err(e) = &result {
+        let _ = app.emit(
+            "IMPORT_FAILED",
+            json!({ "file_path": file_path, "error": e }),
+        );
+    }
+    result
+}
+
+/// Transcodes `import_videos` runs at once.
+const MAX_PARALLEL_IMPORTS: usize = 4;
+
+/// Outcome of `import_videos`: the timeline after every import that worked, and
+/// one message per file that didn't.
+#[derive(Serialize, Debug, Clone)]
+pub struct ImportResult {
+    pub state: TimelineState,
+    pub errors: Vec<String>,
+}
+
+/// Import several files: probe them all at once, then transcode up to
+/// `MAX_PARALLEL_IMPORTS` at a time. Clips are appended in `file_paths` order;
+/// files that fail are skipped.
+#[tauri::command]
+pub async fn import_videos(
+    app: AppHandle,
+    file_paths: Vec<String>,
+) -> Result<ImportResult, String> {
+    use futures::StreamExt;
+
+    let editing = app.state::<PreferenceManager>().get_preferences().editing;
+    let ffmpeg = (*app.state::<FFmpegEngine>()).clone();
+    let total = file_paths.len();
+
+    let probed = probe_paths_concurrently(&file_paths, ffmpeg_probe).await;
+    let tasks =
+        file_paths
+            .iter()
+            .cloned()
+            .zip(probed)
+            .enumerate()
+            .map(|(index, (path, probed))| {
+                let app = app.clone();
+                let ffmpeg = ffmpeg.clone();
+                async move {
+                    let result = tokio::task::spawn_blocking(move || {
+                        probed.and_then(|metadata| prepare_import(&app, &ffmpeg, &path, metadata))
+                    })
+                    .await
+                    .map_err(|e| format!("Import task failed: {}", e))
+                    .and_then(|prepared| prepared);
+                    (index, result)
+                }
+            });
+    let mut finished = futures::stream::iter(tasks).buffer_unordered(MAX_PARALLEL_IMPORTS);
+    let mut results = Vec::with_capacity(total);
+    while let Some((index, result)) = finished.next().await {
+        let last_clip_id = result
+            .as_ref()
+            .map(|p| p.clip_id.clone())
+            .unwrap_or_default();
+        results.push((index, result));
+        let _ = app.emit(
+            "IMPORT_PROGRESS",
+            json!({ "completed": results.len(), "total": total, "last_clip_id": last_clip_id }),
+        );
+    }
+    results.sort_by_key(|(index, _)| *index);
+
+    // All the clips that made it in form a single undo step
+    let engine = app.state::<TimelineEngine>();
+    let mut errors = Vec::new();
+    let state = edit_timeline(&app, &engine, |state| {
+        for (index, result) in results {
+            let file_path = &file_paths[index];
+            if let Err(e) =
+                result.and_then(|prepared| add_imported_clip(&app, state, &editing, prepared))
+            {
+                let _ = app.emit(
+                    "IMPORT_FAILED",
+                    json!({ "file_path": file_path, "error": e }),
+                );
+                errors.push(format!("{}: {}", file_path, e));
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok(ImportResult { state, errors })
+}
+
+fn import_video_logic(
+    app: &AppHandle,
+    engine: &TimelineEngine,
+    ffmpeg: &FFmpegEngine,
+    editing: &EditingPreferences,
+    file_path: &str,
+) -> Result<TimelineState, String> {
+    let metadata = ffmpeg_probe(file_path)?;
+    let prepared = prepare_import(app, ffmpeg, file_path, metadata)?;
+    edit_timeline(app, engine, |state| {
+        add_imported_clip(app, state, editing, prepared)
+    })
+}
+
+// A transcoded file waiting to be placed on the timeline
+struct PreparedImport {
+    clip_id: String,
+    metadata: ClipMetadata,
+    source_hash: Option<String>,
+    stored_path: String,
+}
+
+// Hash and transcode `file_path`, already probed as `metadata`, into the uploads
+// directory
+fn prepare_import(
+    app: &AppHandle,
+    ffmpeg: &FFmpegEngine,
+    file_path: &str,
+    metadata: ClipMetadata,
+) -> Result<PreparedImport, String> {
     println!("➡️ Importing video: {}", file_path);
 
-    // 1. Probe the file for metadata
-    let duration = ffmpeg_probe(&file_path)?;
+    // 1. Announce the import with the probed duration
+    let duration = metadata.duration;
+    let _ = app.emit(
+        "IMPORT_STARTED",
+        json!({ "file_path": file_path, "estimated_duration": duration }),
+    );
+
+    // 1.5 Hash the ORIGINAL source so re-imports of the same file can be detected
+    let source_hash = match ffmpeg.compute_source_hash(file_path) {
+        Ok(hash) => Some(hash),
+        Err(e) => {
+            println!("⚠️ Could not hash source file: {}", e);
+            None
+        }
+    };
 
     // 2. Transcode to H.264 MP4 (Ensure compatibility)
-    let (uploads_dir, _) = get_video_dirs(&app);
-    let original_path = std::path::Path::new(&file_path);
+    let (uploads_dir, _) = get_video_dirs(app);
+    let original_path = std::path::Path::new(file_path);
     let file_stem = original_path.file_stem().unwrap().to_string_lossy();
 
     // Always use .mp4 extension for the destination
@@ -189,131 +442,1959 @@ pub fn import_video(
     // -preset fast: Balance speed/quality
     // -pix_fmt yuv420p: Ensure broad compatibility
     // -c:a aac: Ensure audio compatibility
-    let status = std::process::Command::new("ffmpeg")
-        .args(&[
-            "-y",
-            "-i",
-            &file_path,
-            "-c:v",
-            "libx264",
-            "-preset",
-            "fast",
-            "-pix_fmt",
-            "yuv420p",
-            "-c:a",
-            "aac",
-            &dest_path_str,
-        ])
-        .output()
-        .map_err(|e| format!("Failed to execute ffmpeg: {}", e))?;
-
-    if !status.status.success() {
-        return Err(format!(
-            "Transcoding failed: {}",
-            String::from_utf8_lossy(&status.stderr)
-        ));
-    }
+    let mut cmd = Command::new("ffmpeg");
+    cmd.args([
+        "-y",
+        "-i",
+        file_path,
+        "-c:v",
+        "libx264",
+        "-preset",
+        "fast",
+        "-pix_fmt",
+        "yuv420p",
+        "-c:a",
+        "aac",
+        &dest_path_str,
+    ]);
+    run_ffmpeg_with_progress(app, cmd, file_path, duration)
+        .map_err(|e| format!("Transcoding failed: {}", e))?;
 
     println!("✅ Transcoding Complete: {:?}", dest_path);
 
-    // 3. Lock state
-    let mut state = engine.state.lock().map_err(|_| "Failed to lock state")?;
+    // 2.5 Optional destructive loudness normalization of the stored copy
+    let general = app
+        .try_state::<PreferenceManager>()
+        .map(|prefs| prefs.get_preferences().general)
+        .unwrap_or_default();
+    let loudness = if general.auto_normalize_on_import {
+        let stats = ffmpeg
+            .normalize_loudness(&dest_path_str, general.target_import_lufs)
+            .map_err(|e| format!("Normalization failed: {}", e))?;
+        Some(json!({
+            "measured_lufs": stats.input_i,
+            "target_lufs": general.target_import_lufs,
+        }))
+    } else {
+        None
+    };
+    log_artifact(
+        app,
+        ArtifactType::Import,
+        &serde_json::to_string_pretty(&json!({
+            "original_path": file_path,
+            "stored_path": dest_path_str,
+            "duration": duration,
+            "loudness": loudness,
+        }))
+        .unwrap_or_default(),
+    );
+
+    // The stored copy is what renders, so record its streams (now H.264)
+    let stored = ffmpeg_probe(&dest_path_str).unwrap_or(metadata);
+
+    Ok(PreparedImport {
+        clip_id: Uuid::new_v4().to_string(),
+        metadata: ClipMetadata { duration, ..stored },
+        source_hash,
+        stored_path: dest_path_str,
+    })
+}
+
+// Append a transcoded file to the end of the default video track, or the
+// default audio track when it has no video
+fn add_imported_clip(
+    app: &AppHandle,
+    state: &mut TimelineState,
+    editing: &EditingPreferences,
+    prepared: PreparedImport,
+) -> Result<(), String> {
+    check_clip_limit(state, editing)?;
 
-    // 4. Create Clip with NEW path
-    let new_clip = Clip {
-        id: Uuid::new_v4().to_string(),
-        track_id: "video_track_1".to_string(),
+    let metadata = prepared.metadata;
+    let duration = metadata.duration;
+    let mut new_clip = Clip {
+        id: prepared.clip_id.clone(),
+        track_id: editing.default_video_track_id.clone(),
         start: state.duration,
         duration,
-        source_file: dest_path_str,
+        source_file: prepared.stored_path,
+        source_hash: prepared.source_hash,
+        width: metadata.width,
+        height: metadata.height,
+        fps: metadata.fps,
+        video_codec: metadata.video_codec,
+        has_audio: metadata.has_audio,
+        ..Default::default()
     };
+    if new_clip.is_audio_only() {
+        new_clip.track_id = editing.default_audio_track_id.clone();
+    }
+    new_clip.sync_frames();
 
-    // 5. Update State
+    state.ensure_track(&new_clip.track_id);
     state.clips.push(new_clip);
     state.duration += duration;
 
     println!("✅ Video Imported. Duration: {:.2}s", duration);
+    let _ = app.emit(
+        "IMPORT_COMPLETE",
+        json!({ "clip_id": prepared.clip_id, "duration": duration }),
+    );
+    Ok(())
+}
 
-    // 6. Emit Update
-    app.emit("STATE_UPDATE", &*state)
-        .map_err(|e| e.to_string())?;
+// --- COMMAND 3b: Import from URL ---
+// Returns a job id immediately; the download and import run in the background and
+// report through DOWNLOAD_PROGRESS / DOWNLOAD_COMPLETE / DOWNLOAD_FAILED.
+#[tauri::command]
+pub fn import_from_url(
+    app: AppHandle,
+    prefs: State<'_, PreferenceManager>,
+    url: String,
+    filename_hint: Option<String>,
+) -> Result<String, String> {
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err(format!("Unsupported URL scheme: {}", url));
+    }
 
-    Ok(state.clone())
+    let job_id = Uuid::new_v4().to_string();
+    let max_bytes = prefs.get_preferences().system.max_download_size_bytes;
+    let (uploads_dir, _) = get_video_dirs(&app);
+    let file_name = download_file_name(&url, filename_hint.as_deref());
+    let temp_path = uploads_dir.join(format!("download_{}_{}", job_id, file_name));
+
+    tauri::async_runtime::spawn(async move {
+        let result = match download_to_file(&app, &url, &temp_path, max_bytes).await {
+            Ok(()) => {
+                let import_app = app.clone();
+                let import_path = temp_path.to_string_lossy().to_string();
+                tokio::task::spawn_blocking(move || {
+                    let engine = import_app.state::<TimelineEngine>();
+                    let ffmpeg = import_app.state::<FFmpegEngine>();
+                    let prefs = import_app.state::<PreferenceManager>();
+                    import_video(import_app.clone(), engine, ffmpeg, prefs, import_path)
+                })
+                .await
+                .map_err(|e| format!("Task join error: {}", e))
+                .and_then(|r| r)
+            }
+            Err(e) => Err(e),
+        };
+
+        // The import transcodes into its own file, so the raw download is never needed
+        let _ = std::fs::remove_file(&temp_path);
+
+        match result {
+            Ok(state) => {
+                let clip_id = state.clips.last().map(|c| c.id.clone());
+                let _ = app.emit("DOWNLOAD_COMPLETE", json!({ "clip_id": clip_id }));
+            }
+            Err(error) => {
+                println!("❌ Download import failed for {}: {}", url, error);
+                let _ = app.emit("DOWNLOAD_FAILED", json!({ "url": url, "error": error }));
+            }
+        }
+    });
+
+    Ok(job_id)
 }
 
-// Helper to run ffprobe
-fn ffmpeg_probe(path: &str) -> Result<f64, String> {
-    use std::env;
-    use std::process::Command;
+// Stream `url` to `dest`, emitting DOWNLOAD_PROGRESS and enforcing `max_bytes`.
+async fn download_to_file(
+    app: &AppHandle,
+    url: &str,
+    dest: &std::path::Path,
+    max_bytes: u64,
+) -> Result<(), String> {
+    use futures::StreamExt;
+    use std::io::Write;
 
-    // Log the PATH for debugging
-    if let Ok(path_env) = env::var("PATH") {
-        println!("🔍 PATH: {}", path_env);
+    let response = reqwest::Client::new()
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("Download request failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Server returned {}", response.status()));
+    }
+
+    let total_bytes = response.content_length();
+    if let Some(total) = total_bytes {
+        if total > max_bytes {
+            return Err(format!(
+                "File is {} bytes, above the {} byte download limit",
+                total, max_bytes
+            ));
+        }
+    }
+
+    let mut file = std::fs::File::create(dest).map_err(|e| e.to_string())?;
+    let mut stream = response.bytes_stream();
+    let mut bytes_downloaded: u64 = 0;
+    let mut last_emit = Instant::now();
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| format!("Download interrupted: {}", e))?;
+        bytes_downloaded += chunk.len() as u64;
+        if bytes_downloaded > max_bytes {
+            return Err(format!("Download exceeded the {} byte limit", max_bytes));
+        }
+        file.write_all(&chunk).map_err(|e| e.to_string())?;
+
+        // Throttle progress events to ~4/s
+        if last_emit.elapsed().as_millis() >= 250 {
+            last_emit = Instant::now();
+            let _ = app.emit(
+                "DOWNLOAD_PROGRESS",
+                json!({
+                    "url": url,
+                    "bytes_downloaded": bytes_downloaded,
+                    "total_bytes": total_bytes,
+                    "percent": total_bytes
+                        .map(|t| (bytes_downloaded as f64 / t.max(1) as f64 * 100.0) as f32),
+                }),
+            );
+        }
+    }
+
+    let _ = app.emit(
+        "DOWNLOAD_PROGRESS",
+        json!({
+            "url": url,
+            "bytes_downloaded": bytes_downloaded,
+            "total_bytes": total_bytes,
+            "percent": total_bytes.map(|_| 100.0f32),
+        }),
+    );
+    Ok(())
+}
+
+// Filesystem-safe name for a download, from the hint or the URL's last path segment.
+fn download_file_name(url: &str, hint: Option<&str>) -> String {
+    let raw = hint.unwrap_or_else(|| {
+        url.split(['?', '#'])
+            .next()
+            .unwrap_or("")
+            .rsplit('/')
+            .next()
+            .unwrap_or("")
+    });
+    let cleaned: String = raw
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        .collect();
+    let cleaned = cleaned.trim_start_matches('.');
+    if cleaned.is_empty() {
+        "download".to_string()
     } else {
-        println!("⚠️ Could not read PATH env var");
+        cleaned.to_string()
     }
+}
 
-    let run_probe = |cmd: &str| -> Result<f64, String> {
-        println!("Trying ffprobe at: {}", cmd);
-        let output = Command::new(cmd)
-            .args(&[
-                "-v",
-                "error",
-                "-show_entries",
-                "format=duration",
-                "-of",
-                "json",
-                path,
-            ])
-            .output()
-            .map_err(|e| format!("Failed to run {}: {}", cmd, e))?;
+// Run an FFmpeg command, emitting IMPORT_PROGRESS events parsed from its stderr.
+// `estimated_duration` (from ffprobe) is used to turn FFmpeg's `time=` into a percentage.
+fn run_ffmpeg_with_progress(
+    app: &AppHandle,
+    mut cmd: Command,
+    file_path: &str,
+    estimated_duration: f64,
+) -> Result<(), String> {
+    let mut child = cmd
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to execute ffmpeg: {}", e))?;
 
-        if !output.status.success() {
+    let stderr = child
+        .stderr
+        .take()
+        .ok_or("Failed to capture ffmpeg stderr")?;
+    let stop = Arc::new(Mutex::new(false));
+    let started = Instant::now();
+
+    let reader = {
+        let stop = stop.clone();
+        let app = app.clone();
+        let file_path = file_path.to_string();
+        std::thread::spawn(move || {
+            // Keep the tail of stderr for error reporting
+            let mut tail: Vec<String> = Vec::new();
+            // FFmpeg terminates progress lines with '\r', everything else with '\n'
+            for chunk in BufReader::new(stderr).split(b'\r') {
+                if *stop.lock().unwrap() {
+                    break;
+                }
+                let Ok(chunk) = chunk else { break };
+                for line in String::from_utf8_lossy(&chunk).lines() {
+                    if let Some(progress) = parse_ffmpeg_progress_line(line) {
+                        let percent = if estimated_duration > 0.0 {
+                            (progress.time_secs / estimated_duration * 100.0).min(100.0) as f32
+                        } else {
+                            0.0
+                        };
+                        let _ = app.emit(
+                            "IMPORT_PROGRESS",
+                            json!({
+                                "file_path": file_path,
+                                "percent": percent,
+                                "fps": progress.fps,
+                                "elapsed_ms": started.elapsed().as_millis() as u64,
+                            }),
+                        );
+                    } else if !line.trim().is_empty() {
+                        tail.push(line.to_string());
+                        if tail.len() > 20 {
+                            tail.remove(0);
+                        }
+                    }
+                }
+            }
+            tail.join("\n")
+        })
+    };
+
+    let status = child
+        .wait()
+        .map_err(|e| format!("Failed to wait for ffmpeg: {}", e))?;
+    *stop.lock().unwrap() = true;
+    let stderr_tail = reader.join().unwrap_or_default();
+
+    if !status.success() {
+        return Err(stderr_tail);
+    }
+    Ok(())
+}
+
+// --- COMMAND 5: Duplicate Detection ---
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DuplicateGroup {
+    pub hash: String,
+    pub clip_ids: Vec<String>,
+}
+
+// Group clips sharing a source hash. Only groups with 2+ clips are returned.
+fn find_duplicate_groups(state: &TimelineState) -> Vec<DuplicateGroup> {
+    let mut by_hash: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for clip in &state.clips {
+        if let Some(hash) = &clip.source_hash {
+            by_hash.entry(hash).or_default().push(clip.id.clone());
+        }
+    }
+
+    by_hash
+        .into_iter()
+        .filter(|(_, ids)| ids.len() > 1)
+        .map(|(hash, clip_ids)| DuplicateGroup {
+            hash: hash.to_string(),
+            clip_ids,
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn find_duplicate_clips(
+    engine: State<'_, TimelineEngine>,
+) -> Result<Vec<DuplicateGroup>, String> {
+    let state = engine.state.lock().map_err(|_| "Failed to lock state")?;
+    Ok(find_duplicate_groups(&state))
+}
+
+// Point every duplicate at the primary's source file.
+// Returns the source files that are no longer referenced by any clip.
+pub(crate) fn consolidate_duplicates_logic(
+    state: &mut TimelineState,
+    primary_clip_id: &str,
+    duplicate_clip_ids: &[String],
+) -> Result<Vec<String>, String> {
+    let primary = state
+        .clips
+        .iter()
+        .find(|c| c.id == primary_clip_id)
+        .ok_or(format!("Primary clip {} not found", primary_clip_id))?;
+    let primary_source = primary.source_file.clone();
+    let primary_hash = primary.source_hash.clone();
+
+    for id in duplicate_clip_ids {
+        let clip = state
+            .clips
+            .iter()
+            .find(|c| c.id == *id)
+            .ok_or(format!("Duplicate clip {} not found", id))?;
+        if primary_hash.is_none() || clip.source_hash != primary_hash {
             return Err(format!(
-                "ffprobe failed: {}",
-                String::from_utf8_lossy(&output.stderr)
+                "Clip {} does not share a source hash with {}",
+                id, primary_clip_id
             ));
         }
+    }
 
-        let output_str = String::from_utf8_lossy(&output.stdout);
-        let json: serde_json::Value = serde_json::from_str(&output_str)
-            .map_err(|e| format!("Failed to parse ffprobe output: {}", e))?;
+    let mut replaced = Vec::new();
+    for clip in state.clips.iter_mut() {
+        if duplicate_clip_ids.contains(&clip.id) && clip.source_file != primary_source {
+            replaced.push(std::mem::replace(
+                &mut clip.source_file,
+                primary_source.clone(),
+            ));
+        }
+    }
 
-        let duration_str = json["format"]["duration"]
-            .as_str()
-            .ok_or("Could not find duration in ffprobe output")?;
+    replaced.sort();
+    replaced.dedup();
+    replaced.retain(|path| !state.clips.iter().any(|c| c.source_file == *path));
+    Ok(replaced)
+}
 
-        duration_str
-            .parse::<f64>()
-            .map_err(|e| format!("Failed to parse duration as float: {}", e))
-    };
+#[tauri::command]
+pub fn consolidate_duplicates(
+    app: AppHandle,
+    engine: State<'_, TimelineEngine>,
+    primary_clip_id: String,
+    duplicate_clip_ids: Vec<String>,
+) -> Result<TimelineState, String> {
+    let mut orphaned = Vec::new();
+    let state = edit_timeline(&app, &engine, |state| {
+        orphaned = consolidate_duplicates_logic(state, &primary_clip_id, &duplicate_clip_ids)?;
+        Ok(())
+    })?;
 
-    // Try default first
-    match run_probe("ffprobe") {
-        Ok(d) => Ok(d),
-        Err(e) => {
-            println!("⚠️ Default ffprobe failed: {}. Trying fallback...", e);
-            // Try Homebrew path
-            run_probe("/opt/homebrew/bin/ffprobe")
+    // Remove redundant transcoded copies, but only ones we own in uploads/
+    let (uploads_dir, _) = get_video_dirs(&app);
+    for path in orphaned {
+        let path = std::path::Path::new(&path);
+        if path.starts_with(&uploads_dir) {
+            match std::fs::remove_file(path) {
+                Ok(_) => println!("🗑️ Removed redundant copy: {:?}", path),
+                Err(e) => println!("⚠️ Failed to remove {:?}: {}", path, e),
+            }
         }
     }
+
+    println!(
+        "✅ Consolidated {} duplicates onto {}",
+        duplicate_clip_ids.len(),
+        primary_clip_id
+    );
+
+    Ok(state)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::timeline::TimelineState;
+// --- COMMAND 6: Timeline Lints ---
+#[tauri::command]
+pub fn analyze_timeline(engine: State<'_, TimelineEngine>) -> Result<Vec<TimelineLint>, String> {
+    let state = engine.state.lock().map_err(|_| "Failed to lock state")?;
+    Ok(analyze_state(&state))
+}
 
-    #[test]
-    fn test_add_test_clips_logic() {
-        let mut state = TimelineState {
-            clips: vec![],
-            duration: 0.0,
-            playhead_time: 0.0,
-            version: 0,
+#[tauri::command]
+pub fn apply_lint_fix(
+    app: AppHandle,
+    engine: State<'_, TimelineEngine>,
+    lint_id: String,
+) -> Result<TimelineState, String> {
+    let state = edit_timeline(&app, &engine, |state| {
+        // Re-run analysis so the fix is computed against the current state
+        let lint = analyze_state(state)
+            .into_iter()
+            .find(|l| l.id == lint_id)
+            .ok_or(format!("Lint '{}' no longer applies", lint_id))?;
+
+        apply_fix(state, &lint)?;
+        validate_state_invariants(state)
+            .map_err(|e| format!("Lint fix rejected - invariant violated: {}", e))
+    })?;
+
+    println!("✅ Applied lint fix: {}", lint_id);
+
+    Ok(state)
+}
+
+// --- COMMAND 6b: Loudness Normalization ---
+// Normalizes the clip's stored file in place to the import LUFS target. Every
+// clip that plays the file gets its cached renders and loudness measurements
+// dropped and its hash redone.
+#[tauri::command]
+pub fn normalize_clip(
+    app: AppHandle,
+    engine: State<'_, TimelineEngine>,
+    ffmpeg: State<'_, FFmpegEngine>,
+    prefs: State<'_, PreferenceManager>,
+    clip_id: String,
+) -> Result<(), String> {
+    let source_file = {
+        let state = engine.state.lock().map_err(|_| "Failed to lock state")?;
+        state
+            .clips
+            .iter()
+            .find(|c| c.id == clip_id)
+            .map(|c| c.source_file.clone())
+            .ok_or(format!("Clip '{}' not found", clip_id))?
+    };
+
+    let target_lufs = prefs.get_preferences().general.target_import_lufs;
+    ffmpeg.normalize_loudness(&source_file, target_lufs)?;
+    let source_hash = ffmpeg.compute_source_hash(&source_file).ok();
+
+    // The file on disk changed, so this isn't an undo step
+    let mut state = engine.state.lock().map_err(|_| "Failed to lock state")?;
+    for clip in state
+        .clips
+        .iter_mut()
+        .filter(|c| c.source_file == source_file)
+    {
+        ffmpeg.invalidate_render_cache_for_clip(&clip.id);
+        clip.source_hash = source_hash.clone();
+        clip.audio_loudness = None;
+    }
+    state.version += 1;
+    app.emit("STATE_UPDATE", &*state)
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// --- COMMAND 7: Merge Short Clips ---
+// Absorbs each clip shorter than `min_duration` into its longer neighbour on the
+// same track (the neighbour is extended over the short clip's span). Only
+// neighbours that touch the short clip and have enough unused source to cover
+// it qualify. Returns the ids of the removed clips; clips with no such
+// neighbour are left alone.
+pub(crate) fn merge_short_clips_logic(state: &mut TimelineState, min_duration: f64) -> Vec<String> {
+    const TOUCH_TOLERANCE: f64 = 0.001;
+    let mut removed = Vec::new();
+
+    loop {
+        let mut order: Vec<usize> = (0..state.clips.len()).collect();
+        order.sort_by(|&a, &b| {
+            let (ca, cb) = (&state.clips[a], &state.clips[b]);
+            ca.track_id
+                .cmp(&cb.track_id)
+                .then(ca.start.partial_cmp(&cb.start).unwrap())
+        });
+
+        let mut merge = None;
+        for (pos, &idx) in order.iter().enumerate() {
+            let clip = &state.clips[idx];
+            if clip.effective_duration() >= min_duration {
+                continue;
+            }
+            let needed = clip.effective_duration() - TOUCH_TOLERANCE;
+            let same_track = |j: &usize| state.clips[*j].track_id == clip.track_id;
+            let prev = pos.checked_sub(1).map(|p| order[p]).filter(|j| {
+                let prev = &state.clips[*j];
+                same_track(j)
+                    && (clip.start - prev.end()).abs() < TOUCH_TOLERANCE
+                    && (prev.duration - prev.source_out()) / prev.speed_multiplier >= needed
+            });
+            let next = order.get(pos + 1).copied().filter(|j| {
+                let next = &state.clips[*j];
+                same_track(j)
+                    && (next.start - clip.end()).abs() < TOUCH_TOLERANCE
+                    && (next.source_in() - next.source_offset) / next.speed_multiplier >= needed
+            });
+            let neighbour = match (prev, next) {
+                (Some(p), Some(n))
+                    if state.clips[n].effective_duration()
+                        > state.clips[p].effective_duration() =>
+                {
+                    n
+                }
+                (Some(p), _) => p,
+                (None, Some(n)) => n,
+                (None, None) => continue,
+            };
+            merge = Some((idx, neighbour));
+            break;
+        }
+
+        let Some((short_idx, neighbour_idx)) = merge else {
+            break;
         };
-        add_test_clips_logic(&mut state, 5);
-        assert_eq!(state.clips.len(), 5);
-        assert_eq!(state.duration, 25.0);
+        let short = state.clips[short_idx].clone();
+        let neighbour = &mut state.clips[neighbour_idx];
+        if neighbour.start < short.start {
+            neighbour.set_effective_duration(short.end() - neighbour.start);
+        } else {
+            // Pull the in point back so the neighbour starts where the short clip did
+            let shift = (neighbour.start - short.start) * neighbour.speed_multiplier;
+            neighbour.edit_in = Some((neighbour.source_in() - shift).max(neighbour.source_offset));
+            neighbour.start = short.start;
+        }
+        state.clips.remove(short_idx);
+        removed.push(short.id);
+    }
+
+    removed
+}
+
+#[tauri::command]
+pub fn merge_short_clips(
+    app: AppHandle,
+    engine: State<'_, TimelineEngine>,
+    min_duration: f64,
+) -> Result<TimelineState, String> {
+    if min_duration <= 0.0 {
+        return Err(format!("Invalid minimum duration: {}", min_duration));
+    }
+
+    // Nothing to merge means no undo step
+    {
+        let state = engine.state.lock().map_err(|_| "Failed to lock state")?;
+        if merge_short_clips_logic(&mut state.clone(), min_duration).is_empty() {
+            return Ok(state.clone());
+        }
+    }
+
+    let mut removed = Vec::new();
+    let state = edit_timeline(&app, &engine, |state| {
+        removed = merge_short_clips_logic(state, min_duration);
+        state.recalculate_duration();
+        validate_state_invariants(state)
+            .map_err(|e| format!("Merge rejected - invariant violated: {}", e))
+    })?;
+
+    println!("✅ Merged {} short clips: {:?}", removed.len(), removed);
+
+    Ok(state)
+}
+
+// Bake every clip's trim handles into `source_offset` and `duration`; returns
+// the ids of the clips touched.
+pub(crate) fn apply_virtual_trim_logic(state: &mut TimelineState) -> Vec<String> {
+    state
+        .clips
+        .iter_mut()
+        .filter(|c| c.has_virtual_trim())
+        .map(|c| {
+            c.apply_virtual_trim();
+            c.id.clone()
+        })
+        .collect()
+}
+
+/// Make every clip's virtual trim permanent. The clips keep playing the same
+/// frames, but `reset_virtual_trim` can no longer bring back what was cut.
+#[tauri::command]
+pub fn apply_virtual_trim(
+    app: AppHandle,
+    engine: State<'_, TimelineEngine>,
+) -> Result<TimelineState, String> {
+    {
+        let state = engine.state.lock().map_err(|_| "Failed to lock state")?;
+        if !state.clips.iter().any(|c| c.has_virtual_trim()) {
+            return Ok(state.clone());
+        }
+    }
+
+    let mut applied = Vec::new();
+    let state = edit_timeline(&app, &engine, |state| {
+        applied = apply_virtual_trim_logic(state);
+        state.recalculate_duration();
+        validate_state_invariants(state)
+            .map_err(|e| format!("Apply trim rejected - invariant violated: {}", e))
+    })?;
+
+    println!("✅ Applied virtual trim to {} clips", applied.len());
+
+    Ok(state)
+}
+
+/// Clear a clip's trim handles, restoring the source up to its last permanent trim.
+#[tauri::command]
+pub fn reset_virtual_trim(
+    app: AppHandle,
+    engine: State<'_, TimelineEngine>,
+    clip_id: String,
+) -> Result<TimelineState, String> {
+    edit_timeline(&app, &engine, |state| {
+        reset_virtual_trim_logic(state, &clip_id)?;
+        state.recalculate_duration();
+        // Restoring the full length can run into the next clip
+        validate_state_invariants(state)
+            .map_err(|e| format!("Reset trim rejected - invariant violated: {}", e))
+    })
+}
+
+// Clear the trim handles of `clip_id`, moving it back so the source frames
+// that were trimmed off the head land before the ones already playing.
+pub(crate) fn reset_virtual_trim_logic(
+    state: &mut TimelineState,
+    clip_id: &str,
+) -> Result<(), String> {
+    let clip = state
+        .clips
+        .iter_mut()
+        .find(|c| c.id == clip_id)
+        .ok_or(format!("Clip {} not found", clip_id))?;
+    let head = clip.source_in() - clip.source_offset;
+    clip.start = (clip.start - head / clip.speed_multiplier).max(0.0);
+    clip.edit_in = None;
+    clip.edit_out = None;
+    Ok(())
+}
+
+// Order clips by (track, start) so storage order matches timeline order.
+pub(crate) fn normalize_clip_order_logic(state: &mut TimelineState) {
+    state.clips.sort_by(|a, b| {
+        a.track_id
+            .cmp(&b.track_id)
+            .then(a.start.partial_cmp(&b.start).unwrap())
+    });
+}
+
+// Close every gap on `track_id` by butting each clip against the one before it.
+// Returns the number of clips that moved.
+pub(crate) fn normalize_clip_starts_logic(state: &mut TimelineState, track_id: &str) -> usize {
+    normalize_clip_order_logic(state);
+    let mut cursor = 0.0;
+    let mut moved = 0;
+    for clip in state.clips.iter_mut().filter(|c| c.track_id == track_id) {
+        if (clip.start - cursor).abs() > 1e-9 {
+            clip.start = cursor;
+            moved += 1;
+        }
+        cursor = clip.end();
+    }
+    moved
+}
+
+// Round every clip's start and end to the nearest frame at `fps`, keeping at
+// least one frame per clip. The end moves by adjusting the out handle, so clips
+// that touched (to within drift) touch exactly, and frame counts are redone.
+// Returns the clips that changed.
+pub(crate) fn normalize_clip_positions_logic(state: &mut TimelineState, fps: f64) -> usize {
+    let to_frame = |t: f64| (t * fps).round() / fps;
+    let mut changed = 0;
+    for clip in &mut state.clips {
+        let start = to_frame(clip.start).max(0.0);
+        let end = to_frame(clip.end()).max(start + 1.0 / fps);
+        let edit_out =
+            (clip.source_in() + (end - start) * clip.speed_multiplier).min(clip.duration);
+        let moved = (clip.start - start).abs() > 1e-9;
+        let trimmed = (clip.source_out() - edit_out).abs() > 1e-9;
+        clip.start = start;
+        if trimmed {
+            clip.edit_out = Some(edit_out);
+        }
+        clip.sync_frames();
+        if moved || trimmed {
+            changed += 1;
+        }
+    }
+    changed
+}
+
+#[tauri::command]
+pub fn normalize_clip_order(
+    app: AppHandle,
+    engine: State<'_, TimelineEngine>,
+) -> Result<TimelineState, String> {
+    edit_timeline(&app, &engine, |state| {
+        normalize_clip_order_logic(state);
+        validate_state_invariants(state)
+            .map_err(|e| format!("Normalize rejected - invariant violated: {}", e))
+    })
+}
+
+/// Snap clip positions to the frame grid at `fps`, clearing accumulated drift.
+#[tauri::command]
+pub fn normalize_clip_positions(
+    app: AppHandle,
+    engine: State<'_, TimelineEngine>,
+    prefs: State<'_, PreferenceManager>,
+    fps: f64,
+) -> Result<TimelineState, String> {
+    if !(fps > 0.0 && fps <= 240.0) {
+        return Err(format!("Invalid fps {} (must be in (0, 240])", fps));
+    }
+    let user_prefs = prefs.get_preferences();
+    let mut changed = 0;
+    let state = edit_timeline(&app, &engine, |state| {
+        changed = normalize_clip_positions_logic(state, fps);
+        state.recalculate_duration();
+        validate_state_invariants_with(state, &user_prefs.editing)
+            .map_err(|e| format!("Normalize rejected - invariant violated: {}", e))?;
+        enforce_gap_policy(state, &user_prefs.general)
+    })?;
+
+    println!("✅ Snapped {} clips to the {} fps frame grid", changed, fps);
+
+    Ok(state)
+}
+
+#[tauri::command]
+pub fn normalize_clip_starts(
+    app: AppHandle,
+    engine: State<'_, TimelineEngine>,
+    track_id: String,
+) -> Result<TimelineState, String> {
+    let mut moved = 0;
+    let state = edit_timeline(&app, &engine, |state| {
+        moved = normalize_clip_starts_logic(state, &track_id);
+        state.recalculate_duration();
+        validate_state_invariants(state)
+            .map_err(|e| format!("Normalize rejected - invariant violated: {}", e))
+    })?;
+
+    println!("✅ Compacted {} clips on track {}", moved, track_id);
+
+    Ok(state)
+}
+
+fn reorder_and_emit(
+    app: &AppHandle,
+    engine: &TimelineEngine,
+    clip_id: &str,
+    target_index: usize,
+    preserve_gaps: bool,
+) -> Result<TimelineState, String> {
+    edit_timeline(app, engine, |state| {
+        reorder_clip(state, clip_id, target_index, preserve_gaps)?;
+        state.recalculate_duration();
+        validate_state_invariants(state)
+            .map_err(|e| format!("Reorder rejected - invariant violated: {}", e))
+    })
+}
+
+/// Wipe `[start_time, end_time]` on one track, or every unlocked track when
+/// `track_id` is `None`, closing the hole when `auto_ripple_edits` is on.
+#[tauri::command]
+pub fn delete_clips_in_range(
+    app: AppHandle,
+    engine: State<'_, TimelineEngine>,
+    prefs: State<'_, PreferenceManager>,
+    start_time: f64,
+    end_time: f64,
+    track_id: Option<String>,
+) -> Result<TimelineState, String> {
+    let user_prefs = prefs.get_preferences();
+    let mut removed = 0;
+    let state = edit_timeline(&app, &engine, |state| {
+        removed = action_router::delete_clips_in_range(
+            state,
+            start_time,
+            end_time,
+            track_id.as_deref(),
+            user_prefs.general.auto_ripple_edits,
+        )?;
+        validate_state_invariants_with(state, &user_prefs.editing)
+            .map_err(|e| format!("Range delete rejected - invariant violated: {}", e))?;
+        enforce_gap_policy(state, &user_prefs.general)
+    })?;
+
+    prefs.log_interaction(
+        "MANUAL_DELETE_RANGE",
+        json!({
+            "start_time": start_time,
+            "end_time": end_time,
+            "track_id": track_id,
+            "removed_clips": removed,
+        }),
+    );
+
+    Ok(state)
+}
+
+/// Move a clip to `target_index` on its track and pack the track back to back.
+#[tauri::command]
+pub fn reorder_clips(
+    app: AppHandle,
+    engine: State<'_, TimelineEngine>,
+    clip_id: String,
+    target_index: usize,
+) -> Result<TimelineState, String> {
+    reorder_and_emit(&app, &engine, &clip_id, target_index, false)
+}
+
+/// Ripple-aware reorder: move a clip to `target_index` keeping the gaps between clips.
+#[tauri::command]
+pub fn reorder_clips_preserve_gaps(
+    app: AppHandle,
+    engine: State<'_, TimelineEngine>,
+    clip_id: String,
+    target_index: usize,
+) -> Result<TimelineState, String> {
+    reorder_and_emit(&app, &engine, &clip_id, target_index, true)
+}
+
+/// Copy a clip with a new ID, at `position` on its track or at the end of the timeline.
+#[tauri::command]
+pub fn duplicate_clip(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    clip_id: String,
+    position: Option<f64>,
+) -> Result<TimelineState, String> {
+    let state = handlers::duplicate_clip(&app_state, &clip_id, position)?;
+    app.emit("STATE_UPDATE", &state)
+        .map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+/// Open `gap_duration_s` of space on `track_id` at `at_time` by pushing later
+/// clips right, ready for a clip to be dropped in.
+#[tauri::command]
+pub fn insert_gap_at_time(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    track_id: String,
+    at_time: f64,
+    gap_duration_s: f64,
+) -> Result<TimelineState, String> {
+    let state = handlers::insert_gap_at_time(&app_state, &track_id, at_time, gap_duration_s)?;
+    app.emit("STATE_UPDATE", &state)
+        .map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+/// Stop normalizing clip loudness in renders.
+#[tauri::command]
+pub fn clear_loudness_normalization(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+) -> Result<TimelineState, String> {
+    let state = handlers::clear_loudness_normalization(&app_state)?;
+    app.emit("STATE_UPDATE", &state)
+        .map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+/// Name and color a clip for grouping; `None` clears either.
+#[tauri::command]
+pub fn update_clip_label(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    clip_id: String,
+    label: Option<String>,
+    color_hex: Option<String>,
+) -> Result<TimelineState, String> {
+    let state = handlers::update_clip_label(&app_state, &clip_id, label, color_hex)?;
+    app.emit("STATE_UPDATE", &state)
+        .map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+#[tauri::command]
+pub fn tag_clip(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    clip_id: String,
+    tags: Vec<String>,
+) -> Result<TimelineState, String> {
+    let state = handlers::tag_clip(&app_state, &clip_id, &tags)?;
+    app.emit("STATE_UPDATE", &state)
+        .map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+#[tauri::command]
+pub fn untag_clip(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    clip_id: String,
+    tags: Vec<String>,
+) -> Result<TimelineState, String> {
+    let state = handlers::untag_clip(&app_state, &clip_id, &tags)?;
+    app.emit("STATE_UPDATE", &state)
+        .map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+#[tauri::command]
+pub fn get_clips_by_tag(app_state: State<'_, AppState>, tag: String) -> Result<Vec<Clip>, String> {
+    handlers::get_clips_by_tag(&app_state, &tag)
+}
+
+#[tauri::command]
+pub fn copy_clips(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    clip_ids: Vec<String>,
+) -> Result<TimelineState, String> {
+    let state = handlers::copy_clips(&app_state, &clip_ids)?;
+    app.emit("STATE_UPDATE", &state)
+        .map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+#[tauri::command]
+pub fn cut_clips(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    clip_ids: Vec<String>,
+) -> Result<TimelineState, String> {
+    let state = handlers::cut_clips(&app_state, &clip_ids)?;
+    app.emit("STATE_UPDATE", &state)
+        .map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+/// Insert the clipboard on `track_id` with its earliest clip at `start_time`.
+#[tauri::command]
+pub fn paste_clips(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    start_time: f64,
+    track_id: String,
+) -> Result<TimelineState, String> {
+    let state = handlers::paste_clips(&app_state, start_time, &track_id)?;
+    app.emit("STATE_UPDATE", &state)
+        .map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+/// Register a crossfade between two clips on the same track, replacing any
+/// existing one for the pair. The clips may then overlap by up to `duration`.
+#[tauri::command]
+pub fn register_transition(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    clip_a_id: String,
+    clip_b_id: String,
+    duration: f64,
+) -> Result<TimelineState, String> {
+    let state = handlers::register_transition(&app_state, clip_a_id, clip_b_id, duration)?;
+    app.emit("STATE_UPDATE", &state)
+        .map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+/// Duck `clip_id`'s audio while `trigger_clip_id` (an audio-track clip) plays,
+/// or stop ducking it when `None`.
+#[tauri::command]
+pub fn set_clip_audio_ducking(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    clip_id: String,
+    trigger_clip_id: Option<String>,
+) -> Result<TimelineState, String> {
+    let state = handlers::set_clip_audio_ducking(&app_state, &clip_id, trigger_clip_id)?;
+    app.emit("STATE_UPDATE", &state)
+        .map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+#[tauri::command]
+pub fn set_loop_region(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    start: f64,
+    end: f64,
+) -> Result<(), String> {
+    let state = handlers::set_loop_region(&app_state, start, end)?;
+    app.emit("STATE_UPDATE", &state).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn clear_loop_region(app: AppHandle, app_state: State<'_, AppState>) -> Result<(), String> {
+    if let Some(state) = handlers::clear_loop_region(&app_state)? {
+        app.emit("STATE_UPDATE", &state)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_chapter_markers(app_state: State<'_, AppState>) -> Result<Vec<ChapterMarker>, String> {
+    handlers::get_chapter_markers(&app_state)
+}
+
+#[tauri::command]
+pub fn add_chapter_marker(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    time_s: f64,
+    label: String,
+    color_hex: Option<String>,
+) -> Result<TimelineState, String> {
+    let state = handlers::add_chapter_marker(&app_state, time_s, label, color_hex)?;
+    app.emit("STATE_UPDATE", &state)
+        .map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+#[tauri::command]
+pub fn remove_chapter_marker(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    marker_id: String,
+) -> Result<TimelineState, String> {
+    let state = handlers::remove_chapter_marker(&app_state, &marker_id)?;
+    app.emit("STATE_UPDATE", &state)
+        .map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+#[tauri::command]
+pub fn update_chapter_marker(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    marker_id: String,
+    time_s: Option<f64>,
+    label: Option<String>,
+    color_hex: Option<String>,
+) -> Result<TimelineState, String> {
+    let state = handlers::update_chapter_marker(&app_state, &marker_id, time_s, label, color_hex)?;
+    app.emit("STATE_UPDATE", &state)
+        .map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+#[tauri::command]
+pub fn list_subtitle_tracks(app_state: State<'_, AppState>) -> Result<Vec<SubtitleTrack>, String> {
+    handlers::list_subtitle_tracks(&app_state)
+}
+
+/// Attach an SRT file to the timeline, shifted by `offset_s` seconds on export.
+#[tauri::command]
+pub fn import_subtitle_file(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    srt_path: String,
+    offset_s: f64,
+) -> Result<TimelineState, String> {
+    let state = handlers::import_subtitle_file(&app_state, srt_path, offset_s)?;
+    app.emit("STATE_UPDATE", &state)
+        .map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+/// Leave a track out of the audio mix (or put it back).
+#[tauri::command]
+pub fn set_track_muted(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    track_id: String,
+    muted: bool,
+) -> Result<TimelineState, String> {
+    let state = handlers::set_track_muted(&app_state, &track_id, muted)?;
+    app.emit("STATE_UPDATE", &state)
+        .map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+/// While any track is soloed, only soloed tracks are heard.
+#[tauri::command]
+pub fn set_track_soloed(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    track_id: String,
+    soloed: bool,
+) -> Result<TimelineState, String> {
+    let state = handlers::set_track_soloed(&app_state, &track_id, soloed)?;
+    app.emit("STATE_UPDATE", &state)
+        .map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+/// Locked tracks reject edit plans and direct edits to their clips.
+#[tauri::command]
+pub fn set_track_locked(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    track_id: String,
+    locked: bool,
+) -> Result<TimelineState, String> {
+    let state = handlers::set_track_locked(&app_state, &track_id, locked)?;
+    app.emit("STATE_UPDATE", &state)
+        .map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+#[tauri::command]
+pub fn remove_subtitle_track(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    track_id: String,
+) -> Result<TimelineState, String> {
+    let state = handlers::remove_subtitle_track(&app_state, &track_id)?;
+    app.emit("STATE_UPDATE", &state)
+        .map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+/// Markers as "ffmetadata", "youtube" or "podlove" text.
+#[tauri::command]
+pub fn export_chapter_markers(
+    app_state: State<'_, AppState>,
+    format: String,
+) -> Result<String, String> {
+    let state = handlers::get_timeline_state(&app_state)?;
+    chapters::export_chapter_markers(&state, &format)
+}
+
+/// Step back to the state before the last edit plan.
+#[tauri::command]
+pub fn undo(app: AppHandle, engine: State<'_, TimelineEngine>) -> Result<TimelineState, String> {
+    let state = engine.undo()?;
+    app.emit("STATE_UPDATE", &state)
+        .map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+#[tauri::command]
+pub fn redo(app: AppHandle, engine: State<'_, TimelineEngine>) -> Result<TimelineState, String> {
+    let state = engine.redo()?;
+    app.emit("STATE_UPDATE", &state)
+        .map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+// Apply a single-action plan built from a direct edit and record it like an AI
+// edit: artifact, interaction history, cache invalidation.
+fn apply_manual_edit(
+    app: &AppHandle,
+    engine: &TimelineEngine,
+    prefs: &PreferenceManager,
+    operation: &str,
+    plan: EditPlan,
+) -> Result<TimelineState, String> {
+    let mut state = engine.state.lock().map_err(|_| "Failed to lock state")?;
+    let snapshot = state.clone();
+
+    let user_prefs = prefs.get_preferences();
+    let applied = apply_plan_to_state_with(&mut state, &plan, &user_prefs.editing)
+        .and_then(|skipped| enforce_gap_policy(&state, &user_prefs.general).map(|_| skipped));
+    let skipped = match applied {
+        Ok(skipped) => skipped,
+        Err(e) => {
+            *state = snapshot;
+            log_artifact(
+                app,
+                ArtifactType::Error,
+                &format!("Manual {} rolled back: {}", operation, e),
+            );
+            return Err(e);
+        }
+    };
+    if skipped.len() == plan.actions.len() {
+        *state = snapshot;
+        return Ok(state.clone());
+    }
+    state.version += 1;
+    engine.push_undo_snapshot(&mut state, snapshot);
+
+    app.emit("STATE_UPDATE", &*state)
+        .map_err(|e| e.to_string())?;
+
+    if let Some(ffmpeg) = app.try_state::<FFmpegEngine>() {
+        for action in &plan.actions {
+            ffmpeg.invalidate_render_cache_for_clip(&action.target_clip_id);
+        }
+    }
+    let plan_json = serde_json::to_string_pretty(&plan).unwrap_or_default();
+    log_artifact(
+        app,
+        ArtifactType::ManualEdit {
+            operation: operation.to_string(),
+        },
+        &plan_json,
+    );
+    prefs.log_interaction(
+        &format!("MANUAL_{}", operation.to_uppercase()),
+        json!({ "plan": plan }),
+    );
+
+    Ok(state.clone())
+}
+
+#[tauri::command]
+pub fn trim_clip(
+    app: AppHandle,
+    engine: State<'_, TimelineEngine>,
+    prefs: State<'_, PreferenceManager>,
+    clip_id: String,
+    trim_start_delta: Option<f64>,
+    trim_end_delta: Option<f64>,
+) -> Result<TimelineState, String> {
+    let plan = generate_plan_for_trim(&clip_id, trim_start_delta, trim_end_delta);
+    apply_manual_edit(&app, &engine, &prefs, "trim", plan)
+}
+
+#[tauri::command]
+pub fn move_clip(
+    app: AppHandle,
+    engine: State<'_, TimelineEngine>,
+    prefs: State<'_, PreferenceManager>,
+    clip_id: String,
+    new_start_time: f64,
+) -> Result<TimelineState, String> {
+    let plan = generate_plan_for_move(&clip_id, new_start_time);
+    apply_manual_edit(&app, &engine, &prefs, "move", plan)
+}
+
+#[tauri::command]
+pub fn split_clip(
+    app: AppHandle,
+    engine: State<'_, TimelineEngine>,
+    prefs: State<'_, PreferenceManager>,
+    clip_id: String,
+    split_time: f64,
+) -> Result<TimelineState, String> {
+    let plan = generate_plan_for_split(&clip_id, split_time);
+    apply_manual_edit(&app, &engine, &prefs, "split", plan)
+}
+
+/// What ffprobe reports about a file, without importing it.
+#[derive(Serialize, Debug, Clone)]
+pub struct VideoMetadata {
+    pub path: String,
+    pub duration: f64,
+    /// None when the file has no readable video stream.
+    pub video: Option<VideoCodecInfo>,
+}
+
+fn probe_video_metadata(path: &str) -> Result<VideoMetadata, String> {
+    let duration = ffmpeg_probe(path)?.duration;
+    let video = FFmpegEngine::new().detect_clip_codec(path).ok();
+    Ok(VideoMetadata {
+        path: path.to_string(),
+        duration,
+        video,
+    })
+}
+
+// Run `probe` on every path at once; results line up with `paths`. Probing is
+// cheap next to transcoding, so it isn't rate-limited.
+pub(crate) async fn probe_paths_concurrently<T: Send + 'static>(
+    paths: &[String],
+    probe: fn(&str) -> Result<T, String>,
+) -> Vec<Result<T, String>> {
+    let tasks = paths
+        .iter()
+        .cloned()
+        .map(|path| tokio::task::spawn_blocking(move || probe(&path)));
+    futures::future::join_all(tasks)
+        .await
+        .into_iter()
+        .map(|joined| joined.map_err(|e| format!("Probe task failed: {}", e))?)
+        .collect()
+}
+
+#[tauri::command]
+pub async fn probe_video_files_batch(paths: Vec<String>) -> Vec<Result<VideoMetadata, String>> {
+    probe_paths_concurrently(&paths, probe_video_metadata).await
+}
+
+/// Duration and stream properties of a media file, as stored on imported clips.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ClipMetadata {
+    pub duration: f64,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fps: Option<f64>,
+    pub video_codec: Option<String>,
+    pub has_audio: bool,
+}
+
+/// Read `ClipMetadata` out of ffprobe's `-show_entries format=duration:stream=...`
+/// JSON. The first video stream wins; cover art counts as video.
+pub fn parse_probe_output(json: &serde_json::Value) -> Result<ClipMetadata, String> {
+    let duration = json["format"]["duration"]
+        .as_str()
+        .ok_or("Could not find duration in ffprobe output")?
+        .parse::<f64>()
+        .map_err(|e| format!("Failed to parse duration as float: {}", e))?;
+
+    let streams = json["streams"].as_array().map(Vec::as_slice).unwrap_or(&[]);
+    let of_type = |kind: &str| streams.iter().find(|s| s["codec_type"] == kind);
+    let video = of_type("video");
+    let dimension = |key: &str| video.and_then(|s| s[key].as_u64()).map(|v| v as u32);
+
+    Ok(ClipMetadata {
+        duration,
+        width: dimension("width"),
+        height: dimension("height"),
+        fps: video
+            .and_then(|s| s["r_frame_rate"].as_str())
+            .and_then(crate::ffmpeg::parse_frame_rate),
+        video_codec: video
+            .and_then(|s| s["codec_name"].as_str())
+            .map(str::to_string),
+        has_audio: of_type("audio").is_some(),
+    })
+}
+
+/// Probe a file's duration and streams without importing it.
+#[tauri::command]
+pub async fn get_media_info(file_path: String) -> Result<ClipMetadata, String> {
+    tokio::task::spawn_blocking(move || ffmpeg_probe(&file_path))
+        .await
+        .map_err(|e| format!("Probe task failed: {}", e))?
+}
+
+// Helper to run ffprobe
+fn ffmpeg_probe(path: &str) -> Result<ClipMetadata, String> {
+    use std::env;
+    use std::process::Command;
+
+    // Log the PATH for debugging
+    if let Ok(path_env) = env::var("PATH") {
+        println!("🔍 PATH: {}", path_env);
+    } else {
+        println!("⚠️ Could not read PATH env var");
+    }
+
+    let run_probe = |cmd: &str| -> Result<ClipMetadata, String> {
+        println!("Trying ffprobe at: {}", cmd);
+        let output = Command::new(cmd)
+            .args([
+                "-v",
+                "error",
+                "-show_entries",
+                "format=duration:stream=codec_type,codec_name,width,height,r_frame_rate",
+                "-of",
+                "json",
+                path,
+            ])
+            .output()
+            .map_err(|e| format!("Failed to run {}: {}", cmd, e))?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "ffprobe failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        let output_str = String::from_utf8_lossy(&output.stdout);
+        let json: serde_json::Value = serde_json::from_str(&output_str)
+            .map_err(|e| format!("Failed to parse ffprobe output: {}", e))?;
+
+        parse_probe_output(&json)
+    };
+
+    // Try default first
+    match run_probe("ffprobe") {
+        Ok(d) => Ok(d),
+        Err(e) => {
+            println!("⚠️ Default ffprobe failed: {}. Trying fallback...", e);
+            // Try Homebrew path
+            run_probe("/opt/homebrew/bin/ffprobe")
+        }
+    }
+}
+
+#[tauri::command]
+pub fn set_project_metadata(
+    project: State<'_, ProjectMetadataStore>,
+    title: Option<String>,
+    description: Option<String>,
+    author: Option<String>,
+    tags: Vec<String>,
+) -> Result<(), String> {
+    let mut metadata = project.metadata.lock().unwrap();
+    *metadata = ProjectMetadata {
+        title,
+        description,
+        author,
+        tags,
+    };
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_project_metadata(project: State<'_, ProjectMetadataStore>) -> ProjectMetadata {
+    project.metadata.lock().unwrap().clone()
+}
+
+fn now_millis() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+/// Write the timeline, project metadata and a preferences snapshot to `path` as
+/// pretty-printed JSON.
+#[tauri::command]
+pub fn save_project(
+    engine: State<'_, TimelineEngine>,
+    project: State<'_, ProjectMetadataStore>,
+    prefs: State<'_, PreferenceManager>,
+    path: String,
+) -> Result<(), String> {
+    // Keep the original creation time when overwriting an existing project
+    let created_at = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|json| read_project_file_info(&json).ok())
+        .map(|info| info.created_at)
+        .filter(|&t| t > 0)
+        .unwrap_or_else(now_millis);
+
+    let metadata = project.metadata.lock().unwrap().clone();
+    // The interaction log is personal history and the API key a secret;
+    // neither belongs in a shareable project file, and nor does the clipboard
+    let mut preferences = prefs.get_preferences();
+    preferences.interactions.clear();
+    preferences.general.llm_api_key = None;
+    let mut timeline = engine
+        .state
+        .lock()
+        .map_err(|_| "Failed to lock state")?
+        .clone();
+    timeline.clipboard = None;
+    let file = ProjectFile {
+        project_version: CURRENT_PROJECT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at,
+        last_saved_at: now_millis(),
+        timeline,
+        project_metadata: Some(metadata),
+        preferences: Some(preferences),
+    };
+
+    let json = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to save project: {}", e))
+}
+
+#[tauri::command]
+pub fn load_project(
+    app: AppHandle,
+    engine: State<'_, TimelineEngine>,
+    project: State<'_, ProjectMetadataStore>,
+    path: String,
+) -> Result<TimelineState, String> {
+    let json =
+        std::fs::read_to_string(&path).map_err(|e| format!("Failed to read project: {}", e))?;
+    let raw: serde_json::Value =
+        serde_json::from_str(&json).map_err(|e| format!("Invalid project file: {}", e))?;
+
+    let version = detect_project_version(&raw);
+    if version < CURRENT_PROJECT_VERSION {
+        // Migrated files are only written back on the next save, but keep the original anyway
+        let backup = format!("{}.bak.{}", path, now_millis());
+        std::fs::copy(&path, &backup)
+            .map_err(|e| format!("Failed to back up project before migrating: {}", e))?;
+        println!(
+            "📦 Migrating project v{} -> v{} (backup: {})",
+            version, CURRENT_PROJECT_VERSION, backup
+        );
+    }
+    let file = upgrade_project(raw)?;
+
+    let mut loaded = file.timeline;
+    loaded.backfill_tracks();
+    loaded.recalculate_duration();
+    validate_state_invariants(&loaded)
+        .map_err(|e| format!("Project rejected - invariant violated: {}", e))?;
+
+    let mut state = engine.state.lock().map_err(|_| "Failed to lock state")?;
+    // Stay ahead of the current version so the frontend never ignores the update
+    loaded.version = loaded.version.max(state.version + 1);
+    *state = loaded;
+    engine.clear_history(&mut state);
+    *project.metadata.lock().unwrap() = file.project_metadata.unwrap_or_default();
+
+    app.emit("STATE_UPDATE", &*state)
+        .map_err(|e| e.to_string())?;
+
+    Ok(state.clone())
+}
+
+/// Same as `load_project`, under the name the file menu uses.
+#[tauri::command]
+pub fn open_project(
+    app: AppHandle,
+    engine: State<'_, TimelineEngine>,
+    project: State<'_, ProjectMetadataStore>,
+    file_path: String,
+) -> Result<TimelineState, String> {
+    load_project(app, engine, project, file_path)
+}
+
+/// Start over with an empty timeline, no metadata and no undo history.
+#[tauri::command]
+pub fn new_project(
+    app: AppHandle,
+    engine: State<'_, TimelineEngine>,
+    project: State<'_, ProjectMetadataStore>,
+) -> Result<TimelineState, String> {
+    let mut state = engine.state.lock().map_err(|_| "Failed to lock state")?;
+    let version = state.version + 1;
+    *state = TimelineState {
+        version,
+        ..Default::default()
+    };
+    engine.clear_history(&mut state);
+    *project.metadata.lock().unwrap() = ProjectMetadata::default();
+
+    app.emit("STATE_UPDATE", &*state)
+        .map_err(|e| e.to_string())?;
+
+    Ok(state.clone())
+}
+
+#[tauri::command]
+pub fn get_project_file_info(path: String) -> Result<ProjectFileInfo, String> {
+    let json =
+        std::fs::read_to_string(&path).map_err(|e| format!("Failed to read project: {}", e))?;
+    read_project_file_info(&json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timeline::TimelineState;
+
+    #[test]
+    fn test_add_test_clips_logic() {
+        let mut state = TimelineState {
+            clips: vec![],
+            duration: 0.0,
+            playhead_time: 0.0,
+            version: 0,
+            ..Default::default()
+        };
+        add_test_clips_logic(&mut state, 5, &GenerateClipOptions::default());
+        assert_eq!(state.clips.len(), 5);
+        assert_eq!(state.duration, 25.0);
+    }
+
+    #[test]
+    fn test_test_clip_args() {
+        let defaults = test_clip_args(&GenerateClipOptions::default(), "out.mp4");
+        assert_eq!(defaults[4], "testsrc=duration=5:size=1280x720:rate=30");
+        assert!(!defaults.iter().any(|a| a.contains("sine")));
+        assert_eq!(defaults.last().unwrap(), "out.mp4");
+
+        let options = GenerateClipOptions {
+            pattern: TestPattern::Smptehdbars,
+            duration: 2.5,
+            include_audio: true,
+            audio_frequency_hz: Some(1000),
+            ..Default::default()
+        };
+        let args = test_clip_args(&options, "out.mp4");
+        assert_eq!(args[4], "smptehdbars=duration=2.5:size=1280x720:rate=30");
+        assert!(args.contains(&"sine=frequency=1000:duration=2.5".to_string()));
+        assert!(args.iter().any(|a| a.contains("amix")));
+    }
+
+    fn hashed_clip(id: &str, source: &str, hash: Option<&str>) -> Clip {
+        Clip {
+            id: id.to_string(),
+            track_id: "video_track_1".to_string(),
+            duration: 5.0,
+            source_file: source.to_string(),
+            source_hash: hash.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_find_duplicate_groups() {
+        let state = TimelineState {
+            clips: vec![
+                hashed_clip("a", "/uploads/a.mp4", Some("h1")),
+                hashed_clip("b", "/uploads/b.mp4", Some("h2")),
+                hashed_clip("c", "/uploads/c.mp4", Some("h1")),
+                hashed_clip("d", "/uploads/d.mp4", None),
+            ],
+            ..Default::default()
+        };
+
+        let groups = find_duplicate_groups(&state);
+        assert_eq!(
+            groups,
+            vec![DuplicateGroup {
+                hash: "h1".to_string(),
+                clip_ids: vec!["a".to_string(), "c".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_consolidate_duplicates_logic() {
+        let mut state = TimelineState {
+            clips: vec![
+                hashed_clip("a", "/uploads/a.mp4", Some("h1")),
+                hashed_clip("b", "/uploads/b.mp4", Some("h2")),
+                hashed_clip("c", "/uploads/c.mp4", Some("h1")),
+            ],
+            ..Default::default()
+        };
+
+        let orphaned = consolidate_duplicates_logic(&mut state, "a", &["c".to_string()]).unwrap();
+        assert_eq!(orphaned, vec!["/uploads/c.mp4".to_string()]);
+        assert_eq!(state.clips[2].source_file, "/uploads/a.mp4");
+
+        // Mismatched hash is rejected
+        assert!(consolidate_duplicates_logic(&mut state, "a", &["b".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_merge_short_clips_logic() {
+        let clip = |id: &str, track: &str, start: f64, duration: f64| Clip {
+            id: id.to_string(),
+            track_id: track.to_string(),
+            start,
+            duration,
+            ..Default::default()
+        };
+        let mut state = TimelineState {
+            clips: vec![
+                Clip {
+                    edit_out: Some(5.0),
+                    ..clip("a", "v1", 0.0, 6.0)
+                },
+                clip("b", "v1", 5.0, 0.05),
+                clip("c", "v1", 5.05, 0.05),
+                clip("d", "v1", 5.1, 3.0),
+                clip("lonely", "v2", 0.0, 0.05),
+            ],
+            ..Default::default()
+        };
+
+        let removed = merge_short_clips_logic(&mut state, 0.1);
+        assert_eq!(removed, vec!["b", "c"]);
+
+        let ids: Vec<_> = state.clips.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "d", "lonely"]);
+        assert!((state.clips[0].source_out() - 5.1).abs() < 1e-9);
+        assert_eq!(state.clips[1].start, 5.1);
+
+        // Gaps and neighbours without spare source rule a merge out; the one
+        // after can also grow back over the short clip
+        let mut state = TimelineState {
+            clips: vec![
+                clip("a", "v1", 0.0, 3.0),
+                clip("b", "v1", 3.0, 0.05),
+                clip("c", "v1", 4.0, 0.05),
+                Clip {
+                    edit_in: Some(1.0),
+                    ..clip("d", "v1", 4.05, 5.0)
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(merge_short_clips_logic(&mut state, 0.1), vec!["c"]);
+        let d = state.clips.iter().find(|c| c.id == "d").unwrap();
+        assert_eq!(d.start, 4.0);
+        assert!((d.source_in() - 0.95).abs() < 1e-9);
+        assert!((d.end() - 8.05).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_apply_virtual_trim_logic() {
+        let mut state = TimelineState {
+            clips: vec![
+                Clip {
+                    id: "trimmed".to_string(),
+                    duration: 10.0,
+                    edit_in: Some(2.0),
+                    edit_out: Some(6.0),
+                    ..Default::default()
+                },
+                Clip {
+                    id: "plain".to_string(),
+                    start: 4.0,
+                    duration: 3.0,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(apply_virtual_trim_logic(&mut state), vec!["trimmed"]);
+        let trimmed = &state.clips[0];
+        assert_eq!((trimmed.source_offset, trimmed.duration), (2.0, 6.0));
+        assert_eq!((trimmed.edit_in, trimmed.edit_out), (None, None));
+        assert_eq!(
+            (trimmed.source_in(), trimmed.effective_duration()),
+            (2.0, 4.0)
+        );
+        assert_eq!(state.clips[1].duration, 3.0);
+        assert!(apply_virtual_trim_logic(&mut state).is_empty());
+
+        // Growing stops at the end of the source
+        state.clips[0].set_effective_duration(10.0);
+        assert_eq!(state.clips[0].effective_duration(), 4.0);
+
+        // A head-only trim is baked too
+        state.clips[1].edit_in = Some(1.0);
+        assert_eq!(apply_virtual_trim_logic(&mut state), vec!["plain"]);
+        assert_eq!(state.clips[1].source_in(), 1.0);
+        assert_eq!(state.clips[1].effective_duration(), 2.0);
+    }
+
+    #[test]
+    fn test_reset_after_apply_keeps_the_permanent_trim() {
+        let mut state = TimelineState {
+            clips: vec![Clip {
+                id: "a".to_string(),
+                start: 5.0,
+                duration: 10.0,
+                edit_in: Some(2.0),
+                edit_out: Some(6.0),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        apply_virtual_trim_logic(&mut state);
+
+        // Trim again, then reset: only the second trim is undone
+        state.clips[0].edit_in = Some(3.0);
+        state.clips[0].edit_out = Some(5.0);
+        state.clips[0].start = 6.0;
+        reset_virtual_trim_logic(&mut state, "a").unwrap();
+        let clip = &state.clips[0];
+        assert_eq!(
+            (clip.start, clip.source_in(), clip.source_out()),
+            (5.0, 2.0, 6.0)
+        );
+        assert!(!clip.has_virtual_trim());
+
+        assert!(reset_virtual_trim_logic(&mut state, "missing").is_err());
+    }
+
+    #[test]
+    fn test_normalize_clip_starts_logic() {
+        let clip = |id: &str, track: &str, start: f64| Clip {
+            id: id.to_string(),
+            track_id: track.to_string(),
+            start,
+            duration: 2.0,
+            ..Default::default()
+        };
+        let mut state = TimelineState {
+            clips: vec![
+                clip("c", "v1", 9.0),
+                clip("x", "v2", 4.0),
+                clip("a", "v1", 1.0),
+                clip("b", "v1", 5.0),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(normalize_clip_starts_logic(&mut state, "v1"), 3);
+
+        let layout: Vec<_> = state
+            .clips
+            .iter()
+            .map(|c| (c.id.as_str(), c.start))
+            .collect();
+        assert_eq!(layout, vec![("a", 0.0), ("b", 2.0), ("c", 4.0), ("x", 4.0)]);
+    }
+
+    #[test]
+    fn test_normalize_clip_positions_logic() {
+        let clip = |id: &str, start: f64, edit_out: Option<f64>| Clip {
+            id: id.to_string(),
+            track_id: "v1".to_string(),
+            start,
+            duration: 5.0,
+            edit_out,
+            ..Default::default()
+        };
+        // "b" starts a hair before "a" ends, as repeated trims leave them
+        let mut state = TimelineState {
+            clips: vec![
+                clip("a", 0.0, Some(2.0004)),
+                clip("b", 1.9999, Some(1.0)),
+                clip("c", 4.0, None),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(normalize_clip_positions_logic(&mut state, 25.0), 2);
+        assert_eq!(state.clips[0].end(), 2.0);
+        assert_eq!(state.clips[1].start, 2.0);
+        assert_eq!(state.clips[1].end(), 3.0);
+        assert_eq!(state.clips[2].edit_out, None);
+    }
+
+    #[test]
+    fn test_parse_probe_output() {
+        let json = json!({
+            "streams": [
+                { "codec_type": "audio", "codec_name": "aac" },
+                {
+                    "codec_type": "video",
+                    "codec_name": "h264",
+                    "width": 1920,
+                    "height": 1080,
+                    "r_frame_rate": "30000/1001"
+                }
+            ],
+            "format": { "duration": "12.500000" }
+        });
+        let metadata = parse_probe_output(&json).unwrap();
+        assert_eq!(metadata.duration, 12.5);
+        assert_eq!((metadata.width, metadata.height), (Some(1920), Some(1080)));
+        assert!((metadata.fps.unwrap() - 29.97).abs() < 0.01);
+        assert_eq!(metadata.video_codec.as_deref(), Some("h264"));
+        assert!(metadata.has_audio);
+
+        let audio_only = json!({
+            "streams": [{ "codec_type": "audio", "codec_name": "mp3" }],
+            "format": { "duration": "3.0" }
+        });
+        let metadata = parse_probe_output(&audio_only).unwrap();
+        assert_eq!((metadata.width, metadata.video_codec), (None, None));
+        assert!(metadata.has_audio);
+        assert!(parse_probe_output(&json!({ "streams": [] })).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_probe_paths_concurrently_keeps_order() {
+        let paths = vec![
+            "/nonexistent/first.mp4".to_string(),
+            "/nonexistent/second.mp4".to_string(),
+        ];
+        let results = probe_paths_concurrently(&paths, probe_video_metadata).await;
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.is_err()));
+    }
+
+    #[test]
+    fn test_timeline_summary_counts_tracks() {
+        use crate::timeline::{Track, TrackType};
+        let clip = |id: &str, track: &str| Clip {
+            id: id.to_string(),
+            track_id: track.to_string(),
+            duration: 1.0,
+            ..Default::default()
+        };
+        let state = TimelineState {
+            clips: vec![clip("a", "v1"), clip("b", "v1"), clip("c", "v2")],
+            duration: 2.0,
+            playhead_time: 0.5,
+            version: 9,
+            ..Default::default()
+        };
+        assert_eq!(
+            state.summary(),
+            TimelineSummary {
+                version: 9,
+                clip_count: 3,
+                duration: 2.0,
+                playhead_time: 0.5,
+                track_count: 2,
+            }
+        );
+
+        // Empty tracks count too
+        let state = TimelineState {
+            tracks: vec![
+                Track::new("v1", "Video 1", TrackType::Video, 0),
+                Track::new("v2", "Video 2", TrackType::Video, 1),
+                Track::new("a1", "Audio 1", TrackType::Audio, 2),
+            ],
+            ..state
+        };
+        assert_eq!(state.summary().track_count, 3);
+    }
+
+    #[test]
+    fn test_download_file_name() {
+        assert_eq!(
+            download_file_name("https://cdn.example.com/media/clip%201.mp4?sig=abc", None),
+            "clip201.mp4"
+        );
+        assert_eq!(
+            download_file_name("https://example.com/", Some("../../etc/passwd")),
+            "etcpasswd"
+        );
+        assert_eq!(download_file_name("https://example.com/", None), "download");
     }
 }
@@ -1,12 +1,23 @@
 use serde::{Deserialize, Serialize};
 
+/// Bumped whenever a change to the plan format needs a migration in
+/// `migrate_edit_plan`.
+pub const CURRENT_SCHEMA_VERSION: u32 = 1;
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct EditPlan {
+    /// Plans written before versioning, including LLM output, are version 1.
+    #[serde(default = "first_schema_version")]
+    pub schema_version: u32,
     pub actions: Vec<EditAction>,
     pub thought_process: Option<String>,
     pub confidence: Option<f32>,
 }
 
+fn first_schema_version() -> u32 {
+    1
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct EditAction {
     #[serde(rename = "type")]
@@ -22,14 +33,193 @@ pub enum ActionType {
     Move,
     Trim,
     Split,
+    Reorder,
+    SetLoopRegion,
+    AddMarker,
+    /// Delete, then pull later clips on the same track back over the gap.
+    RippleDelete,
+    /// Change playback rate; later clips on the track follow the new end.
+    SetSpeed,
+    SetVolume,
+    /// Move the cut between the target and the next clip on its track.
+    RollingTrim,
+    /// Play a different part of the source without moving or resizing the clip.
+    /// The source range is the clip's `edit_in`/`edit_out` trim handles, read
+    /// through `Clip::source_in()`/`source_out()`; there are no separate fields.
+    SlipClip,
+    /// Move the target, trimming its neighbours on the track to make room.
+    SlideClip,
+    /// Shift the clip's audio against its picture for a J-cut or L-cut.
+    AudioOffset,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct ActionParameters {
     pub new_start_time: Option<f64>,
     pub trim_start_delta: Option<f64>,
     pub trim_end_delta: Option<f64>,
     pub split_time: Option<f64>,
+    /// REORDER: position among the clip's track, by start time.
+    pub target_index: Option<usize>,
+    /// REORDER: keep the gaps between clips instead of packing them together.
+    pub preserve_gaps: Option<bool>,
+    /// SET_LOOP_REGION: loop bounds; default to the target clip's extent.
+    pub loop_start: Option<f64>,
+    pub loop_end: Option<f64>,
+    /// ADD_MARKER: timeline position; defaults to the target clip's start.
+    pub marker_time: Option<f64>,
+    pub marker_label: Option<String>,
+    pub marker_color: Option<String>,
+    /// SET_SPEED: playback rate, in `(0, MAX_SPEED_MULTIPLIER)`.
+    pub speed_multiplier: Option<f64>,
+    /// SET_VOLUME: audio gain, clamped to `[MIN_VOLUME_DB, MAX_VOLUME_DB]`.
+    pub volume_db: Option<f64>,
+    /// ROLLING_TRIM: seconds to move the cut; positive lengthens the target.
+    pub roll_delta: Option<f64>,
+    /// SLIP_CLIP: timeline seconds to move both trim handles; positive plays later footage.
+    pub slip_delta: Option<f64>,
+    /// SLIDE_CLIP: seconds to move the target; positive moves it later.
+    pub slide_delta: Option<f64>,
+    /// AUDIO_OFFSET: seconds added to the clip's `audio_offset_s`.
+    pub audio_offset_delta: Option<f64>,
+}
+
+// Direct (non-AI) edits are recorded as single-action plans so the artifact
+// trail has one format for every edit.
+fn manual_plan(
+    action_type: ActionType,
+    clip_id: &str,
+    parameters: ActionParameters,
+    description: String,
+) -> EditPlan {
+    EditPlan {
+        schema_version: CURRENT_SCHEMA_VERSION,
+        actions: vec![EditAction {
+            action_type,
+            target_clip_id: clip_id.to_string(),
+            parameters: Some(parameters),
+        }],
+        thought_process: Some(description),
+        confidence: Some(1.0),
+    }
+}
+
+pub fn generate_plan_for_trim(
+    clip_id: &str,
+    trim_start_delta: Option<f64>,
+    trim_end_delta: Option<f64>,
+) -> EditPlan {
+    manual_plan(
+        ActionType::Trim,
+        clip_id,
+        ActionParameters {
+            trim_start_delta,
+            trim_end_delta,
+            ..Default::default()
+        },
+        "Manual trim".to_string(),
+    )
+}
+
+pub fn generate_plan_for_move(clip_id: &str, new_start_time: f64) -> EditPlan {
+    manual_plan(
+        ActionType::Move,
+        clip_id,
+        ActionParameters {
+            new_start_time: Some(new_start_time),
+            ..Default::default()
+        },
+        "Manual move".to_string(),
+    )
+}
+
+pub fn generate_plan_for_split(clip_id: &str, split_time: f64) -> EditPlan {
+    manual_plan(
+        ActionType::Split,
+        clip_id,
+        ActionParameters {
+            split_time: Some(split_time),
+            ..Default::default()
+        },
+        "Manual split".to_string(),
+    )
+}
+
+/// Split `clip_id` at each of `split_times`. The splits run latest first so
+/// every one still lands inside the clip that keeps the original id.
+pub fn generate_plan_for_scene_splits(clip_id: &str, split_times: &[f64]) -> EditPlan {
+    let mut times = split_times.to_vec();
+    times.sort_by(|a, b| b.total_cmp(a));
+    EditPlan {
+        schema_version: CURRENT_SCHEMA_VERSION,
+        actions: times
+            .into_iter()
+            .map(|split_time| EditAction {
+                action_type: ActionType::Split,
+                target_clip_id: clip_id.to_string(),
+                parameters: Some(ActionParameters {
+                    split_time: Some(split_time),
+                    ..Default::default()
+                }),
+            })
+            .collect(),
+        thought_process: Some(format!("Split at {} scene changes", split_times.len())),
+        confidence: Some(1.0),
+    }
+}
+
+/// Cut the timeline `ranges` out of the clip spanning `clip_start..clip_end`.
+/// Every action targets the original clip, which keeps the head after each
+/// split, so the ranges are handled latest first: a split at the range's end,
+/// then a trim back to its start. A range reaching the clip's start deletes
+/// what remains. The removed ranges are left as gaps.
+pub fn generate_plan_for_silence_removal(
+    clip_id: &str,
+    clip_start: f64,
+    clip_end: f64,
+    ranges: &[(f64, f64)],
+) -> EditPlan {
+    let mut ranges = ranges.to_vec();
+    ranges.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let action = |action_type, parameters| EditAction {
+        action_type,
+        target_clip_id: clip_id.to_string(),
+        parameters: Some(parameters),
+    };
+
+    let mut actions = Vec::new();
+    let mut end = clip_end;
+    for &(start, stop) in &ranges {
+        if stop < end {
+            actions.push(action(
+                ActionType::Split,
+                ActionParameters {
+                    split_time: Some(stop),
+                    ..Default::default()
+                },
+            ));
+        }
+        // What is left of the original clip is now entirely silent
+        if start <= clip_start {
+            actions.push(action(ActionType::Delete, ActionParameters::default()));
+            break;
+        }
+        actions.push(action(
+            ActionType::Trim,
+            ActionParameters {
+                trim_end_delta: Some(start - stop.min(end)),
+                ..Default::default()
+            },
+        ));
+        end = start;
+    }
+
+    EditPlan {
+        schema_version: CURRENT_SCHEMA_VERSION,
+        actions,
+        thought_process: Some(format!("Remove {} silent regions", ranges.len())),
+        confidence: Some(1.0),
+    }
 }
 
 impl EditAction {
@@ -0,0 +1,224 @@
+// src-tauri/src/export_queue.rs
+// Exports submitted while another is rendering wait here and run one at a time.
+// Finished jobs are kept as history and persisted next to the preferences.
+use crate::ffmpeg::{ExportOptions, EXPORT_CANCELLED_ERROR};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Complete,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportJob {
+    pub id: String,
+    pub options: ExportOptions,
+    /// Unix millis.
+    pub submitted_at: i64,
+    pub status: JobStatus,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CompletedExport {
+    pub id: String,
+    pub options: ExportOptions,
+    pub submitted_at: i64,
+    pub finished_at: i64,
+    /// Complete, Failed or Cancelled.
+    pub status: JobStatus,
+    pub output_path: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ExportQueueState {
+    /// Pending jobs in submission order; the running job stays at the front.
+    pub jobs: VecDeque<ExportJob>,
+    /// Oldest first.
+    pub completed: Vec<CompletedExport>,
+}
+
+pub struct ExportQueue {
+    state: Mutex<ExportQueueState>,
+    wake: Notify,
+    /// Empty for in-memory queues, which never persist.
+    history_path: PathBuf,
+}
+
+fn now_millis() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+impl ExportQueue {
+    /// Queue whose history is loaded from, and saved to, `history_path`.
+    pub fn new(history_path: PathBuf) -> Self {
+        let completed = fs::read_to_string(&history_path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self {
+            state: Mutex::new(ExportQueueState {
+                jobs: VecDeque::new(),
+                completed,
+            }),
+            wake: Notify::new(),
+            history_path,
+        }
+    }
+
+    pub fn new_in_memory() -> Self {
+        Self::new(PathBuf::new())
+    }
+
+    /// Add a job and wake the worker. Returns the job ID.
+    pub fn enqueue(&self, options: ExportOptions) -> String {
+        let id = Uuid::new_v4().to_string();
+        self.state.lock().unwrap().jobs.push_back(ExportJob {
+            id: id.clone(),
+            options,
+            submitted_at: now_millis(),
+            status: JobStatus::Queued,
+        });
+        self.wake.notify_one();
+        id
+    }
+
+    /// Mark the oldest queued job running, unless a job is already running.
+    pub fn start_next(&self) -> Option<ExportJob> {
+        let mut state = self.state.lock().unwrap();
+        let front = state.jobs.front_mut()?;
+        if front.status != JobStatus::Queued {
+            return None;
+        }
+        front.status = JobStatus::Running;
+        Some(front.clone())
+    }
+
+    /// Move the running job `id` into the history with its outcome.
+    pub fn finish(&self, id: &str, result: Result<String, String>) {
+        let mut state = self.state.lock().unwrap();
+        let Some(index) = state.jobs.iter().position(|j| j.id == id) else {
+            return;
+        };
+        let job = state.jobs.remove(index).unwrap();
+        let (status, output_path, error) = match result {
+            Ok(path) => (JobStatus::Complete, Some(path), None),
+            // Stopped through `cancel_export`
+            Err(e) if e == EXPORT_CANCELLED_ERROR => (JobStatus::Cancelled, None, None),
+            Err(e) => (JobStatus::Failed, None, Some(e)),
+        };
+        state.completed.push(CompletedExport {
+            id: job.id,
+            options: job.options,
+            submitted_at: job.submitted_at,
+            finished_at: now_millis(),
+            status,
+            output_path,
+            error,
+        });
+        self.save_history(&state.completed);
+    }
+
+    /// Drop a job that has not started. A running job is stopped with
+    /// `cancel_export` instead, and `finish` then records it as cancelled.
+    pub fn cancel(&self, id: &str) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap();
+        let index = state
+            .jobs
+            .iter()
+            .position(|j| j.id == id)
+            .ok_or_else(|| format!("Export job {} not found", id))?;
+        if state.jobs[index].status == JobStatus::Running {
+            return Err(format!("Export job {} is already running", id));
+        }
+        let job = state.jobs.remove(index).unwrap();
+        state.completed.push(CompletedExport {
+            id: job.id,
+            options: job.options,
+            submitted_at: job.submitted_at,
+            finished_at: now_millis(),
+            status: JobStatus::Cancelled,
+            output_path: None,
+            error: None,
+        });
+        self.save_history(&state.completed);
+        Ok(())
+    }
+
+    pub fn jobs(&self) -> Vec<ExportJob> {
+        self.state.lock().unwrap().jobs.iter().cloned().collect()
+    }
+
+    /// Up to `limit` finished jobs, most recent first.
+    pub fn history(&self, limit: usize) -> Vec<CompletedExport> {
+        let state = self.state.lock().unwrap();
+        state.completed.iter().rev().take(limit).cloned().collect()
+    }
+
+    /// Wait until a job is enqueued.
+    pub async fn wait_for_work(&self) {
+        self.wake.notified().await;
+    }
+
+    fn save_history(&self, completed: &[CompletedExport]) {
+        if self.history_path.as_os_str().is_empty() {
+            return;
+        }
+        let json = serde_json::to_string_pretty(completed).unwrap_or_default();
+        if let Err(e) = fs::write(&self.history_path, json) {
+            eprintln!("⚠️ [Export] Failed to save export history: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jobs_run_one_at_a_time_and_land_in_history() {
+        let queue = ExportQueue::new_in_memory();
+        let a = queue.enqueue(ExportOptions::default());
+        let b = queue.enqueue(ExportOptions::default());
+        let c = queue.enqueue(ExportOptions::default());
+
+        assert_eq!(queue.start_next().unwrap().id, a);
+        assert!(queue.start_next().is_none()); // `a` is still running
+        assert!(queue.cancel(&a).is_err());
+        queue.cancel(&c).unwrap();
+
+        let d = queue.enqueue(ExportOptions::default());
+        queue.finish(&a, Ok("/exports/a.mp4".to_string()));
+        assert_eq!(queue.start_next().unwrap().id, b);
+        queue.finish(&b, Err("ffmpeg exited with 1".to_string()));
+        assert_eq!(queue.start_next().unwrap().id, d);
+        queue.finish(&d, Err(EXPORT_CANCELLED_ERROR.to_string()));
+        assert!(queue.jobs().is_empty());
+
+        let history = queue.history(10);
+        let statuses: Vec<_> = history.iter().map(|e| (e.id.as_str(), e.status)).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (d.as_str(), JobStatus::Cancelled),
+                (b.as_str(), JobStatus::Failed),
+                (a.as_str(), JobStatus::Complete),
+                (c.as_str(), JobStatus::Cancelled),
+            ]
+        );
+        assert_eq!(history[0].error, None);
+        assert_eq!(history[2].output_path.as_deref(), Some("/exports/a.mp4"));
+        assert_eq!(queue.history(1).len(), 1);
+    }
+}
@@ -1,75 +1,1932 @@
-use crate::timeline::TimelineState;
-use std::path::Path;
-use std::process::Command;
+use crate::project::ProjectMetadata;
+use crate::render_cache::{self, RenderCache};
+use crate::subtitles::{self, SubtitleMode};
+use crate::timeline::{Clip, SubtitleTrack, TimelineState, TransitionPoint};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{AppHandle, Emitter};
 
-#[derive(Clone, Debug)]
-pub struct FFmpegEngine;
+/// Number of leading bytes hashed by `compute_source_hash`.
+const SOURCE_HASH_BYTES: u64 = 1024 * 1024;
+
+/// Sample rate audio is resampled to before measuring waveform levels.
+const WAVEFORM_SAMPLE_RATE: u32 = 8000;
+/// Level reported for digital silence, which astats prints as "-inf".
+const WAVEFORM_SILENCE_DB: f32 = -90.0;
+
+// Default export format; `ExportPreferences` can change the size and rate.
+// Clips already matching the export format can be stream-copied.
+pub const DEFAULT_EXPORT_WIDTH: u32 = 1920;
+pub const DEFAULT_EXPORT_HEIGHT: u32 = 1080;
+pub const DEFAULT_EXPORT_FPS: f64 = 30.0;
+const EXPORT_CODEC: &str = "h264";
+/// Software H.264 encoder, used when the preferred one isn't available.
+pub const FALLBACK_VIDEO_ENCODER: &str = "libx264";
+const EXPORT_PIX_FMT: &str = "yuv420p";
+/// Frame rates `RenderOptions::validate` accepts.
+const EXPORT_FPS_RANGE: std::ops::RangeInclusive<f64> = 1.0..=120.0;
+
+/// Upper bound on `cols * rows` for clip mosaics.
+pub const MAX_MOSAIC_FRAMES: u32 = 100;
+
+/// Per-export options supplied by the frontend.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ExportOptions {
+    /// Stream-copy instead of re-encoding when every clip already matches the export format.
+    pub allow_codec_passthrough: bool,
+    /// Probe the finished file and compare it with what was requested.
+    pub verify_render_output: bool,
+    /// Reuse segments cached by earlier exports and re-encode only changed ones.
+    pub use_render_cache: bool,
+    /// Filled in from `ExportPreferences::subtitle_mode` when an export starts.
+    #[serde(skip)]
+    pub subtitle_mode: SubtitleMode,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            allow_codec_passthrough: true,
+            verify_render_output: true,
+            use_render_cache: true,
+            subtitle_mode: SubtitleMode::default(),
+        }
+    }
+}
+
+/// Output format and process limits for a render, from `ExportPreferences`
+/// and `SystemPreferences`. Every re-encoded input is scaled and padded to
+/// `width`x`height` and resampled to `fps`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderOptions {
+    pub width: u32,
+    pub height: u32,
+    pub fps: f64,
+    /// H.264 encoder for `-c:v`, e.g. "libx264" or "h264_videotoolbox".
+    pub video_encoder: String,
+    pub process: FfmpegProcessSettings,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            width: DEFAULT_EXPORT_WIDTH,
+            height: DEFAULT_EXPORT_HEIGHT,
+            fps: DEFAULT_EXPORT_FPS,
+            video_encoder: FALLBACK_VIDEO_ENCODER.to_string(),
+            process: FfmpegProcessSettings::default(),
+        }
+    }
+}
+
+impl RenderOptions {
+    /// libx264 with yuv420p needs even, non-zero dimensions.
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, value) in [("width", self.width), ("height", self.height)] {
+            if value == 0 || value % 2 != 0 {
+                return Err(format!(
+                    "Export {} {} must be an even number greater than 0",
+                    name, value
+                ));
+            }
+        }
+        if !EXPORT_FPS_RANGE.contains(&self.fps) {
+            return Err(format!(
+                "Export frame rate {} must be between {} and {}",
+                self.fps,
+                EXPORT_FPS_RANGE.start(),
+                EXPORT_FPS_RANGE.end()
+            ));
+        }
+        Ok(())
+    }
+
+    // Render cache subdirectory, so segments never mix formats or encoders
+    fn cache_key(&self) -> String {
+        format!(
+            "{}x{}@{}_{}",
+            self.width, self.height, self.fps, self.video_encoder
+        )
+    }
+}
+
+/// Resource limits for the FFmpeg process, taken from `SystemPreferences`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FfmpegProcessSettings {
+    pub threads: Option<u32>,
+    /// Unix niceness; ignored on other platforms.
+    pub nice: Option<i8>,
+}
+
+impl FfmpegProcessSettings {
+    /// `ffmpeg` command with the priority applied. Finish it with `output_args`
+    /// so the thread limit applies.
+    fn command(&self) -> Command {
+        match self.nice {
+            Some(level) if cfg!(unix) => {
+                let mut cmd = Command::new("nice");
+                cmd.arg("-n").arg(level.to_string()).arg("ffmpeg");
+                cmd
+            }
+            _ => Command::new("ffmpeg"),
+        }
+    }
+
+    /// The thread limit and `output_path`. `-threads` is per file, so it has to
+    /// sit right before the output to limit the encoder rather than a decoder.
+    fn output_args(&self, output_path: &Path) -> Vec<OsString> {
+        let mut args = Vec::new();
+        if let Some(threads) = self.threads {
+            args.push("-threads".into());
+            args.push(threads.to_string().into());
+        }
+        args.push(output_path.into());
+        args
+    }
+}
+
+/// Logical CPUs available to the process (at least 1).
+pub fn detect_cpu_count() -> u32 {
+    std::thread::available_parallelism()
+        .map(|n| n.get() as u32)
+        .unwrap_or(1)
+}
+
+/// What `render_timeline` actually did.
+#[derive(Serialize, Debug, Clone)]
+pub struct RenderOutcome {
+    pub passthrough: bool,
+    /// Present when `verify_render_output` was requested.
+    pub verification: Option<RenderVerification>,
+}
+
+/// ffprobe's view of a rendered file, checked against the export settings.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RenderVerification {
+    pub success: bool,
+    pub actual_duration_secs: f64,
+    pub actual_codec: String,
+    pub actual_resolution: Option<(u32, u32)>,
+    pub file_size_bytes: u64,
+    pub warnings: Vec<String>,
+}
+
+/// Returned to the frontend by the export commands.
+#[derive(Serialize, Debug, Clone)]
+pub struct ExportResult {
+    pub output_path: String,
+    pub passthrough: bool,
+    pub verification: Option<RenderVerification>,
+}
+
+/// Whether `clip` can't be stream-copied, judged without probing the source:
+/// speed and volume changes need filters, and metadata stored at import may
+/// already show a format mismatch.
+pub fn clip_needs_reencode(clip: &Clip, render: &RenderOptions) -> bool {
+    (clip.speed_multiplier - 1.0).abs() > 1e-6
+        || clip.volume_db.abs() > 1e-6
+        || clip
+            .video_codec
+            .as_deref()
+            .is_some_and(|c| c != EXPORT_CODEC)
+        || clip.width.is_some_and(|w| w != render.width)
+        || clip.height.is_some_and(|h| h != render.height)
+        || clip.fps.is_some_and(|fps| (fps - render.fps).abs() >= 0.01)
+}
+
+/// Compare probed output properties with the expected duration and export format.
+pub fn check_render_output(
+    expected_duration: f64,
+    actual_duration: f64,
+    video: Option<&VideoCodecInfo>,
+    file_size_bytes: u64,
+    render: &RenderOptions,
+) -> RenderVerification {
+    let mut warnings = Vec::new();
+
+    if file_size_bytes == 0 {
+        warnings.push("Output file is empty".to_string());
+    }
+    if (actual_duration - expected_duration).abs() > 1.0 {
+        warnings.push(format!(
+            "Duration {:.2}s differs from expected {:.2}s",
+            actual_duration, expected_duration
+        ));
+    }
+    match video {
+        Some(info) => {
+            if info.codec_name != EXPORT_CODEC {
+                warnings.push(format!(
+                    "Codec is {} (expected {})",
+                    info.codec_name, EXPORT_CODEC
+                ));
+            }
+            if (info.width, info.height) != (render.width, render.height) {
+                warnings.push(format!(
+                    "Resolution is {}x{} (expected {}x{})",
+                    info.width, info.height, render.width, render.height
+                ));
+            }
+        }
+        None => warnings.push("No video stream found in output".to_string()),
+    }
+
+    RenderVerification {
+        success: warnings.is_empty(),
+        actual_duration_secs: actual_duration,
+        actual_codec: video.map(|v| v.codec_name.clone()).unwrap_or_default(),
+        actual_resolution: video.map(|v| (v.width, v.height)),
+        file_size_bytes,
+        warnings,
+    }
+}
+
+/// Video stream properties reported by ffprobe.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VideoCodecInfo {
+    pub codec_name: String,
+    pub width: u32,
+    pub height: u32,
+    pub fps: f64,
+    pub pix_fmt: Option<String>,
+}
+
+/// Parse an ffprobe rational frame rate such as "30000/1001" or "25".
+pub fn parse_frame_rate(rate: &str) -> Option<f64> {
+    match rate.split_once('/') {
+        Some((num, den)) => {
+            let num: f64 = num.parse().ok()?;
+            let den: f64 = den.parse().ok()?;
+            if den == 0.0 {
+                None
+            } else {
+                Some(num / den)
+            }
+        }
+        None => rate.parse().ok(),
+    }
+}
+
+/// A single `frame=... fps=... time=...` stats line from FFmpeg's stderr.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FfmpegProgress {
+    pub frame: u64,
+    pub fps: f32,
+    pub time_secs: f64,
+}
+
+/// Parse an FFmpeg stats line. Returns None for any other stderr output.
+pub fn parse_ffmpeg_progress_line(line: &str) -> Option<FfmpegProgress> {
+    // Values may be padded after '=' (e.g. "frame=  120 fps= 30")
+    let value_of = |key: &str| -> Option<&str> {
+        let idx = line.find(key)?;
+        line[idx + key.len()..].split_whitespace().next()
+    };
+
+    let time_str = value_of("time=")?;
+    let mut parts = time_str.split(':');
+    let hours: f64 = parts.next()?.parse().ok()?;
+    let minutes: f64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+
+    Some(FfmpegProgress {
+        frame: value_of("frame=").and_then(|v| v.parse().ok()).unwrap_or(0),
+        fps: value_of("fps=").and_then(|v| v.parse().ok()).unwrap_or(0.0),
+        time_secs: hours * 3600.0 + minutes * 60.0 + seconds,
+    })
+}
+
+/// Seconds of output written, from an `out_time_ms=` line of `-progress` output.
+/// Despite the name FFmpeg reports microseconds; "N/A" before the first frame.
+pub fn parse_progress_out_time(line: &str) -> Option<f64> {
+    let micros: f64 = line.strip_prefix("out_time_ms=")?.trim().parse().ok()?;
+    Some(micros / 1_000_000.0)
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct ExportProgress {
+    percent: f32,
+    elapsed_ms: u64,
+}
+
+/// Proxies sit next to `uploads/` so they survive restarts.
+pub fn proxy_dir(uploads_dir: &Path) -> PathBuf {
+    uploads_dir.parent().unwrap_or(uploads_dir).join("proxies")
+}
+
+/// Where `stabilize_clip` writes vidstab transform files, next to `uploads/`.
+pub fn stabilize_dir(uploads_dir: &Path) -> PathBuf {
+    uploads_dir
+        .parent()
+        .unwrap_or(uploads_dir)
+        .join("stabilize")
+}
+
+/// Point clips at their proxy files, for exports with `use_proxies` set. Clips
+/// whose proxy is missing on disk keep rendering from the source.
+pub fn substitute_proxy_sources(clips: &mut [Clip]) {
+    for clip in clips {
+        if let Some(proxy) = clip.proxy_path.as_ref().filter(|p| Path::new(p).exists()) {
+            clip.source_file = proxy.clone();
+        }
+    }
+}
+
+/// Error a render returns when its job was stopped with `ExportJobs::cancel`.
+pub const EXPORT_CANCELLED_ERROR: &str = "Export cancelled";
+
+/// The FFmpeg process each cancellable export is currently running, by job ID.
+#[derive(Clone, Default)]
+pub struct ExportJobs(pub Arc<Mutex<HashMap<String, Child>>>);
+
+impl ExportJobs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Kill the job's running FFmpeg. Returns false when `job_id` isn't running.
+    pub fn cancel(&self, job_id: &str) -> bool {
+        let Some(mut child) = self.0.lock().unwrap().remove(job_id) else {
+            return false;
+        };
+        let _ = child.kill();
+        let _ = child.wait();
+        true
+    }
+}
+
+/// Emits EXPORT_PROGRESS while a render's FFmpeg runs write output, and registers
+/// them with `ExportJobs` when the render has a job ID. The render sets
+/// `total_secs`; renders made of several runs set `offset_secs` to where the
+/// current run starts.
+pub struct RenderProgress {
+    app: AppHandle,
+    total_secs: Cell<f64>,
+    offset_secs: Cell<f64>,
+    started: Instant,
+    job: Option<(ExportJobs, String)>,
+}
+
+impl RenderProgress {
+    pub fn new(app: &AppHandle) -> Self {
+        Self {
+            app: app.clone(),
+            total_secs: Cell::new(0.0),
+            offset_secs: Cell::new(0.0),
+            started: Instant::now(),
+            job: None,
+        }
+    }
+
+    /// Make the render's FFmpeg runs cancellable as `job_id`.
+    pub fn with_job(mut self, jobs: &ExportJobs, job_id: String) -> Self {
+        self.job = Some((jobs.clone(), job_id));
+        self
+    }
+
+    fn emit(&self, percent: f32) {
+        let _ = self.app.emit(
+            "EXPORT_PROGRESS",
+            ExportProgress {
+                percent,
+                elapsed_ms: self.started.elapsed().as_millis() as u64,
+            },
+        );
+    }
+
+    // Run `cmd` (which must include `-progress pipe:1`) until FFmpeg reports
+    // `progress=end` or is killed, then wait for it to exit
+    fn run(&self, cmd: &mut Command) -> Result<(), String> {
+        let mut child = cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
+
+        // Drain stderr on its own thread so FFmpeg never blocks on a full pipe
+        let stderr = child
+            .stderr
+            .take()
+            .ok_or("Failed to capture ffmpeg stderr")?;
+        let stderr_reader = std::thread::spawn(move || {
+            let mut text = String::new();
+            let _ = BufReader::new(stderr).read_to_string(&mut text);
+            text
+        });
+
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or("Failed to capture ffmpeg stdout")?;
+
+        // A registered child is only reachable through the registry, so whoever
+        // removes it first (us when done, or `cancel`) owns it
+        let mut local = Some(child);
+        if let Some((jobs, id)) = &self.job {
+            jobs.0
+                .lock()
+                .unwrap()
+                .insert(id.clone(), local.take().unwrap());
+        }
+
+        for line in BufReader::new(stdout).lines() {
+            let Ok(line) = line else { break };
+            if let Some(out_secs) = parse_progress_out_time(&line) {
+                let done = self.offset_secs.get() + out_secs;
+                let total = self.total_secs.get();
+                if total > 0.0 {
+                    self.emit((done / total * 100.0).clamp(0.0, 100.0) as f32);
+                }
+            } else if line.trim() == "progress=end" {
+                break;
+            }
+        }
+
+        let child = match &self.job {
+            Some((jobs, id)) => jobs.0.lock().unwrap().remove(id),
+            None => local,
+        };
+        let Some(mut child) = child else {
+            let _ = stderr_reader.join();
+            return Err(EXPORT_CANCELLED_ERROR.to_string());
+        };
+        let status = child
+            .wait()
+            .map_err(|e| format!("Failed to wait for ffmpeg: {}", e))?;
+        let stderr = stderr_reader.join().unwrap_or_default();
+        if !status.success() {
+            return Err(format!("FFmpeg failed: {}", stderr));
+        }
+        Ok(())
+    }
+}
+
+/// Loudness `normalize_audio_levels` aims for when no target is given (YouTube's).
+pub const DEFAULT_LOUDNESS_TARGET_LUFS: f64 = -14.0;
+
+/// First-pass measurements printed by FFmpeg's `loudnorm` filter.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LoudnormStats {
+    pub input_i: f64,
+    pub input_tp: f64,
+    pub input_lra: f64,
+    pub input_thresh: f64,
+    pub target_offset: f64,
+}
+
+/// Parse the JSON block `loudnorm=print_format=json` writes at the end of stderr.
+pub fn parse_loudnorm_stats(stderr: &str) -> Option<LoudnormStats> {
+    let start = stderr.rfind('{')?;
+    let end = stderr[start..].find('}')? + start;
+    let json: serde_json::Value = serde_json::from_str(&stderr[start..=end]).ok()?;
+    // loudnorm reports every number as a string
+    let field = |key: &str| json[key].as_str()?.trim().parse::<f64>().ok();
+
+    Some(LoudnormStats {
+        input_i: field("input_i")?,
+        input_tp: field("input_tp")?,
+        input_lra: field("input_lra")?,
+        input_thresh: field("input_thresh")?,
+        target_offset: field("target_offset")?,
+    })
+}
+
+/// Second-pass `loudnorm` filter reaching `target_lufs` from first-pass `stats`
+/// with a single linear gain.
+pub fn loudnorm_filter(target_lufs: f64, stats: &LoudnormStats) -> String {
+    format!(
+        "loudnorm=I={}:measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true",
+        target_lufs,
+        stats.input_i,
+        stats.input_tp,
+        stats.input_lra,
+        stats.input_thresh,
+        stats.target_offset
+    )
+}
+
+// Run loudnorm's measuring pass over `path`, or the `(start, length)` seconds of it
+fn measure_loudness(
+    path: &str,
+    target_lufs: f64,
+    range: Option<(f64, f64)>,
+) -> Result<LoudnormStats, String> {
+    let mut command = Command::new("ffmpeg");
+    command.args(["-hide_banner", "-nostats"]);
+    if let Some((start, length)) = range {
+        command.args([
+            "-ss",
+            &format!("{:.3}", start),
+            "-t",
+            &format!("{:.3}", length),
+        ]);
+    }
+    let output = command
+        .args(["-i", path, "-vn", "-af"])
+        .arg(format!("loudnorm=I={}:print_format=json", target_lufs))
+        .args(["-f", "null", "-"])
+        .output()
+        .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        return Err(format!("Loudness measurement failed: {}", stderr));
+    }
+    parse_loudnorm_stats(&stderr)
+        .ok_or_else(|| "Could not read loudnorm measurements (does the file have audio?)".into())
+}
+
+/// Scene score above which `detect_scene_changes` reports a cut by default.
+pub const DEFAULT_SCENE_THRESHOLD: f64 = 0.4;
+
+/// A cut found by FFmpeg's scene detection, in source time.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SceneChange {
+    pub timestamp_s: f64,
+    pub score: f64,
+}
+
+// `metadata=print` writes "lavfi.scene_score=0.52" for each selected frame,
+// just before showinfo's line with its "pts_time:12.345"
+fn parse_scene_changes(stderr: &str) -> Vec<SceneChange> {
+    let mut changes = Vec::new();
+    let mut score = None;
+    for line in stderr.lines() {
+        if let Some(value) = line.split("lavfi.scene_score=").nth(1) {
+            score = value.trim().parse::<f64>().ok();
+        } else if line.contains("Parsed_showinfo") {
+            let Some(time) = line.split("pts_time:").nth(1) else {
+                continue;
+            };
+            let time = time.split_whitespace().next().unwrap_or("");
+            if let Ok(timestamp_s) = time.parse::<f64>() {
+                changes.push(SceneChange {
+                    timestamp_s,
+                    score: score.take().unwrap_or(0.0),
+                });
+            }
+        }
+    }
+    changes
+}
+
+/// Timeline positions of the `changes` that fall inside `clip`'s edit range.
+pub fn scene_split_times(clip: &Clip, changes: &[SceneChange]) -> Vec<f64> {
+    changes
+        .iter()
+        .filter(|c| c.timestamp_s > clip.source_in() && c.timestamp_s < clip.source_out())
+        .map(|c| clip.start + (c.timestamp_s - clip.source_in()) / clip.speed_multiplier)
+        .collect()
+}
+
+/// Level below which `detect_silence_regions` counts audio as silent by default.
+pub const DEFAULT_SILENCE_NOISE_DB: f64 = -30.0;
+/// Shortest silence `detect_silence_regions` reports by default.
+pub const DEFAULT_SILENCE_MIN_SECS: f64 = 0.5;
+
+// silencedetect prints "silence_start: 12.3" and later
+// "silence_end: 14.1 | silence_duration: 1.8". A silence still running when
+// the file ends has no end line, so it runs to `source_duration`.
+fn parse_silence_regions(stderr: &str, source_duration: f64) -> Vec<(f64, f64)> {
+    let value = |line: &str, key: &str| {
+        line.split(key)
+            .nth(1)?
+            .split_whitespace()
+            .next()?
+            .parse::<f64>()
+            .ok()
+    };
+    let mut regions = Vec::new();
+    let mut start = None;
+    for line in stderr.lines() {
+        if let Some(t) = value(line, "silence_start:") {
+            start = Some(t.max(0.0));
+        } else if let Some(end) = value(line, "silence_end:") {
+            if let Some(start) = start.take() {
+                regions.push((start, end));
+            }
+        }
+    }
+    if let Some(start) = start {
+        regions.push((start, source_duration));
+    }
+    regions
+}
+
+/// Timeline ranges covered by the source-time `regions` within `clip`'s edit range.
+pub fn silence_timeline_ranges(clip: &Clip, regions: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let to_timeline = |t: f64| clip.start + (t - clip.source_in()) / clip.speed_multiplier;
+    regions
+        .iter()
+        .map(|&(start, end)| (start.max(clip.source_in()), end.min(clip.source_out())))
+        .filter(|(start, end)| end > start)
+        .map(|(start, end)| (to_timeline(start), to_timeline(end)))
+        .collect()
+}
+
+/// Encoders and filters every render needs.
+const REQUIRED_ENCODERS: [&str; 2] = ["libx264", "aac"];
+const REQUIRED_FILTERS: [&str; 3] = ["scale", "pad", "concat"];
+/// Cached capabilities are re-detected after this long.
+const CAPABILITIES_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// What the installed FFmpeg build supports.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FfmpegCapabilities {
+    pub version: String,
+    pub encoder_names: Vec<String>,
+    pub filter_names: Vec<String>,
+    pub detected_at: SystemTime,
+}
+
+impl FfmpegCapabilities {
+    pub fn has_encoder(&self, name: &str) -> bool {
+        self.encoder_names.iter().any(|n| n == name)
+    }
+
+    pub fn has_filter(&self, name: &str) -> bool {
+        self.filter_names.iter().any(|n| n == name)
+    }
+
+    /// `preferred` when this build lists it, otherwise libx264.
+    pub fn video_encoder_or_fallback<'a>(&self, preferred: &'a str) -> &'a str {
+        if self.has_encoder(preferred) {
+            preferred
+        } else {
+            FALLBACK_VIDEO_ENCODER
+        }
+    }
+
+    /// Required encoders and filters this build lacks, e.g. "encoder libx264".
+    pub fn missing_render_requirements(&self) -> Vec<String> {
+        let has = |names: &[String], name: &str| names.iter().any(|n| n == name);
+        let encoders = REQUIRED_ENCODERS
+            .iter()
+            .filter(|&&e| !has(&self.encoder_names, e))
+            .map(|e| format!("encoder {}", e));
+        let filters = REQUIRED_FILTERS
+            .iter()
+            .filter(|&&f| !has(&self.filter_names, f))
+            .map(|f| format!("filter {}", f));
+        encoders.chain(filters).collect()
+    }
+
+    fn is_stale(&self) -> bool {
+        self.detected_at
+            .elapsed()
+            .map_or(true, |age| age > CAPABILITIES_MAX_AGE)
+    }
+}
+
+/// RMS levels keyed by (clip_id, samples_per_second).
+type WaveformCache = HashMap<(String, u32), Vec<f32>>;
+
+#[derive(Clone, Debug, Default)]
+pub struct FFmpegEngine {
+    render_cache: Arc<Mutex<RenderCache>>,
+    capabilities: Arc<Mutex<Option<FfmpegCapabilities>>>,
+    waveforms: Arc<Mutex<WaveformCache>>,
+}
 
 impl FFmpegEngine {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Drop cached render segments that `clip_id` contributed to.
+    pub fn invalidate_render_cache_for_clip(&self, clip_id: &str) {
+        let removed = self.render_cache.lock().unwrap().invalidate_clip(clip_id);
+        if removed > 0 {
+            println!(
+                "♻️ [RenderCache] Invalidated {} segments for clip {}",
+                removed, clip_id
+            );
+        }
+    }
+
+    pub fn clear_render_cache(&self) -> Result<(), String> {
+        self.render_cache.lock().unwrap().clear()
+    }
+
+    /// Run `ffmpeg -version`, `-encoders` and `-filters` and parse the results.
+    pub fn detect_ffmpeg_capabilities(&self) -> Result<FfmpegCapabilities, String> {
+        let run = |arg: &str| {
+            let output = Command::new("ffmpeg")
+                .args(["-hide_banner", arg])
+                .output()
+                .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
+            if !output.status.success() {
+                return Err(format!(
+                    "ffmpeg {} failed: {}",
+                    arg,
+                    String::from_utf8_lossy(&output.stderr)
+                ));
+            }
+            Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        };
+
+        Ok(FfmpegCapabilities {
+            version: parse_ffmpeg_version(&run("-version")?),
+            encoder_names: parse_encoder_names(&run("-encoders")?),
+            filter_names: parse_filter_names(&run("-filters")?),
+            detected_at: SystemTime::now(),
+        })
+    }
+
+    /// Cached capabilities, re-detected when missing or older than an hour.
+    pub fn capabilities(&self) -> Result<FfmpegCapabilities, String> {
+        let mut cached = self.capabilities.lock().unwrap();
+        if let Some(capabilities) = cached.as_ref().filter(|c| !c.is_stale()) {
+            return Ok(capabilities.clone());
+        }
+        let capabilities = self.detect_ffmpeg_capabilities()?;
+        *cached = Some(capabilities.clone());
+        Ok(capabilities)
+    }
+
+    // Fail early, with a readable message, when FFmpeg can't do what a render needs
+    fn check_render_capabilities(&self) -> Result<(), String> {
+        let capabilities = self.capabilities()?;
+        let missing = capabilities.missing_render_requirements();
+        if !missing.is_empty() {
+            return Err(format!(
+                "FFmpeg {} is missing {} required for rendering",
+                capabilities.version,
+                missing.join(", ")
+            ));
+        }
+        Ok(())
+    }
+
+    /// Two-pass EBU R128 normalization of `path` to `target_lufs`, rewriting the file
+    /// in place (video is stream-copied). Returns the first-pass measurements.
+    pub fn normalize_loudness(
+        &self,
+        path: &str,
+        target_lufs: f32,
+    ) -> Result<LoudnormStats, String> {
+        // Pass 1: measure
+        let stats = measure_loudness(path, target_lufs as f64, None)?;
+
+        // Pass 2: apply, using the measurements for an accurate linear gain
+        let normalized_path = Path::new(path).with_extension("normalized.mp4");
+        let output = Command::new("ffmpeg")
+            .args(["-y", "-i", path, "-c:v", "copy", "-af"])
+            .arg(loudnorm_filter(target_lufs as f64, &stats))
+            .args(["-c:a", "aac"])
+            .arg(&normalized_path)
+            .output()
+            .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
+
+        if !output.status.success() {
+            let _ = std::fs::remove_file(&normalized_path);
+            return Err(format!(
+                "Loudness normalization failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        std::fs::rename(&normalized_path, path)
+            .map_err(|e| format!("Failed to replace source with normalized file: {}", e))?;
+
+        println!(
+            "🔊 Normalized {} from {:.1} LUFS to {:.1} LUFS",
+            path, stats.input_i, target_lufs
+        );
+        Ok(stats)
+    }
+
+    /// First-pass loudness of the part of `clip`'s source that plays, for
+    /// normalizing it to `target_lufs` at render time. `None` without audio.
+    pub fn measure_clip_loudness(
+        &self,
+        clip: &Clip,
+        target_lufs: f64,
+    ) -> Result<Option<LoudnormStats>, String> {
+        if !self.has_audio_stream(&clip.source_file)? {
+            return Ok(None);
+        }
+        let range = (clip.source_in(), clip.source_length());
+        measure_loudness(&clip.source_file, target_lufs, Some(range)).map(Some)
+    }
+
+    /// Compute a SHA256 over the first 1MB of a source file.
+    /// Cheap enough to run on every import while still identifying identical media.
+    pub fn compute_source_hash(&self, source_file: &str) -> Result<String, String> {
+        let file = std::fs::File::open(source_file)
+            .map_err(|e| format!("Failed to open {}: {}", source_file, e))?;
+
+        let mut buffer = Vec::new();
+        file.take(SOURCE_HASH_BYTES)
+            .read_to_end(&mut buffer)
+            .map_err(|e| format!("Failed to read {}: {}", source_file, e))?;
+
+        Ok(format!("{:x}", Sha256::digest(&buffer)))
+    }
+
+    /// Render a `cols` x `rows` contact sheet of frames sampled evenly across the clip.
+    pub fn export_clip_mosaic(
+        &self,
+        clip: &Clip,
+        cols: u32,
+        rows: u32,
+        frame_width: u32,
+        output_path: &Path,
+    ) -> Result<String, String> {
+        let frame_count = cols * rows;
+        if frame_count == 0 || frame_width == 0 {
+            return Err("Mosaic needs at least one column, row and pixel of width".to_string());
+        }
+        if frame_count > MAX_MOSAIC_FRAMES {
+            return Err(format!(
+                "Mosaic of {}x{} exceeds the {} frame limit",
+                cols, rows, MAX_MOSAIC_FRAMES
+            ));
+        }
+
+        let frames_dir =
+            std::env::temp_dir().join(format!("ghost_mosaic_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&frames_dir).map_err(|e| e.to_string())?;
+
+        let result = self.extract_and_tile(clip, cols, rows, frame_width, &frames_dir, output_path);
+        let _ = std::fs::remove_dir_all(&frames_dir);
+        result?;
+
+        Ok(output_path.to_string_lossy().to_string())
+    }
+
+    /// Save one `width`-wide frame from the start of the clip's edit range.
+    pub fn extract_thumbnail(
+        &self,
+        clip: &Clip,
+        width: u32,
+        output_path: &Path,
+    ) -> Result<String, String> {
+        // Slightly past the in point, which is often a black or transition frame
+        let t = clip.source_in() + (clip.effective_duration() * 0.1).min(1.0);
+        let output = Command::new("ffmpeg")
+            .arg("-y")
+            .arg("-ss")
+            .arg(format!("{:.3}", t))
+            .arg("-i")
+            .arg(&clip.source_file)
+            .args(["-frames:v", "1", "-vf"])
+            .arg(format!("scale={}:-2", width))
+            .arg(output_path)
+            .output()
+            .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "Thumbnail extraction at {:.2}s failed: {}",
+                t,
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        Ok(output_path.to_string_lossy().to_string())
+    }
+
+    /// Single frame of `source_file` at `offset` seconds, scaled to `width`.
+    pub fn extract_frame_at(
+        &self,
+        source_file: &str,
+        offset: f64,
+        width: u32,
+        output_path: &Path,
+    ) -> Result<String, String> {
+        let output = Command::new("ffmpeg")
+            .arg("-y")
+            .arg("-ss")
+            .arg(format!("{:.3}", offset))
+            .arg("-i")
+            .arg(source_file)
+            .args(["-vframes", "1", "-vf"])
+            .arg(format!("scale={}:-1", width))
+            .args(["-q:v", "3"])
+            .arg(output_path)
+            .output()
+            .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "Frame extraction at {:.2}s failed: {}",
+                offset,
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        Ok(output_path.to_string_lossy().to_string())
+    }
+
+    /// Stabilization pass 1: run vidstabdetect over `clip`'s edit range, writing
+    /// the per-frame transforms to `trf_path`.
+    pub fn detect_stabilization(&self, clip: &Clip, trf_path: &Path) -> Result<(), String> {
+        let output = Command::new("ffmpeg")
+            .args(["-y", "-hide_banner", "-nostats"])
+            .args(["-ss", &format!("{:.3}", clip.source_in())])
+            .args(["-t", &format!("{:.3}", clip.source_length())])
+            .arg("-i")
+            .arg(&clip.source_file)
+            .arg("-vf")
+            .arg(format!(
+                "vidstabdetect=result='{}'",
+                subtitles::escape_filter_path(&trf_path.to_string_lossy())
+            ))
+            .args(["-an", "-f", "null", "-"])
+            .output()
+            .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
+
+        if !output.status.success() || !trf_path.exists() {
+            let _ = std::fs::remove_file(trf_path);
+            return Err(format!(
+                "Stabilization analysis failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        Ok(())
+    }
+
+    /// Encode a 720p CRF 28 H.264 copy of `source_file` for smooth preview playback.
+    pub fn generate_proxy(&self, source_file: &str, output_path: &Path) -> Result<String, String> {
+        let output = Command::new("ffmpeg")
+            .arg("-y")
+            .arg("-i")
+            .arg(source_file)
+            .args([
+                "-vf",
+                "scale=-2:720",
+                "-c:v",
+                "libx264",
+                "-preset",
+                "veryfast",
+            ])
+            .args(["-crf", "28", "-c:a", "aac", "-b:a", "128k"])
+            .arg(output_path)
+            .output()
+            .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
+
+        if !output.status.success() {
+            let _ = std::fs::remove_file(output_path);
+            return Err(format!(
+                "Proxy generation failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        Ok(output_path.to_string_lossy().to_string())
+    }
+
+    fn extract_and_tile(
+        &self,
+        clip: &Clip,
+        cols: u32,
+        rows: u32,
+        frame_width: u32,
+        frames_dir: &Path,
+        output_path: &Path,
+    ) -> Result<(), String> {
+        let frame_count = cols * rows;
+
+        // 1. One frame from the middle of each of N equal slices
+        for i in 0..frame_count {
+            let t = clip.source_in() + clip.source_length() * (i as f64 + 0.5) / frame_count as f64;
+            let output = Command::new("ffmpeg")
+                .arg("-y")
+                .arg("-ss")
+                .arg(format!("{:.3}", t))
+                .arg("-i")
+                .arg(&clip.source_file)
+                .args(["-frames:v", "1", "-vf"])
+                .arg(format!("scale={}:-2", frame_width))
+                .arg(frames_dir.join(format!("frame_{:03}.jpg", i)))
+                .output()
+                .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
+
+            if !output.status.success() {
+                return Err(format!(
+                    "Frame extraction at {:.2}s failed: {}",
+                    t,
+                    String::from_utf8_lossy(&output.stderr)
+                ));
+            }
+        }
+
+        // 2. Tile the frames into a single image
+        let output = Command::new("ffmpeg")
+            .arg("-y")
+            .arg("-i")
+            .arg(frames_dir.join("frame_%03d.jpg"))
+            .arg("-vf")
+            .arg(format!("tile={}x{}", cols, rows))
+            .args(["-frames:v", "1"])
+            .arg(output_path)
+            .output()
+            .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "Mosaic assembly failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Probe the first video stream of a file.
+    pub fn detect_clip_codec(&self, source_file: &str) -> Result<VideoCodecInfo, String> {
+        let output = Command::new("ffprobe")
+            .args([
+                "-v",
+                "error",
+                "-select_streams",
+                "v:0",
+                "-show_entries",
+                "stream=codec_name,width,height,r_frame_rate,pix_fmt",
+                "-of",
+                "json",
+                source_file,
+            ])
+            .output()
+            .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "ffprobe failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout)
+            .map_err(|e| format!("Failed to parse ffprobe output: {}", e))?;
+        let stream = &json["streams"][0];
+
+        Ok(VideoCodecInfo {
+            codec_name: stream["codec_name"]
+                .as_str()
+                .ok_or("No video stream found")?
+                .to_string(),
+            width: stream["width"].as_u64().unwrap_or(0) as u32,
+            height: stream["height"].as_u64().unwrap_or(0) as u32,
+            fps: stream["r_frame_rate"]
+                .as_str()
+                .and_then(parse_frame_rate)
+                .unwrap_or(0.0),
+            pix_fmt: stream["pix_fmt"].as_str().map(str::to_string),
+        })
+    }
+
+    // True when every clip can be stream-copied into the export without re-encoding.
+    fn can_passthrough(&self, clips: &[Clip], render: &RenderOptions) -> bool {
+        let mut frame_rate: Option<f64> = None;
+        for clip in clips {
+            if clip_needs_reencode(clip, render) {
+                return false;
+            }
+            let info = match self.detect_clip_codec(&clip.source_file) {
+                Ok(info) => info,
+                Err(e) => {
+                    println!("⚠️ Passthrough check failed for {}: {}", clip.id, e);
+                    return false;
+                }
+            };
+            let matches_target = info.codec_name == EXPORT_CODEC
+                && info.width == render.width
+                && info.height == render.height
+                && (info.fps - render.fps).abs() < 0.01
+                && info.pix_fmt.as_deref() == Some(EXPORT_PIX_FMT);
+            // All clips must also share a frame rate for a clean concat
+            let same_rate = frame_rate.is_none_or(|r| (r - info.fps).abs() < 0.01);
+            if !matches_target || !same_rate {
+                return false;
+            }
+            frame_rate = Some(info.fps);
+        }
+        true
+    }
+
+    // Concat already-compatible clips with the concat demuxer and stream copy.
+    fn render_passthrough(
+        &self,
+        clips: &[Clip],
+        output_path: &Path,
+        process: &FfmpegProcessSettings,
+    ) -> Result<(), String> {
+        let list_path = output_path.with_extension("concat.txt");
+        {
+            let mut list = std::fs::File::create(&list_path)
+                .map_err(|e| format!("Failed to create concat list: {}", e))?;
+            for clip in clips {
+                // Concat demuxer quoting: close quote, escaped quote, reopen
+                let escaped = clip.source_file.replace('\'', "'\\''");
+                writeln!(list, "file '{}'", escaped)
+                    .map_err(|e| format!("Failed to write concat list: {}", e))?;
+                if clip.source_in() > 0.0 {
+                    writeln!(list, "inpoint {:.4}", clip.source_in())
+                        .map_err(|e| format!("Failed to write concat list: {}", e))?;
+                }
+                writeln!(list, "outpoint {:.4}", clip.source_out())
+                    .map_err(|e| format!("Failed to write concat list: {}", e))?;
+            }
+        }
+
+        let mut cmd = process.command();
+        cmd.arg("-y")
+            .arg("-f")
+            .arg("concat")
+            .arg("-safe")
+            .arg("0")
+            .arg("-i")
+            .arg(&list_path)
+            .arg("-c:v")
+            .arg("copy")
+            .arg("-c:a")
+            .arg("copy")
+            .args(process.output_args(output_path));
+
+        println!("🎥 Running FFmpeg (passthrough): {:?}", cmd);
+
+        let output = cmd
+            .output()
+            .map_err(|e| format!("Failed to spawn ffmpeg: {}", e));
+        let _ = std::fs::remove_file(&list_path);
+        let output = output?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("FFmpeg failed: {}", stderr));
+        }
+        Ok(())
+    }
+
+    // Render through the segment cache: unchanged segments are reused, the rest
+    // re-encoded, then everything is stream-copied together.
+    fn render_cached(
+        &self,
+        clips: &[Clip],
+        output_path: &Path,
+        render: &RenderOptions,
+        progress: Option<&RenderProgress>,
+    ) -> Result<(), String> {
+        let dir = render_cache::cache_dir().join(render.cache_key());
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create render cache dir: {}", e))?;
+
+        let mut files: Vec<PathBuf> = Vec::new();
+        let mut encoded = 0;
+        for segment in render_cache::plan_cache_segments(clips) {
+            let path = dir.join(segment.file_name());
+            if !path.exists() {
+                // Encode beside the final name so a failed run never looks cached
+                let partial = path.with_extension("partial.mp4");
+                if let Some(progress) = progress {
+                    progress
+                        .offset_secs
+                        .set(segment.index as f64 * render_cache::CACHE_SEGMENT_SECS);
+                }
+                self.encode_segments(
+                    &segment.pieces,
+                    &[],
+                    &AudioMix::default(),
+                    &partial,
+                    render,
+                    progress,
+                )?;
+                std::fs::rename(&partial, &path)
+                    .map_err(|e| format!("Failed to store cached segment: {}", e))?;
+                encoded += 1;
+            }
+
+            let mut cache = self.render_cache.lock().unwrap();
+            for piece in &segment.pieces {
+                cache.record(&piece.clip.id, &path);
+            }
+            files.push(path);
+        }
+        println!(
+            "♻️ [RenderCache] Re-encoded {} of {} segments",
+            encoded,
+            files.len()
+        );
+
+        let mut list = String::new();
+        for file in &files {
+            let escaped = file.to_string_lossy().replace('\'', "'\\''");
+            list.push_str(&format!("file '{}'\n", escaped));
+        }
+        let list_path = output_path.with_extension("segments.txt");
+        std::fs::write(&list_path, list)
+            .map_err(|e| format!("Failed to write concat list: {}", e))?;
+
+        let mut cmd = render.process.command();
+        cmd.arg("-y")
+            .args(["-f", "concat", "-safe", "0", "-i"])
+            .arg(&list_path)
+            .args(["-c", "copy"])
+            .args(render.process.output_args(output_path));
+
+        println!("🎥 Running FFmpeg (segment concat): {:?}", cmd);
+
+        let output = cmd
+            .output()
+            .map_err(|e| format!("Failed to spawn ffmpeg: {}", e));
+        let _ = std::fs::remove_file(&list_path);
+        let output = output?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("FFmpeg failed: {}", stderr));
+        }
+        Ok(())
+    }
+
+    /// Render the timeline to `output_path`, reporting to `progress` while FFmpeg
+    /// encodes. Passthrough stream copies are quick and can't be cancelled.
+    pub fn render_timeline(
+        &self,
+        progress: &RenderProgress,
+        state: &TimelineState,
+        output_path: &Path,
+        options: &ExportOptions,
+        metadata: &ProjectMetadata,
+        render: &RenderOptions,
+    ) -> Result<RenderOutcome, String> {
+        if state.clips.is_empty() {
+            return Err("Timeline is empty".to_string());
+        }
+        self.check_render_capabilities()?;
+
+        // 1. Video-track clips in start order make up the picture
+        let clips = sequence_clips(state);
+        if clips.is_empty() {
+            return Err("Timeline has no clips on a video track".to_string());
+        }
+        // Clips are concatenated back to back, so gaps don't count
+        let fades = transition_fades(&clips, &state.transitions);
+        let has_fades = fades.iter().any(|&d| d > 0.0);
+        // Ducking, muting, audio-track clips, audio offsets, loudness normalization
+        // and stabilization need every clip in one filter graph, so they render
+        // like fades
+        let audio = AudioMix::for_state(&clips, state);
+        let single_pass =
+            has_fades || audio.needs_single_pass() || clips.iter().any(needs_own_filter_pass);
+        // Clips are concatenated back to back (gaps don't count), crossfades overlap
+        let expected_duration: f64 =
+            clips.iter().map(|c| c.effective_duration()).sum::<f64>() - fades.iter().sum::<f64>();
+        progress.total_secs.set(expected_duration);
+
+        // 1.5 Skip re-encoding entirely when the sources already match the export format
+        if !single_pass && options.allow_codec_passthrough && self.can_passthrough(&clips, render) {
+            self.render_passthrough(&clips, output_path, &render.process)?;
+            self.add_subtitles(output_path, &state.subtitle_tracks, options.subtitle_mode)?;
+            self.embed_metadata(output_path, metadata)?;
+            println!("✅ Render Complete (passthrough): {:?}", output_path);
+            return Ok(RenderOutcome {
+                passthrough: true,
+                verification: options
+                    .verify_render_output
+                    .then(|| self.verify_render_output(output_path, expected_duration, render)),
+            });
+        }
+
+        // 2. Re-encode each clip's edit range, a cached segment at a time if enabled
+        // Crossfades span segment boundaries, so they always render in one pass
+        if !single_pass && options.use_render_cache {
+            self.render_cached(&clips, output_path, render, Some(progress))?;
+            self.add_subtitles(output_path, &state.subtitle_tracks, options.subtitle_mode)?;
+            self.embed_metadata(output_path, metadata)?;
+            progress.emit(100.0);
+            println!("✅ Render Complete (cached segments): {:?}", output_path);
+            return Ok(RenderOutcome {
+                passthrough: false,
+                verification: options
+                    .verify_render_output
+                    .then(|| self.verify_render_output(output_path, expected_duration, render)),
+            });
+        }
+
+        let segments: Vec<RenderSegment> = clips
+            .into_iter()
+            .map(|clip| RenderSegment {
+                offset: clip.source_in(),
+                duration: clip.effective_duration(),
+                clip,
+            })
+            .collect();
+        self.encode_segments(
+            &segments,
+            &fades,
+            &audio,
+            output_path,
+            render,
+            Some(progress),
+        )?;
+        self.add_subtitles(output_path, &state.subtitle_tracks, options.subtitle_mode)?;
+        self.embed_metadata(output_path, metadata)?;
+        progress.emit(100.0);
+
+        println!("✅ Render Complete: {:?}", output_path);
+        Ok(RenderOutcome {
+            passthrough: false,
+            verification: options
+                .verify_render_output
+                .then(|| self.verify_render_output(output_path, expected_duration, render)),
+        })
+    }
+
+    /// Probe a rendered file and check it against the export settings.
+    pub fn verify_render_output(
+        &self,
+        output_path: &Path,
+        expected_duration: f64,
+        render: &RenderOptions,
+    ) -> RenderVerification {
+        let file_size_bytes = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+        let path_str = output_path.to_string_lossy();
+        let actual_duration = self.probe_duration(&path_str).unwrap_or(0.0);
+        let video = self.detect_clip_codec(&path_str).ok();
+
+        let verification = check_render_output(
+            expected_duration,
+            actual_duration,
+            video.as_ref(),
+            file_size_bytes,
+            render,
+        );
+        if !verification.success {
+            println!("⚠️ Render verification failed: {:?}", verification.warnings);
+        }
+        verification
+    }
+
+    /// Container duration in seconds, from ffprobe.
+    pub fn probe_duration(&self, path: &str) -> Result<f64, String> {
+        let output = Command::new("ffprobe")
+            .args([
+                "-v",
+                "error",
+                "-show_entries",
+                "format=duration",
+                "-of",
+                "json",
+                path,
+            ])
+            .output()
+            .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "ffprobe failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout)
+            .map_err(|e| format!("Failed to parse ffprobe output: {}", e))?;
+        json["format"]["duration"]
+            .as_str()
+            .and_then(|d| d.parse::<f64>().ok())
+            .ok_or("Could not find duration in ffprobe output".to_string())
+    }
+
+    // Probe failures count as no audio, so the clip renders with silence
+    fn source_has_audio(&self, path: &str) -> bool {
+        self.has_audio_stream(path).unwrap_or_else(|e| {
+            println!("⚠️ Audio probe failed for {}: {}", path, e);
+            false
+        })
+    }
+
+    pub fn has_audio_stream(&self, path: &str) -> Result<bool, String> {
+        let output = Command::new("ffprobe")
+            .args([
+                "-v",
+                "error",
+                "-select_streams",
+                "a",
+                "-show_entries",
+                "stream=index",
+                "-of",
+                "csv=p=0",
+                path,
+            ])
+            .output()
+            .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "ffprobe failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        Ok(!String::from_utf8_lossy(&output.stdout).trim().is_empty())
+    }
+
+    /// RMS level in dB of each `1 / samples_per_second` window of the clip's
+    /// source, oldest first. Empty when the source has no audio. Cached per clip.
+    pub fn waveform_data(&self, clip: &Clip, samples_per_second: u32) -> Result<Vec<f32>, String> {
+        let samples_per_second = samples_per_second.clamp(1, WAVEFORM_SAMPLE_RATE);
+        let key = (clip.id.clone(), samples_per_second);
+        if let Some(levels) = self.waveforms.lock().unwrap().get(&key) {
+            return Ok(levels.clone());
+        }
+
+        let levels = if self.has_audio_stream(&clip.source_file)? {
+            // A fixed rate and frame size make each astats reset cover one window
+            let filter = format!(
+                "aresample={},asetnsamples=n={}:p=0,astats=metadata=1:reset=1,\
+                 ametadata=print:key=lavfi.astats.Overall.RMS_level",
+                WAVEFORM_SAMPLE_RATE,
+                WAVEFORM_SAMPLE_RATE / samples_per_second
+            );
+            let output = Command::new("ffmpeg")
+                .arg("-hide_banner")
+                .arg("-i")
+                .arg(&clip.source_file)
+                .args(["-af", &filter, "-f", "null", "-"])
+                .output()
+                .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
+            if !output.status.success() {
+                return Err(format!(
+                    "Waveform extraction failed: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                ));
+            }
+            parse_rms_levels(&String::from_utf8_lossy(&output.stderr))
+        } else {
+            Vec::new()
+        };
+
+        self.waveforms.lock().unwrap().insert(key, levels.clone());
+        Ok(levels)
+    }
+
+    /// Cuts in the clip's source whose scene score exceeds `threshold`
+    /// (clamped to `[0, 1]`; lower finds more).
+    pub fn detect_scene_changes(
+        &self,
+        clip: &Clip,
+        threshold: f64,
+    ) -> Result<Vec<SceneChange>, String> {
+        let filter = format!(
+            "select='gt(scene,{})',metadata=print,showinfo",
+            threshold.clamp(0.0, 1.0)
+        );
+        let output = Command::new("ffmpeg")
+            .arg("-hide_banner")
+            .arg("-i")
+            .arg(&clip.source_file)
+            .args(["-vf", &filter, "-an", "-f", "null", "-"])
+            .output()
+            .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "Scene detection failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        Ok(parse_scene_changes(&String::from_utf8_lossy(
+            &output.stderr,
+        )))
+    }
+
+    /// Timeline ranges of the clip where its audio stays below `noise_db` for
+    /// at least `min_duration_s`. Clips without audio have none.
+    pub fn detect_silence_regions(
+        &self,
+        clip: &Clip,
+        noise_db: f64,
+        min_duration_s: f64,
+    ) -> Result<Vec<(f64, f64)>, String> {
+        if !self.has_audio_stream(&clip.source_file)? {
+            return Ok(Vec::new());
+        }
+        let filter = format!("silencedetect=noise={}dB:d={}", noise_db, min_duration_s);
+        let output = Command::new("ffmpeg")
+            .arg("-hide_banner")
+            .arg("-i")
+            .arg(&clip.source_file)
+            .args(["-af", &filter, "-vn", "-f", "null", "-"])
+            .output()
+            .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "Silence detection failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        let regions =
+            parse_silence_regions(&String::from_utf8_lossy(&output.stderr), clip.duration);
+        Ok(silence_timeline_ranges(clip, &regions))
+    }
+
+    /// Write project title/author/description into the MP4 container. This is a
+    /// stream-copy remux, so it costs a second pass over the file but no re-encode.
+    pub fn embed_metadata(
+        &self,
+        output_path: &Path,
+        metadata: &ProjectMetadata,
+    ) -> Result<(), String> {
+        if metadata.is_empty() {
+            return Ok(());
+        }
+
+        let tagged_path = output_path.with_extension("tagged.mp4");
+        let mut cmd = Command::new("ffmpeg");
+        cmd.arg("-y").arg("-i").arg(output_path);
+        cmd.args(["-map", "0", "-map_metadata", "0", "-c", "copy"]);
+        for (key, value) in metadata.ffmpeg_tags() {
+            cmd.arg("-metadata").arg(format!("{}={}", key, value));
+        }
+        cmd.arg(&tagged_path);
+
+        println!("🏷️ Embedding metadata: {:?}", cmd);
+        let output = cmd
+            .output()
+            .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
+
+        if !output.status.success() {
+            let _ = std::fs::remove_file(&tagged_path);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("FFmpeg metadata pass failed: {}", stderr));
+        }
+
+        std::fs::rename(&tagged_path, output_path)
+            .map_err(|e| format!("Failed to replace export with tagged file: {}", e))
+    }
+
+    /// Add `tracks` to a finished export, burnt in or as `mov_text` streams.
+    /// Each file is shifted by its track's offset on the way.
+    pub fn add_subtitles(
+        &self,
+        output_path: &Path,
+        tracks: &[SubtitleTrack],
+        mode: SubtitleMode,
+    ) -> Result<(), String> {
+        if tracks.is_empty() {
+            return Ok(());
+        }
+        let work_dir = std::env::temp_dir().join(format!("ghost_subs_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&work_dir)
+            .map_err(|e| format!("Failed to create subtitle work dir: {}", e))?;
+        let result = self.mux_subtitles(output_path, tracks, mode, &work_dir);
+        let _ = std::fs::remove_dir_all(&work_dir);
+        result
+    }
+
+    fn mux_subtitles(
+        &self,
+        output_path: &Path,
+        tracks: &[SubtitleTrack],
+        mode: SubtitleMode,
+        work_dir: &Path,
+    ) -> Result<(), String> {
+        let mut srt_paths = Vec::with_capacity(tracks.len());
+        for (i, track) in tracks.iter().enumerate() {
+            let content = std::fs::read_to_string(&track.srt_path)
+                .map_err(|e| format!("Failed to read subtitles {}: {}", track.srt_path, e))?;
+            let shifted = work_dir.join(format!("track_{}.srt", i));
+            std::fs::write(&shifted, subtitles::shift_srt(&content, track.offset_s))
+                .map_err(|e| format!("Failed to write shifted subtitles: {}", e))?;
+            srt_paths.push(shifted);
+        }
+
+        let subtitled_path = output_path.with_extension("subtitled.mp4");
+        let mut cmd = Command::new("ffmpeg");
+        cmd.arg("-y").arg("-i").arg(output_path);
+        match mode {
+            SubtitleMode::BurnIn => {
+                let paths: Vec<&Path> = srt_paths.iter().map(PathBuf::as_path).collect();
+                cmd.arg("-vf")
+                    .arg(subtitles::burn_in_filter(&paths))
+                    .args(["-c:v", "libx264", "-preset", "fast", "-pix_fmt", "yuv420p"])
+                    .args(["-c:a", "copy"]);
+            }
+            SubtitleMode::Soft => {
+                for path in &srt_paths {
+                    cmd.arg("-i").arg(path);
+                }
+                cmd.args(["-map", "0"]);
+                for i in 1..=srt_paths.len() {
+                    cmd.arg("-map").arg(i.to_string());
+                }
+                cmd.args(["-c", "copy", "-c:s", "mov_text"]);
+            }
+        }
+        cmd.arg(&subtitled_path);
+
+        println!("💬 Adding subtitles: {:?}", cmd);
+        let output = cmd
+            .output()
+            .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
+        if !output.status.success() {
+            let _ = std::fs::remove_file(&subtitled_path);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("FFmpeg subtitle pass failed: {}", stderr));
+        }
+
+        std::fs::rename(&subtitled_path, output_path)
+            .map_err(|e| format!("Failed to replace export with subtitled file: {}", e))
+    }
+
+    /// Render only `[start_time, end_time]` of the timeline. Clips crossing either
+    /// boundary are trimmed to exactly the portion inside the region, so this path
+    /// always re-encodes (stream copy can only cut on keyframes).
+    pub fn export_region(
+        &self,
+        state: &TimelineState,
+        start_time: f64,
+        end_time: f64,
+        output_path: &Path,
+        options: &ExportOptions,
+        render: &RenderOptions,
+    ) -> Result<RenderOutcome, String> {
+        let segments = region_segments(state, start_time, end_time)?;
+        self.check_render_capabilities()?;
+        let audio = AudioMix::for_region(state, start_time, end_time);
+        let single_pass =
+            audio.needs_single_pass() || segments.iter().any(|s| needs_own_filter_pass(&s.clip));
+
+        if !single_pass && options.use_render_cache {
+            // Trim each clip to its part of the region so the cache plans the
+            // same pieces
+            let clips: Vec<Clip> = segments
+                .iter()
+                .map(|s| {
+                    let mut clip = s.clip.clone();
+                    clip.edit_in = Some(s.offset);
+                    clip.edit_out = Some(s.offset + s.duration * clip.speed_multiplier);
+                    clip
+                })
+                .collect();
+            self.render_cached(&clips, output_path, render, None)?;
+        } else {
+            self.encode_segments(&segments, &[], &audio, output_path, render, None)?;
+        }
+
+        println!(
+            "✅ Region Render Complete [{:.2}s, {:.2}s]: {:?}",
+            start_time, end_time, output_path
+        );
+        let expected_duration: f64 = segments.iter().map(|s| s.duration).sum();
+        Ok(RenderOutcome {
+            passthrough: false,
+            verification: options
+                .verify_render_output
+                .then(|| self.verify_render_output(output_path, expected_duration, render)),
+        })
+    }
+
+    /// Render `[start_time, end_time]` as an animated GIF `scale` pixels wide.
+    /// The region is rendered like `export_region`, then converted with a
+    /// palette generated from its own frames. `fps` and `scale` are clamped
+    /// to `gif_filters`' ranges.
+    pub fn export_gif(
+        &self,
+        state: &TimelineState,
+        start_time: f64,
+        end_time: f64,
+        scale: u32,
+        fps: u32,
+        output_path: &Path,
+    ) -> Result<String, String> {
+        let work_dir = std::env::temp_dir().join(format!("ghost_gif_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&work_dir)
+            .map_err(|e| format!("Failed to create GIF work dir: {}", e))?;
+        let region = work_dir.join("region.mp4");
+        let result = self
+            .export_region(
+                state,
+                start_time,
+                end_time,
+                &region,
+                // The intermediate file is thrown away, so don't probe it
+                &ExportOptions {
+                    verify_render_output: false,
+                    ..Default::default()
+                },
+                &RenderOptions::default(),
+            )
+            .and_then(|_| self.encode_gif(&region, &gif_filters(fps, scale), output_path));
+        let _ = std::fs::remove_dir_all(&work_dir);
+        result?;
+
+        println!(
+            "✅ GIF Export Complete [{:.2}s, {:.2}s]: {:?}",
+            start_time, end_time, output_path
+        );
+        Ok(output_path.to_string_lossy().to_string())
     }
 
-    pub fn render_timeline(&self, state: &TimelineState, output_path: &Path) -> Result<(), String> {
+    // Both palette passes over an already rendered `region`
+    fn encode_gif(
+        &self,
+        region: &Path,
+        (palette_filter, gif_filter): &(String, String),
+        output_path: &Path,
+    ) -> Result<(), String> {
+        let palette = region.with_file_name("palette.png");
+
+        // Pass 1: the 256 colours that best fit the region
+        let output = Command::new("ffmpeg")
+            .arg("-y")
+            .arg("-i")
+            .arg(region)
+            .arg("-vf")
+            .arg(palette_filter)
+            .arg(&palette)
+            .output()
+            .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "GIF palette generation failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        // Pass 2: map every frame onto that palette
+        let output = Command::new("ffmpeg")
+            .arg("-y")
+            .arg("-i")
+            .arg(region)
+            .arg("-i")
+            .arg(&palette)
+            .arg("-lavfi")
+            .arg(gif_filter)
+            .arg(output_path)
+            .output()
+            .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
+        if !output.status.success() {
+            let _ = std::fs::remove_file(output_path);
+            return Err(format!(
+                "GIF encoding failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        Ok(())
+    }
+
+    /// Mix the timeline's audio, laid out like `render_timeline` lays out the
+    /// video, into an "mp3", "aac" or "wav" file. Video clips without audio add
+    /// silence; audio-track clips are mixed in at their start time.
+    pub fn export_audio_only(
+        &self,
+        state: &TimelineState,
+        format: &str,
+        bitrate_kbps: Option<u32>,
+        output_path: &Path,
+    ) -> Result<String, String> {
         if state.clips.is_empty() {
             return Err("Timeline is empty".to_string());
         }
+        let codec_args = audio_codec_args(format, bitrate_kbps)?;
 
-        // 1. Sort clips by start time to ensure correct sequence
-        let mut clips = state.clips.clone();
-        clips.sort_by(|a, b| a.start.partial_cmp(&b.start).unwrap());
+        let clips = sequence_clips(state);
+        let fades = transition_fades(&clips, &state.transitions);
+        let audio = AudioMix::for_state(&clips, state);
+        let segments: Vec<RenderSegment> = clips
+            .into_iter()
+            .map(|clip| RenderSegment {
+                offset: clip.source_in(),
+                duration: clip.effective_duration(),
+                clip,
+            })
+            .collect();
 
-        // 2. Build FFmpeg Command
-        let mut cmd = Command::new("ffmpeg");
-        cmd.arg("-y"); // Overwrite output
+        let process = FfmpegProcessSettings::default();
+        let mut cmd = process.command();
+        cmd.arg("-y");
+        let (audio, has_audio) = self.add_segment_inputs(&mut cmd, &segments, &audio);
+        if segments.is_empty() && audio.overlays.is_empty() {
+            return Err("Timeline has no audio to export".to_string());
+        }
+        cmd.arg("-filter_complex")
+            .arg(build_audio_filter(&segments, &fades, &audio, &has_audio))
+            .args(["-map", "[outa]", "-vn"])
+            .args(&codec_args)
+            .args(process.output_args(output_path));
 
-        // Add Inputs
-        for clip in &clips {
-            cmd.arg("-i").arg(&clip.source_file);
+        println!("🎧 Running FFmpeg: {:?}", cmd);
+        let output = cmd
+            .output()
+            .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
+        if !output.status.success() {
+            let _ = std::fs::remove_file(output_path);
+            return Err(format!(
+                "Audio export failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
         }
 
-        // 3. Build Filter Complex
-        // Goal: Scale all inputs to 1920x1080 (with padding) -> Trim -> Concat
-        let mut filter_complex = String::new();
-        let mut concat_inputs = String::new();
+        println!("✅ Audio Export Complete: {:?}", output_path);
+        Ok(output_path.to_string_lossy().to_string())
+    }
 
-        for (i, clip) in clips.iter().enumerate() {
-            // Video Filter Chain:
-            // 1. Scale to fit within 1920x1080 while maintaining aspect ratio
-            // 2. Pad to exactly 1920x1080 (centering the video)
-            // 3. Trim to duration
-            // 4. Reset timestamps
+    // Add an input per segment, seeked to its portion of the source, then one
+    // per ducking trigger and one per overlay. Returns the triggers and overlays
+    // that can be heard (those with audio) and which segments have audio.
+    fn add_segment_inputs(
+        &self,
+        cmd: &mut Command,
+        segments: &[RenderSegment],
+        audio: &AudioMix,
+    ) -> (AudioMix, Vec<bool>) {
+        for segment in segments {
+            cmd.arg("-ss")
+                .arg(format!("{:.4}", segment.offset))
+                .arg("-to")
+                .arg(format!("{:.4}", segment.offset + segment.source_duration()))
+                .arg("-i")
+                .arg(&segment.clip.source_file);
+        }
+        let has_audio: Vec<bool> = segments
+            .iter()
+            .map(|s| self.source_has_audio(&s.clip.source_file))
+            .collect();
+        // A trigger without audio never gets loud, so it can't duck anything
+        let mut audio = audio.clone();
+        audio
+            .triggers
+            .retain(|t| self.source_has_audio(&t.source_file));
 
-            // scale=1920:1080:force_original_aspect_ratio=decrease
-            // pad=1920:1080:(ow-iw)/2:(oh-ih)/2
+        audio
+            .overlays
+            .retain(|o| self.source_has_audio(&o.segment.clip.source_file));
 
-            filter_complex.push_str(&format!(
-                "[{}:v]scale=1920:1080:force_original_aspect_ratio=decrease,pad=1920:1080:(ow-iw)/2:(oh-ih)/2,trim=duration={:.4},setpts=PTS-STARTPTS[v{}];",
-                i, clip.duration, i
-            ));
+        // Sidechain sources, indexed after the segments
+        for trigger in &audio.triggers {
+            cmd.arg("-ss")
+                .arg(format!("{:.4}", trigger.source_in()))
+                .arg("-to")
+                .arg(format!("{:.4}", trigger.source_out()))
+                .arg("-i")
+                .arg(&trigger.source_file);
+        }
+        // Then the audio-track clips
+        for overlay in &audio.overlays {
+            let segment = &overlay.segment;
+            cmd.arg("-ss")
+                .arg(format!("{:.4}", segment.offset))
+                .arg("-to")
+                .arg(format!("{:.4}", segment.offset + segment.source_duration()))
+                .arg("-i")
+                .arg(&segment.clip.source_file);
+        }
+        (audio, has_audio)
+    }
 
-            concat_inputs.push_str(&format!("[v{}]", i));
+    // Scale/pad/trim each segment and concat them into an H.264/AAC MP4. `fades[i]`
+    // is the crossfade into segment i (missing or 0 = hard cut). Sources without
+    // an audio stream contribute silence. Reports to `progress` when given.
+    fn encode_segments(
+        &self,
+        segments: &[RenderSegment],
+        fades: &[f64],
+        audio: &AudioMix,
+        output_path: &Path,
+        render: &RenderOptions,
+        progress: Option<&RenderProgress>,
+    ) -> Result<(), String> {
+        // 1. Build FFmpeg Command
+        let mut cmd = render.process.command();
+        cmd.arg("-y"); // Overwrite output
+        if progress.is_some() {
+            cmd.args(["-progress", "pipe:1", "-nostats"]);
         }
 
-        // Concat Filter
-        filter_complex.push_str(&format!(
-            "{}concat=n={}:v=1:a=0[outv]",
-            concat_inputs,
-            clips.len()
-        ));
+        let (audio, has_audio) = self.add_segment_inputs(&mut cmd, segments, audio);
 
+        // 2. Build Filter Complex
+        let mut filter_complex = build_filter_complex(segments, fades, render);
+        filter_complex.push(';');
+        filter_complex.push_str(&build_audio_filter(segments, fades, &audio, &has_audio));
         cmd.arg("-filter_complex").arg(filter_complex);
         cmd.arg("-map").arg("[outv]");
+        cmd.arg("-map").arg("[outa]");
+        cmd.args(["-c:a", "aac", "-b:a", "192k"]);
 
         // Output Format (MP4 / H.264)
-        cmd.arg("-c:v").arg("libx264");
-        cmd.arg("-preset").arg("fast");
+        cmd.arg("-c:v").arg(&render.video_encoder);
+        // Hardware encoders have their own preset names
+        if render.video_encoder == FALLBACK_VIDEO_ENCODER {
+            cmd.arg("-preset").arg("fast");
+        }
         cmd.arg("-pix_fmt").arg("yuv420p"); // Ensure compatibility
-        cmd.arg(output_path);
+        cmd.args(render.process.output_args(output_path));
 
         println!("🎥 Running FFmpeg: {:?}", cmd);
 
         // 3. Execute
+        if let Some(progress) = progress {
+            return progress.run(&mut cmd);
+        }
         let output = cmd
             .output()
             .map_err(|e| format!("Failed to spawn ffmpeg: {}", e))?;
@@ -79,7 +1936,1204 @@ impl FFmpegEngine {
             return Err(format!("FFmpeg failed: {}", stderr));
         }
 
-        println!("✅ Render Complete: {:?}", output_path);
         Ok(())
     }
 }
+
+/// Crossfade length into each of `clips` (sorted, rendered back to back); 0.0 is a
+/// hard cut. Fades are capped so they never exceed either clip.
+pub fn transition_fades(clips: &[Clip], transitions: &[TransitionPoint]) -> Vec<f64> {
+    let mut fades = vec![0.0; clips.len()];
+    for i in 1..clips.len() {
+        let (a, b) = (&clips[i - 1], &clips[i]);
+        let registered = transitions.iter().find(|t| t.connects(&a.id, &b.id));
+        if let Some(t) = registered {
+            let limit = a.effective_duration().min(b.effective_duration()) - 0.01;
+            fades[i] = t.duration.min(limit).max(0.0);
+        }
+    }
+    fades
+}
+
+// Goal: Scale all inputs to the export size (with padding) -> Trim -> Concat, or
+// chain xfade/concat pairwise when there are crossfades.
+fn build_filter_complex(
+    segments: &[RenderSegment],
+    fades: &[f64],
+    render: &RenderOptions,
+) -> String {
+    let fade_into = |i: usize| fades.get(i).copied().unwrap_or(0.0);
+    let has_fades = (0..segments.len()).any(|i| fade_into(i) > 0.0);
+    let mut filter_complex = String::new();
+
+    for (i, segment) in segments.iter().enumerate() {
+        // Video Filter Chain:
+        // 1. Scale to fit within the export size while maintaining aspect ratio
+        // 2. Pad to exactly the export size (centering the video)
+        // 3. Trim to the segment's length in the source (input already seeked)
+        // 4. Reset timestamps, scaled by the clip's speed, and resample to the
+        //    export frame rate (xfade also needs a shared timebase)
+
+        // scale=1920:1080:force_original_aspect_ratio=decrease
+        // pad=1920:1080:(ow-iw)/2:(oh-ih)/2
+
+        let normalize = if has_fades {
+            format!(",fps={},settb=AVTB", render.fps)
+        } else {
+            format!(",fps={}", render.fps)
+        };
+        let (w, h) = (render.width, render.height);
+        let speed = segment.clip.speed_multiplier;
+        let setpts = if (speed - 1.0).abs() > 1e-6 {
+            format!("setpts=(PTS-STARTPTS)/{:.4}", speed)
+        } else {
+            "setpts=PTS-STARTPTS".to_string()
+        };
+        let stabilize = match segment
+            .clip
+            .stabilization_for(segment.offset, segment.source_duration())
+        {
+            Some(trf) => format!(
+                "vidstabtransform=smoothing={}:input='{}',",
+                segment.clip.stabilization_smoothing,
+                subtitles::escape_filter_path(trf)
+            ),
+            _ => String::new(),
+        };
+        filter_complex.push_str(&format!(
+            "[{}:v]{}scale={}:{}:force_original_aspect_ratio=decrease,pad={}:{}:(ow-iw)/2:(oh-ih)/2,trim=duration={:.4},{}{}[v{}];",
+            i,
+            stabilize,
+            w,
+            h,
+            w,
+            h,
+            segment.source_duration(),
+            setpts,
+            normalize,
+            i
+        ));
+    }
+
+    if !has_fades {
+        // Concat Filter
+        let concat_inputs: String = (0..segments.len()).map(|i| format!("[v{}]", i)).collect();
+        filter_complex.push_str(&format!(
+            "{}concat=n={}:v=1:a=0[outv]",
+            concat_inputs,
+            segments.len()
+        ));
+        return filter_complex;
+    }
+
+    // Fold left to right, tracking the running output length for xfade offsets
+    let mut current = "v0".to_string();
+    let mut length = segments.first().map_or(0.0, |s| s.duration);
+    for (i, segment) in segments.iter().enumerate().skip(1) {
+        let label = if i == segments.len() - 1 {
+            "outv".to_string()
+        } else {
+            format!("x{}", i)
+        };
+        let fade = fade_into(i);
+        if fade > 0.0 {
+            filter_complex.push_str(&format!(
+                "[{}][v{}]xfade=transition=fade:duration={:.4}:offset={:.4}[{}];",
+                current,
+                i,
+                fade,
+                length - fade,
+                label
+            ));
+        } else {
+            filter_complex.push_str(&format!(
+                "[{}][v{}]concat=n=2:v=1:a=0[{}];",
+                current, i, label
+            ));
+        }
+        length += segment.duration - fade;
+        current = label;
+    }
+    filter_complex.pop(); // trailing ';'
+    filter_complex
+}
+
+/// "ffmpeg version 6.1.1-3ubuntu5 Copyright ..." -> "6.1.1-3ubuntu5".
+fn parse_ffmpeg_version(output: &str) -> String {
+    output
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("ffmpeg version "))
+        .and_then(|rest| rest.split_whitespace().next())
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+// `ffmpeg -encoders` lists " V....D libx264  description" after a " ------" rule
+fn parse_encoder_names(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with("------"))
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(str::to_string)
+        .collect()
+}
+
+// `ffmpeg -filters` lists " TSC scale  V->V  description"; legend lines have no "->"
+fn parse_filter_names(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _flags = fields.next()?;
+            let name = fields.next()?;
+            fields
+                .next()
+                .filter(|io| io.contains("->"))
+                .map(|_| name.to_string())
+        })
+        .collect()
+}
+
+// ametadata prints "lavfi.astats.Overall.RMS_level=-23.4" once per frame
+fn parse_rms_levels(stderr: &str) -> Vec<f32> {
+    stderr
+        .lines()
+        .filter_map(|line| line.split("lavfi.astats.Overall.RMS_level=").nth(1))
+        .filter_map(|value| value.trim().parse::<f32>().ok())
+        .map(|db| {
+            if db.is_finite() {
+                db.max(WAVEFORM_SILENCE_DB)
+            } else {
+                WAVEFORM_SILENCE_DB
+            }
+        })
+        .collect()
+}
+
+/// Clips on video tracks, sorted by start: the ones concatenated into the
+/// picture. Audio-track clips are mixed in at their own time instead.
+pub fn sequence_clips(state: &TimelineState) -> Vec<Clip> {
+    let mut clips: Vec<Clip> = state
+        .clips
+        .iter()
+        .filter(|c| !state.is_audio_track(&c.track_id))
+        .cloned()
+        .collect();
+    clips.sort_by(|a, b| a.start.total_cmp(&b.start));
+    clips
+}
+
+/// An audio-track clip's part of the mix, laid over the segments' audio.
+#[derive(Debug, Clone)]
+pub struct AudioOverlay {
+    pub segment: RenderSegment,
+    /// Seconds into the output where the segment starts playing.
+    pub output_start: f64,
+}
+
+/// Audio rendering inputs beyond the segments themselves.
+#[derive(Debug, Clone, Default)]
+pub struct AudioMix {
+    /// Ducking trigger clips, fed in as sidechain inputs.
+    pub triggers: Vec<Clip>,
+    /// Tracks whose clips are silenced in the mix, by mute or solo.
+    pub muted_track_ids: Vec<String>,
+    /// Audio-track clips, mixed under the segments' audio.
+    pub overlays: Vec<AudioOverlay>,
+}
+
+impl AudioMix {
+    pub fn for_state(clips: &[Clip], state: &TimelineState) -> Self {
+        let muted_track_ids = state.silenced_track_ids();
+        // A muted trigger is not heard, so it ducks nothing
+        let all_clips: Vec<Clip> = state
+            .clips
+            .iter()
+            .filter(|c| !muted_track_ids.contains(&c.track_id))
+            .cloned()
+            .collect();
+        let overlays = Self::overlays_in(state, &muted_track_ids, 0.0, f64::INFINITY);
+        Self {
+            triggers: ducking_triggers(clips, &all_clips),
+            muted_track_ids,
+            overlays,
+        }
+    }
+
+    /// Muting and audio-track clips within `[start_time, end_time]`, with the
+    /// region start at output time 0. Regions don't duck.
+    pub fn for_region(state: &TimelineState, start_time: f64, end_time: f64) -> Self {
+        let muted_track_ids = state.silenced_track_ids();
+        let overlays = Self::overlays_in(state, &muted_track_ids, start_time, end_time);
+        Self {
+            triggers: Vec::new(),
+            muted_track_ids,
+            overlays,
+        }
+    }
+
+    fn overlays_in(
+        state: &TimelineState,
+        muted_track_ids: &[String],
+        start_time: f64,
+        end_time: f64,
+    ) -> Vec<AudioOverlay> {
+        state
+            .clips
+            .iter()
+            .filter(|c| state.is_audio_track(&c.track_id) && !muted_track_ids.contains(&c.track_id))
+            .filter_map(|clip| {
+                let duration = clip.end().min(end_time) - clip.start.max(start_time);
+                if duration <= 0.001 {
+                    return None;
+                }
+                Some(AudioOverlay {
+                    segment: RenderSegment {
+                        offset: clip.source_in()
+                            + (start_time - clip.start).max(0.0) * clip.speed_multiplier,
+                        duration,
+                        clip: clip.clone(),
+                    },
+                    output_start: (clip.start - start_time).max(0.0),
+                })
+            })
+            .collect()
+    }
+
+    /// Ducking, muting and audio-track clips aren't applied by the cached or
+    /// passthrough renders.
+    pub fn needs_single_pass(&self) -> bool {
+        !self.triggers.is_empty() || !self.muted_track_ids.is_empty() || !self.overlays.is_empty()
+    }
+
+    fn is_muted(&self, clip: &Clip) -> bool {
+        self.muted_track_ids.contains(&clip.track_id)
+    }
+}
+
+/// Trigger clips (looked up in `all_clips`) that duck any of `clips`, each once.
+pub fn ducking_triggers(clips: &[Clip], all_clips: &[Clip]) -> Vec<Clip> {
+    let mut triggers: Vec<Clip> = Vec::new();
+    for id in clips.iter().filter_map(|c| c.duck_when_active.as_deref()) {
+        if triggers.iter().any(|t| t.id == id) {
+            continue;
+        }
+        if let Some(trigger) = all_clips.iter().find(|c| c.id == id) {
+            triggers.push(trigger.clone());
+        }
+    }
+    triggers
+}
+
+// Audio counterpart of `build_filter_complex`, ending in [outa]. Each trigger is
+// asplit into one sidechain per clip it ducks, aligned to that clip's timeline
+// start, and drives a sidechaincompress on the clip's audio. Trigger input k is
+// at index `segments.len() + k`. Segments whose `has_audio` is false, and clips
+// on muted tracks, contribute silence. Overlay input k follows the triggers and
+// is delayed to its output start, then amixed over the joined segments.
+fn build_audio_filter(
+    segments: &[RenderSegment],
+    fades: &[f64],
+    audio: &AudioMix,
+    has_audio: &[bool],
+) -> String {
+    let triggers = &audio.triggers;
+    let trigger_index = |segment: &RenderSegment| {
+        let id = segment.clip.duck_when_active.as_deref()?;
+        triggers.iter().position(|t| t.id == id)
+    };
+    let mut filter = String::new();
+
+    // Split each trigger's audio once per ducked clip
+    let mut sidechains: Vec<Vec<String>> = vec![Vec::new(); triggers.len()];
+    for (i, segment) in segments.iter().enumerate() {
+        if let Some(k) = trigger_index(segment) {
+            sidechains[k].push(format!("sc{}", i));
+        }
+    }
+    for (k, labels) in sidechains.iter().enumerate() {
+        if labels.is_empty() {
+            continue;
+        }
+        let outputs: String = labels.iter().map(|l| format!("[{}_raw]", l)).collect();
+        filter.push_str(&format!(
+            "[{}:a]asplit={}{};",
+            segments.len() + k,
+            labels.len(),
+            outputs
+        ));
+    }
+
+    for (i, segment) in segments.iter().enumerate() {
+        let trim = if has_audio.get(i).copied().unwrap_or(true) {
+            segment_audio_chain(i, segment)
+        } else {
+            format!(
+                "aevalsrc=0:channel_layout=stereo:sample_rate=48000,atrim=duration={:.4}",
+                segment.duration
+            )
+        };
+        if audio.is_muted(&segment.clip) {
+            filter.push_str(&format!("{},volume=0[a{}];", trim, i));
+            continue;
+        }
+        let Some(k) = trigger_index(segment) else {
+            filter.push_str(&format!("{}[a{}];", trim, i));
+            continue;
+        };
+        let trigger = &triggers[k];
+        let clip = &segment.clip;
+
+        // Seconds the trigger starts after this segment's first rendered frame
+        let segment_start =
+            clip.start + (segment.offset - clip.source_in()) / clip.speed_multiplier;
+        let lead = trigger.start - segment_start;
+        let align = if lead >= 0.0 {
+            format!("adelay={:.0}:all=1", lead * 1000.0)
+        } else {
+            format!("atrim=start={:.4},asetpts=PTS-STARTPTS", -lead)
+        };
+        // A hard compressor blended with the dry signal: while the trigger is
+        // loud the output settles at the dry level scaled by ducking_amount_db
+        let mix = 1.0 - 10f64.powf(clip.ducking_amount_db.min(0.0) / 20.0);
+        filter.push_str(&format!(
+            "[sc{i}_raw]{align},apad[sc{i}];{trim}[ad{i}];[ad{i}][sc{i}]sidechaincompress=threshold=0.02:ratio=20:attack={attack}:release={release}:mix={mix:.4},atrim=duration={duration:.4}[a{i}];",
+            i = i,
+            align = align,
+            trim = trim,
+            attack = clip.duck_attack_ms,
+            release = clip.duck_release_ms,
+            mix = mix,
+            duration = segment.duration,
+        ));
+    }
+
+    // Join like the video: one concat for hard cuts only, otherwise acrossfade
+    // where the video crossfades and concat pairwise between
+    let joined = if audio.overlays.is_empty() {
+        "outa"
+    } else {
+        "amain"
+    };
+    if segments.is_empty() {
+        // Audio-only timeline: the overlays play over silence
+        let length = audio
+            .overlays
+            .iter()
+            .map(|o| o.output_start + o.segment.duration)
+            .fold(0.0, f64::max);
+        filter.push_str(&format!(
+            "aevalsrc=0:channel_layout=stereo:sample_rate=48000,atrim=duration={:.4}[{}];",
+            length, joined
+        ));
+    } else if fades.iter().all(|&d| d <= 0.0) {
+        let inputs: String = (0..segments.len()).map(|i| format!("[a{}]", i)).collect();
+        filter.push_str(&format!(
+            "{}concat=n={}:v=0:a=1[{}];",
+            inputs,
+            segments.len(),
+            joined
+        ));
+    } else {
+        join_crossfaded_audio(&mut filter, segments.len(), fades, joined);
+    }
+
+    if !audio.overlays.is_empty() {
+        let first_input = segments.len() + audio.triggers.len();
+        let mut mix_inputs = format!("[{}]", joined);
+        for (k, overlay) in audio.overlays.iter().enumerate() {
+            filter.push_str(&format!(
+                "{},adelay={:.0}:all=1[m{}];",
+                segment_audio_chain(first_input + k, &overlay.segment),
+                overlay.output_start * 1000.0,
+                k
+            ));
+            mix_inputs.push_str(&format!("[m{}]", k));
+        }
+        filter.push_str(&format!(
+            "{}amix=inputs={}:duration=first:normalize=0[outa];",
+            mix_inputs,
+            audio.overlays.len() + 1
+        ));
+    }
+    filter.pop(); // trailing ';'
+    filter
+}
+
+// "[{input}:a]atrim=...", through speed, loudness, gain and audio offset, for
+// a segment whose input is already seeked to its offset
+fn segment_audio_chain(input: usize, segment: &RenderSegment) -> String {
+    let (start, align) = audio_offset_filters(segment);
+    // loudnorm works at 192 kHz, so resample back before the concat
+    let loudness = segment
+        .clip
+        .audio_loudness
+        .as_ref()
+        .map_or(String::new(), |l| {
+            format!(
+                ",{},aresample=48000",
+                loudnorm_filter(l.target_lufs, &l.stats)
+            )
+        });
+    format!(
+        "[{}:a]atrim={}duration={:.4},asetpts=PTS-STARTPTS{}{}{}{}",
+        input,
+        start,
+        segment.source_duration(),
+        atempo_chain(segment.clip.speed_multiplier),
+        loudness,
+        volume_filter(segment.clip.volume_db),
+        align
+    )
+}
+
+// acrossfade where `fades` has a crossfade, concat pairwise between, into [`output`]
+fn join_crossfaded_audio(filter: &mut String, count: usize, fades: &[f64], output: &str) {
+    let mut current = "a0".to_string();
+    for i in 1..count {
+        let label = if i == count - 1 {
+            output.to_string()
+        } else {
+            format!("ax{}", i)
+        };
+        let fade = fades.get(i).copied().unwrap_or(0.0);
+        if fade > 0.0 {
+            filter.push_str(&format!(
+                "[{}][a{}]acrossfade=d={:.4}[{}];",
+                current, i, fade, label
+            ));
+        } else {
+            filter.push_str(&format!(
+                "[{}][a{}]concat=n=2:v=0:a=1[{}];",
+                current, i, label
+            ));
+        }
+        current = label;
+    }
+}
+
+// ("start=...:" for the atrim, filters after it) shifting a segment's audio by
+// its clip's audio_offset_s. A late start is delayed and cut back to the
+// segment's length; an early one reads ahead in the source and pads the end.
+fn audio_offset_filters(segment: &RenderSegment) -> (String, String) {
+    let offset = segment.clip.audio_offset_s;
+    if offset > 1e-6 {
+        (
+            String::new(),
+            format!(
+                ",adelay={:.0}:all=1,atrim=duration={:.4}",
+                offset * 1000.0,
+                segment.duration
+            ),
+        )
+    } else if offset < -1e-6 {
+        (
+            format!("start={:.4}:", -offset * segment.clip.speed_multiplier),
+            format!(",apad,atrim=duration={:.4}", segment.duration),
+        )
+    } else {
+        (String::new(), String::new())
+    }
+}
+
+/// The part of a clip's source that contributes to a render.
+#[derive(Debug, Clone)]
+pub struct RenderSegment {
+    pub clip: Clip,
+    /// Seconds into the clip's source where the segment begins.
+    pub offset: f64,
+    /// Seconds of output the segment renders to.
+    pub duration: f64,
+}
+
+impl RenderSegment {
+    /// Seconds of source read to fill `duration` at the clip's speed.
+    pub fn source_duration(&self) -> f64 {
+        self.duration * self.clip.speed_multiplier
+    }
+}
+
+/// ",volume=...dB" for a clip's gain (empty at 0 dB).
+pub fn volume_filter(volume_db: f64) -> String {
+    if volume_db.abs() < 1e-6 {
+        String::new()
+    } else {
+        format!(",volume={:.2}dB", volume_db)
+    }
+}
+
+/// ",atempo=..." filters changing audio tempo by `speed` (empty at 1x). Each
+/// atempo only accepts `[0.5, 2.0]`, so larger changes are chained.
+pub fn atempo_chain(speed: f64) -> String {
+    let mut chain = String::new();
+    let mut remaining = speed;
+    while remaining > 2.0 {
+        chain.push_str(",atempo=2.0000");
+        remaining /= 2.0;
+    }
+    while remaining < 0.5 {
+        chain.push_str(",atempo=0.5000");
+        remaining /= 0.5;
+    }
+    if (remaining - 1.0).abs() > 1e-6 {
+        chain.push_str(&format!(",atempo={:.4}", remaining));
+    }
+    chain
+}
+
+/// Encoder arguments for an audio-only export. `bitrate_kbps` defaults to 192
+/// and is ignored for uncompressed WAV.
+pub fn audio_codec_args(format: &str, bitrate_kbps: Option<u32>) -> Result<Vec<String>, String> {
+    let bitrate = format!("{}k", bitrate_kbps.unwrap_or(192));
+    let args: &[&str] = match format {
+        "mp3" => &["-c:a", "libmp3lame", "-b:a", &bitrate],
+        "aac" => &["-c:a", "aac", "-b:a", &bitrate],
+        "wav" => &["-c:a", "pcm_s16le"],
+        other => {
+            return Err(format!(
+                "Unknown audio format '{}' (expected mp3, aac or wav)",
+                other
+            ))
+        }
+    };
+    Ok(args.iter().map(|a| a.to_string()).collect())
+}
+
+/// Filters for the two GIF passes: `palettegen` for the first, `paletteuse`
+/// (with the palette as input 1) for the second. `fps` is clamped to [1, 30]
+/// and `scale`, the output width, to [100, 1920].
+pub fn gif_filters(fps: u32, scale: u32) -> (String, String) {
+    let base = format!(
+        "fps={},scale={}:-1:flags=lanczos",
+        fps.clamp(1, 30),
+        scale.clamp(100, 1920)
+    );
+    (
+        format!("{},palettegen", base),
+        format!("{}[x];[x][1:v]paletteuse", base),
+    )
+}
+
+/// Video-track clip portions overlapping `[start_time, end_time]`, in timeline order.
+// Audio offsets, loudness normalization and stabilization are applied in the
+// filter graph of a full render, so the clip can't come from the segment cache.
+fn needs_own_filter_pass(clip: &Clip) -> bool {
+    clip.audio_offset_s.abs() > 1e-6
+        || clip.audio_loudness.is_some()
+        || clip.stabilization_trf_path.is_some()
+}
+
+pub fn region_segments(
+    state: &TimelineState,
+    start_time: f64,
+    end_time: f64,
+) -> Result<Vec<RenderSegment>, String> {
+    if end_time <= start_time {
+        return Err(format!(
+            "Invalid region: end {:.2}s must be after start {:.2}s",
+            end_time, start_time
+        ));
+    }
+    if start_time < 0.0 || end_time > state.duration + 0.001 {
+        return Err(format!(
+            "Region [{:.2}, {:.2}] is outside the timeline [0, {:.2}]",
+            start_time, end_time, state.duration
+        ));
+    }
+
+    let segments: Vec<RenderSegment> = sequence_clips(state)
+        .into_iter()
+        .filter_map(|clip| {
+            let duration = clip.end().min(end_time) - clip.start.max(start_time);
+            if duration <= 0.001 {
+                return None;
+            }
+            Some(RenderSegment {
+                offset: clip.source_in()
+                    + (start_time - clip.start).max(0.0) * clip.speed_multiplier,
+                duration,
+                clip,
+            })
+        })
+        .collect();
+
+    if segments.is_empty() {
+        return Err("No clips in the selected region".to_string());
+    }
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timeline::AudioLoudness;
+
+    #[test]
+    fn test_parse_ffmpeg_progress_line() {
+        let line = "frame=  120 fps= 29.5 q=28.0 size=    512kB time=00:01:02.50 bitrate= 512.0kbits/s speed=1.2x";
+        let progress = parse_ffmpeg_progress_line(line).expect("should parse stats line");
+        assert_eq!(progress.frame, 120);
+        assert_eq!(progress.fps, 29.5);
+        assert_eq!(progress.time_secs, 62.5);
+
+        assert!(parse_ffmpeg_progress_line("Input #0, mov,mp4, from 'a.mov':").is_none());
+    }
+
+    #[test]
+    fn test_audio_codec_args() {
+        assert_eq!(
+            audio_codec_args("mp3", Some(320)).unwrap(),
+            ["-c:a", "libmp3lame", "-b:a", "320k"]
+        );
+        assert_eq!(
+            audio_codec_args("aac", None).unwrap(),
+            ["-c:a", "aac", "-b:a", "192k"]
+        );
+        assert_eq!(
+            audio_codec_args("wav", Some(320)).unwrap(),
+            ["-c:a", "pcm_s16le"]
+        );
+        assert!(audio_codec_args("flac", None).is_err());
+    }
+
+    #[test]
+    fn test_gif_filters_clamp_fps_and_scale() {
+        let (palette, gif) = gif_filters(60, 480);
+        assert_eq!(palette, "fps=30,scale=480:-1:flags=lanczos,palettegen");
+        assert_eq!(
+            gif,
+            "fps=30,scale=480:-1:flags=lanczos[x];[x][1:v]paletteuse"
+        );
+        assert!(gif_filters(0, 4000).0.starts_with("fps=1,scale=1920:-1"));
+        assert!(gif_filters(12, 10).0.starts_with("fps=12,scale=100:-1"));
+    }
+
+    #[test]
+    fn test_substitute_proxy_sources_skips_missing_files() {
+        let proxy = std::env::temp_dir().join(format!("ghost_proxy_{}.mp4", uuid::Uuid::new_v4()));
+        std::fs::write(&proxy, b"").unwrap();
+        let clip = |id: &str, proxy_path: Option<String>| Clip {
+            id: id.to_string(),
+            source_file: format!("/media/{}.mov", id),
+            proxy_path,
+            ..Default::default()
+        };
+        let mut clips = vec![
+            clip("a", Some(proxy.to_string_lossy().to_string())),
+            clip("b", Some("/missing/b_720p.mp4".to_string())),
+            clip("c", None),
+        ];
+        substitute_proxy_sources(&mut clips);
+        let _ = std::fs::remove_file(&proxy);
+
+        assert_eq!(clips[0].source_file, proxy.to_string_lossy());
+        assert_eq!(clips[1].source_file, "/media/b.mov");
+        assert_eq!(clips[2].source_file, "/media/c.mov");
+    }
+
+    #[test]
+    fn test_atempo_chain_stays_within_filter_limits() {
+        assert_eq!(atempo_chain(1.0), "");
+        assert_eq!(atempo_chain(1.5), ",atempo=1.5000");
+        assert_eq!(atempo_chain(4.0), ",atempo=2.0000,atempo=2.0000");
+        assert_eq!(atempo_chain(0.25), ",atempo=0.5000,atempo=0.5000");
+        assert_eq!(atempo_chain(3.0), ",atempo=2.0000,atempo=1.5000");
+    }
+
+    #[test]
+    fn test_parse_progress_out_time() {
+        assert_eq!(parse_progress_out_time("out_time_ms=12500000"), Some(12.5));
+        assert_eq!(parse_progress_out_time("out_time_ms=N/A"), None);
+        assert_eq!(parse_progress_out_time("progress=continue"), None);
+    }
+
+    #[test]
+    fn test_region_segments_trim_boundary_clips() {
+        let clip = |id: &str, start: f64, duration: f64| Clip {
+            id: id.to_string(),
+            track_id: "v1".to_string(),
+            start,
+            duration,
+            source_file: format!("/{}.mp4", id),
+            ..Default::default()
+        };
+        let state = TimelineState {
+            clips: vec![
+                clip("a", 0.0, 5.0),
+                clip("b", 5.0, 10.0),
+                clip("c", 15.0, 5.0),
+            ],
+            duration: 20.0,
+            ..Default::default()
+        };
+
+        let segments = region_segments(&state, 7.0, 15.0).unwrap();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].clip.id, "b");
+        assert_eq!(segments[0].offset, 2.0);
+        assert_eq!(segments[0].duration, 8.0);
+
+        let segments = region_segments(&state, 3.0, 17.0).unwrap();
+        let summary: Vec<_> = segments
+            .iter()
+            .map(|s| (s.clip.id.as_str(), s.offset, s.duration))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("a", 3.0, 2.0), ("b", 0.0, 10.0), ("c", 0.0, 2.0)]
+        );
+
+        assert!(region_segments(&state, 10.0, 5.0).is_err());
+        assert!(region_segments(&state, 0.0, 25.0).is_err());
+    }
+
+    #[test]
+    fn test_build_filter_complex_with_crossfade() {
+        let segment = |id: &str, duration: f64| RenderSegment {
+            clip: Clip {
+                id: id.to_string(),
+                duration,
+                ..Default::default()
+            },
+            offset: 0.0,
+            duration,
+        };
+        let segments = vec![segment("a", 5.0), segment("b", 4.0), segment("c", 3.0)];
+
+        let cuts = build_filter_complex(&segments, &[], &RenderOptions::default());
+        assert!(cuts.ends_with("[v0][v1][v2]concat=n=3:v=1:a=0[outv]"));
+        assert!(cuts.starts_with("[0:v]scale=1920:1080:force_original_aspect_ratio=decrease"));
+        assert!(!cuts.contains("xfade"));
+
+        // Stabilized clips get vidstabtransform, but only over the analyzed range
+        let mut shaky = segments.clone();
+        shaky[0].clip.stabilization_trf_path = Some("/stabilize/a.trf".to_string());
+        shaky[0].clip.stabilization_range = Some((0.0, 5.0));
+        let filter = build_filter_complex(&shaky, &[], &RenderOptions::default());
+        assert!(filter.starts_with(
+            "[0:v]vidstabtransform=smoothing=10:input='/stabilize/a.trf',scale=1920:1080:"
+        ));
+        shaky[0].offset = 1.0;
+        let filter = build_filter_complex(&shaky, &[], &RenderOptions::default());
+        assert!(!filter.contains("vidstabtransform"));
+        shaky[0].offset = 0.0;
+        shaky[0].clip.stabilization_range = Some((0.0, 4.0));
+        let filter = build_filter_complex(&shaky, &[], &RenderOptions::default());
+        assert!(!filter.contains("vidstabtransform"));
+
+        let transitions = vec![TransitionPoint {
+            clip_a_id: "b".to_string(),
+            clip_b_id: "a".to_string(),
+            duration: 1.0,
+        }];
+        let clips: Vec<Clip> = segments.iter().map(|s| s.clip.clone()).collect();
+        let fades = transition_fades(&clips, &transitions);
+        assert_eq!(fades, vec![0.0, 1.0, 0.0]);
+
+        let vertical = RenderOptions {
+            width: 1080,
+            height: 1920,
+            fps: 29.97,
+            ..Default::default()
+        };
+        let faded = build_filter_complex(&segments, &fades, &vertical);
+        assert!(faded.contains("scale=1080:1920:"));
+        assert!(faded.contains(",fps=29.97,settb=AVTB[v0];"));
+        assert!(faded.contains("[v0][v1]xfade=transition=fade:duration=1.0000:offset=4.0000[x1];"));
+        assert!(faded.ends_with("[x1][v2]concat=n=2:v=1:a=0[outv]"));
+        assert!(faded.contains("settb=AVTB"));
+    }
+
+    #[test]
+    fn test_ducking_audio_filter_aligns_sidechain() {
+        let music = Clip {
+            id: "music".to_string(),
+            track_id: "audio_track_1".to_string(),
+            start: 3.0,
+            duration: 20.0,
+            ..Default::default()
+        };
+        let segments: Vec<RenderSegment> = [("a", 0.0, None), ("b", 5.0, Some("music"))]
+            .iter()
+            .map(|&(id, start, trigger)| RenderSegment {
+                clip: Clip {
+                    id: id.to_string(),
+                    start,
+                    duration: 5.0,
+                    duck_when_active: trigger.map(str::to_string),
+                    ..Default::default()
+                },
+                offset: 0.0,
+                duration: 5.0,
+            })
+            .collect();
+
+        let clips: Vec<Clip> = segments.iter().map(|s| s.clip.clone()).collect();
+        let mut all_clips = clips.clone();
+        all_clips.push(music);
+        let triggers = ducking_triggers(&clips, &all_clips);
+        assert_eq!(triggers.len(), 1);
+
+        let audio = AudioMix {
+            triggers,
+            ..Default::default()
+        };
+        let filter = build_audio_filter(&segments, &[], &audio, &[true, true]);
+        assert!(filter.starts_with("[2:a]asplit=1[sc1_raw];"));
+        assert!(filter.contains("[0:a]atrim=duration=5.0000,asetpts=PTS-STARTPTS[a0];"));
+        // "b" starts 2s after the music, so the sidechain skips ahead
+        assert!(filter.contains("[sc1_raw]atrim=start=2.0000,asetpts=PTS-STARTPTS,apad[sc1];"));
+        assert!(filter.contains(
+            "sidechaincompress=threshold=0.02:ratio=20:attack=50:release=200:mix=0.4988"
+        ));
+        assert!(filter.ends_with("[a0][a1]concat=n=2:v=0:a=1[outa]"));
+    }
+
+    #[test]
+    fn test_audio_track_clips_are_mixed_not_concatenated() {
+        let editing = crate::preferences::EditingPreferences::default();
+        let state = TimelineState {
+            clips: vec![
+                Clip {
+                    id: "v".to_string(),
+                    track_id: editing.default_video_track_id.clone(),
+                    duration: 10.0,
+                    ..Default::default()
+                },
+                Clip {
+                    id: "music".to_string(),
+                    track_id: editing.default_audio_track_id.clone(),
+                    start: 2.0,
+                    duration: 30.0,
+                    edit_out: Some(6.0),
+                    volume_db: -10.0,
+                    ..Default::default()
+                },
+            ],
+            duration: 10.0,
+            ..Default::default()
+        };
+
+        let clips = sequence_clips(&state);
+        assert_eq!(clips.len(), 1);
+        let audio = AudioMix::for_state(&clips, &state);
+        assert!(audio.needs_single_pass());
+        let segments = vec![RenderSegment {
+            offset: 0.0,
+            duration: 10.0,
+            clip: clips[0].clone(),
+        }];
+
+        let video = build_filter_complex(&segments, &[], &RenderOptions::default());
+        assert!(!video.contains("[1:v]"));
+        let filter = build_audio_filter(&segments, &[], &audio, &[true]);
+        assert!(filter.contains("[a0]concat=n=1:v=0:a=1[amain];"));
+        assert!(filter.contains(
+            "[1:a]atrim=duration=6.0000,asetpts=PTS-STARTPTS,volume=-10.00dB,adelay=2000:all=1[m0];"
+        ));
+        assert!(filter.ends_with("[amain][m0]amix=inputs=2:duration=first:normalize=0[outa]"));
+
+        // With no video, the music plays over silence as long as it runs
+        let filter = build_audio_filter(&[], &[], &audio, &[]);
+        assert!(filter.starts_with(
+            "aevalsrc=0:channel_layout=stereo:sample_rate=48000,atrim=duration=8.0000[amain];[0:a]"
+        ));
+
+        // A region starting inside the music reads ahead in it
+        let region = AudioMix::for_region(&state, 4.0, 10.0);
+        let overlay = &region.overlays[0];
+        assert_eq!((overlay.output_start, overlay.segment.offset), (0.0, 2.0));
+        assert!((overlay.segment.duration - 4.0).abs() < 1e-9);
+        assert_eq!(region_segments(&state, 4.0, 10.0).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_audio_filter_substitutes_silence() {
+        let segments: Vec<RenderSegment> = ["a", "b", "c"]
+            .iter()
+            .map(|id| RenderSegment {
+                clip: Clip {
+                    id: id.to_string(),
+                    duration: 4.0,
+                    ..Default::default()
+                },
+                offset: 0.0,
+                duration: 4.0,
+            })
+            .collect();
+
+        let filter = build_audio_filter(&segments, &[], &AudioMix::default(), &[true, false, true]);
+        assert!(filter.contains(
+            "aevalsrc=0:channel_layout=stereo:sample_rate=48000,atrim=duration=4.0000[a1];"
+        ));
+        assert!(filter.contains("[2:a]atrim=duration=4.0000,asetpts=PTS-STARTPTS[a2];"));
+        assert!(filter.ends_with("[a0][a1][a2]concat=n=3:v=0:a=1[outa]"));
+
+        let faded = build_audio_filter(
+            &segments,
+            &[0.0, 1.0, 0.0],
+            &AudioMix::default(),
+            &[true; 3],
+        );
+        assert!(
+            faded.ends_with("[a0][a1]acrossfade=d=1.0000[ax1];[ax1][a2]concat=n=2:v=0:a=1[outa]")
+        );
+
+        // Gain follows the tempo change on a sped-up clip
+        let mut louder = segments[..1].to_vec();
+        louder[0].clip.speed_multiplier = 2.0;
+        louder[0].clip.volume_db = -6.0;
+        let filter = build_audio_filter(&louder, &[], &AudioMix::default(), &[true]);
+        assert!(filter.starts_with(
+            "[0:a]atrim=duration=8.0000,asetpts=PTS-STARTPTS,atempo=2.0000,volume=-6.00dB[a0];"
+        ));
+
+        // Measured clips get a linear loudnorm, before their own gain
+        louder[0].clip.audio_loudness = Some(AudioLoudness {
+            target_lufs: -14.0,
+            stats: LoudnormStats {
+                input_i: -20.0,
+                input_tp: -3.5,
+                input_lra: 4.0,
+                input_thresh: -30.5,
+                target_offset: 0.5,
+            },
+        });
+        let filter = build_audio_filter(&louder, &[], &AudioMix::default(), &[true]);
+        assert!(filter.contains(
+            "atempo=2.0000,loudnorm=I=-14:measured_I=-20:measured_TP=-3.5:measured_LRA=4:measured_thresh=-30.5:offset=0.5:linear=true,aresample=48000,volume=-6.00dB[a0];"
+        ));
+        louder[0].clip.audio_loudness = None;
+
+        // L-cut audio is delayed, J-cut audio read ahead, both kept to the segment
+        louder[0].clip.audio_offset_s = 0.5;
+        let filter = build_audio_filter(&louder, &[], &AudioMix::default(), &[true]);
+        assert!(filter.contains("volume=-6.00dB,adelay=500:all=1,atrim=duration=4.0000[a0];"));
+        louder[0].clip.audio_offset_s = -0.5;
+        let filter = build_audio_filter(&louder, &[], &AudioMix::default(), &[true]);
+        assert!(filter.starts_with("[0:a]atrim=start=1.0000:duration=8.0000,"));
+        assert!(filter.contains("volume=-6.00dB,apad,atrim=duration=4.0000[a0];"));
+    }
+
+    #[test]
+    fn test_parse_ffmpeg_capabilities() {
+        let version = "ffmpeg version 6.1.1-3ubuntu5 Copyright (c) 2000-2023\nbuilt with gcc 13";
+        assert_eq!(parse_ffmpeg_version(version), "6.1.1-3ubuntu5");
+
+        let encoders = "Encoders:\n V..... = Video\n A..... = Audio\n ------\n V....D libx264              libx264 H.264\n A....D aac                  AAC (Advanced Audio Coding)\n";
+        assert_eq!(parse_encoder_names(encoders), vec!["libx264", "aac"]);
+
+        let filters = "Filters:\n  T.. = Timeline support\n  | = Source or sink filter\n TSC scale             V->V       Scale the input video size.\n ... pad               V->V       Pad the input video.\n";
+        let filter_names = parse_filter_names(filters);
+        assert_eq!(filter_names, vec!["scale", "pad"]);
+
+        let capabilities = FfmpegCapabilities {
+            version: "6.1.1".to_string(),
+            encoder_names: parse_encoder_names(encoders),
+            filter_names,
+            detected_at: SystemTime::now(),
+        };
+        assert!(capabilities.has_encoder("aac"));
+        assert_eq!(
+            capabilities.video_encoder_or_fallback("h264_videotoolbox"),
+            "libx264"
+        );
+        assert_eq!(capabilities.video_encoder_or_fallback("libx264"), "libx264");
+        assert_eq!(
+            capabilities.missing_render_requirements(),
+            vec!["filter concat"]
+        );
+        assert!(!capabilities.is_stale());
+    }
+
+    #[test]
+    fn test_parse_rms_levels() {
+        let stderr = "frame:0    pts:0       pts_time:0\n\
+            [Parsed_ametadata_3 @ 0x5581] lavfi.astats.Overall.RMS_level=-23.5\n\
+            frame:1    pts:800     pts_time:0.1\n\
+            [Parsed_ametadata_3 @ 0x5581] lavfi.astats.Overall.RMS_level=-inf\n\
+            size=N/A time=00:00:00.20 bitrate=N/A speed= 412x\n";
+        assert_eq!(parse_rms_levels(stderr), vec![-23.5, WAVEFORM_SILENCE_DB]);
+        assert!(parse_rms_levels("Output #0, null, to 'pipe:':").is_empty());
+    }
+
+    #[test]
+    fn test_parse_scene_changes_and_split_times() {
+        let stderr = "[Parsed_metadata_1 @ 0x55e0] frame:0    pts:6006    pts_time:6.006\n\
+            [Parsed_metadata_1 @ 0x55e0] lavfi.scene_score=0.612\n\
+            [Parsed_showinfo_2 @ 0x55e1] n:   0 pts:   6006 pts_time:6.006   duration:1001\n\
+            [Parsed_metadata_1 @ 0x55e0] frame:1    pts:14014   pts_time:14.014\n\
+            [Parsed_metadata_1 @ 0x55e0] lavfi.scene_score=0.457\n\
+            [Parsed_showinfo_2 @ 0x55e1] n:   1 pts:  14014 pts_time:14.014  duration:1001\n";
+        let changes = parse_scene_changes(stderr);
+        assert_eq!(
+            changes,
+            vec![
+                SceneChange {
+                    timestamp_s: 6.006,
+                    score: 0.612
+                },
+                SceneChange {
+                    timestamp_s: 14.014,
+                    score: 0.457
+                },
+            ]
+        );
+
+        // Only cuts inside the edit range, mapped through start and speed
+        let clip = Clip {
+            start: 100.0,
+            duration: 20.0,
+            edit_in: Some(10.0),
+            speed_multiplier: 2.0,
+            ..Default::default()
+        };
+        let times = scene_split_times(&clip, &changes);
+        assert_eq!(times.len(), 1);
+        assert!((times[0] - 102.007).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_parse_silence_regions_and_timeline_ranges() {
+        let stderr = "[silencedetect @ 0x7f80] silence_start: 2.5\n\
+            size=N/A time=00:00:04.00 bitrate=N/A speed= 310x\n\
+            [silencedetect @ 0x7f80] silence_end: 4 | silence_duration: 1.5\n\
+            [silencedetect @ 0x7f80] silence_start: 18.75\n";
+        let regions = parse_silence_regions(stderr, 20.0);
+        assert_eq!(regions, vec![(2.5, 4.0), (18.75, 20.0)]);
+
+        // Clipped to the edit range, then placed on the timeline
+        let clip = Clip {
+            start: 10.0,
+            duration: 20.0,
+            edit_in: Some(3.0),
+            edit_out: Some(19.0),
+            ..Default::default()
+        };
+        assert_eq!(
+            silence_timeline_ranges(&clip, &regions),
+            vec![(10.0, 11.0), (25.75, 26.0)]
+        );
+    }
+
+    #[test]
+    fn test_render_options_validate() {
+        assert!(RenderOptions::default().validate().is_ok());
+        let with = |width: u32, height: u32, fps: f64| RenderOptions {
+            width,
+            height,
+            fps,
+            ..Default::default()
+        };
+        assert!(with(3840, 2160, 60.0).validate().is_ok());
+        assert!(with(1080, 1920, 29.97).validate().is_ok());
+        assert!(with(1279, 720, 30.0).validate().is_err());
+        assert!(with(1280, 0, 30.0).validate().is_err());
+        assert!(with(1280, 720, 0.5).validate().is_err());
+        assert!(with(1280, 720, 121.0).validate().is_err());
+    }
+
+    #[test]
+    fn test_thread_limit_goes_before_the_output() {
+        let process = FfmpegProcessSettings {
+            threads: Some(2),
+            nice: None,
+        };
+        let mut cmd = process.command();
+        cmd.arg("-i")
+            .arg("in.mp4")
+            .args(process.output_args(Path::new("out.mp4")));
+        let args: Vec<_> = cmd.get_args().collect();
+        assert_eq!(args, ["-i", "in.mp4", "-threads", "2", "out.mp4"]);
+
+        let unlimited = FfmpegProcessSettings::default().output_args(Path::new("out.mp4"));
+        assert_eq!(unlimited, ["out.mp4"]);
+    }
+
+    #[test]
+    fn test_volume_change_rules_out_passthrough() {
+        let render = RenderOptions::default();
+        let mut clip = Clip {
+            video_codec: Some("h264".to_string()),
+            width: Some(render.width),
+            height: Some(render.height),
+            fps: Some(render.fps),
+            ..Default::default()
+        };
+        assert!(!clip_needs_reencode(&clip, &render));
+
+        clip.volume_db = -6.0;
+        assert!(clip_needs_reencode(&clip, &render));
+        let engine = FFmpegEngine::new();
+        assert!(!engine.can_passthrough(&[clip], &render));
+    }
+
+    #[test]
+    fn test_check_render_output() {
+        let good = VideoCodecInfo {
+            codec_name: "h264".to_string(),
+            width: 1920,
+            height: 1080,
+            fps: 30.0,
+            pix_fmt: Some("yuv420p".to_string()),
+        };
+        let render = RenderOptions::default();
+        let result = check_render_output(10.0, 10.4, Some(&good), 1024, &render);
+        assert!(result.success);
+        assert_eq!(result.actual_resolution, Some((1920, 1080)));
+
+        let wrong = VideoCodecInfo {
+            codec_name: "hevc".to_string(),
+            width: 1280,
+            height: 720,
+            ..good
+        };
+        let result = check_render_output(10.0, 12.0, Some(&wrong), 1024, &render);
+        assert!(!result.success);
+        assert_eq!(result.warnings.len(), 3);
+        let hd = RenderOptions {
+            width: 1280,
+            height: 720,
+            ..render.clone()
+        };
+        let result = check_render_output(10.0, 10.0, Some(&wrong), 1024, &hd);
+        assert_eq!(result.warnings.len(), 1);
+
+        let result = check_render_output(10.0, 0.0, None, 0, &render);
+        assert!(!result.success);
+        assert_eq!(result.actual_codec, "");
+    }
+
+    #[test]
+    fn test_parse_loudnorm_stats() {
+        let stderr = r#"[Parsed_loudnorm_0 @ 0x55d5] 
+{
+	"input_i" : "-23.54",
+	"input_tp" : "-5.82",
+	"input_lra" : "7.10",
+	"input_thresh" : "-34.02",
+	"output_i" : "-14.01",
+	"output_tp" : "-1.50",
+	"output_lra" : "5.60",
+	"output_thresh" : "-24.41",
+	"normalization_type" : "dynamic",
+	"target_offset" : "0.01"
+}"#;
+        let stats = parse_loudnorm_stats(stderr).unwrap();
+        assert_eq!(stats.input_i, -23.54);
+        assert_eq!(stats.input_thresh, -34.02);
+        assert_eq!(stats.target_offset, 0.01);
+
+        assert!(parse_loudnorm_stats("no json here").is_none());
+    }
+
+    #[test]
+    fn test_parse_frame_rate() {
+        assert_eq!(parse_frame_rate("25"), Some(25.0));
+        assert_eq!(parse_frame_rate("30/1"), Some(30.0));
+        assert!((parse_frame_rate("30000/1001").unwrap() - 29.97).abs() < 0.01);
+        assert_eq!(parse_frame_rate("0/0"), None);
+    }
+}
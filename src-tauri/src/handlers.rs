@@ -0,0 +1,1027 @@
+// src-tauri/src/handlers.rs
+// Command logic that only needs `AppState`. The Tauri commands in commands.rs
+// are thin wrappers that call these and emit STATE_UPDATE on change.
+use crate::action_router::{find_gaps, validate_state_invariants};
+use crate::app_state::AppState;
+use crate::preferences::EditingPreferences;
+use crate::subtitles;
+use crate::timeline::{
+    seconds_to_timecode, ChapterMarker, Clip, SubtitleTrack, TimelineState, TimelineStatistics,
+    TimelineSummary, Track, TransitionPoint, MIN_LOOP_REGION_SECS,
+};
+use std::collections::HashMap;
+use std::sync::MutexGuard;
+use uuid::Uuid;
+
+fn lock_timeline(app: &AppState) -> Result<MutexGuard<'_, TimelineState>, String> {
+    app.timeline
+        .state
+        .lock()
+        .map_err(|_| "Failed to lock state".to_string())
+}
+
+pub fn get_timeline_state(app: &AppState) -> Result<TimelineState, String> {
+    let state = lock_timeline(app)?;
+    Ok(state.clone())
+}
+
+pub fn get_timeline_state_if_changed(
+    app: &AppState,
+    known_version: u64,
+) -> Result<Option<TimelineState>, String> {
+    let state = lock_timeline(app)?;
+    Ok((state.version != known_version).then(|| state.clone()))
+}
+
+pub fn get_timeline_summary(app: &AppState) -> Result<TimelineSummary, String> {
+    let state = lock_timeline(app)?;
+    Ok(state.summary())
+}
+
+/// Read-only; never changes the version.
+pub fn get_timeline_statistics(app: &AppState) -> Result<TimelineStatistics, String> {
+    let state = lock_timeline(app)?;
+    Ok(timeline_statistics(&state))
+}
+
+pub fn timeline_statistics(state: &TimelineState) -> TimelineStatistics {
+    let mut clips_per_track = HashMap::new();
+    for clip in &state.clips {
+        *clips_per_track.entry(clip.track_id.clone()).or_insert(0) += 1;
+    }
+    let gaps = find_gaps(state);
+    let durations: Vec<f64> = state.clips.iter().map(Clip::effective_duration).collect();
+    let (shortest, longest) = if durations.is_empty() {
+        (0.0, 0.0)
+    } else {
+        (
+            durations.iter().copied().fold(f64::INFINITY, f64::min),
+            durations.iter().copied().fold(0.0, f64::max),
+        )
+    };
+
+    TimelineStatistics {
+        total_duration_s: state.duration,
+        clip_count: state.clips.len(),
+        clips_per_track,
+        gap_count: gaps.len(),
+        total_gap_s: gaps.iter().map(|g| g.gap_end - g.gap_start).sum(),
+        average_clip_duration_s: durations.iter().sum::<f64>() / durations.len().max(1) as f64,
+        shortest_clip_s: shortest,
+        longest_clip_s: longest,
+        duration_timecode: seconds_to_timecode(state.duration, state.output_fps, false),
+    }
+}
+
+pub fn add_clip(app: &AppState, file_path: String, duration: f64) -> Result<TimelineState, String> {
+    println!("➡️ Received Add Clip Command for: {}", file_path);
+
+    let editing = app.prefs.get_preferences().editing;
+    let state = app.timeline.apply_edit(|state| {
+        check_clip_limit(state, &editing)?;
+
+        // Create the new clip struct
+        let new_clip = Clip {
+            id: Uuid::new_v4().to_string(), // Generate a unique ID
+            track_id: editing.default_video_track_id,
+            start: state.duration, // Append to the end
+            duration,
+            source_file: file_path,
+            ..Default::default()
+        };
+
+        // Add clip to state
+        state.ensure_track(&new_clip.track_id);
+        state.clips.push(new_clip);
+        // Update total duration
+        state.duration += duration;
+        Ok(())
+    })?;
+
+    println!("✅ Clip Added. New State Duration: {:.2}s", state.duration);
+
+    // Return the updated state so UI can redraw instantly
+    Ok(state)
+}
+
+// Fail before adding a clip that `max_timeline_clips` would reject
+pub(crate) fn check_clip_limit(
+    state: &TimelineState,
+    editing: &EditingPreferences,
+) -> Result<(), String> {
+    match editing.max_timeline_clips {
+        Some(max) if state.clips.len() >= max => {
+            Err(format!("Timeline clip limit reached ({} clips)", max))
+        }
+        _ => Ok(()),
+    }
+}
+
+pub fn register_transition(
+    app: &AppState,
+    clip_a_id: String,
+    clip_b_id: String,
+    duration: f64,
+) -> Result<TimelineState, String> {
+    app.timeline.apply_edit(|state| {
+        let find = |id: &str| {
+            state
+                .clips
+                .iter()
+                .find(|c| c.id == id)
+                .ok_or(format!("Clip {} not found", id))
+        };
+        let (a, b) = (find(&clip_a_id)?, find(&clip_b_id)?);
+        if a.id == b.id || a.track_id != b.track_id {
+            return Err("Transitions need two different clips on the same track".to_string());
+        }
+        let max = a.effective_duration().min(b.effective_duration());
+        if duration <= 0.0 || duration > max {
+            return Err(format!(
+                "Invalid transition duration {:.2}s (must be in (0, {:.2}])",
+                duration, max
+            ));
+        }
+
+        state
+            .transitions
+            .retain(|t| !t.connects(&clip_a_id, &clip_b_id));
+        state.transitions.push(TransitionPoint {
+            clip_a_id,
+            clip_b_id,
+            duration,
+        });
+        validate_state_invariants(state)
+            .map_err(|e| format!("Transition rejected - invariant violated: {}", e))
+    })
+}
+
+pub fn set_loop_region(app: &AppState, start: f64, end: f64) -> Result<TimelineState, String> {
+    app.timeline.apply_edit(|state| {
+        if start < 0.0 || end > state.duration || end - start < MIN_LOOP_REGION_SECS {
+            return Err(format!(
+                "Invalid loop region [{:.2}, {:.2}] (timeline is {:.2}s, minimum length {}s)",
+                start, end, state.duration, MIN_LOOP_REGION_SECS
+            ));
+        }
+        state.loop_region = Some((start, end));
+        Ok(())
+    })
+}
+
+/// The new state, or `None` when there was no loop region to clear.
+pub fn clear_loop_region(app: &AppState) -> Result<Option<TimelineState>, String> {
+    if lock_timeline(app)?.loop_region.is_none() {
+        return Ok(None);
+    }
+    app.timeline
+        .apply_edit(|state| {
+            state.loop_region = None;
+            Ok(())
+        })
+        .map(Some)
+}
+
+pub fn set_clip_audio_ducking(
+    app: &AppState,
+    clip_id: &str,
+    trigger_clip_id: Option<String>,
+) -> Result<TimelineState, String> {
+    app.timeline
+        .apply_edit(|state| set_clip_audio_ducking_logic(state, clip_id, trigger_clip_id))
+}
+
+fn set_clip_audio_ducking_logic(
+    state: &mut TimelineState,
+    clip_id: &str,
+    trigger_clip_id: Option<String>,
+) -> Result<(), String> {
+    if let Some(trigger_id) = &trigger_clip_id {
+        let trigger = state
+            .clips
+            .iter()
+            .find(|c| &c.id == trigger_id)
+            .ok_or(format!("Trigger clip {} not found", trigger_id))?;
+        if !state.is_audio_track(&trigger.track_id) {
+            return Err(format!(
+                "Trigger clip {} is on track '{}', not an audio track",
+                trigger_id, trigger.track_id
+            ));
+        }
+        if trigger_id == clip_id {
+            return Err("A clip cannot duck itself".to_string());
+        }
+    }
+
+    let clip = state
+        .clips
+        .iter_mut()
+        .find(|c| c.id == clip_id)
+        .ok_or(format!("Clip {} not found", clip_id))?;
+    clip.duck_when_active = trigger_clip_id;
+    Ok(())
+}
+
+/// Copy `clip_id` under a new ID onto the same track: at `position`, or after
+/// the end of the timeline when `None`.
+pub fn duplicate_clip(
+    app: &AppState,
+    clip_id: &str,
+    position: Option<f64>,
+) -> Result<TimelineState, String> {
+    let prefs = app.prefs.get_preferences();
+    app.timeline.apply_edit(|state| {
+        check_clip_limit(state, &prefs.editing)?;
+        duplicate_clip_logic(state, clip_id, position, prefs.general.auto_ripple_edits)?;
+        validate_state_invariants(state)
+            .map_err(|e| format!("Duplicate rejected - invariant violated: {}", e))
+    })
+}
+
+// With `ripple`, clips starting at or after `position` on the track move right
+// to make room. Returns the new clip's ID.
+fn duplicate_clip_logic(
+    state: &mut TimelineState,
+    clip_id: &str,
+    position: Option<f64>,
+    ripple: bool,
+) -> Result<String, String> {
+    let original = state
+        .clips
+        .iter()
+        .find(|c| c.id == clip_id)
+        .ok_or(format!("Clip {} not found", clip_id))?;
+    if state.track(&original.track_id).is_some_and(|t| t.locked) {
+        return Err(format!(
+            "Clip {} is on locked track {}",
+            clip_id, original.track_id
+        ));
+    }
+
+    let start = position.unwrap_or(state.duration);
+    if start < 0.0 {
+        return Err(format!("Invalid position {:.2}s", start));
+    }
+    let copy = Clip {
+        id: Uuid::new_v4().to_string(),
+        start,
+        ..original.clone()
+    };
+
+    if ripple && position.is_some() {
+        let length = copy.effective_duration();
+        for clip in state
+            .clips
+            .iter_mut()
+            .filter(|c| c.track_id == copy.track_id && c.start >= start - 0.001)
+        {
+            clip.start += length;
+        }
+    }
+
+    let id = copy.id.clone();
+    state.clips.push(copy);
+    state.recalculate_duration();
+    Ok(id)
+}
+
+/// Push every clip on `track_id` starting at or after `at_time` right by
+/// `gap_duration_s`, opening space for a new clip.
+pub fn insert_gap_at_time(
+    app: &AppState,
+    track_id: &str,
+    at_time: f64,
+    gap_duration_s: f64,
+) -> Result<TimelineState, String> {
+    if !(gap_duration_s > 0.0 && gap_duration_s.is_finite()) {
+        return Err(format!("Gap duration {} must be positive", gap_duration_s));
+    }
+    if !(at_time >= 0.0 && at_time.is_finite()) {
+        return Err(format!("Invalid gap position {:.2}s", at_time));
+    }
+    app.timeline.apply_edit(|state| {
+        if state.track(track_id).is_some_and(|t| t.locked) {
+            return Err(format!("Track {} is locked", track_id));
+        }
+        for clip in state
+            .clips
+            .iter_mut()
+            .filter(|c| c.track_id == track_id && c.start >= at_time)
+        {
+            clip.start += gap_duration_s;
+        }
+        state.recalculate_duration();
+        validate_state_invariants(state)
+            .map_err(|e| format!("Gap rejected - invariant violated: {}", e))
+    })
+}
+
+/// Drop every clip's loudness measurements, so renders stop normalizing.
+pub fn clear_loudness_normalization(app: &AppState) -> Result<TimelineState, String> {
+    app.timeline.apply_edit(|state| {
+        for clip in &mut state.clips {
+            clip.audio_loudness = None;
+        }
+        Ok(())
+    })
+}
+
+/// Set or clear a clip's label and color. An empty label clears it.
+pub fn update_clip_label(
+    app: &AppState,
+    clip_id: &str,
+    label: Option<String>,
+    color_hex: Option<String>,
+) -> Result<TimelineState, String> {
+    check_color_hex("clip", color_hex.as_deref())?;
+    app.timeline.apply_edit(|state| {
+        let clip = find_clip_mut(state, clip_id)?;
+        clip.label = label.filter(|l| !l.trim().is_empty());
+        clip.color_hex = color_hex;
+        Ok(())
+    })
+}
+
+/// Add `tags` to a clip. Tags are trimmed and lowercased; blanks and ones
+/// the clip already has are ignored.
+pub fn tag_clip(app: &AppState, clip_id: &str, tags: &[String]) -> Result<TimelineState, String> {
+    app.timeline.apply_edit(|state| {
+        let clip = find_clip_mut(state, clip_id)?;
+        for tag in tags.iter().filter_map(|t| normalize_tag(t)) {
+            if !clip.tags.contains(&tag) {
+                clip.tags.push(tag);
+            }
+        }
+        Ok(())
+    })
+}
+
+pub fn untag_clip(app: &AppState, clip_id: &str, tags: &[String]) -> Result<TimelineState, String> {
+    app.timeline.apply_edit(|state| {
+        let clip = find_clip_mut(state, clip_id)?;
+        let remove: Vec<String> = tags.iter().filter_map(|t| normalize_tag(t)).collect();
+        clip.tags.retain(|t| !remove.contains(t));
+        Ok(())
+    })
+}
+
+/// Clips carrying `tag`, compared case-insensitively, in timeline order.
+pub fn get_clips_by_tag(app: &AppState, tag: &str) -> Result<Vec<Clip>, String> {
+    let tag = normalize_tag(tag).ok_or("Tag is empty")?;
+    let state = lock_timeline(app)?;
+    let mut clips: Vec<Clip> = state
+        .clips
+        .iter()
+        .filter(|c| c.tags.contains(&tag))
+        .cloned()
+        .collect();
+    clips.sort_by(|a, b| a.start.total_cmp(&b.start));
+    Ok(clips)
+}
+
+fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim().to_lowercase();
+    (!tag.is_empty()).then_some(tag)
+}
+
+fn find_clip_mut<'a>(state: &'a mut TimelineState, clip_id: &str) -> Result<&'a mut Clip, String> {
+    state
+        .clips
+        .iter_mut()
+        .find(|c| c.id == clip_id)
+        .ok_or(format!("Clip {} not found", clip_id))
+}
+
+/// Put `clip_ids` on the clipboard, earliest first. The clips stay where they
+/// are, so this isn't an undo step.
+pub fn copy_clips(app: &AppState, clip_ids: &[String]) -> Result<TimelineState, String> {
+    let mut state = lock_timeline(app)?;
+    state.clipboard = Some(clips_for_clipboard(&state, clip_ids)?);
+    state.version += 1;
+    Ok(state.clone())
+}
+
+/// Copy `clip_ids` to the clipboard and remove them, leaving gaps behind.
+pub fn cut_clips(app: &AppState, clip_ids: &[String]) -> Result<TimelineState, String> {
+    app.timeline.apply_edit(|state| {
+        let clips = clips_for_clipboard(state, clip_ids)?;
+        if let Some(clip) = clips
+            .iter()
+            .find(|c| state.track(&c.track_id).is_some_and(|t| t.locked))
+        {
+            return Err(format!(
+                "Clip {} is on locked track {}",
+                clip.id, clip.track_id
+            ));
+        }
+
+        state.clips.retain(|c| !clip_ids.contains(&c.id));
+        state
+            .transitions
+            .retain(|t| !clip_ids.contains(&t.clip_a_id) && !clip_ids.contains(&t.clip_b_id));
+        state.clipboard = Some(clips);
+        state.recalculate_duration();
+        validate_state_invariants(state)
+            .map_err(|e| format!("Cut rejected - invariant violated: {}", e))
+    })
+}
+
+/// Insert the clipboard on `track_id`, its earliest clip at `start_time` and the
+/// rest at their copied offsets, under fresh IDs. Clips already in the way are
+/// pushed right with `auto_ripple_edits`, otherwise the paste is rejected.
+pub fn paste_clips(
+    app: &AppState,
+    start_time: f64,
+    track_id: &str,
+) -> Result<TimelineState, String> {
+    let prefs = app.prefs.get_preferences();
+    app.timeline.apply_edit(|state| {
+        check_clip_limit(state, &prefs.editing)?;
+        paste_clips_logic(state, start_time, track_id, prefs.general.auto_ripple_edits)?;
+        validate_state_invariants(state)
+            .map_err(|e| format!("Paste rejected - invariant violated: {}", e))
+    })
+}
+
+fn clips_for_clipboard(state: &TimelineState, clip_ids: &[String]) -> Result<Vec<Clip>, String> {
+    if clip_ids.is_empty() {
+        return Err("No clips selected".to_string());
+    }
+    let mut clips = clip_ids
+        .iter()
+        .map(|id| {
+            state
+                .clips
+                .iter()
+                .find(|c| &c.id == id)
+                .cloned()
+                .ok_or(format!("Clip {} not found", id))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    clips.sort_by(|a, b| a.start.total_cmp(&b.start));
+    Ok(clips)
+}
+
+fn paste_clips_logic(
+    state: &mut TimelineState,
+    start_time: f64,
+    track_id: &str,
+    ripple: bool,
+) -> Result<(), String> {
+    let clipboard = state.clipboard.clone().ok_or("Clipboard is empty")?;
+    let first_start = clipboard.first().ok_or("Clipboard is empty")?.start;
+    if !start_time.is_finite() || start_time < 0.0 {
+        return Err(format!("Invalid paste position {:.2}s", start_time));
+    }
+    if state.track(track_id).is_some_and(|t| t.locked) {
+        return Err(format!("Track {} is locked", track_id));
+    }
+
+    let pasted: Vec<Clip> = clipboard
+        .into_iter()
+        .map(|clip| Clip {
+            id: Uuid::new_v4().to_string(),
+            track_id: track_id.to_string(),
+            start: start_time + (clip.start - first_start),
+            ..clip
+        })
+        .collect();
+    let paste_end = pasted.iter().map(Clip::end).fold(start_time, f64::max);
+    let length = paste_end - start_time;
+
+    let on_track = || state.clips.iter().filter(|c| c.track_id == track_id);
+    if let Some(straddling) =
+        on_track().find(|c| c.start < start_time - 0.001 && c.end() > start_time + 0.001)
+    {
+        return Err(format!(
+            "Cannot paste at {:.2}s: it falls inside clip {}",
+            start_time, straddling.id
+        ));
+    }
+    let blocked = on_track().any(|c| c.start < paste_end - 0.001 && c.end() > start_time + 0.001);
+    if blocked {
+        if !ripple {
+            return Err(format!(
+                "Pasted clips would overlap clips on {} between {:.2}s and {:.2}s",
+                track_id, start_time, paste_end
+            ));
+        }
+        for clip in state
+            .clips
+            .iter_mut()
+            .filter(|c| c.track_id == track_id && c.start >= start_time - 0.001)
+        {
+            clip.start += length;
+        }
+    }
+
+    state.clips.extend(pasted);
+    state.recalculate_duration();
+    Ok(())
+}
+
+pub fn get_chapter_markers(app: &AppState) -> Result<Vec<ChapterMarker>, String> {
+    let state = lock_timeline(app)?;
+    Ok(state.markers.clone())
+}
+
+pub fn add_chapter_marker(
+    app: &AppState,
+    time_s: f64,
+    label: String,
+    color_hex: Option<String>,
+) -> Result<TimelineState, String> {
+    check_color_hex("marker", color_hex.as_deref())?;
+    app.timeline.apply_edit(|state| {
+        state.markers.push(ChapterMarker {
+            id: Uuid::new_v4().to_string(),
+            time_s,
+            label,
+            color_hex,
+        });
+        state.sort_markers();
+        check_marker_change(state)
+    })
+}
+
+pub fn remove_chapter_marker(app: &AppState, marker_id: &str) -> Result<TimelineState, String> {
+    app.timeline.apply_edit(|state| {
+        let count = state.markers.len();
+        state.markers.retain(|m| m.id != marker_id);
+        if state.markers.len() == count {
+            return Err(format!("Marker {} not found", marker_id));
+        }
+        Ok(())
+    })
+}
+
+/// Change the given fields of a marker, leaving the others as they are.
+pub fn update_chapter_marker(
+    app: &AppState,
+    marker_id: &str,
+    time_s: Option<f64>,
+    label: Option<String>,
+    color_hex: Option<String>,
+) -> Result<TimelineState, String> {
+    check_color_hex("marker", color_hex.as_deref())?;
+    app.timeline.apply_edit(|state| {
+        let marker = state
+            .markers
+            .iter_mut()
+            .find(|m| m.id == marker_id)
+            .ok_or(format!("Marker {} not found", marker_id))?;
+        if let Some(time_s) = time_s {
+            marker.time_s = time_s;
+        }
+        if let Some(label) = label {
+            marker.label = label;
+        }
+        if color_hex.is_some() {
+            marker.color_hex = color_hex;
+        }
+        state.sort_markers();
+        check_marker_change(state)
+    })
+}
+
+// Keep a marker edit only if the timeline is still valid
+fn check_marker_change(state: &TimelineState) -> Result<(), String> {
+    validate_state_invariants(state)
+        .map_err(|e| format!("Marker rejected - invariant violated: {}", e))
+}
+
+fn check_color_hex(what: &str, color_hex: Option<&str>) -> Result<(), String> {
+    match color_hex {
+        Some(color)
+            if color.len() != 7
+                || !color.starts_with('#')
+                || !color[1..].chars().all(|c| c.is_ascii_hexdigit()) =>
+        {
+            Err(format!(
+                "Invalid {} color '{}' (expected #RRGGBB)",
+                what, color
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+pub fn list_subtitle_tracks(app: &AppState) -> Result<Vec<SubtitleTrack>, String> {
+    let state = lock_timeline(app)?;
+    Ok(state.subtitle_tracks.clone())
+}
+
+/// Attach an SRT file whose cues are shifted by `offset_s` on export.
+pub fn import_subtitle_file(
+    app: &AppState,
+    srt_path: String,
+    offset_s: f64,
+) -> Result<TimelineState, String> {
+    let content = std::fs::read_to_string(&srt_path)
+        .map_err(|e| format!("Failed to read {}: {}", srt_path, e))?;
+    subtitles::count_srt_cues(&content)?;
+    if !offset_s.is_finite() {
+        return Err(format!("Invalid subtitle offset {}", offset_s));
+    }
+
+    app.timeline.apply_edit(|state| {
+        state.subtitle_tracks.push(SubtitleTrack {
+            id: Uuid::new_v4().to_string(),
+            srt_path,
+            offset_s,
+        });
+        Ok(())
+    })
+}
+
+pub fn set_track_muted(
+    app: &AppState,
+    track_id: &str,
+    muted: bool,
+) -> Result<TimelineState, String> {
+    app.timeline.apply_edit(|state| {
+        find_track_mut(state, track_id)?.muted = muted;
+        state.track_settings_mut(track_id).muted = muted;
+        Ok(())
+    })
+}
+
+pub fn set_track_soloed(
+    app: &AppState,
+    track_id: &str,
+    soloed: bool,
+) -> Result<TimelineState, String> {
+    app.timeline.apply_edit(|state| {
+        find_track_mut(state, track_id)?;
+        state.track_settings_mut(track_id).soloed = soloed;
+        Ok(())
+    })
+}
+
+pub fn set_track_locked(
+    app: &AppState,
+    track_id: &str,
+    locked: bool,
+) -> Result<TimelineState, String> {
+    app.timeline.apply_edit(|state| {
+        find_track_mut(state, track_id)?.locked = locked;
+        Ok(())
+    })
+}
+
+fn find_track_mut<'a>(
+    state: &'a mut TimelineState,
+    track_id: &str,
+) -> Result<&'a mut Track, String> {
+    state
+        .tracks
+        .iter_mut()
+        .find(|t| t.id == track_id)
+        .ok_or_else(|| format!("Track {} not found", track_id))
+}
+
+pub fn remove_subtitle_track(app: &AppState, track_id: &str) -> Result<TimelineState, String> {
+    app.timeline.apply_edit(|state| {
+        let count = state.subtitle_tracks.len();
+        state.subtitle_tracks.retain(|t| t.id != track_id);
+        if state.subtitle_tracks.len() == count {
+            return Err(format!("Subtitle track {} not found", track_id));
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handlers_run_against_in_memory_state() {
+        let app = AppState::new_for_testing();
+        add_clip(&app, "/a.mp4".to_string(), 4.0).unwrap();
+        let state = add_clip(&app, "/b.mp4".to_string(), 6.0).unwrap();
+        assert_eq!(state.clips[1].start, 4.0);
+        assert_eq!(state.clips[1].track_id, "video_track_1");
+
+        let (a, b) = (state.clips[0].id.clone(), state.clips[1].id.clone());
+        assert!(register_transition(&app, a.clone(), a.clone(), 1.0).is_err());
+        let state = register_transition(&app, a, b, 1.0).unwrap();
+        assert_eq!(state.transitions.len(), 1);
+
+        assert!(set_loop_region(&app, 2.0, 11.0).is_err());
+        let state = set_loop_region(&app, 2.0, 8.0).unwrap();
+        assert!(get_timeline_state_if_changed(&app, state.version)
+            .unwrap()
+            .is_none());
+        assert!(clear_loop_region(&app).unwrap().is_some());
+        assert!(clear_loop_region(&app).unwrap().is_none());
+        assert_eq!(get_timeline_summary(&app).unwrap().clip_count, 2);
+    }
+
+    #[test]
+    fn test_timeline_statistics_counts_gaps_and_lengths() {
+        let app = AppState::new_for_testing();
+        let stats = get_timeline_statistics(&app).unwrap();
+        assert_eq!((stats.clip_count, stats.shortest_clip_s), (0, 0.0));
+
+        add_clip(&app, "/a.mp4".to_string(), 4.0).unwrap();
+        add_clip(&app, "/b.mp4".to_string(), 2.0).unwrap();
+        let version = {
+            let mut state = lock_timeline(&app).unwrap();
+            state.clips[1].start = 5.0;
+            state.recalculate_duration();
+            state.version
+        };
+
+        let stats = get_timeline_statistics(&app).unwrap();
+        assert_eq!(stats.total_duration_s, 7.0);
+        assert_eq!(stats.duration_timecode, "00:00:07:00");
+        assert_eq!(stats.clips_per_track["video_track_1"], 2);
+        assert_eq!((stats.gap_count, stats.total_gap_s), (1, 1.0));
+        assert_eq!(stats.average_clip_duration_s, 3.0);
+        assert_eq!((stats.shortest_clip_s, stats.longest_clip_s), (2.0, 4.0));
+        assert_eq!(lock_timeline(&app).unwrap().version, version);
+    }
+
+    #[test]
+    fn test_set_clip_audio_ducking_requires_audio_trigger() {
+        let clip = |id: &str, track: &str| Clip {
+            id: id.to_string(),
+            track_id: track.to_string(),
+            duration: 5.0,
+            ..Default::default()
+        };
+        let mut state = TimelineState {
+            clips: vec![
+                clip("v", "video_track_1"),
+                clip("other", "video_track_1"),
+                clip("music", "audio_track_1"),
+            ],
+            ..Default::default()
+        };
+
+        assert!(set_clip_audio_ducking_logic(&mut state, "v", Some("other".into())).is_err());
+        assert!(set_clip_audio_ducking_logic(&mut state, "v", Some("gone".into())).is_err());
+        assert!(set_clip_audio_ducking_logic(&mut state, "music", Some("music".into())).is_err());
+
+        set_clip_audio_ducking_logic(&mut state, "v", Some("music".into())).unwrap();
+        assert_eq!(state.clips[0].duck_when_active.as_deref(), Some("music"));
+        assert_eq!(state.clips[0].ducking_amount_db, -6.0);
+
+        set_clip_audio_ducking_logic(&mut state, "v", None).unwrap();
+        assert_eq!(state.clips[0].duck_when_active, None);
+    }
+
+    #[test]
+    fn test_duplicate_clip_appends_or_ripples() {
+        let clip = |id: &str, start: f64| Clip {
+            id: id.to_string(),
+            track_id: "video_track_1".to_string(),
+            start,
+            duration: 4.0,
+            speed_multiplier: 2.0,
+            volume_db: -3.0,
+            ..Default::default()
+        };
+        let mut state = TimelineState {
+            clips: vec![clip("a", 0.0), clip("b", 2.0)],
+            duration: 4.0,
+            ..Default::default()
+        };
+
+        let appended = duplicate_clip_logic(&mut state, "a", None, true).unwrap();
+        let copy = state.clips.iter().find(|c| c.id == appended).unwrap();
+        assert_eq!(
+            (copy.start, copy.speed_multiplier, copy.volume_db),
+            (4.0, 2.0, -3.0)
+        );
+        assert_eq!(state.duration, 6.0);
+
+        // Inserting at b's start pushes b and the appended copy along
+        let inserted = duplicate_clip_logic(&mut state, "a", Some(2.0), true).unwrap();
+        let mut starts: Vec<_> = state
+            .clips
+            .iter()
+            .map(|c| (c.id.as_str(), c.start))
+            .collect();
+        starts.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        assert_eq!(
+            starts,
+            vec![
+                ("a", 0.0),
+                (inserted.as_str(), 2.0),
+                ("b", 4.0),
+                (appended.as_str(), 6.0)
+            ]
+        );
+        assert!(validate_state_invariants(&state).is_ok());
+
+        // Without ripple the copy lands on top of "b"
+        duplicate_clip_logic(&mut state, "a", Some(4.0), false).unwrap();
+        assert!(validate_state_invariants(&state).is_err());
+        assert!(duplicate_clip_logic(&mut state, "gone", None, true).is_err());
+    }
+
+    #[test]
+    fn test_update_clip_label_validates_color() {
+        let app = AppState::new_for_testing();
+        let state = add_clip(&app, "/a.mp4".to_string(), 4.0).unwrap();
+        let id = state.clips[0].id.clone();
+
+        assert!(update_clip_label(&app, &id, None, Some("#FF55".into())).is_err());
+        assert!(update_clip_label(&app, &id, None, Some("FF5500".into())).is_err());
+        assert!(update_clip_label(&app, "gone", None, None).is_err());
+        let state =
+            update_clip_label(&app, &id, Some("Interview".into()), Some("#FF5500".into())).unwrap();
+        assert_eq!(state.clips[0].label.as_deref(), Some("Interview"));
+        assert_eq!(state.clips[0].color_hex.as_deref(), Some("#FF5500"));
+
+        let state = update_clip_label(&app, &id, Some(" ".into()), None).unwrap();
+        assert_eq!(
+            (
+                state.clips[0].label.as_ref(),
+                state.clips[0].color_hex.as_ref()
+            ),
+            (None, None)
+        );
+    }
+
+    #[test]
+    fn test_handler_edits_are_undoable() {
+        let app = AppState::new_for_testing();
+        let state = add_clip(&app, "/a.mp4".to_string(), 4.0).unwrap();
+        let id = state.clips[0].id.clone();
+        assert_eq!((state.version, state.can_undo), (1, true));
+
+        update_clip_label(&app, &id, Some("Intro".into()), None).unwrap();
+        let state = duplicate_clip(&app, &id, None).unwrap();
+        assert_eq!(state.clips.len(), 2);
+        assert!(state.can_undo);
+
+        let state = app.timeline.undo().unwrap();
+        assert_eq!(state.clips.len(), 1);
+        let state = app.timeline.undo().unwrap();
+        assert_eq!(state.clips[0].label, None);
+        assert!(state.can_redo);
+
+        // A new edit drops the redo branch; a rejected one leaves no trace
+        let state = tag_clip(&app, &id, &["b-roll".to_string()]).unwrap();
+        assert!(!state.can_redo);
+        assert!(update_clip_label(&app, &id, None, Some("red".into())).is_err());
+        assert_eq!(get_timeline_state(&app).unwrap().version, state.version);
+    }
+
+    #[test]
+    fn test_tag_and_untag_clips() {
+        let app = AppState::new_for_testing();
+        add_clip(&app, "/a.mp4".to_string(), 4.0).unwrap();
+        let state = add_clip(&app, "/b.mp4".to_string(), 6.0).unwrap();
+        let (a, b) = (state.clips[0].id.clone(), state.clips[1].id.clone());
+
+        let tags = |list: &[&str]| list.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        let state = tag_clip(&app, &a, &tags(&["Outtake", " broll ", "", "outtake"])).unwrap();
+        assert_eq!(state.clips[0].tags, vec!["outtake", "broll"]);
+        tag_clip(&app, &b, &tags(&["outtake"])).unwrap();
+        assert!(tag_clip(&app, "gone", &tags(&["x"])).is_err());
+
+        let outtakes = get_clips_by_tag(&app, "OUTTAKE").unwrap();
+        assert_eq!(
+            outtakes.iter().map(|c| &c.id).collect::<Vec<_>>(),
+            vec![&a, &b]
+        );
+        assert!(get_clips_by_tag(&app, "  ").is_err());
+
+        let state = untag_clip(&app, &a, &tags(&["outtake"])).unwrap();
+        assert_eq!(state.clips[0].tags, vec!["broll"]);
+        assert_eq!(get_clips_by_tag(&app, "outtake").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_insert_gap_at_time_shifts_later_clips() {
+        let app = AppState::new_for_testing();
+        add_clip(&app, "/a.mp4".to_string(), 4.0).unwrap();
+        add_clip(&app, "/b.mp4".to_string(), 6.0).unwrap();
+
+        assert!(insert_gap_at_time(&app, "video_track_1", 4.0, 0.0).is_err());
+        assert!(insert_gap_at_time(&app, "video_track_1", -1.0, 2.0).is_err());
+        let state = insert_gap_at_time(&app, "video_track_1", 4.0, 2.5).unwrap();
+        let starts: Vec<f64> = state.clips.iter().map(|c| c.start).collect();
+        assert_eq!(starts, vec![0.0, 6.5]);
+        assert_eq!(state.duration, 12.5);
+
+        // Other tracks stay put
+        let state = insert_gap_at_time(&app, "audio_track_1", 0.0, 1.0).unwrap();
+        assert_eq!(state.clips[0].start, 0.0);
+    }
+
+    #[test]
+    fn test_cut_and_paste_clips() {
+        let app = AppState::new_for_testing();
+        add_clip(&app, "/a.mp4".to_string(), 4.0).unwrap();
+        let state = add_clip(&app, "/b.mp4".to_string(), 6.0).unwrap();
+        let (a, b) = (state.clips[0].id.clone(), state.clips[1].id.clone());
+        assert!(paste_clips(&app, 0.0, "video_track_1").is_err());
+
+        let state = copy_clips(&app, std::slice::from_ref(&a)).unwrap();
+        assert_eq!(state.clips.len(), 2);
+        let state = cut_clips(&app, std::slice::from_ref(&b)).unwrap();
+        assert_eq!(state.clips.len(), 1);
+        assert_eq!(state.clipboard.as_ref().unwrap()[0].id, b);
+
+        // Pasting over "a" ripples it along by the pasted length
+        let state = paste_clips(&app, 0.0, "video_track_1").unwrap();
+        let pasted = state
+            .clips
+            .iter()
+            .find(|c| c.source_file == "/b.mp4")
+            .unwrap();
+        assert_ne!(pasted.id, b);
+        assert_eq!(pasted.start, 0.0);
+        assert_eq!(state.clips.iter().find(|c| c.id == a).unwrap().start, 6.0);
+        assert!(paste_clips(&app, 8.0, "video_track_1").is_err());
+
+        // Undo takes the paste back but keeps the clipboard
+        let state = app.timeline.undo().unwrap();
+        assert_eq!(state.clips.len(), 1);
+        assert!(state.clipboard.is_some());
+    }
+
+    #[test]
+    fn test_chapter_markers_stay_inside_timeline() {
+        let app = AppState::new_for_testing();
+        add_clip(&app, "/a.mp4".to_string(), 10.0).unwrap();
+
+        assert!(add_chapter_marker(&app, 12.0, "Late".into(), None).is_err());
+        assert!(add_chapter_marker(&app, 1.0, "Red".into(), Some("red".into())).is_err());
+        add_chapter_marker(&app, 6.0, "Outro".into(), None).unwrap();
+        let state = add_chapter_marker(&app, 0.0, "Intro".into(), Some("#ff0000".into())).unwrap();
+        let labels: Vec<_> = state.markers.iter().map(|m| m.label.as_str()).collect();
+        assert_eq!(labels, vec!["Intro", "Outro"]);
+
+        let outro = state.markers[1].id.clone();
+        assert!(update_chapter_marker(&app, &outro, Some(11.0), None, None).is_err());
+        let state = update_chapter_marker(&app, &outro, None, Some("End".into()), None).unwrap();
+        assert_eq!(state.markers[1].label, "End");
+        assert_eq!(state.markers[1].time_s, 6.0);
+
+        remove_chapter_marker(&app, &outro).unwrap();
+        assert!(remove_chapter_marker(&app, &outro).is_err());
+        assert_eq!(get_chapter_markers(&app).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_track_mute_and_solo_silence_tracks() {
+        let app = AppState::new_for_testing();
+        assert!(set_track_muted(&app, "missing", true).is_err());
+
+        let state = set_track_muted(&app, "audio_track_1", true).unwrap();
+        assert_eq!(state.silenced_track_ids(), vec!["audio_track_1"]);
+
+        // Soloing one track silences the rest; muting still wins on that track
+        set_track_muted(&app, "audio_track_1", false).unwrap();
+        let state = set_track_soloed(&app, "audio_track_1", true).unwrap();
+        assert_eq!(state.silenced_track_ids(), vec!["video_track_1"]);
+        let state = set_track_muted(&app, "audio_track_1", true).unwrap();
+        assert_eq!(
+            state.silenced_track_ids(),
+            vec!["video_track_1", "audio_track_1"]
+        );
+        assert_eq!(
+            state.track_settings,
+            vec![crate::timeline::TrackSettings {
+                track_id: "audio_track_1".to_string(),
+                muted: true,
+                soloed: true,
+            }]
+        );
+        // Sent with STATE_UPDATE and saved with the project
+        let json = serde_json::to_value(&state).unwrap();
+        assert_eq!(json["track_settings"][0]["soloed"], true);
+
+        let state = set_track_locked(&app, "video_track_1", true).unwrap();
+        assert!(state.track("video_track_1").unwrap().locked);
+    }
+
+    #[test]
+    fn test_subtitle_tracks_import_and_remove() {
+        let app = AppState::new_for_testing();
+        let dir = std::env::temp_dir().join(format!("ghost_srt_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let srt = dir.join("captions.srt");
+        std::fs::write(&srt, "1\n00:00:01,000 --> 00:00:02,000\nHello\n").unwrap();
+        let not_srt = dir.join("notes.txt");
+        std::fs::write(&not_srt, "no cues here").unwrap();
+
+        let srt_path = srt.to_string_lossy().to_string();
+        assert!(import_subtitle_file(&app, not_srt.to_string_lossy().to_string(), 0.0).is_err());
+        let state = import_subtitle_file(&app, srt_path.clone(), 2.5).unwrap();
+        assert_eq!(state.subtitle_tracks.len(), 1);
+        assert_eq!(state.subtitle_tracks[0].offset_s, 2.5);
+
+        let id = state.subtitle_tracks[0].id.clone();
+        remove_subtitle_track(&app, &id).unwrap();
+        assert!(remove_subtitle_track(&app, &id).is_err());
+        assert!(list_subtitle_tracks(&app).unwrap().is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,401 @@
+// src-tauri/src/interaction_log.rs
+// Compact analytics log of LLM calls: one JSON line per call in
+// artifacts/interaction_log.jsonl. Prompts and responses are stored as hashes only;
+// the full text lives in the per-call artifact files.
+// `LlmQualityTracker` keeps running totals of the same calls in llm_quality.json.
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+
+const LOG_FILE_NAME: &str = "interaction_log.jsonl";
+const ROTATED_FILE_NAME: &str = "interaction_log.1.jsonl";
+/// The log is rotated once it grows past this size.
+const MAX_LOG_BYTES: u64 = 10 * 1024 * 1024;
+/// Default `ai_confidence_threshold`: plans below it are rejected before
+/// validation. Quality stats count low-confidence plans against this value.
+pub const CONFIDENCE_THRESHOLD: f32 = 0.6;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum InteractionOutcome {
+    Success,
+    ParseError,
+    ValidationError,
+    ExecutionError,
+    Cancelled,
+    Timeout,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LlmInteraction {
+    pub timestamp_ms: u64,
+    pub session_id: String,
+    pub request_id: String,
+    pub model: String,
+    pub prompt_hash: Option<String>,
+    pub response_hash: Option<String>,
+    pub latency_ms: Option<u64>,
+    pub input_tokens: Option<u32>,
+    pub output_tokens: Option<u32>,
+    pub confidence: Option<f32>,
+    pub actions_count: Option<usize>,
+    pub applied: bool,
+    pub outcome: InteractionOutcome,
+}
+
+impl LlmInteraction {
+    /// A record for a call that has not produced anything yet.
+    pub fn new(request_id: &str, model: &str) -> Self {
+        Self {
+            timestamp_ms: now_ms(),
+            session_id: session_id().to_string(),
+            request_id: request_id.to_string(),
+            model: model.to_string(),
+            prompt_hash: None,
+            response_hash: None,
+            latency_ms: None,
+            input_tokens: None,
+            output_tokens: None,
+            confidence: None,
+            actions_count: None,
+            applied: false,
+            outcome: InteractionOutcome::Success,
+        }
+    }
+
+    pub fn record_prompt(&mut self, prompt: &str) {
+        self.prompt_hash = Some(hash_text(prompt));
+    }
+
+    pub fn record_completion(&mut self, completion: &crate::llm::OllamaCompletion) {
+        self.response_hash = Some(hash_text(&completion.text));
+        self.latency_ms = Some(completion.latency_ms);
+        self.input_tokens = completion.input_tokens;
+        self.output_tokens = completion.output_tokens;
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LlmAnalytics {
+    pub total_calls: usize,
+    pub success_rate: f64,
+    pub avg_latency_ms: f64,
+    pub avg_confidence: f64,
+}
+
+/// Random id for this app launch, shared by every record it writes.
+pub fn session_id() -> &'static str {
+    static SESSION_ID: OnceLock<String> = OnceLock::new();
+    SESSION_ID.get_or_init(|| uuid::Uuid::new_v4().to_string())
+}
+
+fn hash_text(text: &str) -> String {
+    let digest = Sha256::digest(text.as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+fn log_path(app_handle: &AppHandle) -> PathBuf {
+    crate::llm::get_artifacts_dir(app_handle).join(LOG_FILE_NAME)
+}
+
+/// Append `entry` to the interaction log. Failures are logged, never raised,
+/// so analytics can't break an edit.
+pub fn log_llm_interaction(app_handle: &AppHandle, entry: &LlmInteraction) {
+    if let Err(e) = append_interaction(&log_path(app_handle), entry) {
+        println!("⚠️ Failed to write interaction log: {}", e);
+    }
+}
+
+pub fn append_interaction(path: &Path, entry: &LlmInteraction) -> Result<(), String> {
+    if fs::metadata(path).map(|m| m.len()).unwrap_or(0) >= MAX_LOG_BYTES {
+        fs::rename(path, path.with_file_name(ROTATED_FILE_NAME)).map_err(|e| e.to_string())?;
+    }
+
+    let line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| e.to_string())?;
+    writeln!(file, "{}", line).map_err(|e| e.to_string())
+}
+
+/// Every parseable record in the log at `path` and its rotated predecessor.
+pub fn read_interactions(path: &Path) -> Vec<LlmInteraction> {
+    [path.with_file_name(ROTATED_FILE_NAME), path.to_path_buf()]
+        .iter()
+        .filter_map(|p| fs::File::open(p).ok())
+        .flat_map(|file| BufReader::new(file).lines().map_while(Result::ok))
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect()
+}
+
+pub fn compute_analytics(entries: &[LlmInteraction], since_ms: u64) -> LlmAnalytics {
+    let recent: Vec<_> = entries
+        .iter()
+        .filter(|e| e.timestamp_ms >= since_ms)
+        .collect();
+
+    let mean = |values: Vec<f64>| {
+        if values.is_empty() {
+            0.0
+        } else {
+            values.iter().sum::<f64>() / values.len() as f64
+        }
+    };
+
+    let successes = recent
+        .iter()
+        .filter(|e| e.outcome == InteractionOutcome::Success)
+        .count();
+
+    LlmAnalytics {
+        total_calls: recent.len(),
+        success_rate: if recent.is_empty() {
+            0.0
+        } else {
+            successes as f64 / recent.len() as f64
+        },
+        avg_latency_ms: mean(
+            recent
+                .iter()
+                .filter_map(|e| e.latency_ms)
+                .map(|v| v as f64)
+                .collect(),
+        ),
+        avg_confidence: mean(
+            recent
+                .iter()
+                .filter_map(|e| e.confidence)
+                .map(f64::from)
+                .collect(),
+        ),
+    }
+}
+
+/// Analytics over the last `days` days of the log.
+pub fn analytics_for_days(app_handle: &AppHandle, days: u32) -> LlmAnalytics {
+    let since_ms = now_ms().saturating_sub(days as u64 * 24 * 60 * 60 * 1000);
+    compute_analytics(&read_interactions(&log_path(app_handle)), since_ms)
+}
+
+/// Running LLM quality counters, persisted across launches.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct LlmQualityStats {
+    pub total_calls: u64,
+    pub success_count: u64,
+    pub parse_error_count: u64,
+    pub validation_error_count: u64,
+    pub execution_error_count: u64,
+    pub low_confidence_count: u64,
+    pub avg_latency_ms: f64,
+    pub avg_confidence: f64,
+    /// Calls that reported a latency / a parsed plan, i.e. the averages' sample sizes.
+    pub latency_samples: u64,
+    pub confidence_samples: u64,
+}
+
+impl LlmQualityStats {
+    pub fn record(&mut self, entry: &LlmInteraction) {
+        self.total_calls += 1;
+        match entry.outcome {
+            InteractionOutcome::Success => self.success_count += 1,
+            InteractionOutcome::ParseError => self.parse_error_count += 1,
+            InteractionOutcome::ValidationError => self.validation_error_count += 1,
+            InteractionOutcome::ExecutionError => self.execution_error_count += 1,
+            InteractionOutcome::Cancelled | InteractionOutcome::Timeout => {}
+        }
+
+        if let Some(latency) = entry.latency_ms {
+            self.latency_samples += 1;
+            self.avg_latency_ms +=
+                (latency as f64 - self.avg_latency_ms) / self.latency_samples as f64;
+        }
+        // `actions_count` is only set once a plan parsed; a missing confidence
+        // counts as 0.5, as in the confidence gate
+        if entry.actions_count.is_some() {
+            let confidence = entry.confidence.unwrap_or(0.5);
+            self.confidence_samples += 1;
+            self.avg_confidence +=
+                (confidence as f64 - self.avg_confidence) / self.confidence_samples as f64;
+            if confidence < CONFIDENCE_THRESHOLD {
+                self.low_confidence_count += 1;
+            }
+        }
+    }
+
+    pub fn success_rate(&self) -> f64 {
+        if self.total_calls == 0 {
+            0.0
+        } else {
+            self.success_count as f64 / self.total_calls as f64
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LlmQualityReport {
+    #[serde(flatten)]
+    pub stats: LlmQualityStats,
+    pub success_rate: f64,
+}
+
+/// Managed `LlmQualityStats`, saved after every update.
+pub struct LlmQualityTracker {
+    stats: Mutex<LlmQualityStats>,
+    /// Empty for in-memory trackers, which never persist.
+    file_path: PathBuf,
+}
+
+impl LlmQualityTracker {
+    pub fn new(file_path: PathBuf) -> Self {
+        let stats = fs::read_to_string(&file_path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self {
+            stats: Mutex::new(stats),
+            file_path,
+        }
+    }
+
+    pub fn new_in_memory() -> Self {
+        Self::new(PathBuf::new())
+    }
+
+    pub fn record(&self, entry: &LlmInteraction) {
+        let mut stats = self.stats.lock().unwrap();
+        stats.record(entry);
+        self.save(&stats);
+    }
+
+    pub fn report(&self) -> LlmQualityReport {
+        let stats = self.stats.lock().unwrap().clone();
+        LlmQualityReport {
+            success_rate: stats.success_rate(),
+            stats,
+        }
+    }
+
+    pub fn reset(&self) {
+        let mut stats = self.stats.lock().unwrap();
+        *stats = LlmQualityStats::default();
+        self.save(&stats);
+    }
+
+    fn save(&self, stats: &LlmQualityStats) {
+        if self.file_path.as_os_str().is_empty() {
+            return;
+        }
+        let json = serde_json::to_string_pretty(stats).unwrap_or_default();
+        if let Err(e) = fs::write(&self.file_path, json) {
+            println!("⚠️ Failed to save LLM quality stats: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(
+        timestamp_ms: u64,
+        outcome: InteractionOutcome,
+        latency: u64,
+        confidence: f32,
+    ) -> LlmInteraction {
+        LlmInteraction {
+            timestamp_ms,
+            latency_ms: Some(latency),
+            confidence: Some(confidence),
+            outcome,
+            ..LlmInteraction::new("req", "llama3.2")
+        }
+    }
+
+    #[test]
+    fn test_compute_analytics_filters_by_time() {
+        let entries = vec![
+            entry(100, InteractionOutcome::Success, 1000, 0.9),
+            entry(200, InteractionOutcome::ParseError, 3000, 0.5),
+            entry(10, InteractionOutcome::Timeout, 60000, 0.1),
+        ];
+
+        let analytics = compute_analytics(&entries, 50);
+        assert_eq!(analytics.total_calls, 2);
+        assert_eq!(analytics.success_rate, 0.5);
+        assert_eq!(analytics.avg_latency_ms, 2000.0);
+        assert!((analytics.avg_confidence - 0.7).abs() < 1e-6);
+
+        assert_eq!(compute_analytics(&[], 0).total_calls, 0);
+    }
+
+    #[test]
+    fn test_quality_stats_accumulate_outcomes() {
+        let mut stats = LlmQualityStats::default();
+        let parsed = |outcome, latency, confidence| LlmInteraction {
+            actions_count: Some(1),
+            ..entry(0, outcome, latency, confidence)
+        };
+        stats.record(&parsed(InteractionOutcome::Success, 1000, 0.9));
+        stats.record(&parsed(InteractionOutcome::ValidationError, 3000, 0.3));
+        stats.record(&entry(0, InteractionOutcome::ParseError, 2000, 0.0));
+        stats.record(&LlmInteraction {
+            outcome: InteractionOutcome::Timeout,
+            ..LlmInteraction::new("req", "llama3.2")
+        });
+
+        assert_eq!(stats.total_calls, 4);
+        assert_eq!(stats.success_count, 1);
+        assert_eq!(stats.parse_error_count, 1);
+        assert_eq!(stats.validation_error_count, 1);
+        assert_eq!(stats.low_confidence_count, 1);
+        assert!((stats.avg_latency_ms - 2000.0).abs() < 1e-9);
+        // The unparsed response doesn't count towards confidence
+        assert!((stats.avg_confidence - 0.6).abs() < 1e-6);
+        assert!((stats.success_rate() - 0.25).abs() < 1e-9);
+
+        let tracker = LlmQualityTracker::new_in_memory();
+        tracker.record(&parsed(InteractionOutcome::Success, 10, 0.8));
+        assert_eq!(tracker.report().success_rate, 1.0);
+        tracker.reset();
+        assert_eq!(tracker.report().stats, LlmQualityStats::default());
+    }
+
+    #[test]
+    fn test_append_and_read_round_trip() {
+        let dir = std::env::temp_dir().join(format!("ghost_ilog_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(LOG_FILE_NAME);
+
+        append_interaction(&path, &entry(1, InteractionOutcome::Success, 10, 0.8)).unwrap();
+        append_interaction(&path, &entry(2, InteractionOutcome::Cancelled, 20, 0.8)).unwrap();
+
+        let entries = read_interactions(&path);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].outcome, InteractionOutcome::Cancelled);
+
+        let mut with_prompt = entry(3, InteractionOutcome::Success, 30, 0.8);
+        with_prompt.record_prompt("secret prompt text");
+        append_interaction(&path, &with_prompt).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("\"outcome\":\"success\""));
+        assert!(!contents.contains("secret prompt text"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,46 +1,113 @@
 // src-tauri/src/lib.rs
 
 pub mod action_router;
+pub mod analyzer;
+pub mod app_state;
+pub mod chapters;
 pub mod commands;
 pub mod edit_plan;
+pub mod export_queue;
 pub mod ffmpeg;
+pub mod handlers;
+pub mod interaction_log;
 pub mod llm;
+pub mod llm_queue;
 pub mod preferences;
+pub mod project;
 pub mod prompt;
+pub mod render_cache;
+pub mod source_watcher;
+pub mod subtitles;
+pub mod thumbnails;
 pub mod timeline;
 pub mod validator;
 
 #[cfg(test)]
 mod llm_tests;
 
-use commands::{add_clip, add_test_clips, get_timeline_state, import_video};
-use ffmpeg::FFmpegEngine;
-use llm::{log_artifact, send_prompt_to_ollama, ArtifactType, LlmResponseMetadata};
+use app_state::{ActiveRequests, AppState, MacroRecorder};
+use commands::{
+    add_chapter_marker, add_clip, add_test_clips, analyze_timeline, apply_lint_fix,
+    apply_virtual_trim, clear_loop_region, clear_loudness_normalization, consolidate_duplicates,
+    copy_clips, cut_clips, delete_clips_in_range, duplicate_clip, export_chapter_markers,
+    find_duplicate_clips, get_chapter_markers, get_clips_by_tag, get_media_info,
+    get_project_file_info, get_project_metadata, get_timeline_state, get_timeline_state_if_changed,
+    get_timeline_statistics, get_timeline_summary, import_from_url, import_subtitle_file,
+    import_video, import_videos, insert_gap_at_time, list_subtitle_tracks, load_project,
+    merge_short_clips, move_clip, new_project, normalize_clip, normalize_clip_order,
+    normalize_clip_positions, normalize_clip_starts, open_project, paste_clips,
+    probe_video_files_batch, redo, register_transition, remove_chapter_marker,
+    remove_subtitle_track, reorder_clips, reorder_clips_preserve_gaps, reset_virtual_trim,
+    save_project, set_clip_audio_ducking, set_loop_region, set_project_metadata, set_track_locked,
+    set_track_muted, set_track_soloed, split_clip, tag_clip, trim_clip, undo, untag_clip,
+    update_chapter_marker, update_clip_label,
+};
+use export_queue::{CompletedExport, ExportJob, ExportQueue};
+use ffmpeg::{ExportJobs, ExportOptions, ExportResult, FFmpegEngine, RenderProgress};
+use interaction_log::{
+    log_llm_interaction, InteractionOutcome, LlmInteraction, LlmQualityReport, LlmQualityTracker,
+};
+use llm::{
+    log_artifact, send_prompt, ArtifactType, ConversationHistory, LlmProvider, LlmResponseMetadata,
+    LlmRetryEvent, OllamaCompletion,
+};
+use llm_queue::{LlmQueue, QueuedRequest};
 use preferences::PreferenceManager;
-use prompt::{build_context_block, build_prompt, SYSTEM_PROMPT};
-use std::collections::HashMap;
-use std::sync::Arc;
-use tauri::{Emitter, Manager, State}; // Import Manager trait for .path() and Emitter for .emit()
+use project::ProjectMetadataStore;
+use prompt::{build_context_block, build_explanation_prompt, build_prompt, sanitize_user_input};
+use source_watcher::SourceFileWatcher;
+use tauri::{Emitter, Listener, Manager, State}; // Import Manager trait for .path() and Emitter for .emit()
+use thumbnails::{ThumbnailGenerationProgress, ThumbnailGenerationState};
 use timeline::TimelineEngine;
-use tokio::sync::Mutex;
 
 #[tauri::command]
 fn get_user_preferences(prefs: State<'_, PreferenceManager>) -> preferences::UserPreferences {
     prefs.get_preferences()
 }
 
-// Item 7: Active Requests State
-struct ActiveRequests(Arc<Mutex<HashMap<String, tokio::task::JoinHandle<()>>>>);
+/// Switch FFmpeg's thread count and priority to a preset and persist it.
+#[tauri::command]
+fn set_ffmpeg_performance_mode(
+    prefs: State<'_, PreferenceManager>,
+    mode: preferences::PerfMode,
+) -> preferences::UserPreferences {
+    let cpu_count = ffmpeg::detect_cpu_count();
+    prefs.update(|p| mode.apply(&mut p.system, cpu_count))
+}
 
-impl ActiveRequests {
-    fn new() -> Self {
-        Self(Arc::new(Mutex::new(HashMap::new())))
+// Sanitize an edit request (when enabled) and record anything suspicious.
+fn prepare_user_input(
+    app_handle: &tauri::AppHandle,
+    prefs: &PreferenceManager,
+    user_input: &str,
+) -> String {
+    if !prefs.get_preferences().ai.input_sanitization_enabled {
+        return user_input.to_string();
+    }
+    let sanitized = sanitize_user_input(user_input);
+    if !sanitized.warnings.is_empty() {
+        println!("⚠️ [Backend] Input sanitization: {:?}", sanitized.warnings);
+        let details = serde_json::json!({
+            "input": user_input,
+            "sanitized": sanitized.text,
+            "warnings": sanitized.warnings,
+        });
+        log_artifact(
+            app_handle,
+            ArtifactType::InputSanitization,
+            &serde_json::to_string_pretty(&details).unwrap_or_default(),
+        );
     }
+    sanitized.text
 }
 
 // Item 6: Read Artifact Command
 #[tauri::command]
 fn read_artifact(app_handle: tauri::AppHandle, filename: String) -> Result<String, String> {
+    read_artifact_file(&app_handle, &filename)
+}
+
+fn read_artifact_file(app_handle: &tauri::AppHandle, filename: &str) -> Result<String, String> {
     // Sanitize filename
     if filename.contains("..") || !filename.ends_with(".txt") {
         return Err("Invalid filename".to_string());
@@ -61,12 +128,136 @@ fn read_artifact(app_handle: tauri::AppHandle, filename: String) -> Result<Strin
     std::fs::read_to_string(file_path).map_err(|e| e.to_string())
 }
 
+fn saved_plan_path(
+    app_handle: &tauri::AppHandle,
+    filename: &str,
+) -> Result<std::path::PathBuf, String> {
+    // Same rules as read_artifact, for .json files
+    if filename.contains("..") || filename.contains(['/', '\\']) || !filename.ends_with(".json") {
+        return Err("Invalid filename".to_string());
+    }
+    Ok(llm::get_plans_dir(app_handle)?.join(filename))
+}
+
+/// Save a plan as reusable JSON. It must parse and validate against the timeline.
+#[tauri::command]
+fn export_edit_plan(
+    app_handle: tauri::AppHandle,
+    engine: State<'_, TimelineEngine>,
+    raw_llm_output: String,
+    filename: String,
+) -> Result<(), String> {
+    let path = saved_plan_path(&app_handle, &filename)?;
+    let plan = llm::parse_edit_plan(&raw_llm_output).map_err(|e| e.to_string())?;
+    validator::validate_plan(&plan, &engine)?;
+    let json = serde_json::to_string_pretty(&plan).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to save plan: {}", e))
+}
+
+/// Load a plan saved by `export_edit_plan`. Apply it by passing it back to
+/// `apply_edit_plan` as JSON.
+#[tauri::command]
+fn import_edit_plan(
+    app_handle: tauri::AppHandle,
+    filename: String,
+) -> Result<edit_plan::EditPlan, String> {
+    let path = saved_plan_path(&app_handle, &filename)?;
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    llm::parse_edit_plan(&content).map_err(|e| e.to_string())
+}
+
+/// File names of the saved plans, sorted.
+#[tauri::command]
+fn list_saved_plans(app_handle: tauri::AppHandle) -> Result<Vec<String>, String> {
+    let entries = std::fs::read_dir(llm::get_plans_dir(&app_handle)?).map_err(|e| e.to_string())?;
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.ends_with(".json"))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Record every AI edit applied from now on, until `stop_macro_recording`.
+#[tauri::command]
+fn start_macro_recording(recorder: State<'_, MacroRecorder>) {
+    recorder.start();
+}
+
+/// Save the AI edits recorded since `start_macro_recording` as macro `name`,
+/// replacing any macro of that name.
+#[tauri::command]
+fn stop_macro_recording(
+    recorder: State<'_, MacroRecorder>,
+    prefs: State<'_, PreferenceManager>,
+    name: String,
+) -> Result<(), String> {
+    // Check the name first so a bad one doesn't throw the recording away
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Macro name cannot be empty".to_string());
+    }
+    let plans = recorder.stop().ok_or("No macro is being recorded")?;
+    if plans.is_empty() {
+        return Err("No AI edits were recorded".to_string());
+    }
+    prefs.update(|p| {
+        p.macros.retain(|m| m.name != name);
+        p.macros.push(preferences::Macro { name, plans });
+    });
+    Ok(())
+}
+
+/// Run a macro's plans in order. If any step fails the timeline and its undo
+/// history go back to how they were before the first.
+#[tauri::command]
+fn apply_macro(
+    app_handle: tauri::AppHandle,
+    engine: State<'_, TimelineEngine>,
+    prefs: State<'_, PreferenceManager>,
+    name: String,
+) -> Result<timeline::TimelineState, String> {
+    let recorded = prefs
+        .get_preferences()
+        .macros
+        .into_iter()
+        .find(|m| m.name == name)
+        .ok_or_else(|| format!("No macro named '{}'", name))?;
+
+    let checkpoint = engine.checkpoint();
+    let mut state = engine.state.lock().unwrap().clone();
+    for (step, plan) in recorded.plans.into_iter().enumerate() {
+        let result = validator::validate_plan(&plan, &engine)
+            .and_then(|_| action_router::run_edit_plan(&engine, &app_handle, &prefs, plan));
+        match result {
+            Ok(new_state) => state = new_state,
+            Err(e) => {
+                let restored = engine.restore_checkpoint(checkpoint);
+                let _ = app_handle.emit("STATE_UPDATE", &restored);
+                return Err(format!(
+                    "Macro '{}' failed at step {}, nothing was changed: {}",
+                    name,
+                    step + 1,
+                    e
+                ));
+            }
+        }
+    }
+    Ok(state)
+}
+
 // Item 7: Cancel Request Command
 #[tauri::command]
 async fn cancel_request(
     active_requests: State<'_, ActiveRequests>,
+    llm_queue: State<'_, LlmQueue>,
     request_id: String,
 ) -> Result<(), String> {
+    // Not started yet: just drop it from the queue
+    if llm_queue.remove(&request_id) {
+        return Ok(());
+    }
+
     let mut map = active_requests.0.lock().await;
     if let Some(handle) = map.remove(&request_id) {
         handle.abort();
@@ -76,13 +267,279 @@ async fn cancel_request(
     }
 }
 
+// --- OLLAMA MODEL MANAGEMENT ---
+// The HTTP calls are blocking, so each runs on the blocking pool.
+
+#[tauri::command]
+async fn list_ollama_models() -> Result<Vec<llm::OllamaModel>, String> {
+    tokio::task::spawn_blocking(llm::list_ollama_models)
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[tauri::command]
+async fn pull_ollama_model(model_name: String) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || llm::pull_ollama_model(&model_name))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[tauri::command]
+async fn delete_ollama_model(model_name: String) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || llm::delete_ollama_model(&model_name))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Choose the Ollama model used for AI edits.
+#[tauri::command]
+fn set_ollama_model(prefs: State<'_, PreferenceManager>, model_name: String) -> Result<(), String> {
+    let model_name = model_name.trim().to_string();
+    if model_name.is_empty() {
+        return Err("Model name cannot be empty".to_string());
+    }
+    prefs.update(|p| p.general.model_name = model_name);
+    Ok(())
+}
+
+/// Resolution and frame rate of future exports. Width and height must be even
+/// and `fps` within [1, 120].
+#[tauri::command]
+fn set_export_format(
+    prefs: State<'_, PreferenceManager>,
+    width: u32,
+    height: u32,
+    fps: f64,
+) -> Result<preferences::UserPreferences, String> {
+    let render = ffmpeg::RenderOptions {
+        width,
+        height,
+        fps,
+        ..Default::default()
+    };
+    render.validate()?;
+    Ok(prefs.update(|p| {
+        p.export.resolution_width = width;
+        p.export.resolution_height = height;
+        p.export.fps = fps;
+    }))
+}
+
+/// Choose the `-c:v` encoder for exports, e.g. "h264_videotoolbox". Exports use
+/// libx264 instead while FFmpeg doesn't list it.
+#[tauri::command]
+fn set_preferred_encoder(
+    prefs: State<'_, PreferenceManager>,
+    encoder: String,
+) -> Result<preferences::UserPreferences, String> {
+    let encoder = encoder.trim().to_string();
+    if encoder.is_empty() {
+        return Err("Encoder name cannot be empty".to_string());
+    }
+    Ok(prefs.update(|p| p.export.preferred_encoder = encoder))
+}
+
+#[tauri::command]
+fn get_prompt_template(prefs: State<'_, PreferenceManager>) -> preferences::PromptTemplate {
+    prefs.get_preferences().prompt_template
+}
+
+/// Replace the text wrapped around edit requests; takes effect on the next prompt.
+#[tauri::command]
+fn set_prompt_template(
+    prefs: State<'_, PreferenceManager>,
+    template: preferences::PromptTemplate,
+) -> Result<preferences::PromptTemplate, String> {
+    if template.system_prompt.trim().is_empty() {
+        return Err("System prompt cannot be empty".to_string());
+    }
+    Ok(prefs
+        .update(|p| p.prompt_template = template)
+        .prompt_template)
+}
+
+/// Restore the built-in prompt template.
+#[tauri::command]
+fn reset_prompt_template(prefs: State<'_, PreferenceManager>) -> preferences::PromptTemplate {
+    prefs
+        .update(|p| p.prompt_template = preferences::PromptTemplate::default())
+        .prompt_template
+}
+
+#[tauri::command]
+fn list_prompt_examples(
+    prefs: State<'_, PreferenceManager>,
+) -> Result<Vec<preferences::PromptExample>, String> {
+    Ok(prefs.get_preferences().ai.custom_examples)
+}
+
+/// Append a few-shot example to the edit prompt. The expected output must be valid JSON.
+#[tauri::command]
+fn add_prompt_example(
+    prefs: State<'_, PreferenceManager>,
+    example: preferences::PromptExample,
+) -> Result<Vec<preferences::PromptExample>, String> {
+    if example.user_input.trim().is_empty() {
+        return Err("Example input cannot be empty".to_string());
+    }
+    serde_json::from_str::<serde_json::Value>(&example.expected_output_json)
+        .map_err(|e| format!("Expected output is not valid JSON: {}", e))?;
+    Ok(prefs
+        .update(|p| p.ai.custom_examples.push(example))
+        .ai
+        .custom_examples)
+}
+
+#[tauri::command]
+fn remove_prompt_example(
+    prefs: State<'_, PreferenceManager>,
+    index: usize,
+) -> Result<Vec<preferences::PromptExample>, String> {
+    let count = prefs.get_preferences().ai.custom_examples.len();
+    if index >= count {
+        return Err(format!(
+            "No prompt example at index {} ({} defined)",
+            index, count
+        ));
+    }
+    Ok(prefs
+        .update(|p| {
+            p.ai.custom_examples.remove(index);
+        })
+        .ai
+        .custom_examples)
+}
+
+/// Whether the installed FFmpeg lists `codec_name` as an encoder or a filter.
+#[tauri::command]
+async fn check_codec_support(
+    ffmpeg: State<'_, FFmpegEngine>,
+    codec_name: String,
+) -> Result<bool, String> {
+    let ffmpeg_engine = (*ffmpeg).clone();
+    tokio::task::spawn_blocking(move || {
+        let capabilities = ffmpeg_engine.capabilities()?;
+        let name = codec_name.trim();
+        Ok(capabilities.has_encoder(name) || capabilities.has_filter(name))
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Minimum plan confidence for AI edits to be applied, in [0.0, 1.0].
+#[tauri::command]
+fn set_confidence_threshold(prefs: State<'_, PreferenceManager>, value: f32) -> Result<(), String> {
+    if !(0.0..=1.0).contains(&value) {
+        return Err(format!(
+            "Confidence threshold {} must be between 0.0 and 1.0",
+            value
+        ));
+    }
+    prefs.update(|p| p.general.ai_confidence_threshold = value);
+    Ok(())
+}
+
+/// Forget every logged interaction.
+#[tauri::command]
+fn clear_interaction_history(prefs: State<'_, PreferenceManager>) -> Result<(), String> {
+    prefs.clear_interactions();
+    Ok(())
+}
+
+/// Write the interaction history to `output_path`; `format` is "json" or "csv".
+#[tauri::command]
+fn export_interaction_history(
+    prefs: State<'_, PreferenceManager>,
+    format: String,
+    output_path: String,
+) -> Result<(), String> {
+    prefs.export_interactions(&format, std::path::Path::new(&output_path))
+}
+
+#[tauri::command]
+async fn check_ollama_health() -> llm::OllamaStatus {
+    tokio::task::spawn_blocking(llm::check_ollama_health)
+        .await
+        .unwrap_or(llm::OllamaStatus {
+            running: false,
+            version: None,
+        })
+}
+
+/// Ask the LLM to describe the timeline. Returns the raw text; nothing is applied.
+#[tauri::command]
+async fn explain_timeline(
+    app_handle: tauri::AppHandle,
+    engine: State<'_, TimelineEngine>,
+    prefs: State<'_, PreferenceManager>,
+    project: State<'_, ProjectMetadataStore>,
+    question: Option<String>,
+) -> Result<String, String> {
+    let metadata = project.metadata.lock().unwrap().clone();
+    let user_prefs = prefs.get_preferences();
+    let prompt = build_explanation_prompt(&engine, &metadata, &user_prefs.ai, question.as_deref());
+    let model = user_prefs
+        .ai
+        .explanation_model
+        .unwrap_or(user_prefs.general.model_name.clone());
+    let general = user_prefs.general;
+
+    log_artifact(&app_handle, ArtifactType::Explanation, &prompt);
+
+    let mut record = LlmInteraction::new("explain_timeline", &model);
+    record.record_prompt(&prompt);
+
+    let on_retry = llm_retry_emitter(&app_handle);
+    let result =
+        tokio::task::spawn_blocking(move || send_prompt(&prompt, &[], &model, &general, &on_retry))
+            .await
+            .map_err(|e| format!("Task join error: {}", e))?;
+
+    match &result {
+        Ok(completion) => record.record_completion(completion),
+        Err(_) => record.outcome = InteractionOutcome::ExecutionError,
+    }
+    log_llm_interaction(&app_handle, &record);
+
+    match result {
+        Ok(completion) => {
+            log_artifact(&app_handle, ArtifactType::Explanation, &completion.text);
+            Ok(completion.text)
+        }
+        Err(e) => {
+            log_artifact(
+                &app_handle,
+                ArtifactType::Error,
+                &format!("Explanation Error: {}", e),
+            );
+            Err(e)
+        }
+    }
+}
+
+#[tauri::command]
+fn get_llm_analytics(app_handle: tauri::AppHandle, days: u32) -> interaction_log::LlmAnalytics {
+    interaction_log::analytics_for_days(&app_handle, days)
+}
+
+#[tauri::command]
+fn get_llm_queue_status(llm_queue: State<'_, LlmQueue>) -> llm_queue::QueueStatus {
+    llm_queue.status()
+}
+
 #[tauri::command]
 async fn build_prompt_preview(
     state: tauri::State<'_, TimelineEngine>,
+    prefs: tauri::State<'_, PreferenceManager>,
+    project: tauri::State<'_, ProjectMetadataStore>,
     user_input: String,
 ) -> Result<String, String> {
     // Only return the Context + User Input part for editing
-    let context = build_context_block(&state);
+    let context = build_context_block(
+        &state,
+        &project.metadata.lock().unwrap(),
+        &prefs.get_preferences().ai,
+    );
     Ok(format!("{}\nUser Instruction: {}", context, user_input))
 }
 
@@ -106,6 +563,8 @@ async fn process_user_prompt(
                 char_count: 52,
                 truncated: false,
                 artifact_filename: "".to_string(),
+                input_tokens: None,
+                output_tokens: None,
             });
         }
     }
@@ -119,25 +578,49 @@ async fn process_user_prompt(
     let full_prompt = if let Some(override_text) = prompt_override {
         println!("⚠️ Using Prompt Override");
         // If overridden, we assume the user edited the CONTEXT + INSTRUCTION part.
-        // We still prepend the SYSTEM_PROMPT to ensure rules are followed.
+        // We still prepend the system prompt to ensure rules are followed.
         // NOTE: We might want to inject preferences here too, but for override we assume user knows what they are doing.
         // For now, let's just use the override as is, or prepend the raw system prompt.
         // Let's stick to the previous behavior for override but maybe we should inject prefs?
         // Let's keep it simple: Override means override.
-        format!("{}\n{}", SYSTEM_PROMPT, override_text)
+        let template = prefs.get_preferences().prompt_template;
+        format!("{}\n{}", template.system_prompt, override_text)
     } else {
-        build_prompt(&state, &prefs, &user_input)
+        let project = app_handle.state::<ProjectMetadataStore>();
+        let metadata = project.metadata.lock().unwrap().clone();
+        let user_input = prepare_user_input(&app_handle, &prefs, &user_input);
+        let success_rate = quality_success_rate(&app_handle);
+        build_prompt(&state, &prefs, &metadata, &user_input, success_rate)
     };
 
+    // Earlier turns of this session, for follow-up requests
+    let conversation = app_handle.state::<ConversationHistory>();
+    let history_len = prefs.get_preferences().ai.conversation_history_messages;
+    let history = conversation.recent(history_len);
+
     // 2. Log the prompt artifact
-    log_artifact(&app_handle, ArtifactType::Prompt, &full_prompt);
+    log_artifact(
+        &app_handle,
+        ArtifactType::Prompt,
+        &with_conversation_history(&full_prompt, &history),
+    );
 
     // 3. Send to Ollama (blocking call wrapped in spawn_blocking)
     let (tx, rx) = tokio::sync::oneshot::channel();
     let prompt_clone = full_prompt.clone();
+    let general = prefs.get_preferences().general;
+    let model = general.model_name.clone();
+
+    let on_retry = llm_retry_emitter(&app_handle);
 
     let handle = tokio::task::spawn_blocking(move || {
-        let result = send_prompt_to_ollama(&prompt_clone);
+        let result = send_prompt(
+            &prompt_clone,
+            &history,
+            &general.model_name,
+            &general,
+            &on_retry,
+        );
         let _ = tx.send(result);
     });
 
@@ -149,33 +632,56 @@ async fn process_user_prompt(
         .insert(request_id.clone(), handle);
 
     // 4. Wait for result with timeout
+    let mut record = LlmInteraction::new(&request_id, &model);
+    record.record_prompt(&full_prompt);
     let final_result = match tokio::time::timeout(std::time::Duration::from_secs(60), rx).await {
-        Ok(Ok(result)) => result,
-        Ok(Err(_)) => Err("Request cancelled or sender dropped".to_string()),
-        Err(_) => Err("Global request timeout reached (60s)".to_string()),
+        Ok(Ok(result)) => {
+            if result.is_err() {
+                record.outcome = InteractionOutcome::ExecutionError;
+            }
+            result
+        }
+        Ok(Err(_)) => {
+            record.outcome = InteractionOutcome::Cancelled;
+            Err("Request cancelled or sender dropped".to_string())
+        }
+        Err(_) => {
+            record.outcome = InteractionOutcome::Timeout;
+            Err("Global request timeout reached (60s)".to_string())
+        }
     };
 
     // Cleanup
     active_requests.0.lock().await.remove(&request_id);
 
+    if let Ok(completion) = &final_result {
+        record.record_completion(completion);
+    }
+    log_llm_interaction(&app_handle, &record);
+
     match final_result {
-        Ok((text, latency_ms, char_count, truncated)) => {
+        Ok(completion) => {
             println!(
                 "✅ [Backend] Received response from Ollama ({} chars, {}ms)",
-                char_count, latency_ms
+                completion.char_count, completion.latency_ms
             );
-            println!("📄 [Backend] Response Preview: {:.100}...", text);
+            println!("📄 [Backend] Response Preview: {:.100}...", completion.text);
+            conversation.push("user", &user_input);
+            conversation.push("assistant", &completion.text);
 
             // Log the response (full text)
-            let artifact_filename = log_artifact(&app_handle, ArtifactType::LlmResponse, &text);
+            let artifact_filename =
+                log_artifact(&app_handle, ArtifactType::LlmResponse, &completion.text);
 
             // Return rich metadata
             Ok(LlmResponseMetadata {
-                text, // This might be truncated if Item 8 logic in llm.rs triggered
-                latency_ms,
-                char_count,
-                truncated,
+                text: completion.text, // This might be truncated if Item 8 logic in llm.rs triggered
+                latency_ms: completion.latency_ms,
+                char_count: completion.char_count,
+                truncated: completion.truncated,
                 artifact_filename,
+                input_tokens: completion.input_tokens,
+                output_tokens: completion.output_tokens,
             })
         }
         Err(e) => {
@@ -187,6 +693,8 @@ async fn process_user_prompt(
 }
 
 // --- WEEK 7: Apply Edit Plan ---
+/// Parse, validate and run an edit plan given as JSON, such as LLM output or
+/// a saved plan.
 #[tauri::command]
 async fn apply_edit_plan(
     engine: State<'_, TimelineEngine>,
@@ -244,11 +752,93 @@ async fn apply_edit_plan(
         Err(e) => {
             let err_msg = format!("Router Execution Error: {}", e);
             log_artifact(&app_handle, ArtifactType::Error, &err_msg);
-            return Err(err_msg);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Re-run the plan stored in an `apply_plan` artifact against the current timeline.
+/// With `dry_run` the plan is only simulated and nothing is changed.
+#[tauri::command]
+async fn replay_edit_plan_from_artifact(
+    engine: State<'_, TimelineEngine>,
+    prefs: State<'_, PreferenceManager>,
+    app_handle: tauri::AppHandle,
+    artifact_filename: String,
+    dry_run: bool,
+) -> Result<String, String> {
+    use action_router::{preview_edit_plan, run_edit_plan};
+    use llm::extract_plan_from_artifact;
+    use validator::validate_plan_with_state;
+
+    let content = read_artifact_file(&app_handle, &artifact_filename)?;
+    let plan = extract_plan_from_artifact(&content)?;
+
+    let current = engine.state.lock().unwrap().clone();
+    validate_plan_with_state(&plan, &current)?;
+
+    if dry_run {
+        let user_prefs = prefs.get_preferences();
+        let mut plan = plan;
+        if !user_prefs.general.auto_ripple_edits {
+            action_router::downgrade_ripple_deletes(&mut plan);
+        }
+        let preview = preview_edit_plan(&current, &plan, &user_prefs.editing)?;
+        return Ok(format!(
+            "Dry run: {} actions would leave {} clips ({:.2}s)",
+            plan.actions.len(),
+            preview.clips.len(),
+            preview.duration
+        ));
+    }
+
+    match run_edit_plan(&engine, &app_handle, &prefs, plan.clone()) {
+        Ok(_new_state) => {
+            let plan_json = serde_json::to_string_pretty(&plan).unwrap_or_default();
+            log_artifact(
+                &app_handle,
+                ArtifactType::ApplyEditPlan {
+                    plan: plan_json,
+                    result: "Success".to_string(),
+                },
+                &format!("replay of {}", artifact_filename),
+            );
+            Ok("Plan applied successfully".to_string())
+        }
+        Err(e) => {
+            let err_msg = format!("Router Execution Error: {}", e);
+            log_artifact(&app_handle, ArtifactType::Error, &err_msg);
+            Err(err_msg)
         }
     }
 }
 
+/// Parse and validate an LLM edit plan and return the state it would produce,
+/// with one line per change, without touching the timeline.
+#[tauri::command]
+async fn preview_edit_plan(
+    engine: State<'_, TimelineEngine>,
+    prefs: State<'_, PreferenceManager>,
+    raw_llm_output: String,
+) -> Result<(timeline::TimelineState, Vec<String>), String> {
+    use action_router::run_edit_plan_dry_run;
+    use llm::parse_edit_plan;
+    use validator::validate_plan;
+
+    let plan = parse_edit_plan(&raw_llm_output).map_err(|e| format!("LLM Parse Error: {}", e))?;
+    validate_plan(&plan, &engine).map_err(|e| format!("Plan Validation Rejected: {}", e))?;
+    run_edit_plan_dry_run(&engine, &prefs, plan)
+}
+
+/// Result of `execute_ai_edit`: a plain message when the edit ran, or the
+/// queue position when another AI edit was already in flight.
+#[derive(serde::Serialize)]
+#[serde(untagged)]
+enum AiEditOutcome {
+    Applied(String),
+    Queued { queued: bool, position: usize },
+}
+
 // STEP 4 FIX: Atomic AI Edit Command
 // This replaces the two-step process (process_user_prompt + apply_edit_plan)
 // Frontend sends user intent, backend handles everything atomically
@@ -256,15 +846,11 @@ async fn apply_edit_plan(
 async fn execute_ai_edit(
     app_handle: tauri::AppHandle,
     engine: tauri::State<'_, TimelineEngine>,
-    active_requests: tauri::State<'_, ActiveRequests>,
-    prefs: tauri::State<'_, PreferenceManager>,
+    llm_queue: tauri::State<'_, LlmQueue>,
     user_input: String,
     request_id: String,
-) -> Result<String, String> {
-    use action_router::run_edit_plan;
-    use llm::parse_edit_plan;
-    use validator::validate_plan;
-
+    priority: Option<u8>,
+) -> Result<AiEditOutcome, String> {
     println!(
         "🚀 [Backend] execute_ai_edit called with input: '{}'",
         user_input
@@ -278,59 +864,135 @@ async fn execute_ai_edit(
         }
     }
 
-    // 1. Build prompt
-    let full_prompt = build_prompt(&engine, &prefs, &user_input);
-    log_artifact(&app_handle, ArtifactType::Prompt, &full_prompt);
+    // Only one LLM call at a time - queue if another edit is in flight
+    let request = QueuedRequest::new(
+        request_id.clone(),
+        priority.unwrap_or(llm_queue::DEFAULT_PRIORITY),
+        user_input.clone(),
+    );
+    if let Some(position) = llm_queue.try_start_or_enqueue(request) {
+        println!(
+            "⏳ [Backend] LLM busy, queued request {} at position {}",
+            request_id, position
+        );
+        return Ok(AiEditOutcome::Queued {
+            queued: true,
+            position,
+        });
+    }
 
-    // 2. Send to LLM (blocking call wrapped in spawn_blocking)
-    let (tx, rx) = tokio::sync::oneshot::channel();
-    let prompt_clone = full_prompt.clone();
+    let result = run_ai_edit(&app_handle, &user_input, &request_id, false).await;
+    llm_queue.finish();
+    result.map(AiEditOutcome::Applied)
+}
 
-    let handle = tokio::task::spawn_blocking(move || {
-        let result = send_prompt_to_ollama(&prompt_clone);
-        let _ = tx.send(result);
-    });
+/// `execute_ai_edit` with the response streamed: LLM_TOKEN events carry the
+/// text as it is generated and LLM_DONE the full response, which is then parsed
+/// and applied as usual. Runs immediately rather than through the LLM queue.
+#[tauri::command]
+async fn execute_ai_edit_streaming(
+    app_handle: tauri::AppHandle,
+    engine: tauri::State<'_, TimelineEngine>,
+    user_input: String,
+    request_id: String,
+) -> Result<String, String> {
+    if engine.state.lock().unwrap().clips.is_empty() {
+        return Err("No clips in timeline. Cannot perform edit operations.".to_string());
+    }
+    run_ai_edit(&app_handle, &user_input, &request_id, true).await
+}
 
-    // Track request for cancellation
-    active_requests
-        .0
-        .lock()
-        .await
-        .insert(request_id.clone(), handle);
+// Historical AI edit success rate for the prompt, once there is any history
+fn quality_success_rate(app_handle: &tauri::AppHandle) -> Option<f64> {
+    let report = app_handle.state::<LlmQualityTracker>().report();
+    (report.stats.total_calls > 0).then_some(report.success_rate)
+}
 
-    // 3. Wait for LLM response
-    let llm_result = match tokio::time::timeout(std::time::Duration::from_secs(60), rx).await {
-        Ok(Ok(result)) => result,
-        Ok(Err(_)) => {
-            active_requests.0.lock().await.remove(&request_id);
-            return Err("Request cancelled or sender dropped".to_string());
-        }
-        Err(_) => {
-            active_requests.0.lock().await.remove(&request_id);
-            return Err("Global request timeout reached (60s)".to_string());
-        }
-    };
+#[tauri::command]
+fn get_llm_quality_report(tracker: State<'_, LlmQualityTracker>) -> LlmQualityReport {
+    tracker.report()
+}
 
-    active_requests.0.lock().await.remove(&request_id);
+#[tauri::command]
+fn reset_llm_quality_stats(tracker: State<'_, LlmQualityTracker>) {
+    tracker.reset();
+}
 
-    let (llm_text, latency_ms, char_count, _truncated) = match llm_result {
-        Ok(r) => r,
-        Err(e) => {
-            // Human-friendly: Network/LLM issues
-            let user_msg = "AI service is temporarily unavailable. Please try again.".to_string();
-            log_artifact(
-                &app_handle,
-                ArtifactType::Error,
-                &format!("LLM Error: {}", e),
-            );
-            return Err(user_msg);
-        }
+// The full prompt -> LLM -> parse -> validate -> apply pipeline.
+// Takes only the AppHandle so the queue worker can run it outside a command.
+async fn run_ai_edit(
+    app_handle: &tauri::AppHandle,
+    user_input: &str,
+    request_id: &str,
+    streaming: bool,
+) -> Result<String, String> {
+    let model = app_handle
+        .state::<PreferenceManager>()
+        .get_preferences()
+        .general
+        .model_name;
+    let mut record = LlmInteraction::new(request_id, &model);
+    let result = run_ai_edit_inner(
+        app_handle,
+        user_input,
+        request_id,
+        &model,
+        streaming,
+        &mut record,
+    )
+    .await;
+    record.outcome = match &result {
+        Ok(_) => InteractionOutcome::Success,
+        Err((outcome, _)) => *outcome,
+    };
+    log_llm_interaction(app_handle, &record);
+    app_handle.state::<LlmQualityTracker>().record(&record);
+    result.map_err(|(_, message)| message)
+}
+
+async fn run_ai_edit_inner(
+    app_handle: &tauri::AppHandle,
+    user_input: &str,
+    request_id: &str,
+    model: &str,
+    streaming: bool,
+    record: &mut LlmInteraction,
+) -> Result<String, (InteractionOutcome, String)> {
+    use action_router::run_edit_plan;
+    use llm::parse_edit_plan;
+    use validator::validate_plan;
+
+    let engine = app_handle.state::<TimelineEngine>();
+    let prefs = app_handle.state::<PreferenceManager>();
+    let metadata = app_handle
+        .state::<ProjectMetadataStore>()
+        .metadata
+        .lock()
+        .unwrap()
+        .clone();
+    let app_handle = app_handle.clone();
+    let request_id = request_id.to_string();
+
+    // 1. Build prompt
+    let user_input = prepare_user_input(&app_handle, &prefs, user_input);
+    let success_rate = quality_success_rate(&app_handle);
+    let full_prompt = build_prompt(&engine, &prefs, &metadata, &user_input, success_rate);
+    log_artifact(&app_handle, ArtifactType::Prompt, &full_prompt);
+    record.record_prompt(&full_prompt);
+
+    // 2. Send to LLM
+    let completion = if streaming {
+        request_streamed_completion(&app_handle, full_prompt, model, &request_id).await?
+    } else {
+        request_completion(&app_handle, full_prompt, model, &request_id).await?
     };
 
     println!(
         "✅ [Backend] LLM Response ({} chars, {}ms)",
-        char_count, latency_ms
+        completion.char_count, completion.latency_ms
     );
+    record.record_completion(&completion);
+    let llm_text = completion.text;
     log_artifact(&app_handle, ArtifactType::LlmResponse, &llm_text);
 
     // 4. Parse EditPlan
@@ -345,32 +1007,38 @@ async fn execute_ai_edit(
                 &format!("Parse Error: {}", e),
             );
             app_handle.emit("LLM_ERROR", &user_msg).unwrap_or(());
-            return Err(user_msg);
+            return Err((InteractionOutcome::ParseError, user_msg));
         }
     };
 
     println!("✅ [Backend] Plan Parsed: {:?}", plan);
+    record.confidence = plan.confidence;
+    record.actions_count = Some(plan.actions.len());
 
     // 4.5 CONFIDENCE GATE: Reject low-confidence plans
-    const CONFIDENCE_THRESHOLD: f32 = 0.6;
     let confidence = plan.confidence.unwrap_or(0.5); // Default to uncertain if not provided
-    if confidence < CONFIDENCE_THRESHOLD {
+    let threshold = prefs.get_preferences().general.ai_confidence_threshold;
+    if confidence < threshold {
         let thought = plan
             .thought_process
             .as_deref()
             .unwrap_or("No explanation provided");
         let user_msg = format!(
-            "AI is uncertain about this edit (confidence: {:.0}%). Please rephrase or be more specific.\nAI's interpretation: {}",
+            "AI is uncertain about this edit (confidence: {:.0}%, threshold: {:.0}%). Please rephrase or be more specific.\nAI's interpretation: {}",
             confidence * 100.0,
+            threshold * 100.0,
             thought
         );
         log_artifact(
             &app_handle,
             ArtifactType::Error,
-            &format!("Low confidence ({:.2}): {}", confidence, thought),
+            &format!(
+                "Low confidence ({:.2} < {:.2}): {}",
+                confidence, threshold, thought
+            ),
         );
         app_handle.emit("LLM_ERROR", &user_msg).unwrap_or(());
-        return Err(user_msg);
+        return Err((InteractionOutcome::ValidationError, user_msg));
     }
     println!(
         "✅ [Backend] Confidence Gate Passed: {:.0}%",
@@ -388,7 +1056,7 @@ async fn execute_ai_edit(
             &format!("Validation Error: {}", e),
         );
         app_handle.emit("LLM_ERROR", &user_msg).unwrap_or(());
-        return Err(user_msg);
+        return Err((InteractionOutcome::ValidationError, user_msg));
     }
 
     println!("✅ [Backend] Plan Validated");
@@ -406,6 +1074,8 @@ async fn execute_ai_edit(
                 &llm_text,
             );
             println!("✅ [Backend] AI Edit Applied Successfully");
+            app_handle.state::<MacroRecorder>().record(&plan);
+            record.applied = true;
             Ok("AI edit applied successfully".to_string())
         }
         Err(e) => {
@@ -416,11 +1086,165 @@ async fn execute_ai_edit(
                 ArtifactType::Error,
                 &format!("Execution Error: {}", e),
             );
-            Err(user_msg)
+            Err((InteractionOutcome::ExecutionError, user_msg))
+        }
+    }
+}
+
+// Blocking Ollama call on a worker thread, cancellable via `cancel_request` and
+// capped at 60s
+async fn request_completion(
+    app_handle: &tauri::AppHandle,
+    prompt: String,
+    model: &str,
+    request_id: &str,
+) -> Result<OllamaCompletion, (InteractionOutcome, String)> {
+    let active_requests = app_handle.state::<ActiveRequests>();
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let model = model.to_string();
+    let general = app_handle
+        .state::<PreferenceManager>()
+        .get_preferences()
+        .general;
+
+    let on_retry = llm_retry_emitter(app_handle);
+
+    let handle = tokio::task::spawn_blocking(move || {
+        let result = send_prompt(&prompt, &[], &model, &general, &on_retry);
+        let _ = tx.send(result);
+    });
+
+    // Track request for cancellation
+    active_requests
+        .0
+        .lock()
+        .await
+        .insert(request_id.to_string(), handle);
+
+    // Wait for LLM response
+    let llm_result = match tokio::time::timeout(std::time::Duration::from_secs(60), rx).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => {
+            active_requests.0.lock().await.remove(request_id);
+            return Err((
+                InteractionOutcome::Cancelled,
+                "Request cancelled or sender dropped".to_string(),
+            ));
+        }
+        Err(_) => {
+            active_requests.0.lock().await.remove(request_id);
+            return Err((
+                InteractionOutcome::Timeout,
+                "Global request timeout reached (60s)".to_string(),
+            ));
+        }
+    };
+
+    active_requests.0.lock().await.remove(request_id);
+    llm_result.map_err(|e| llm_unavailable(app_handle, &e))
+}
+
+#[derive(Clone, serde::Serialize)]
+struct LlmTokenEvent {
+    /// Text generated since the previous event.
+    partial_text: String,
+    elapsed_ms: u64,
+}
+
+// Streamed Ollama call emitting LLM_TOKEN as text arrives and LLM_DONE at the
+// end. No overall timeout: the tokens show progress, and `cancel_request`
+// aborts the task, which drops the HTTP connection.
+async fn request_streamed_completion(
+    app_handle: &tauri::AppHandle,
+    prompt: String,
+    model: &str,
+    request_id: &str,
+) -> Result<OllamaCompletion, (InteractionOutcome, String)> {
+    let active_requests = app_handle.state::<ActiveRequests>();
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let emitter = app_handle.clone();
+    let model = model.to_string();
+
+    let general = app_handle
+        .state::<PreferenceManager>()
+        .get_preferences()
+        .general;
+    let on_retry = llm_retry_emitter(app_handle);
+
+    let handle = tokio::spawn(async move {
+        let started = std::time::Instant::now();
+        let emit_token = |token: &str| {
+            let event = LlmTokenEvent {
+                partial_text: token.to_string(),
+                elapsed_ms: started.elapsed().as_millis() as u64,
+            };
+            emitter.emit("LLM_TOKEN", event).unwrap_or(());
+        };
+        let result = match general.llm_provider {
+            LlmProvider::Ollama => llm::stream_prompt_to_ollama(&prompt, &model, emit_token).await,
+            // Not streamed: the whole response arrives as one token
+            LlmProvider::OpenAiCompatible => {
+                let sent = tokio::task::spawn_blocking(move || {
+                    send_prompt(&prompt, &[], &model, &general, &on_retry)
+                })
+                .await
+                .map_err(|e| format!("Task join error: {}", e))
+                .and_then(|r| r);
+                if let Ok(completion) = &sent {
+                    emit_token(&completion.text);
+                }
+                sent
+            }
+        };
+        let _ = tx.send(result);
+    });
+
+    // Track request for cancellation
+    active_requests
+        .0
+        .lock()
+        .await
+        .insert(request_id.to_string(), handle);
+    let llm_result = rx.await;
+    active_requests.0.lock().await.remove(request_id);
+
+    match llm_result {
+        Ok(Ok(completion)) => {
+            app_handle
+                .emit(
+                    "LLM_DONE",
+                    serde_json::json!({ "full_text": completion.text }),
+                )
+                .unwrap_or(());
+            Ok(completion)
         }
+        Ok(Err(e)) => Err(llm_unavailable(app_handle, &e)),
+        Err(_) => Err((
+            InteractionOutcome::Cancelled,
+            "Request cancelled or sender dropped".to_string(),
+        )),
     }
 }
 
+// Forwards each retry of an LLM request to the frontend as LLM_RETRY
+fn llm_retry_emitter(app_handle: &tauri::AppHandle) -> impl Fn(&LlmRetryEvent) + Send + 'static {
+    let app_handle = app_handle.clone();
+    move |event| app_handle.emit("LLM_RETRY", event).unwrap_or(())
+}
+
+// Human-friendly: Network/LLM issues
+fn llm_unavailable(app_handle: &tauri::AppHandle, error: &str) -> (InteractionOutcome, String) {
+    log_artifact(
+        app_handle,
+        ArtifactType::Error,
+        &format!("LLM Error: {}", error),
+    );
+    (
+        InteractionOutcome::ExecutionError,
+        "AI service is temporarily unavailable. Please try again.".to_string(),
+    )
+}
+
 // --- COMMANDS ---
 
 /// Seek the timeline playhead to a specific time.
@@ -428,9 +1252,17 @@ async fn execute_ai_edit(
 #[tauri::command]
 async fn seek_timeline(
     engine: State<'_, TimelineEngine>,
+    prefs: State<'_, PreferenceManager>,
     app_handle: tauri::AppHandle,
     time: f64,
 ) -> Result<f64, String> {
+    let user_prefs = prefs.get_preferences();
+    let time = snap_time_to_clip_edge(&engine, time, user_prefs.editing.snap_threshold_s)?;
+    let time = if user_prefs.general.round_to_frame_boundary {
+        engine.snap_to_frame(time)
+    } else {
+        time
+    };
     let clamped_time = engine.seek(time);
 
     // Emit state update so frontend stays in sync
@@ -442,27 +1274,112 @@ async fn seek_timeline(
     Ok(clamped_time)
 }
 
-/// Get the currently active clip at the playhead position.
+/// Seek the playhead to a "HH:MM:SS:FF" timecode at `fps`; a ';' before the
+/// frames marks drop-frame. Returns the resulting time in seconds.
 #[tauri::command]
-fn get_active_clip(engine: State<'_, TimelineEngine>) -> Result<Option<timeline::Clip>, String> {
-    Ok(engine.get_current_clip())
+fn seek_to_timecode(
+    engine: State<'_, TimelineEngine>,
+    app_handle: tauri::AppHandle,
+    tc: String,
+    fps: f64,
+) -> Result<f64, String> {
+    let seconds = timeline::timecode_to_seconds(&tc, fps, tc.contains(';'))?;
+    let time = engine.seek(seconds);
+
+    let state = engine.state.lock().map_err(|_| "Failed to lock state")?;
+    app_handle
+        .emit("STATE_UPDATE", &*state)
+        .map_err(|e| e.to_string())?;
+
+    Ok(time)
 }
 
-/// Export the timeline to a video file using FFmpeg.
-/// This is NOT preview - it generates an actual rendered output file.
+/// Where `seek_timeline` would land for `time` after edge snapping, without seeking.
 #[tauri::command]
-async fn export_timeline(
-    ffmpeg: State<'_, FFmpegEngine>,
+fn snap_to_clip_edge(
     engine: State<'_, TimelineEngine>,
-    _app_handle: tauri::AppHandle,
-) -> Result<String, String> {
-    // 1. Get Timeline State
-    let state = {
-        let guard = engine.state.lock().unwrap();
-        guard.clone()
-    };
+    prefs: State<'_, PreferenceManager>,
+    time: f64,
+) -> Result<f64, String> {
+    let threshold = prefs.get_preferences().editing.snap_threshold_s;
+    snap_time_to_clip_edge(&engine, time, threshold)
+}
 
-    // 2. Determine Output Path
+fn snap_time_to_clip_edge(
+    engine: &TimelineEngine,
+    time: f64,
+    threshold: f64,
+) -> Result<f64, String> {
+    let state = engine.state.lock().map_err(|_| "Failed to lock state")?;
+    Ok(state.snap_to_clip_edge(time, threshold, None))
+}
+
+/// Seek the playhead to a frame number at the timeline's output fps.
+/// Returns the resulting time in seconds.
+#[tauri::command]
+fn seek_to_frame(
+    engine: State<'_, TimelineEngine>,
+    app_handle: tauri::AppHandle,
+    frame_number: u64,
+) -> Result<f64, String> {
+    let time = engine.seek_to_frame(frame_number);
+
+    let state = engine.state.lock().map_err(|_| "Failed to lock state")?;
+    app_handle
+        .emit("STATE_UPDATE", &*state)
+        .map_err(|e| e.to_string())?;
+
+    Ok(time)
+}
+
+/// Get the playhead position as a frame number.
+#[tauri::command]
+fn get_playhead_frame(engine: State<'_, TimelineEngine>) -> u64 {
+    engine.playhead_frame()
+}
+
+/// Set the frame rate used for frame seeking and snapping.
+#[tauri::command]
+fn set_output_fps(
+    engine: State<'_, TimelineEngine>,
+    app_handle: tauri::AppHandle,
+    fps: f64,
+) -> Result<(), String> {
+    engine.set_output_fps(fps)?;
+
+    let state = engine.state.lock().map_err(|_| "Failed to lock state")?;
+    app_handle
+        .emit("STATE_UPDATE", &*state)
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Set the frontend player's preview speed (e.g. 0.5 for slow-motion review).
+#[tauri::command]
+fn set_preview_playback_rate(
+    engine: State<'_, TimelineEngine>,
+    app_handle: tauri::AppHandle,
+    rate: f64,
+) -> Result<(), String> {
+    engine.set_preview_playback_rate(rate)?;
+
+    let state = engine.state.lock().map_err(|_| "Failed to lock state")?;
+    app_handle
+        .emit("STATE_UPDATE", &*state)
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Get the currently active clip at the playhead position.
+#[tauri::command]
+fn get_active_clip(engine: State<'_, TimelineEngine>) -> Result<Option<timeline::Clip>, String> {
+    Ok(engine.get_current_clip())
+}
+
+// Unique file path in videos/exports, created on demand.
+fn new_export_path(extension: &str) -> Result<std::path::PathBuf, String> {
     let current_dir = std::env::current_dir().map_err(|e| e.to_string())?;
 
     let videos_dir = if current_dir.ends_with("src-tauri") {
@@ -476,23 +1393,830 @@ async fn export_timeline(
         std::fs::create_dir_all(&exports_dir).map_err(|e| e.to_string())?;
     }
 
-    let filename = format!("export_{}.mp4", uuid::Uuid::new_v4());
-    let output_path = exports_dir.join(filename);
+    let filename = format!("export_{}.{}", uuid::Uuid::new_v4(), extension);
+    Ok(exports_dir.join(filename))
+}
 
-    // 3. Render using FFmpeg
+/// Save a grid of frames sampled across a clip so long imports can be skimmed.
+#[tauri::command]
+async fn export_clip_mosaic_thumbnail(
+    ffmpeg: State<'_, FFmpegEngine>,
+    engine: State<'_, TimelineEngine>,
+    clip_id: String,
+    cols: u32,
+    rows: u32,
+    frame_width: u32,
+) -> Result<String, String> {
+    let clip = {
+        let state = engine.state.lock().unwrap();
+        state
+            .clips
+            .iter()
+            .find(|c| c.id == clip_id)
+            .cloned()
+            .ok_or(format!("Clip '{}' not found", clip_id))?
+    };
+
+    let output_path = new_export_path("jpg")?;
+    let ffmpeg_engine = (*ffmpeg).clone();
+
+    tokio::task::spawn_blocking(move || {
+        ffmpeg_engine.export_clip_mosaic(&clip, cols, rows, frame_width, &output_path)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+// Extract one clip's thumbnail off the async runtime, recording it in `progress`
+async fn generate_thumbnail(
+    ffmpeg: &FFmpegEngine,
+    progress: &ThumbnailGenerationState,
+    clip: timeline::Clip,
+) -> Result<String, String> {
+    let dir = thumbnails::thumbnail_dir();
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let output_path = dir.join(format!("{}.jpg", clip.id));
+    let clip_id = clip.id.clone();
+    let ffmpeg_engine = ffmpeg.clone();
+
+    progress.start(&clip_id);
+    let result = tokio::task::spawn_blocking(move || {
+        ffmpeg_engine.extract_thumbnail(&clip, thumbnails::THUMBNAIL_WIDTH, &output_path)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))
+    .and_then(|r| r);
+    progress.finish(&clip_id, &result);
+    result
+}
+
+/// Thumbnail for a single clip. Returns the image path.
+#[tauri::command]
+async fn extract_thumbnail(
+    ffmpeg: State<'_, FFmpegEngine>,
+    engine: State<'_, TimelineEngine>,
+    progress: State<'_, ThumbnailGenerationState>,
+    clip_id: String,
+) -> Result<String, String> {
+    let clip = {
+        let state = engine.state.lock().unwrap();
+        state
+            .clips
+            .iter()
+            .find(|c| c.id == clip_id)
+            .cloned()
+            .ok_or(format!("Clip '{}' not found", clip_id))?
+    };
+    progress.add_request();
+    generate_thumbnail(&ffmpeg, &progress, clip).await
+}
+
+/// Thumbnails for every clip, one at a time. Stops early after
+/// `cancel_thumbnail_generation`; progress is available while it runs.
+#[tauri::command]
+async fn generate_all_thumbnails(
+    app_handle: tauri::AppHandle,
+    ffmpeg: State<'_, FFmpegEngine>,
+    engine: State<'_, TimelineEngine>,
+    progress: State<'_, ThumbnailGenerationState>,
+) -> Result<ThumbnailGenerationProgress, String> {
+    let clips = engine.state.lock().unwrap().clips.clone();
+    progress.begin_batch(clips.len());
+    let _ = app_handle.emit(
+        "THUMBNAIL_GENERATION_STARTED",
+        serde_json::json!({ "total": clips.len() }),
+    );
+
+    for clip in clips {
+        if progress.is_cancelled() {
+            println!("⏹️ [Backend] Thumbnail generation cancelled");
+            break;
+        }
+        // Failures are recorded in the progress; keep going with the other clips
+        let _ = generate_thumbnail(&ffmpeg, &progress, clip).await;
+    }
+
+    let result = progress.progress();
+    let _ = app_handle.emit(
+        "THUMBNAIL_GENERATION_COMPLETE",
+        serde_json::json!({
+            "total_generated": result.completed,
+            "failed_count": result.failed.len(),
+        }),
+    );
+    Ok(result)
+}
+
+/// Frame of a clip's source at `time_offset_s`, for the timeline clip blocks.
+/// Returns the cached JPEG when the source has not changed since it was made.
+#[tauri::command]
+async fn generate_clip_thumbnail(
+    app_handle: tauri::AppHandle,
+    ffmpeg: State<'_, FFmpegEngine>,
+    engine: State<'_, TimelineEngine>,
+    clip_id: String,
+    time_offset_s: f64,
+    width_px: u32,
+) -> Result<String, String> {
+    let clip = {
+        let state = engine.state.lock().unwrap();
+        state
+            .clips
+            .iter()
+            .find(|c| c.id == clip_id)
+            .cloned()
+            .ok_or(format!("Clip '{}' not found", clip_id))?
+    };
+
+    let offset = thumbnails::clamp_thumbnail_offset(time_offset_s, clip.duration);
+    let (uploads_dir, _) = commands::get_video_dirs(&app_handle);
+    let dir = thumbnails::clip_thumbnail_dir(&uploads_dir);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let output_path = dir.join(thumbnails::clip_thumbnail_file_name(
+        &clip.id, offset, width_px,
+    ));
+    if thumbnails::is_thumbnail_fresh(&output_path, std::path::Path::new(&clip.source_file)) {
+        return Ok(output_path.to_string_lossy().to_string());
+    }
+
+    let ffmpeg_engine = (*ffmpeg).clone();
+    tokio::task::spawn_blocking(move || {
+        ffmpeg_engine.extract_frame_at(&clip.source_file, offset, width_px, &output_path)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Per-window RMS levels (dB) of a clip's audio for drawing its waveform.
+/// `samples_per_second` defaults to 10; clips without audio return no samples.
+#[tauri::command]
+async fn generate_waveform_data(
+    ffmpeg: State<'_, FFmpegEngine>,
+    engine: State<'_, TimelineEngine>,
+    clip_id: String,
+    samples_per_second: Option<u32>,
+) -> Result<Vec<f32>, String> {
+    let clip = {
+        let state = engine.state.lock().unwrap();
+        state
+            .clips
+            .iter()
+            .find(|c| c.id == clip_id)
+            .cloned()
+            .ok_or(format!("Clip '{}' not found", clip_id))?
+    };
+
+    let ffmpeg_engine = (*ffmpeg).clone();
+    tokio::task::spawn_blocking(move || {
+        ffmpeg_engine.waveform_data(&clip, samples_per_second.unwrap_or(10))
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Cuts in a clip's source whose scene score exceeds `threshold` (default 0.4).
+#[tauri::command]
+async fn detect_scene_changes(
+    ffmpeg: State<'_, FFmpegEngine>,
+    engine: State<'_, TimelineEngine>,
+    clip_id: String,
+    threshold: Option<f64>,
+) -> Result<Vec<ffmpeg::SceneChange>, String> {
+    let clip = find_clip(&engine, &clip_id)?;
+    let ffmpeg_engine = (*ffmpeg).clone();
+    tokio::task::spawn_blocking(move || {
+        ffmpeg_engine
+            .detect_scene_changes(&clip, threshold.unwrap_or(ffmpeg::DEFAULT_SCENE_THRESHOLD))
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Split a clip at every scene change inside its edit range, as one edit plan.
+#[tauri::command]
+async fn auto_split_at_scenes(
+    app_handle: tauri::AppHandle,
+    ffmpeg: State<'_, FFmpegEngine>,
+    engine: State<'_, TimelineEngine>,
+    prefs: State<'_, PreferenceManager>,
+    clip_id: String,
+    threshold: Option<f64>,
+) -> Result<timeline::TimelineState, String> {
+    let clip = find_clip(&engine, &clip_id)?;
+    let ffmpeg_engine = (*ffmpeg).clone();
+    let scan_clip = clip.clone();
+    let changes = tokio::task::spawn_blocking(move || {
+        ffmpeg_engine.detect_scene_changes(
+            &scan_clip,
+            threshold.unwrap_or(ffmpeg::DEFAULT_SCENE_THRESHOLD),
+        )
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))??;
+
+    let split_times = ffmpeg::scene_split_times(&clip, &changes);
+    if split_times.is_empty() {
+        return Ok(engine.state.lock().unwrap().clone());
+    }
+    let plan = edit_plan::generate_plan_for_scene_splits(&clip_id, &split_times);
+    action_router::run_edit_plan(&engine, &app_handle, &prefs, plan)
+}
+
+/// Timeline ranges of a clip that stay below `noise_db` (default -30) for at
+/// least `min_duration_s` (default 0.5).
+#[tauri::command]
+async fn detect_silence_regions(
+    ffmpeg: State<'_, FFmpegEngine>,
+    engine: State<'_, TimelineEngine>,
+    clip_id: String,
+    noise_db: Option<f64>,
+    min_duration_s: Option<f64>,
+) -> Result<Vec<(f64, f64)>, String> {
+    let clip = find_clip(&engine, &clip_id)?;
+    let ffmpeg_engine = (*ffmpeg).clone();
+    tokio::task::spawn_blocking(move || {
+        ffmpeg_engine.detect_silence_regions(
+            &clip,
+            noise_db.unwrap_or(ffmpeg::DEFAULT_SILENCE_NOISE_DB),
+            min_duration_s.unwrap_or(ffmpeg::DEFAULT_SILENCE_MIN_SECS),
+        )
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Cut a clip's silent regions out, as one edit plan. The cuts leave gaps.
+#[tauri::command]
+async fn remove_silence_regions(
+    app_handle: tauri::AppHandle,
+    ffmpeg: State<'_, FFmpegEngine>,
+    engine: State<'_, TimelineEngine>,
+    prefs: State<'_, PreferenceManager>,
+    clip_id: String,
+    noise_db: Option<f64>,
+    min_duration_s: Option<f64>,
+) -> Result<timeline::TimelineState, String> {
+    let clip = find_clip(&engine, &clip_id)?;
+    let ffmpeg_engine = (*ffmpeg).clone();
+    let scan_clip = clip.clone();
+    let ranges = tokio::task::spawn_blocking(move || {
+        ffmpeg_engine.detect_silence_regions(
+            &scan_clip,
+            noise_db.unwrap_or(ffmpeg::DEFAULT_SILENCE_NOISE_DB),
+            min_duration_s.unwrap_or(ffmpeg::DEFAULT_SILENCE_MIN_SECS),
+        )
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))??;
+
+    if ranges.is_empty() {
+        return Ok(engine.state.lock().unwrap().clone());
+    }
+    let plan =
+        edit_plan::generate_plan_for_silence_removal(&clip_id, clip.start, clip.end(), &ranges);
+    action_router::run_edit_plan(&engine, &app_handle, &prefs, plan)
+}
+
+/// Measure every clip's loudness so renders normalize it to `target_lufs`
+/// (default -14 LUFS). Sources aren't rewritten; clips without audio are skipped.
+#[tauri::command]
+async fn normalize_audio_levels(
+    app_handle: tauri::AppHandle,
+    ffmpeg: State<'_, FFmpegEngine>,
+    engine: State<'_, TimelineEngine>,
+    target_lufs: Option<f64>,
+) -> Result<timeline::TimelineState, String> {
+    let target_lufs = target_lufs.unwrap_or(ffmpeg::DEFAULT_LOUDNESS_TARGET_LUFS);
+    if !(-70.0..=-5.0).contains(&target_lufs) {
+        return Err(format!(
+            "Loudness target {} LUFS is outside [-70, -5]",
+            target_lufs
+        ));
+    }
+    let clips = engine.state.lock().unwrap().clips.clone();
+    let ffmpeg_engine = (*ffmpeg).clone();
+    let measured = tokio::task::spawn_blocking(move || {
+        clips
+            .iter()
+            .map(|clip| {
+                let stats = ffmpeg_engine
+                    .measure_clip_loudness(clip, target_lufs)
+                    .map_err(|e| format!("Clip {}: {}", clip.id, e))?;
+                Ok((clip.id.clone(), stats))
+            })
+            .collect::<Result<Vec<_>, String>>()
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))??;
+
+    commands::edit_timeline(&app_handle, &engine, |state| {
+        for (clip_id, stats) in measured {
+            // Clips deleted while measuring are skipped
+            if let Some(clip) = state.clips.iter_mut().find(|c| c.id == clip_id) {
+                clip.audio_loudness =
+                    stats.map(|stats| timeline::AudioLoudness { target_lufs, stats });
+            }
+        }
+        Ok(())
+    })
+}
+
+// The prompt artifact, with the earlier turns it was sent after
+fn with_conversation_history(prompt: &str, history: &[llm::ConversationMessage]) -> String {
+    if history.is_empty() {
+        return prompt.to_string();
+    }
+    let turns: String = history
+        .iter()
+        .map(|m| format!("[{}]\n{}\n\n", m.role, m.content))
+        .collect();
+    format!(
+        "=== CONVERSATION HISTORY ({} messages) ===\n{}=== PROMPT ===\n{}",
+        history.len(),
+        turns,
+        prompt
+    )
+}
+
+/// Messages of the current editing session, oldest first.
+#[tauri::command]
+fn get_conversation_history(
+    history: State<'_, ConversationHistory>,
+) -> Result<Vec<llm::ConversationMessage>, String> {
+    Ok(history.messages())
+}
+
+/// Start a fresh editing session: later requests won't see earlier ones.
+#[tauri::command]
+fn clear_conversation_history(history: State<'_, ConversationHistory>) {
+    history.clear();
+}
+
+fn find_clip(engine: &TimelineEngine, clip_id: &str) -> Result<timeline::Clip, String> {
+    let state = engine.state.lock().unwrap();
+    state
+        .clips
+        .iter()
+        .find(|c| c.id == clip_id)
+        .cloned()
+        .ok_or(format!("Clip '{}' not found", clip_id))
+}
+
+#[tauri::command]
+fn get_thumbnail_generation_progress(
+    progress: State<'_, ThumbnailGenerationState>,
+) -> ThumbnailGenerationProgress {
+    progress.progress()
+}
+
+#[tauri::command]
+fn cancel_thumbnail_generation(progress: State<'_, ThumbnailGenerationState>) {
+    progress.cancel();
+}
+
+// Encode `clip`'s proxy, record it on the clip and emit PROXY_READY
+async fn build_proxy(
+    app_handle: &tauri::AppHandle,
+    clip: timeline::Clip,
+) -> Result<String, String> {
+    let (uploads_dir, _) = commands::get_video_dirs(app_handle);
+    let dir = ffmpeg::proxy_dir(&uploads_dir);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let output_path = dir.join(format!("{}_720p.mp4", clip.id));
+
+    let ffmpeg_engine = (*app_handle.state::<FFmpegEngine>()).clone();
+    let source_file = clip.source_file.clone();
+    let proxy_path = tokio::task::spawn_blocking(move || {
+        ffmpeg_engine.generate_proxy(&source_file, &output_path)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))??;
+
+    {
+        let engine = app_handle.state::<TimelineEngine>();
+        let mut state = engine.state.lock().unwrap();
+        // The clip may have been deleted while the proxy encoded
+        if let Some(c) = state.clips.iter_mut().find(|c| c.id == clip.id) {
+            c.proxy_path = Some(proxy_path.clone());
+            state.version += 1;
+            let _ = app_handle.emit("STATE_UPDATE", &*state);
+        }
+    }
+    let _ = app_handle.emit(
+        "PROXY_READY",
+        serde_json::json!({ "clip_id": clip.id, "proxy_path": proxy_path }),
+    );
+    Ok(proxy_path)
+}
+
+/// Analyze a clip's shake with vidstabdetect and have renders smooth it over
+/// `smoothing` frames. The transforms cover the edit range at the time of
+/// analysis; renders skip them once the clip's in point moves.
+#[tauri::command]
+async fn stabilize_clip(
+    app_handle: tauri::AppHandle,
+    ffmpeg: State<'_, FFmpegEngine>,
+    engine: State<'_, TimelineEngine>,
+    clip_id: String,
+    smoothing: u32,
+) -> Result<(), String> {
+    if !(1..=1000).contains(&smoothing) {
+        return Err(format!(
+            "Smoothing {} must be between 1 and 1000",
+            smoothing
+        ));
+    }
+    let clip = find_clip(&engine, &clip_id)?;
+    let (uploads_dir, _) = commands::get_video_dirs(&app_handle);
+    let dir = ffmpeg::stabilize_dir(&uploads_dir);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let trf_path = dir.join(format!("{}.trf", clip.id));
+    let range = (clip.source_in(), clip.source_out());
+
+    let ffmpeg_engine = (*ffmpeg).clone();
+    let detect_path = trf_path.clone();
+    tokio::task::spawn_blocking(move || {
+        if !ffmpeg_engine.capabilities()?.has_filter("vidstabdetect") {
+            return Err("This FFmpeg build has no vidstab filters (vidstabdetect)".to_string());
+        }
+        ffmpeg_engine.detect_stabilization(&clip, &detect_path)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))??;
+
+    commands::edit_timeline(&app_handle, &engine, |state| {
+        // The clip may have been deleted during the analysis
+        let clip = state
+            .clips
+            .iter_mut()
+            .find(|c| c.id == clip_id)
+            .ok_or(format!("Clip '{}' not found", clip_id))?;
+        clip.stabilization_trf_path = Some(trf_path.to_string_lossy().to_string());
+        clip.stabilization_range = Some(range);
+        clip.stabilization_smoothing = smoothing;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+/// Encode a 720p proxy of a clip's source for preview playback. Returns its path.
+#[tauri::command]
+async fn generate_proxy(
+    app_handle: tauri::AppHandle,
+    engine: State<'_, TimelineEngine>,
+    clip_id: String,
+) -> Result<String, String> {
+    let clip = {
+        let state = engine.state.lock().unwrap();
+        state
+            .clips
+            .iter()
+            .find(|c| c.id == clip_id)
+            .cloned()
+            .ok_or(format!("Clip '{}' not found", clip_id))?
+    };
+    build_proxy(&app_handle, clip).await
+}
+
+/// Start encoding proxies, one at a time, for every clip without one. Returns
+/// how many were started; each finishes with a PROXY_READY event.
+#[tauri::command]
+fn generate_all_proxies(
+    app_handle: tauri::AppHandle,
+    engine: State<'_, TimelineEngine>,
+) -> Result<usize, String> {
+    let missing: Vec<timeline::Clip> = {
+        let state = engine.state.lock().unwrap();
+        state
+            .clips
+            .iter()
+            .filter(|c| {
+                !c.proxy_path
+                    .as_ref()
+                    .is_some_and(|p| std::path::Path::new(p).exists())
+            })
+            .cloned()
+            .collect()
+    };
+    let count = missing.len();
+
+    tauri::async_runtime::spawn(async move {
+        for clip in missing {
+            let clip_id = clip.id.clone();
+            if let Err(e) = build_proxy(&app_handle, clip).await {
+                eprintln!("⚠️ [Proxy] Failed for clip {}: {}", clip_id, e);
+            }
+        }
+    });
+    Ok(count)
+}
+
+/// Export only `[start_time, end_time]` of the timeline, trimming clips that
+/// cross the region boundaries.
+#[tauri::command]
+async fn export_timeline_region(
+    app_handle: tauri::AppHandle,
+    ffmpeg: State<'_, FFmpegEngine>,
+    engine: State<'_, TimelineEngine>,
+    prefs: State<'_, PreferenceManager>,
+    start_time: f64,
+    end_time: f64,
+    options: Option<ExportOptions>,
+) -> Result<String, String> {
+    let options = options.unwrap_or_default();
+    let mut render = prefs.get_preferences().render_options();
+    render.validate()?;
+    let state = {
+        let guard = engine.state.lock().unwrap();
+        guard.clone()
+    };
+
+    let output_path = new_export_path("mp4")?;
     let output_path_clone = output_path.clone();
     let ffmpeg_engine = (*ffmpeg).clone();
 
-    let _ffmpeg_result = tokio::task::spawn_blocking(move || {
-        ffmpeg_engine.render_timeline(&state, &output_path_clone)
+    let render_app = app_handle.clone();
+    let outcome = tokio::task::spawn_blocking(move || {
+        resolve_video_encoder(&render_app, &ffmpeg_engine, &mut render)?;
+        ffmpeg_engine.export_region(
+            &state,
+            start_time,
+            end_time,
+            &output_path_clone,
+            &options,
+            &render,
+        )
     })
     .await
     .map_err(|e| format!("Task join error: {}", e))??;
 
-    // 4. Return Path
+    if let Some(verification) = outcome.verification.filter(|v| !v.success) {
+        let _ = app_handle.emit(
+            "EXPORT_VERIFICATION_FAILED",
+            serde_json::json!({
+                "output_path": output_path.to_string_lossy(),
+                "warnings": verification.warnings,
+                "verification": verification,
+            }),
+        );
+    }
+
     Ok(output_path.to_string_lossy().to_string())
 }
 
+/// Export the timeline's audio as "mp3", "aac" or "wav", to `output_path` or a
+/// new file in the exports directory when it is empty.
+#[tauri::command]
+async fn export_audio_only(
+    ffmpeg: State<'_, FFmpegEngine>,
+    engine: State<'_, TimelineEngine>,
+    format: String,
+    bitrate_kbps: Option<u32>,
+    output_path: String,
+) -> Result<String, String> {
+    let state = engine.state.lock().unwrap().clone();
+    ffmpeg::audio_codec_args(&format, bitrate_kbps)?;
+    let output_path = if output_path.trim().is_empty() {
+        new_export_path(&format)?
+    } else {
+        std::path::PathBuf::from(output_path)
+    };
+    let ffmpeg_engine = (*ffmpeg).clone();
+
+    tokio::task::spawn_blocking(move || {
+        ffmpeg_engine.export_audio_only(&state, &format, bitrate_kbps, &output_path)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Export `[start_time, end_time]` as a GIF `scale` pixels wide at `fps`, to
+/// `output_path` or a new file in the exports directory when it is empty.
+#[tauri::command]
+async fn export_as_gif(
+    ffmpeg: State<'_, FFmpegEngine>,
+    engine: State<'_, TimelineEngine>,
+    start_time: f64,
+    end_time: f64,
+    scale: u32,
+    fps: u32,
+    output_path: String,
+) -> Result<String, String> {
+    let state = engine.state.lock().unwrap().clone();
+    let output_path = if output_path.trim().is_empty() {
+        new_export_path("gif")?
+    } else {
+        std::path::PathBuf::from(output_path)
+    };
+    let ffmpeg_engine = (*ffmpeg).clone();
+
+    tokio::task::spawn_blocking(move || {
+        ffmpeg_engine.export_gif(&state, start_time, end_time, scale, fps, &output_path)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Queue an export of the timeline as it is when the job starts. Returns the job ID.
+#[tauri::command]
+fn enqueue_export(queue: State<'_, ExportQueue>, options: ExportOptions) -> String {
+    queue.enqueue(options)
+}
+
+#[tauri::command]
+fn get_export_queue(queue: State<'_, ExportQueue>) -> Vec<ExportJob> {
+    queue.jobs()
+}
+
+#[tauri::command]
+fn get_export_history(queue: State<'_, ExportQueue>, limit: usize) -> Vec<CompletedExport> {
+    queue.history(limit)
+}
+
+#[tauri::command]
+fn cancel_export_job(queue: State<'_, ExportQueue>, id: String) -> Result<(), String> {
+    queue.cancel(&id)
+}
+
+/// What the installed FFmpeg supports (cached for an hour).
+#[tauri::command]
+async fn get_ffmpeg_capabilities(
+    ffmpeg: State<'_, FFmpegEngine>,
+) -> Result<ffmpeg::FfmpegCapabilities, String> {
+    let ffmpeg_engine = (*ffmpeg).clone();
+    tokio::task::spawn_blocking(move || ffmpeg_engine.capabilities())
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Delete every cached render segment.
+#[tauri::command]
+fn clear_render_cache(ffmpeg: State<'_, FFmpegEngine>) -> Result<(), String> {
+    ffmpeg.clear_render_cache()
+}
+
+/// Export the timeline to a video file using FFmpeg.
+/// This is NOT preview - it generates an actual rendered output file.
+#[tauri::command]
+async fn export_timeline(
+    app_handle: tauri::AppHandle,
+    options: Option<ExportOptions>,
+    job_id: Option<String>,
+) -> Result<ExportResult, String> {
+    render_current_timeline(&app_handle, options.unwrap_or_default(), job_id).await
+}
+
+/// Stop the export started with `job_id`, deleting its partial output. Does
+/// nothing when that export isn't running.
+#[tauri::command]
+fn cancel_export(
+    app_handle: tauri::AppHandle,
+    jobs: State<'_, ExportJobs>,
+    job_id: String,
+) -> Result<(), String> {
+    if jobs.cancel(&job_id) {
+        let _ = app_handle.emit("EXPORT_CANCELLED", serde_json::json!({ "job_id": job_id }));
+    }
+    Ok(())
+}
+
+// Swap a preferred encoder this FFmpeg build lacks for libx264 and tell the
+// frontend. May run `ffmpeg -encoders`, so call it off the async runtime.
+fn resolve_video_encoder(
+    app_handle: &tauri::AppHandle,
+    ffmpeg: &FFmpegEngine,
+    render: &mut ffmpeg::RenderOptions,
+) -> Result<(), String> {
+    let capabilities = ffmpeg.capabilities()?;
+    let used = capabilities.video_encoder_or_fallback(&render.video_encoder);
+    if used != render.video_encoder {
+        println!(
+            "⚠️ Encoder {} unavailable, falling back to {}",
+            render.video_encoder, used
+        );
+        let _ = app_handle.emit(
+            "ENCODER_FALLBACK",
+            serde_json::json!({ "requested": render.video_encoder, "used": used }),
+        );
+        render.video_encoder = used.to_string();
+    }
+    Ok(())
+}
+
+// Shared by `export_timeline` and the export queue worker. With a `job_id` the
+// render can be stopped by `cancel_export`.
+async fn render_current_timeline(
+    app_handle: &tauri::AppHandle,
+    mut options: ExportOptions,
+    job_id: Option<String>,
+) -> Result<ExportResult, String> {
+    let user_prefs = app_handle.state::<PreferenceManager>().get_preferences();
+    let mut render = user_prefs.render_options();
+    render.validate()?;
+
+    // 1. Get Timeline State
+    let mut state = {
+        let engine = app_handle.state::<TimelineEngine>();
+        let guard = engine.state.lock().unwrap();
+        guard.clone()
+    };
+    if user_prefs.export.use_proxies {
+        ffmpeg::substitute_proxy_sources(&mut state.clips);
+    }
+    options.subtitle_mode = user_prefs.export.subtitle_mode;
+    let metadata = app_handle
+        .state::<ProjectMetadataStore>()
+        .metadata
+        .lock()
+        .unwrap()
+        .clone();
+
+    // Warn (but don't block) when some clips are too short for the concat filter
+    let short_clip_ids = validator::validate_minimum_segment_duration(&state);
+    if !short_clip_ids.is_empty() {
+        let _ = app_handle.emit(
+            "RENDER_WARNING",
+            serde_json::json!({
+                "code": "SHORT_SEGMENTS",
+                "message": format!(
+                    "{} clip(s) are shorter than {}s and may fail to concat",
+                    short_clip_ids.len(),
+                    validator::MIN_SEGMENT_FOR_CONCAT_SECS
+                ),
+                "clip_ids": short_clip_ids,
+            }),
+        );
+    }
+
+    // 2. Determine Output Path
+    let output_path = new_export_path("mp4")?;
+
+    // 3. Render using FFmpeg
+    let output_path_clone = output_path.clone();
+    let ffmpeg_engine = (*app_handle.state::<FFmpegEngine>()).clone();
+    let clip_count = state.clips.len();
+    let mut progress = RenderProgress::new(app_handle);
+    if let Some(job_id) = job_id {
+        progress = progress.with_job(&app_handle.state::<ExportJobs>(), job_id);
+    }
+
+    let handle = app_handle.clone();
+    let outcome = tokio::task::spawn_blocking(move || {
+        resolve_video_encoder(&handle, &ffmpeg_engine, &mut render)?;
+        ffmpeg_engine.render_timeline(
+            &progress,
+            &state,
+            &output_path_clone,
+            &options,
+            &metadata,
+            &render,
+        )
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?;
+    let outcome = match outcome {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            // Drop whatever the killed FFmpeg had written
+            if e == ffmpeg::EXPORT_CANCELLED_ERROR {
+                let _ = std::fs::remove_file(&output_path);
+            }
+            return Err(e);
+        }
+    };
+
+    let summary = serde_json::json!({
+        "output_path": output_path.to_string_lossy(),
+        "clip_count": clip_count,
+        "passthrough": outcome.passthrough,
+        "verification": outcome.verification,
+    });
+    log_artifact(
+        app_handle,
+        ArtifactType::Export,
+        &serde_json::to_string_pretty(&summary).unwrap_or_default(),
+    );
+
+    // FFmpeg can exit 0 and still produce the wrong file
+    if let Some(verification) = outcome.verification.as_ref().filter(|v| !v.success) {
+        let _ = app_handle.emit(
+            "EXPORT_VERIFICATION_FAILED",
+            serde_json::json!({
+                "output_path": output_path.to_string_lossy(),
+                "warnings": verification.warnings,
+                "verification": verification,
+            }),
+        );
+    }
+
+    // 4. Return Path + verification
+    Ok(ExportResult {
+        output_path: output_path.to_string_lossy().to_string(),
+        passthrough: outcome.passthrough,
+        verification: outcome.verification,
+    })
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -511,6 +2235,12 @@ pub fn run() {
 
             // Initialize the God State
             let timeline_engine = TimelineEngine::new();
+            timeline_engine.set_history_depth(
+                app.state::<PreferenceManager>()
+                    .get_preferences()
+                    .editing
+                    .undo_history_depth,
+            );
 
             // STEP 2 FIX: Emit initial STATE_UPDATE so frontend starts with correct state
             // This replaces the need for frontend to call fetchState()
@@ -527,11 +2257,114 @@ pub fn run() {
                 });
             }
 
+            // Watch clip sources; every STATE_UPDATE re-syncs the watch list so
+            // clips added or removed by any command are picked up.
+            match SourceFileWatcher::new(app.handle().clone()) {
+                Ok(watcher) => {
+                    watcher.sync(&timeline_engine.state.lock().unwrap());
+                    app.manage(watcher);
+                    let watch_handle = app.handle().clone();
+                    app.listen_any("STATE_UPDATE", move |event| {
+                        if let Ok(state) =
+                            serde_json::from_str::<timeline::TimelineState>(event.payload())
+                        {
+                            watch_handle.state::<SourceFileWatcher>().sync(&state);
+                        }
+                    });
+                }
+                Err(e) => eprintln!("⚠️ [Backend] Source file watcher disabled: {}", e),
+            }
+
             app.manage(timeline_engine);
             app.manage(ActiveRequests::new()); // Register ActiveRequests
+            app.manage(LlmQueue::new());
+            app.manage(ConversationHistory::default());
+            app.manage(MacroRecorder::default());
+            let quality_path = app
+                .path()
+                .app_config_dir()
+                .map(|dir| dir.join("llm_quality.json"))
+                .unwrap_or_default();
+            app.manage(LlmQualityTracker::new(quality_path));
+
+            // Queue worker: runs AI edits that arrived while the LLM was busy
+            let worker_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let queue = worker_handle.state::<LlmQueue>();
+                loop {
+                    while let Some(next) = queue.start_next() {
+                        println!("▶️ [Backend] Running queued request {}", next.request_id);
+                        let result =
+                            run_ai_edit(&worker_handle, &next.user_input, &next.request_id, false)
+                                .await;
+                        queue.finish();
+                        let payload = match result {
+                            Ok(message) => serde_json::json!({
+                                "request_id": next.request_id,
+                                "success": true,
+                                "message": message,
+                            }),
+                            Err(error) => serde_json::json!({
+                                "request_id": next.request_id,
+                                "success": false,
+                                "message": error,
+                            }),
+                        };
+                        let _ = worker_handle.emit("AI_EDIT_RESULT", payload);
+                    }
+                    queue.wait_for_work().await;
+                }
+            });
 
             // Initialize FFmpegEngine
             app.manage(FFmpegEngine::new());
+            app.manage(ExportJobs::new());
+            app.manage(ProjectMetadataStore::new());
+            app.manage(ThumbnailGenerationState::new());
+            app.manage(AppState::from_app(app.handle()));
+
+            let history_path = app
+                .path()
+                .app_config_dir()
+                .map(|dir| dir.join("export_history.json"))
+                .unwrap_or_default();
+            app.manage(ExportQueue::new(history_path));
+
+            // Export worker: renders queued exports one at a time
+            let export_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let queue = export_handle.state::<ExportQueue>();
+                loop {
+                    while let Some(job) = queue.start_next() {
+                        let _ = export_handle
+                            .emit("EXPORT_JOB_STARTED", serde_json::json!({ "id": job.id }));
+                        let result = render_current_timeline(
+                            &export_handle,
+                            job.options,
+                            Some(job.id.clone()),
+                        )
+                        .await;
+                        match &result {
+                            Ok(export) => {
+                                let _ = export_handle.emit(
+                                    "EXPORT_JOB_COMPLETE",
+                                    serde_json::json!({ "id": job.id, "path": export.output_path }),
+                                );
+                            }
+                            // `cancel_export` already sent EXPORT_CANCELLED
+                            Err(error) if error == ffmpeg::EXPORT_CANCELLED_ERROR => {}
+                            Err(error) => {
+                                let _ = export_handle.emit(
+                                    "EXPORT_JOB_FAILED",
+                                    serde_json::json!({ "id": job.id, "error": error }),
+                                );
+                            }
+                        }
+                        queue.finish(&job.id, result.map(|export| export.output_path));
+                    }
+                    queue.wait_for_work().await;
+                }
+            });
 
             Ok(())
         })
@@ -547,9 +2380,133 @@ pub fn run() {
             cancel_request,
             execute_ai_edit, // STEP 4 FIX: Atomic AI edit (replaces apply_edit_plan)
             get_user_preferences,
+            set_ffmpeg_performance_mode,
+            clear_render_cache,
             export_timeline, // Renamed from render_preview
             seek_timeline,   // New: playhead control
-            get_active_clip  // New: get clip at playhead
+            get_active_clip, // New: get clip at playhead
+            find_duplicate_clips,
+            consolidate_duplicates,
+            analyze_timeline,
+            apply_lint_fix,
+            set_preview_playback_rate,
+            list_ollama_models,
+            pull_ollama_model,
+            delete_ollama_model,
+            check_ollama_health,
+            get_llm_queue_status,
+            seek_to_frame,
+            get_playhead_frame,
+            set_output_fps,
+            export_timeline_region,
+            set_project_metadata,
+            get_project_metadata,
+            merge_short_clips,
+            explain_timeline,
+            export_clip_mosaic_thumbnail,
+            replay_edit_plan_from_artifact,
+            import_from_url,
+            get_llm_analytics,
+            normalize_clip,
+            apply_virtual_trim,
+            reset_virtual_trim,
+            normalize_clip_order,
+            normalize_clip_starts,
+            save_project,
+            load_project,
+            get_project_file_info,
+            probe_video_files_batch,
+            reorder_clips,
+            reorder_clips_preserve_gaps,
+            register_transition,
+            set_loop_region,
+            clear_loop_region,
+            get_timeline_state_if_changed,
+            get_timeline_summary,
+            trim_clip,
+            move_clip,
+            split_clip,
+            enqueue_export,
+            get_export_queue,
+            get_export_history,
+            cancel_export_job,
+            set_clip_audio_ducking,
+            get_llm_quality_report,
+            reset_llm_quality_stats,
+            extract_thumbnail,
+            generate_all_thumbnails,
+            get_thumbnail_generation_progress,
+            cancel_thumbnail_generation,
+            get_ffmpeg_capabilities,
+            undo,
+            redo,
+            generate_clip_thumbnail,
+            generate_waveform_data,
+            get_chapter_markers,
+            add_chapter_marker,
+            remove_chapter_marker,
+            update_chapter_marker,
+            export_chapter_markers,
+            execute_ai_edit_streaming,
+            set_ollama_model,
+            cancel_export,
+            generate_proxy,
+            generate_all_proxies,
+            open_project,
+            new_project,
+            import_videos,
+            get_media_info,
+            clear_interaction_history,
+            export_interaction_history,
+            preview_edit_plan,
+            set_confidence_threshold,
+            duplicate_clip,
+            delete_clips_in_range,
+            export_as_gif,
+            export_audio_only,
+            import_subtitle_file,
+            remove_subtitle_track,
+            list_subtitle_tracks,
+            detect_scene_changes,
+            auto_split_at_scenes,
+            detect_silence_regions,
+            remove_silence_regions,
+            get_timeline_statistics,
+            copy_clips,
+            cut_clips,
+            paste_clips,
+            update_clip_label,
+            tag_clip,
+            untag_clip,
+            get_clips_by_tag,
+            set_export_format,
+            set_preferred_encoder,
+            check_codec_support,
+            insert_gap_at_time,
+            set_track_muted,
+            set_track_soloed,
+            set_track_locked,
+            snap_to_clip_edge,
+            seek_to_timecode,
+            normalize_clip_positions,
+            normalize_audio_levels,
+            clear_loudness_normalization,
+            stabilize_clip,
+            get_conversation_history,
+            clear_conversation_history,
+            get_prompt_template,
+            set_prompt_template,
+            reset_prompt_template,
+            list_prompt_examples,
+            add_prompt_example,
+            remove_prompt_example,
+            export_edit_plan,
+            import_edit_plan,
+            apply_edit_plan,
+            list_saved_plans,
+            start_macro_recording,
+            stop_macro_recording,
+            apply_macro
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
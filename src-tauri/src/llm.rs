@@ -1,28 +1,199 @@
 // src-tauri/src/llm.rs
-use crate::edit_plan::EditPlan; // Import EditPlan
+use crate::edit_plan::{EditPlan, CURRENT_SCHEMA_VERSION}; // Import EditPlan
+use crate::preferences::GeneralPreferences;
 use reqwest::blocking::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::fs;
 use std::io::Write;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use tauri::AppHandle;
 use tauri::Manager;
 use thiserror::Error; // Import Error derive
 
 // --- CONSTANTS ---
-const MAX_RESPONSE_CHARS: usize = 16000; // Truncate responses longer than this
+// Truncate responses longer than this
+const MAX_RESPONSE_CHARS: usize = 16000;
+// Using 127.0.0.1 directly to avoid IPv6 resolution issues
+const OLLAMA_BASE_URL: &str = "http://127.0.0.1:11434";
+pub const DEFAULT_MODEL: &str = "llama3.2";
+pub const DEFAULT_OPENAI_API_BASE: &str = "https://api.openai.com/v1";
 
 // --- STRUCTS & ENUMS ---
 
-// The raw JSON structure Ollama sends back
+/// Which API edit and explanation prompts are sent to.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LlmProvider {
+    #[default]
+    Ollama,
+    /// Any server with an OpenAI-style `/chat/completions` endpoint
+    /// (OpenAI, Together.ai, LM Studio, ...).
+    OpenAiCompatible,
+}
+
+// The raw JSON structure Ollama sends back. With `"stream": true` each line is one
+// chunk; a non-streamed reply has the same shape as the final (`done: true`) chunk.
 #[derive(Deserialize, Debug)]
-struct OllamaResponse {
-    // model: String, // Unused for now
-    // created_at: String, // Unused for now
-    response: String,
-    // done: bool, // Unused for now
+pub struct OllamaStreamChunk {
+    pub model: String,
+    pub created_at: String,
+    pub response: String,
+    pub done: bool,
+    pub context: Option<Vec<u32>>,
+    /// Nanoseconds, only present when `done` is true.
+    pub total_duration: Option<u64>,
+    pub prompt_eval_count: Option<u32>,
+    pub eval_count: Option<u32>,
+}
+
+impl OllamaStreamChunk {
+    /// Ollama's own timing for the whole request, in milliseconds.
+    pub fn latency_ms(&self) -> Option<u64> {
+        if !self.done {
+            return None;
+        }
+        self.total_duration.map(|ns| ns / 1_000_000)
+    }
+}
+
+// `/api/chat` replies carry a message instead of `response`
+#[derive(Deserialize, Debug)]
+struct OllamaChatResponse {
+    message: ConversationMessage,
+    total_duration: Option<u64>,
+    prompt_eval_count: Option<u32>,
+    eval_count: Option<u32>,
+}
+
+/// One turn of an editing session, as chat APIs expect it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ConversationMessage {
+    /// "user" or "assistant".
+    pub role: String,
+    pub content: String,
+}
+
+/// Most turns kept in memory, however many are sent.
+const MAX_CONVERSATION_MESSAGES: usize = 200;
+
+/// Earlier edit requests and replies, so follow-ups like "trim 1 more second"
+/// can be resolved. Only kept for the running session.
+#[derive(Debug, Clone, Default)]
+pub struct ConversationHistory(pub Arc<Mutex<Vec<ConversationMessage>>>);
+
+impl ConversationHistory {
+    pub fn push(&self, role: &str, content: &str) {
+        let mut messages = self.0.lock().unwrap();
+        messages.push(ConversationMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+        });
+        let excess = messages.len().saturating_sub(MAX_CONVERSATION_MESSAGES);
+        messages.drain(..excess);
+    }
+
+    /// The last `count` messages, oldest first.
+    pub fn recent(&self, count: usize) -> Vec<ConversationMessage> {
+        let messages = self.0.lock().unwrap();
+        messages[messages.len().saturating_sub(count)..].to_vec()
+    }
+
+    pub fn messages(&self) -> Vec<ConversationMessage> {
+        self.0.lock().unwrap().clone()
+    }
+
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+}
+
+/// Chat `messages` for `prompt` following `history`.
+pub fn chat_messages(history: &[ConversationMessage], prompt: &str) -> serde_json::Value {
+    let mut messages: Vec<serde_json::Value> = history
+        .iter()
+        .map(|m| json!({ "role": m.role, "content": m.content }))
+        .collect();
+    messages.push(json!({ "role": "user", "content": prompt }));
+    serde_json::Value::Array(messages)
+}
+
+// What send_prompt_to_ollama hands back to the commands
+#[derive(Debug, Clone)]
+pub struct OllamaCompletion {
+    pub text: String,
+    pub latency_ms: u64,
+    pub char_count: usize,
+    pub truncated: bool,
+    pub input_tokens: Option<u32>,
+    pub output_tokens: Option<u32>,
+}
+
+/// How often, and how patiently, a prompt is re-sent after a network error.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay_ms: u64,
+}
+
+/// Longest wait between two attempts.
+const MAX_RETRY_DELAY_MS: u64 = 8000;
+
+impl RetryPolicy {
+    pub fn from_preferences(general: &GeneralPreferences) -> Self {
+        Self {
+            max_retries: general.max_retries,
+            base_delay_ms: general.retry_base_delay_ms,
+        }
+    }
+
+    /// `base_delay * 2^attempt`, capped at 8s. `attempt` counts from 0.
+    pub fn delay_ms(&self, attempt: u32) -> u64 {
+        self.base_delay_ms
+            .saturating_mul(2u64.saturating_pow(attempt))
+            .min(MAX_RETRY_DELAY_MS)
+    }
+}
+
+/// Payload of the `LLM_RETRY` event sent before each retry.
+#[derive(Serialize, Debug, Clone)]
+pub struct LlmRetryEvent {
+    /// 1 for the first retry.
+    pub attempt: u32,
+    pub delay_ms: u64,
+    pub reason: String,
+}
+
+// Send a request, retrying with backoff when it never got a response
+// (connection refused, timeout). HTTP error statuses are returned as is.
+fn send_with_retry(
+    policy: RetryPolicy,
+    on_retry: &dyn Fn(&LlmRetryEvent),
+    send: impl Fn() -> reqwest::Result<reqwest::blocking::Response>,
+) -> reqwest::Result<reqwest::blocking::Response> {
+    let mut attempt = 0;
+    loop {
+        match send() {
+            Ok(response) => return Ok(response),
+            Err(e) if attempt < policy.max_retries => {
+                let event = LlmRetryEvent {
+                    attempt: attempt + 1,
+                    delay_ms: policy.delay_ms(attempt),
+                    reason: e.to_string(),
+                };
+                println!(
+                    "🔁 [Backend] LLM request failed ({}), retry {} in {}ms",
+                    event.reason, event.attempt, event.delay_ms
+                );
+                on_retry(&event);
+                std::thread::sleep(std::time::Duration::from_millis(event.delay_ms));
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
 }
 
 // Types of artifacts we can log
@@ -30,7 +201,18 @@ pub enum ArtifactType {
     Prompt,
     LlmResponse,
     Error,
-    ApplyEditPlan { plan: String, result: String },
+    ApplyEditPlan {
+        plan: String,
+        result: String,
+    },
+    Export,
+    Explanation,
+    Import,
+    InputSanitization,
+    /// A direct user edit, recorded as the equivalent `EditPlan`.
+    ManualEdit {
+        operation: String,
+    },
 }
 
 // The rich metadata we will send back to the frontend
@@ -41,12 +223,14 @@ pub struct LlmResponseMetadata {
     pub char_count: usize,
     pub truncated: bool,
     pub artifact_filename: String,
+    pub input_tokens: Option<u32>,
+    pub output_tokens: Option<u32>,
 }
 
 // --- FUNCTIONS ---
 
 // Helper to get the path to the "artifacts" folder next to the app executable
-fn get_artifacts_dir(app_handle: &AppHandle) -> PathBuf {
+pub fn get_artifacts_dir(app_handle: &AppHandle) -> PathBuf {
     let app_dir = app_handle
         .path()
         .app_config_dir()
@@ -61,6 +245,13 @@ fn get_artifacts_dir(app_handle: &AppHandle) -> PathBuf {
     artifacts_dir
 }
 
+/// Saved edit plans, in a "plans" folder beside "artifacts".
+pub fn get_plans_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    let plans_dir = get_artifacts_dir(app_handle).with_file_name("plans");
+    fs::create_dir_all(&plans_dir).map_err(|e| format!("Failed to create plans dir: {}", e))?;
+    Ok(plans_dir)
+}
+
 // Helper to save text to a timestamped file
 pub fn log_artifact(app_handle: &AppHandle, artifact_type: ArtifactType, content: &str) -> String {
     let dir = get_artifacts_dir(app_handle);
@@ -74,6 +265,17 @@ pub fn log_artifact(app_handle: &AppHandle, artifact_type: ArtifactType, content
         ArtifactType::Prompt => ("prompt", content.to_string()),
         ArtifactType::LlmResponse => ("llm_response", content.to_string()),
         ArtifactType::Error => ("error", content.to_string()),
+        ArtifactType::Export => ("export", content.to_string()),
+        ArtifactType::Explanation => ("explanation", content.to_string()),
+        ArtifactType::Import => ("import", content.to_string()),
+        ArtifactType::InputSanitization => ("input_sanitization", content.to_string()),
+        ArtifactType::ManualEdit { operation } => (
+            "manual_edit",
+            format!(
+                "{{\n  \"operation\": \"{}\",\n  \"plan\": {}\n}}",
+                operation, content
+            ),
+        ),
         ArtifactType::ApplyEditPlan { plan, result } => (
             "apply_plan",
             format!(
@@ -113,13 +315,18 @@ pub fn log_artifact(app_handle: &AppHandle, artifact_type: ArtifactType, content
 
 // The main function to send data to Ollama
 // NOTE: This is now a BLOCKING function because we wrap it in a blocking Tokio task in lib.rs
-pub fn send_prompt_to_ollama(prompt: &str) -> Result<(String, u64, usize, bool), String> {
+/// Network errors are retried per `retry`, calling `on_retry` before each wait.
+pub fn send_prompt_to_ollama(
+    prompt: &str,
+    model: &str,
+    retry: RetryPolicy,
+    on_retry: &dyn Fn(&LlmRetryEvent),
+) -> Result<OllamaCompletion, String> {
     let client = Client::new();
-    // Using 127.0.0.1 directly to avoid IPv6 resolution issues
-    let ollama_url = "http://127.0.0.1:11434/api/generate";
+    let ollama_url = format!("{}/api/generate", OLLAMA_BASE_URL);
 
     let request_body = json!({
-        "model": "llama3.2",
+        "model": model,
         "prompt": prompt,
         "stream": false
     });
@@ -131,13 +338,12 @@ pub fn send_prompt_to_ollama(prompt: &str) -> Result<(String, u64, usize, bool),
     let start_time = Instant::now();
 
     // Use blocking send
-    let response = client
-        .post(ollama_url)
-        .json(&request_body)
-        .send()
-        .map_err(|e| format!("Failed to send request to Ollama: {}", e))?;
+    let response = send_with_retry(retry, on_retry, || {
+        client.post(&ollama_url).json(&request_body).send()
+    })
+    .map_err(|e| format!("Failed to send request to Ollama: {}", e))?;
 
-    let latency_ms = start_time.elapsed().as_millis() as u64;
+    let wall_clock_ms = start_time.elapsed().as_millis() as u64;
 
     if !response.status().is_success() {
         return Err(format!(
@@ -150,14 +356,183 @@ pub fn send_prompt_to_ollama(prompt: &str) -> Result<(String, u64, usize, bool),
         .text()
         .map_err(|e| format!("Failed to read response text: {}", e))?;
 
-    let ollama_response: OllamaResponse = serde_json::from_str(&response_text).map_err(|e| {
+    let ollama_response: OllamaStreamChunk = serde_json::from_str(&response_text).map_err(|e| {
         format!(
             "Failed to parse JSON response from Ollama: {}. Raw text: {}",
             e, response_text
         )
     })?;
 
-    let mut final_text = ollama_response.response;
+    // Prefer Ollama's server-side timing over our wall clock
+    let latency_ms = ollama_response.latency_ms().unwrap_or(wall_clock_ms);
+    Ok(finish_completion(
+        ollama_response.response,
+        latency_ms,
+        ollama_response.prompt_eval_count,
+        ollama_response.eval_count,
+    ))
+}
+
+/// `send_prompt_to_ollama` over `/api/chat`, with `history` sent as the
+/// conversation before `prompt`.
+pub fn send_chat_to_ollama(
+    prompt: &str,
+    history: &[ConversationMessage],
+    model: &str,
+    retry: RetryPolicy,
+    on_retry: &dyn Fn(&LlmRetryEvent),
+) -> Result<OllamaCompletion, String> {
+    let client = Client::new();
+    let ollama_url = format!("{}/api/chat", OLLAMA_BASE_URL);
+    let request_body = json!({
+        "model": model,
+        "messages": chat_messages(history, prompt),
+        "stream": false
+    });
+
+    println!(
+        "⏳ [Backend] Sending chat request to Ollama at {} ({} earlier messages)...",
+        ollama_url,
+        history.len()
+    );
+    let start_time = Instant::now();
+
+    let response = send_with_retry(retry, on_retry, || {
+        client.post(&ollama_url).json(&request_body).send()
+    })
+    .map_err(|e| format!("Failed to send request to Ollama: {}", e))?;
+    let wall_clock_ms = start_time.elapsed().as_millis() as u64;
+
+    if !response.status().is_success() {
+        return Err(format!(
+            "Ollama returned an error status: {}",
+            response.status()
+        ));
+    }
+    let response_text = response
+        .text()
+        .map_err(|e| format!("Failed to read response text: {}", e))?;
+    let chat: OllamaChatResponse = serde_json::from_str(&response_text).map_err(|e| {
+        format!(
+            "Failed to parse JSON response from Ollama: {}. Raw text: {}",
+            e, response_text
+        )
+    })?;
+
+    let latency_ms = chat
+        .total_duration
+        .map_or(wall_clock_ms, |ns| ns / 1_000_000);
+    Ok(finish_completion(
+        chat.message.content,
+        latency_ms,
+        chat.prompt_eval_count,
+        chat.eval_count,
+    ))
+}
+
+/// Like `send_prompt_to_ollama`, but with `"stream": true`, calling
+/// `on_token` with each piece of the response as it arrives. Dropping the
+/// future closes the HTTP connection, which stops generation.
+pub async fn stream_prompt_to_ollama<F>(
+    prompt: &str,
+    model: &str,
+    mut on_token: F,
+) -> Result<OllamaCompletion, String>
+where
+    F: FnMut(&str),
+{
+    use futures::StreamExt;
+
+    let ollama_url = format!("{}/api/generate", OLLAMA_BASE_URL);
+    println!(
+        "⏳ [Backend] Streaming request to Ollama at {}...",
+        ollama_url
+    );
+    let start_time = Instant::now();
+
+    let response = reqwest::Client::new()
+        .post(&ollama_url)
+        .json(&json!({
+            "model": model,
+            "prompt": prompt,
+            "stream": true
+        }))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send request to Ollama: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!(
+            "Ollama returned an error status: {}",
+            response.status()
+        ));
+    }
+
+    let mut stream = response.bytes_stream();
+    let mut decoder = OllamaStreamDecoder::default();
+    let mut text = String::new();
+    let mut last_chunk = None;
+    while let Some(bytes) = stream.next().await {
+        let bytes = bytes.map_err(|e| format!("Failed to read Ollama stream: {}", e))?;
+        for chunk in decoder.push(&bytes)? {
+            if !chunk.response.is_empty() {
+                on_token(&chunk.response);
+                text.push_str(&chunk.response);
+            }
+            if chunk.done {
+                last_chunk = Some(chunk);
+            }
+        }
+    }
+
+    let last_chunk = last_chunk.ok_or("Ollama stream ended before the response was done")?;
+    let latency_ms = last_chunk
+        .latency_ms()
+        .unwrap_or(start_time.elapsed().as_millis() as u64);
+    Ok(finish_completion(
+        text,
+        latency_ms,
+        last_chunk.prompt_eval_count,
+        last_chunk.eval_count,
+    ))
+}
+
+/// Splits a streamed response body into its newline-delimited chunks. Network
+/// reads can end mid-line, so the unfinished tail is kept for the next push.
+#[derive(Default)]
+pub struct OllamaStreamDecoder {
+    pending: Vec<u8>,
+}
+
+impl OllamaStreamDecoder {
+    pub fn push(&mut self, bytes: &[u8]) -> Result<Vec<OllamaStreamChunk>, String> {
+        self.pending.extend_from_slice(bytes);
+        let mut chunks = Vec::new();
+        while let Some(newline) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=newline).collect();
+            let line = String::from_utf8_lossy(&line);
+            if line.trim().is_empty() {
+                continue;
+            }
+            chunks.push(serde_json::from_str(&line).map_err(|e| {
+                format!(
+                    "Failed to parse streamed chunk from Ollama: {}. Raw line: {}",
+                    e, line
+                )
+            })?);
+        }
+        Ok(chunks)
+    }
+}
+
+// Truncate an over-long response and package it for the commands
+fn finish_completion(
+    text: String,
+    latency_ms: u64,
+    input_tokens: Option<u32>,
+    output_tokens: Option<u32>,
+) -> OllamaCompletion {
+    let mut final_text = text;
     let char_count = final_text.chars().count();
     let mut truncated = false;
 
@@ -176,9 +551,249 @@ pub fn send_prompt_to_ollama(prompt: &str) -> Result<(String, u64, usize, bool),
         );
     }
 
-    // Return tuple: (text, latency, char_count, truncated status)
     println!("✅ [Backend] Ollama Response Text: {:.200}...", final_text);
-    Ok((final_text, latency_ms, char_count, truncated))
+    OllamaCompletion {
+        text: final_text,
+        latency_ms,
+        char_count,
+        truncated,
+        input_tokens,
+        output_tokens,
+    }
+}
+
+/// Send `prompt` to whichever provider `general.llm_provider` selects,
+/// retrying network errors as `general` configures. A non-empty `history` is
+/// sent as the earlier turns of a chat.
+pub fn send_prompt(
+    prompt: &str,
+    history: &[ConversationMessage],
+    model: &str,
+    general: &GeneralPreferences,
+    on_retry: &dyn Fn(&LlmRetryEvent),
+) -> Result<OllamaCompletion, String> {
+    let retry = RetryPolicy::from_preferences(general);
+    match general.llm_provider {
+        LlmProvider::Ollama if history.is_empty() => {
+            send_prompt_to_ollama(prompt, model, retry, on_retry)
+        }
+        LlmProvider::Ollama => send_chat_to_ollama(prompt, history, model, retry, on_retry),
+        LlmProvider::OpenAiCompatible => send_prompt_to_openai_compatible(
+            prompt,
+            history,
+            model,
+            &general.llm_api_base_url,
+            general.llm_api_key.as_deref(),
+            retry,
+            on_retry,
+        ),
+    }
+}
+
+/// Blocking POST to `{api_base}/chat/completions`, with the prompt as the user
+/// message after `history`.
+pub fn send_prompt_to_openai_compatible(
+    prompt: &str,
+    history: &[ConversationMessage],
+    model: &str,
+    api_base: &str,
+    api_key: Option<&str>,
+    retry: RetryPolicy,
+    on_retry: &dyn Fn(&LlmRetryEvent),
+) -> Result<OllamaCompletion, String> {
+    let url = format!("{}/chat/completions", api_base.trim_end_matches('/'));
+    let request_body = json!({
+        "model": model,
+        "messages": chat_messages(history, prompt),
+        "stream": false
+    });
+
+    println!("⏳ [Backend] Sending request to {}...", url);
+    let start_time = Instant::now();
+
+    let client = Client::new();
+    let response = send_with_retry(retry, on_retry, || {
+        let mut request = client.post(&url).json(&request_body);
+        if let Some(key) = api_key {
+            request = request.header("Authorization", format!("Bearer {}", key));
+        }
+        request.send()
+    })
+    .map_err(|e| format!("Failed to send request to {}: {}", url, e))?;
+    let latency_ms = start_time.elapsed().as_millis() as u64;
+
+    let status = response.status();
+    let response_text = response
+        .text()
+        .map_err(|e| format!("Failed to read response text: {}", e))?;
+    if !status.is_success() {
+        return Err(format!(
+            "LLM API returned an error status: {} {}",
+            status, response_text
+        ));
+    }
+
+    let (text, input_tokens, output_tokens) = parse_chat_completion(&response_text)?;
+    Ok(finish_completion(
+        text,
+        latency_ms,
+        input_tokens,
+        output_tokens,
+    ))
+}
+
+/// The first choice's message and the token usage of a `/chat/completions` reply.
+pub fn parse_chat_completion(body: &str) -> Result<(String, Option<u32>, Option<u32>), String> {
+    let value: serde_json::Value = serde_json::from_str(body).map_err(|e| {
+        format!(
+            "Failed to parse JSON response from LLM API: {}. Raw text: {}",
+            e, body
+        )
+    })?;
+    let text = value["choices"][0]["message"]["content"]
+        .as_str()
+        .ok_or_else(|| format!("LLM API response has no message content: {}", body))?;
+    let usage = |field: &str| value["usage"][field].as_u64().map(|n| n as u32);
+    Ok((
+        text.to_string(),
+        usage("prompt_tokens"),
+        usage("completion_tokens"),
+    ))
+}
+
+// --- OLLAMA MODEL MANAGEMENT ---
+
+// Raw shape of GET /api/tags
+#[derive(Deserialize, Debug)]
+struct OllamaTagsResponse {
+    models: Vec<OllamaTagEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OllamaTagEntry {
+    name: String,
+    modified_at: String,
+    size: u64,
+    details: Option<OllamaModelDetails>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OllamaModelDetails {
+    family: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OllamaModel {
+    pub name: String,
+    pub modified_at: String,
+    pub size_bytes: u64,
+    pub family: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OllamaStatus {
+    pub running: bool,
+    pub version: Option<String>,
+}
+
+pub fn list_ollama_models() -> Result<Vec<OllamaModel>, String> {
+    let url = format!("{}/api/tags", OLLAMA_BASE_URL);
+    let response = Client::new()
+        .get(&url)
+        .send()
+        .map_err(|e| format!("Failed to reach Ollama: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!(
+            "Ollama returned an error status: {}",
+            response.status()
+        ));
+    }
+
+    let tags: OllamaTagsResponse = response
+        .json()
+        .map_err(|e| format!("Failed to parse model list: {}", e))?;
+
+    Ok(tags
+        .models
+        .into_iter()
+        .map(|m| OllamaModel {
+            name: m.name,
+            modified_at: m.modified_at,
+            size_bytes: m.size,
+            family: m.details.and_then(|d| d.family),
+        })
+        .collect())
+}
+
+/// Pull a model. Blocks until Ollama reports the download complete.
+pub fn pull_ollama_model(model_name: &str) -> Result<(), String> {
+    // Model downloads take minutes, so disable the default 30s client timeout
+    let client = Client::builder()
+        .timeout(None)
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+
+    let url = format!("{}/api/pull", OLLAMA_BASE_URL);
+    let response = client
+        .post(&url)
+        .json(&json!({ "model": model_name, "stream": false }))
+        .send()
+        .map_err(|e| format!("Failed to reach Ollama: {}", e))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().unwrap_or_default();
+        return Err(format!(
+            "Failed to pull '{}': {} {}",
+            model_name, status, body
+        ));
+    }
+
+    println!("✅ [Backend] Pulled Ollama model: {}", model_name);
+    Ok(())
+}
+
+pub fn delete_ollama_model(model_name: &str) -> Result<(), String> {
+    let url = format!("{}/api/delete", OLLAMA_BASE_URL);
+    let response = Client::new()
+        .delete(&url)
+        .json(&json!({ "model": model_name }))
+        .send()
+        .map_err(|e| format!("Failed to reach Ollama: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!(
+            "Failed to delete '{}': {}",
+            model_name,
+            response.status()
+        ));
+    }
+
+    println!("🗑️ [Backend] Deleted Ollama model: {}", model_name);
+    Ok(())
+}
+
+pub fn check_ollama_health() -> OllamaStatus {
+    let client = Client::new();
+    let running = client
+        .get(OLLAMA_BASE_URL)
+        .send()
+        .map(|r| r.status().is_success())
+        .unwrap_or(false);
+
+    let version = if running {
+        client
+            .get(format!("{}/api/version", OLLAMA_BASE_URL))
+            .send()
+            .ok()
+            .and_then(|r| r.json::<serde_json::Value>().ok())
+            .and_then(|v| v["version"].as_str().map(str::to_string))
+    } else {
+        None
+    };
+
+    OllamaStatus { running, version }
 }
 
 // --- WEEK 7: JSON PARSING ---
@@ -191,6 +806,43 @@ pub enum LlmParseError {
     NoJsonFound,
     #[error("Failed to parse JSON: {0}")]
     JsonParseError(#[from] serde_json::Error),
+    #[error("Edit plan schema version {0} is newer than this app supports")]
+    UnsupportedSchemaVersion(u32),
+}
+
+/// `EDIT_PLAN_MIGRATIONS[n]` rewrites a version n + 1 plan into version n + 2,
+/// e.g. `|plan| plan["actions"] = ...`. Append one whenever `CURRENT_SCHEMA_VERSION` goes up.
+const EDIT_PLAN_MIGRATIONS: &[fn(&mut serde_json::Value)] = &[];
+
+// Run the migrations from the plan's version up to the current one
+fn migrate_plan_value(mut plan: serde_json::Value) -> Result<serde_json::Value, LlmParseError> {
+    let version = plan
+        .get("schema_version")
+        .and_then(|v| v.as_u64())
+        .map_or(1, |v| u32::try_from(v).unwrap_or(u32::MAX))
+        .max(1);
+    if version > CURRENT_SCHEMA_VERSION {
+        return Err(LlmParseError::UnsupportedSchemaVersion(version));
+    }
+
+    for migrate in &EDIT_PLAN_MIGRATIONS[(version - 1) as usize..] {
+        migrate(&mut plan);
+    }
+    if let Some(fields) = plan.as_object_mut() {
+        fields.insert("schema_version".to_string(), json!(CURRENT_SCHEMA_VERSION));
+    }
+    Ok(plan)
+}
+
+/// Upgrade a serialized plan (e.g. a saved macro) to the current schema version.
+pub fn migrate_edit_plan(plan_json: &str) -> Result<String, String> {
+    let plan: serde_json::Value = serde_json::from_str(plan_json)
+        .map_err(|e| format!("Edit plan is not valid JSON: {}", e))?;
+    if !plan.is_object() {
+        return Err("Edit plan must be a JSON object".to_string());
+    }
+    let migrated = migrate_plan_value(plan).map_err(|e| e.to_string())?;
+    Ok(migrated.to_string())
 }
 
 pub fn parse_edit_plan(raw: &str) -> Result<EditPlan, LlmParseError> {
@@ -209,9 +861,28 @@ pub fn parse_edit_plan(raw: &str) -> Result<EditPlan, LlmParseError> {
 
     let json_str = &trimmed[start..=end];
 
-    // 2. Parse
-    let plan: EditPlan = serde_json::from_str(json_str)?;
-    Ok(plan)
+    // 2. Parse, bringing older plans up to the current schema first
+    let plan: serde_json::Value = serde_json::from_str(json_str)?;
+    Ok(serde_json::from_value(migrate_plan_value(plan)?)?)
+}
+
+/// Pull the `plan` object out of an `apply_plan` artifact. The artifact's
+/// `raw_input` field is written unescaped, so the file as a whole is not always
+/// valid JSON; only the value after `"plan":` is parsed.
+pub fn extract_plan_from_artifact(content: &str) -> Result<EditPlan, String> {
+    let key = "\"plan\":";
+    let start = content
+        .find(key)
+        .ok_or("Artifact does not contain an edit plan")?
+        + key.len();
+
+    let plan_value = serde_json::Deserializer::from_str(&content[start..])
+        .into_iter::<serde_json::Value>()
+        .next()
+        .ok_or("Artifact plan is empty")?
+        .map_err(|e| format!("Artifact plan is not valid JSON: {}", e))?;
+
+    parse_edit_plan(&plan_value.to_string()).map_err(|e| e.to_string())
 }
 
 pub fn is_valid_uuid(id: &str) -> bool {
@@ -0,0 +1,177 @@
+// src-tauri/src/llm_queue.rs
+// Serializes LLM calls so concurrent AI edits don't saturate Ollama's GPU memory.
+// One request runs at a time; the rest wait here ordered by priority, then arrival.
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::Notify;
+
+pub const DEFAULT_PRIORITY: u8 = 5;
+
+#[derive(Debug, Clone)]
+pub struct QueuedRequest {
+    pub request_id: String,
+    /// Higher runs sooner.
+    pub priority: u8,
+    pub submitted_at: Instant,
+    pub user_input: String,
+}
+
+impl QueuedRequest {
+    pub fn new(request_id: String, priority: u8, user_input: String) -> Self {
+        Self {
+            request_id,
+            priority,
+            submitted_at: Instant::now(),
+            user_input,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct QueueStatus {
+    pub active_request_id: Option<String>,
+    pub queued_count: usize,
+    /// (request_id, 1-based position) in execution order.
+    pub positions: Vec<(String, usize)>,
+}
+
+#[derive(Default)]
+struct QueueInner {
+    pending: VecDeque<QueuedRequest>,
+    active_request_id: Option<String>,
+}
+
+#[derive(Default)]
+pub struct LlmQueue {
+    inner: Arc<Mutex<QueueInner>>,
+    wake: Arc<Notify>,
+}
+
+impl LlmQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start the request immediately if the LLM is idle and nothing is waiting
+    /// (returns None), otherwise enqueue it and return its 1-based queue position.
+    /// Requests still waiting for the worker to pick them up keep their place.
+    pub fn try_start_or_enqueue(&self, request: QueuedRequest) -> Option<usize> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.active_request_id.is_none() && inner.pending.is_empty() {
+            inner.active_request_id = Some(request.request_id);
+            return None;
+        }
+
+        // Insert after every request of equal or higher priority (FIFO within a priority)
+        let index = inner
+            .pending
+            .iter()
+            .position(|r| r.priority < request.priority)
+            .unwrap_or(inner.pending.len());
+        inner.pending.insert(index, request);
+        Some(index + 1)
+    }
+
+    /// Pop the next queued request and mark it active, if the LLM is idle.
+    pub fn start_next(&self) -> Option<QueuedRequest> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.active_request_id.is_some() {
+            return None;
+        }
+        let next = inner.pending.pop_front()?;
+        inner.active_request_id = Some(next.request_id.clone());
+        Some(next)
+    }
+
+    /// Mark the active request finished and wake the queue worker.
+    pub fn finish(&self) {
+        self.inner.lock().unwrap().active_request_id = None;
+        self.wake.notify_one();
+    }
+
+    /// Remove a request that has not started yet. Returns true if it was queued.
+    pub fn remove(&self, request_id: &str) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        let before = inner.pending.len();
+        inner.pending.retain(|r| r.request_id != request_id);
+        inner.pending.len() != before
+    }
+
+    pub fn status(&self) -> QueueStatus {
+        let inner = self.inner.lock().unwrap();
+        QueueStatus {
+            active_request_id: inner.active_request_id.clone(),
+            queued_count: inner.pending.len(),
+            positions: inner
+                .pending
+                .iter()
+                .enumerate()
+                .map(|(i, r)| (r.request_id.clone(), i + 1))
+                .collect(),
+        }
+    }
+
+    /// Wait until `finish` signals that the queue may be drained.
+    pub async fn wait_for_work(&self) {
+        self.wake.notified().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(id: &str, priority: u8) -> QueuedRequest {
+        QueuedRequest::new(id.to_string(), priority, "trim it".to_string())
+    }
+
+    #[test]
+    fn test_queue_orders_by_priority_then_arrival() {
+        let queue = LlmQueue::new();
+        assert_eq!(queue.try_start_or_enqueue(request("a", 5)), None);
+        assert_eq!(queue.try_start_or_enqueue(request("b", 5)), Some(1));
+        assert_eq!(queue.try_start_or_enqueue(request("c", 9)), Some(1));
+        assert_eq!(queue.try_start_or_enqueue(request("d", 5)), Some(3));
+
+        let status = queue.status();
+        assert_eq!(status.active_request_id.as_deref(), Some("a"));
+        assert_eq!(
+            status.positions,
+            vec![
+                ("c".to_string(), 1),
+                ("b".to_string(), 2),
+                ("d".to_string(), 3)
+            ]
+        );
+
+        // Busy: nothing starts until the active request finishes
+        assert!(queue.start_next().is_none());
+        queue.finish();
+        assert_eq!(queue.start_next().unwrap().request_id, "c");
+    }
+
+    #[test]
+    fn test_new_request_does_not_jump_waiting_ones() {
+        let queue = LlmQueue::new();
+        queue.try_start_or_enqueue(request("a", 5));
+        queue.try_start_or_enqueue(request("b", 5));
+
+        // "a" finished but the worker hasn't started "b" yet
+        queue.finish();
+        assert_eq!(queue.try_start_or_enqueue(request("c", 5)), Some(2));
+        assert_eq!(queue.start_next().unwrap().request_id, "b");
+    }
+
+    #[test]
+    fn test_remove_queued_request() {
+        let queue = LlmQueue::new();
+        queue.try_start_or_enqueue(request("a", 5));
+        queue.try_start_or_enqueue(request("b", 5));
+
+        assert!(queue.remove("b"));
+        assert!(!queue.remove("a")); // Already running, not removable
+        assert_eq!(queue.status().queued_count, 0);
+    }
+}
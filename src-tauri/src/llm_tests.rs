@@ -1,6 +1,8 @@
 #[cfg(test)]
 mod tests {
-    use crate::llm::parse_edit_plan;
+    use crate::llm::{
+        extract_plan_from_artifact, parse_edit_plan, OllamaStreamChunk, OllamaStreamDecoder,
+    };
 
     #[test]
     fn test_parse_clean_json() {
@@ -57,4 +59,74 @@ mod tests {
             "I will delete the clip with id {abc-123}"
         );
     }
+
+    #[test]
+    fn test_parse_final_stream_chunk() {
+        let input = r#"{
+            "model": "llama3.2",
+            "created_at": "2026-01-01T00:00:00Z",
+            "response": "{}",
+            "done": true,
+            "context": [1, 2, 3],
+            "total_duration": 2500000000,
+            "prompt_eval_count": 812,
+            "eval_count": 64
+        }"#;
+        let chunk: OllamaStreamChunk = serde_json::from_str(input).unwrap();
+        assert_eq!(chunk.latency_ms(), Some(2500));
+        assert_eq!(chunk.prompt_eval_count, Some(812));
+        assert_eq!(chunk.eval_count, Some(64));
+
+        let partial = r#"{"model":"llama3.2","created_at":"t","response":"{","done":false}"#;
+        let chunk: OllamaStreamChunk = serde_json::from_str(partial).unwrap();
+        assert_eq!(chunk.latency_ms(), None);
+    }
+
+    #[test]
+    fn test_stream_decoder_handles_split_lines() {
+        let mut decoder = OllamaStreamDecoder::default();
+        let body = concat!(
+            r#"{"model":"m","created_at":"t","response":"{\"act","done":false}"#,
+            "\n",
+            r#"{"model":"m","created_at":"t","response":"ions\": []}","done":false}"#,
+            "\n\n",
+            r#"{"model":"m","created_at":"t","response":"","done":true,"total_duration":1000000}"#,
+            "\n",
+        );
+        let (head, tail) = body.split_at(70);
+
+        let mut chunks = decoder.push(head.as_bytes()).unwrap();
+        assert_eq!(chunks.len(), 1);
+        chunks.extend(decoder.push(tail.as_bytes()).unwrap());
+        let text: String = chunks.iter().map(|c| c.response.as_str()).collect();
+        assert_eq!(text, r#"{"actions": []}"#);
+        assert!(chunks[2].done);
+
+        assert!(decoder.push(b"not json\n").is_err());
+    }
+
+    #[test]
+    fn test_extract_plan_from_artifact() {
+        // raw_input is unescaped in real artifacts, so the file isn't valid JSON
+        let content = r#"{
+  "plan": {
+  "actions": [
+    {
+      "type": "DELETE",
+      "target_clip_id": "abc-123",
+      "parameters": null
+    }
+  ],
+  "thought_process": null,
+  "confidence": 0.9
+},
+  "result": "Success",
+  "raw_input": "{"actions": "unescaped"}"
+}"#;
+        let plan = extract_plan_from_artifact(content).expect("plan should parse");
+        assert_eq!(plan.actions.len(), 1);
+        assert_eq!(plan.actions[0].target_clip_id, "abc-123");
+
+        assert!(extract_plan_from_artifact("just a prompt").is_err());
+    }
 }
@@ -1,31 +1,71 @@
+use crate::edit_plan::EditPlan;
+use crate::ffmpeg::{
+    FfmpegProcessSettings, RenderOptions, DEFAULT_EXPORT_FPS, DEFAULT_EXPORT_HEIGHT,
+    DEFAULT_EXPORT_WIDTH, FALLBACK_VIDEO_ENCODER,
+};
+use crate::interaction_log::CONFIDENCE_THRESHOLD;
+use crate::llm::{LlmProvider, DEFAULT_MODEL, DEFAULT_OPENAI_API_BASE};
+use crate::prompt::{DEFAULT_CONTEXT_HEADER, DEFAULT_USER_PREFIX, SYSTEM_PROMPT};
+use crate::subtitles::SubtitleMode;
+use crate::timeline::DEFAULT_HISTORY_DEPTH;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fs;
-use std::path::PathBuf;
-use std::sync::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tauri::{AppHandle, Manager};
 
 // --- DATA STRUCTURES ---
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
 pub struct UserPreferences {
     pub general: GeneralPreferences,
+    /// Stored as "llm" by older versions.
+    #[serde(alias = "llm")]
+    pub ai: AiPreferences,
+    pub prompt_template: PromptTemplate,
+    /// Recorded AI edit sequences, replayed with `apply_macro`.
+    pub macros: Vec<Macro>,
+    pub system: SystemPreferences,
+    pub editing: EditingPreferences,
+    pub export: ExportPreferences,
     pub interactions: Vec<InteractionEvent>,
 }
 
-impl Default for UserPreferences {
-    fn default() -> Self {
-        Self {
-            general: GeneralPreferences::default(),
-            interactions: vec![],
-        }
-    }
-}
-
+// Missing fields fall back to defaults so older preference files keep loading
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
 pub struct GeneralPreferences {
     pub default_transition_duration: f64,
     pub auto_ripple_edits: bool,
+    /// Snap seek requests to the nearest frame at the timeline's output fps.
+    pub round_to_frame_boundary: bool,
+    /// Bake loudness normalization into every imported clip.
+    pub auto_normalize_on_import: bool,
+    /// Integrated loudness target for import normalization.
+    pub target_import_lufs: f32,
+    /// Ollama model used for edits, and for explanations unless
+    /// `ai.explanation_model` is set.
+    pub model_name: String,
+    /// Oldest interactions are dropped once there are more than this many.
+    pub max_interaction_history: usize,
+    /// Reject edits that leave a gap between clips on any track.
+    pub require_no_gaps: bool,
+    pub llm_provider: LlmProvider,
+    /// Base URL including the version prefix, e.g. "https://api.openai.com/v1".
+    /// Only used by `LlmProvider::OpenAiCompatible`.
+    pub llm_api_base_url: String,
+    /// Sent as a bearer token when set.
+    pub llm_api_key: Option<String>,
+    /// Retries after a network error before an LLM request fails.
+    pub max_retries: u32,
+    /// First retry delay; doubles with each further retry, up to 8s.
+    pub retry_base_delay_ms: u64,
+    /// AI edit plans with a lower confidence are rejected, in [0.0, 1.0].
+    pub ai_confidence_threshold: f32,
 }
 
 impl Default for GeneralPreferences {
@@ -33,10 +73,238 @@ impl Default for GeneralPreferences {
         Self {
             default_transition_duration: 0.5,
             auto_ripple_edits: true,
+            round_to_frame_boundary: false,
+            auto_normalize_on_import: false,
+            target_import_lufs: -14.0,
+            model_name: DEFAULT_MODEL.to_string(),
+            max_interaction_history: 500,
+            require_no_gaps: false,
+            llm_provider: LlmProvider::Ollama,
+            llm_api_base_url: DEFAULT_OPENAI_API_BASE.to_string(),
+            llm_api_key: None,
+            max_retries: 3,
+            retry_base_delay_ms: 500,
+            ai_confidence_threshold: CONFIDENCE_THRESHOLD,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AiPreferences {
+    /// Model for read-only explanation calls; falls back to the edit model when unset.
+    pub explanation_model: Option<String>,
+    /// Run `sanitize_user_input` on edit requests before they reach the prompt.
+    pub input_sanitization_enabled: bool,
+    /// Earlier messages of the session sent with each edit request; 0 sends none.
+    pub conversation_history_messages: usize,
+    /// Extra few-shot examples appended after the system prompt's own.
+    pub custom_examples: Vec<PromptExample>,
+    /// Which clips are kept when the timeline is too long to send in full.
+    pub context_priority: ContextPriority,
+    /// Estimated tokens (4 chars each) the clip list may use in the prompt.
+    pub context_token_budget: usize,
+    /// Most clips sent in the prompt, clamped to [5, 500].
+    pub context_max_clips: usize,
+    /// Send clip tags and labels; turning them off saves tokens on small models.
+    pub context_include_tags: bool,
+    pub context_include_labels: bool,
+}
+
+impl Default for AiPreferences {
+    fn default() -> Self {
+        Self {
+            explanation_model: None,
+            input_sanitization_enabled: true,
+            conversation_history_messages: 10,
+            custom_examples: Vec::new(),
+            context_priority: ContextPriority::default(),
+            context_token_budget: 4000,
+            context_max_clips: 50,
+            context_include_tags: true,
+            context_include_labels: true,
+        }
+    }
+}
+
+/// Clip selection for prompts on timelines with more clips than fit.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextPriority {
+    /// Clips near the playhead first, then the rest from the start.
+    #[default]
+    NearPlayhead,
+    /// The first clips of the timeline.
+    Chronological,
+}
+
+/// A named sequence of edit plans, applied in order.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Macro {
+    pub name: String,
+    pub plans: Vec<EditPlan>,
+}
+
+/// A user-supplied few-shot example, in the shape of the built-in ones.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PromptExample {
+    pub user_input: String,
+    /// Free-form description of the timeline the example applies to.
+    pub context_description: String,
+    /// The EditPlan the model should answer with.
+    pub expected_output_json: String,
+}
+
+/// The fixed text `build_prompt` wraps around the preferences, timeline and request.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct PromptTemplate {
+    /// `{{PREFERENCE_CONTEXT}}` is replaced with the user's preference summary.
+    pub system_prompt: String,
+    /// Line above the timeline JSON; omitted when empty.
+    pub context_header: String,
+    /// Line above the quoted user request.
+    pub user_prefix: String,
+}
+
+impl Default for PromptTemplate {
+    fn default() -> Self {
+        Self {
+            system_prompt: SYSTEM_PROMPT.to_string(),
+            context_header: DEFAULT_CONTEXT_HEADER.to_string(),
+            user_prefix: DEFAULT_USER_PREFIX.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SystemPreferences {
+    /// Largest file `import_from_url` will download.
+    pub max_download_size_bytes: u64,
+    /// `-threads` passed to FFmpeg when rendering; FFmpeg picks when unset.
+    pub ffmpeg_threads: Option<u32>,
+    /// Niceness FFmpeg renders run at (Unix only).
+    pub ffmpeg_nice: Option<i8>,
+}
+
+impl Default for SystemPreferences {
+    fn default() -> Self {
+        Self {
+            max_download_size_bytes: 2 * 1024 * 1024 * 1024,
+            ffmpeg_threads: None,
+            ffmpeg_nice: None,
+        }
+    }
+}
+
+impl SystemPreferences {
+    pub fn ffmpeg_process_settings(&self) -> FfmpegProcessSettings {
+        FfmpegProcessSettings {
+            threads: self.ffmpeg_threads,
+            nice: self.ffmpeg_nice,
+        }
+    }
+}
+
+impl UserPreferences {
+    /// Export format and FFmpeg limits for a render. Not validated.
+    pub fn render_options(&self) -> RenderOptions {
+        RenderOptions {
+            width: self.export.resolution_width,
+            height: self.export.resolution_height,
+            fps: self.export.fps,
+            video_encoder: self.export.preferred_encoder.clone(),
+            process: self.system.ffmpeg_process_settings(),
         }
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct EditingPreferences {
+    /// Track new clips are placed on by `add_clip` and `import_video`.
+    pub default_video_track_id: String,
+    pub default_audio_track_id: String,
+    /// Shortest a trim may leave a clip.
+    pub min_clip_duration_secs: f64,
+    /// Trim deltas smaller than this are ignored.
+    pub min_trim_delta_secs: f64,
+    /// Upper bound on clips in the timeline; unlimited when unset.
+    pub max_timeline_clips: Option<usize>,
+    /// Number of edit plans that can be undone.
+    pub undo_history_depth: usize,
+    /// Seeks and moves this close to a clip's start or end land on it; 0 disables.
+    pub snap_threshold_s: f64,
+}
+
+impl Default for EditingPreferences {
+    fn default() -> Self {
+        Self {
+            default_video_track_id: "video_track_1".to_string(),
+            default_audio_track_id: "audio_track_1".to_string(),
+            min_clip_duration_secs: 0.1,
+            min_trim_delta_secs: 0.0,
+            max_timeline_clips: None,
+            undo_history_depth: DEFAULT_HISTORY_DEPTH,
+            snap_threshold_s: 0.1,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ExportPreferences {
+    /// Render from clips' proxy files instead of their sources, for quick drafts.
+    pub use_proxies: bool,
+    /// Burn subtitle tracks into the picture or add them as soft streams.
+    pub subtitle_mode: SubtitleMode,
+    /// Output frame size; both must be even for libx264.
+    pub resolution_width: u32,
+    pub resolution_height: u32,
+    /// Output frame rate, e.g. 24, 25, 29.97 or 60.
+    pub fps: f64,
+    /// `-c:v` encoder, e.g. "h264_videotoolbox" or "h264_nvenc". Renders fall
+    /// back to libx264 when FFmpeg doesn't list it.
+    pub preferred_encoder: String,
+}
+
+impl Default for ExportPreferences {
+    fn default() -> Self {
+        Self {
+            use_proxies: false,
+            subtitle_mode: SubtitleMode::default(),
+            resolution_width: DEFAULT_EXPORT_WIDTH,
+            resolution_height: DEFAULT_EXPORT_HEIGHT,
+            fps: DEFAULT_EXPORT_FPS,
+            preferred_encoder: FALLBACK_VIDEO_ENCODER.to_string(),
+        }
+    }
+}
+
+/// Render resource presets for `set_ffmpeg_performance_mode`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum PerfMode {
+    /// Half the cores at low priority, to keep laptops cool while working.
+    Background,
+    /// FFmpeg defaults.
+    Normal,
+    /// Every core at normal priority.
+    Foreground,
+}
+
+impl PerfMode {
+    pub fn apply(self, system: &mut SystemPreferences, cpu_count: u32) {
+        let (threads, nice) = match self {
+            PerfMode::Background => (Some((cpu_count / 2).max(1)), Some(10)),
+            PerfMode::Normal => (None, None),
+            PerfMode::Foreground => (Some(cpu_count.max(1)), None),
+        };
+        system.ffmpeg_threads = threads;
+        system.ffmpeg_nice = nice;
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct InteractionEvent {
     pub timestamp: i64,
@@ -46,9 +314,37 @@ pub struct InteractionEvent {
 
 // --- MANAGER ---
 
+/// How often logged interactions are written out.
+const SAVE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+// Clones share the same preferences.
+#[derive(Clone)]
 pub struct PreferenceManager {
-    preferences: Mutex<UserPreferences>,
+    preferences: Arc<Mutex<UserPreferences>>,
     file_path: PathBuf,
+    /// Set by `log_interaction`; the autosave thread writes and clears it.
+    dirty: Arc<AtomicBool>,
+}
+
+/// `timestamp,event_type,details` rows, with `details` as JSON.
+pub fn interactions_to_csv(events: &[InteractionEvent]) -> String {
+    let quote = |field: &str| format!("\"{}\"", field.replace('"', "\"\""));
+    let mut csv = String::from("timestamp,event_type,details\n");
+    for event in events {
+        csv.push_str(&format!(
+            "{},{},{}\n",
+            event.timestamp,
+            quote(&event.event_type),
+            quote(&event.details.to_string())
+        ));
+    }
+    csv
+}
+
+fn write_preferences(file_path: &Path, prefs: &UserPreferences) {
+    let json = serde_json::to_string_pretty(prefs).unwrap_or_default();
+    // Ignore write errors for now, or log them
+    let _ = fs::write(file_path, json);
 }
 
 impl PreferenceManager {
@@ -63,8 +359,11 @@ impl PreferenceManager {
             let _ = fs::create_dir_all(&app_dir);
         }
 
-        let file_path = app_dir.join("preferences.json");
+        Self::with_file(app_dir.join("preferences.json"))
+    }
 
+    // Load from `file_path` and start the autosave thread
+    fn with_file(file_path: PathBuf) -> Self {
         let preferences = if file_path.exists() {
             match fs::read_to_string(&file_path) {
                 Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
@@ -74,24 +373,44 @@ impl PreferenceManager {
             UserPreferences::default()
         };
 
-        Self {
-            preferences: Mutex::new(preferences),
+        let manager = Self {
+            preferences: Arc::new(Mutex::new(preferences)),
             file_path,
-        }
+            dirty: Arc::new(AtomicBool::new(false)),
+        };
+        manager.spawn_autosave();
+        manager
     }
 
     pub fn new_in_memory() -> Self {
         Self {
-            preferences: Mutex::new(UserPreferences::default()),
+            preferences: Arc::new(Mutex::new(UserPreferences::default())),
             file_path: std::path::PathBuf::from(""),
+            dirty: Arc::new(AtomicBool::new(false)),
         }
     }
 
+    // Every `SAVE_DEBOUNCE`, write the preferences if they changed. Stops once
+    // the last clone of the manager is gone.
+    fn spawn_autosave(&self) {
+        let preferences = Arc::downgrade(&self.preferences);
+        let dirty = self.dirty.clone();
+        let file_path = self.file_path.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(SAVE_DEBOUNCE);
+            let Some(preferences) = preferences.upgrade() else {
+                break;
+            };
+            if dirty.swap(false, Ordering::AcqRel) {
+                write_preferences(&file_path, &preferences.lock().unwrap());
+            }
+        });
+    }
+
     pub fn save(&self) {
         let prefs = self.preferences.lock().unwrap();
-        let json = serde_json::to_string_pretty(&*prefs).unwrap_or_default();
-        // Ignore write errors for now, or log them
-        let _ = fs::write(&self.file_path, json);
+        self.dirty.store(false, Ordering::Release);
+        write_preferences(&self.file_path, &prefs);
     }
 
     pub fn log_interaction(&self, event_type: &str, details: Value) {
@@ -107,14 +426,128 @@ impl PreferenceManager {
             event_type: event_type.to_string(),
             details,
         });
+        let excess = prefs
+            .interactions
+            .len()
+            .saturating_sub(prefs.general.max_interaction_history);
+        prefs.interactions.drain(0..excess);
+
+        // Written by the autosave thread, so bursts of events cost one write
+        self.dirty.store(true, Ordering::Release);
+    }
+
+    /// Mutate the preferences and persist them.
+    pub fn update<F: FnOnce(&mut UserPreferences)>(&self, f: F) -> UserPreferences {
+        let mut prefs = self.preferences.lock().unwrap();
+        f(&mut prefs);
+        let updated = prefs.clone();
 
-        // Drop lock before saving to avoid holding it during I/O
         drop(prefs);
         self.save();
+        updated
     }
 
     pub fn get_preferences(&self) -> UserPreferences {
         let prefs = self.preferences.lock().unwrap();
         prefs.clone()
     }
+
+    pub fn clear_interactions(&self) {
+        self.update(|p| p.interactions.clear());
+    }
+
+    /// Write the interaction history to `output_path` as "json" or "csv".
+    pub fn export_interactions(&self, format: &str, output_path: &Path) -> Result<(), String> {
+        let interactions = self.preferences.lock().unwrap().interactions.clone();
+        let content = match format {
+            "json" => serde_json::to_string_pretty(&interactions).map_err(|e| e.to_string())?,
+            "csv" => interactions_to_csv(&interactions),
+            other => {
+                return Err(format!(
+                    "Unknown export format '{}' (expected json or csv)",
+                    other
+                ))
+            }
+        };
+        fs::write(output_path, content)
+            .map_err(|e| format!("Failed to export interaction history: {}", e))
+    }
+}
+
+impl Drop for PreferenceManager {
+    // Flush interactions the autosave thread hasn't written yet
+    fn drop(&mut self) {
+        if self.dirty.load(Ordering::Acquire) {
+            self.save();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interaction_history_is_capped_and_exports_csv() {
+        let prefs = PreferenceManager::new_in_memory();
+        prefs.update(|p| p.general.max_interaction_history = 2);
+        for action in ["trim", "move", "split \"b\""] {
+            prefs.log_interaction("manual_edit", serde_json::json!({ "action": action }));
+        }
+        let interactions = prefs.get_preferences().interactions;
+        assert_eq!(interactions.len(), 2);
+        assert_eq!(interactions[0].details["action"], "move");
+
+        let csv = interactions_to_csv(&interactions);
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows[0], "timestamp,event_type,details");
+        assert!(rows[2].ends_with(r#","manual_edit","{""action"":""split \""b\""""}""#));
+
+        prefs.clear_interactions();
+        assert!(prefs.get_preferences().interactions.is_empty());
+    }
+
+    #[test]
+    fn test_interactions_are_saved_on_drop() {
+        let path = std::env::temp_dir().join(format!("ghost_prefs_{}.json", uuid::Uuid::new_v4()));
+        let prefs = PreferenceManager::with_file(path.clone());
+        prefs.log_interaction("manual_edit", serde_json::json!({ "action": "trim" }));
+        prefs.log_interaction("manual_edit", serde_json::json!({ "action": "move" }));
+        assert!(!path.exists(), "logging should not write synchronously");
+
+        drop(prefs);
+        let saved: UserPreferences =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(saved.interactions.len(), 2);
+    }
+
+    #[test]
+    fn test_ai_preferences_load_from_the_old_llm_key() {
+        let saved: UserPreferences =
+            serde_json::from_str(r#"{"llm": {"context_max_clips": 20}}"#).unwrap();
+        assert_eq!(saved.ai.context_max_clips, 20);
+        let json = serde_json::to_value(&saved).unwrap();
+        assert_eq!(json["ai"]["context_max_clips"], 20);
+    }
+
+    #[test]
+    fn test_perf_mode_presets() {
+        let mut system = SystemPreferences::default();
+
+        PerfMode::Background.apply(&mut system, 8);
+        assert_eq!(
+            (system.ffmpeg_threads, system.ffmpeg_nice),
+            (Some(4), Some(10))
+        );
+
+        PerfMode::Foreground.apply(&mut system, 8);
+        assert_eq!((system.ffmpeg_threads, system.ffmpeg_nice), (Some(8), None));
+
+        PerfMode::Normal.apply(&mut system, 8);
+        assert_eq!((system.ffmpeg_threads, system.ffmpeg_nice), (None, None));
+
+        PerfMode::Background.apply(&mut system, 1);
+        assert_eq!(system.ffmpeg_threads, Some(1));
+    }
 }
@@ -0,0 +1,211 @@
+// src-tauri/src/project.rs
+// Project-level information that isn't part of the edit itself (title, author, ...).
+use crate::preferences::UserPreferences;
+use crate::timeline::TimelineState;
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Mutex;
+
+/// Version written by `save_project`. Bump it and add a `migrate_project` arm
+/// whenever the file layout changes.
+///
+/// - 0: bare `TimelineState` JSON, before project files were versioned
+/// - 1: `ProjectFile` wrapper
+pub const CURRENT_PROJECT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ProjectMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub author: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl ProjectMetadata {
+    /// True when there is nothing worth writing into an exported container.
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.description.is_none() && self.author.is_none()
+    }
+
+    /// `-metadata key=value` pairs for FFmpeg's MP4 muxer.
+    pub fn ffmpeg_tags(&self) -> Vec<(&'static str, &str)> {
+        let mut tags = Vec::new();
+        if let Some(title) = &self.title {
+            tags.push(("title", title.as_str()));
+        }
+        if let Some(author) = &self.author {
+            tags.push(("artist", author.as_str()));
+        }
+        if let Some(description) = &self.description {
+            tags.push(("comment", description.as_str()));
+        }
+        tags
+    }
+}
+
+pub struct ProjectMetadataStore {
+    pub metadata: Mutex<ProjectMetadata>,
+}
+
+impl ProjectMetadataStore {
+    pub fn new() -> Self {
+        Self {
+            metadata: Mutex::new(ProjectMetadata::default()),
+        }
+    }
+}
+
+impl Default for ProjectMetadataStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// On-disk project: the timeline plus enough bookkeeping to migrate it later.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProjectFile {
+    pub project_version: u32,
+    pub app_version: String,
+    /// Unix millis.
+    pub created_at: i64,
+    pub last_saved_at: i64,
+    pub timeline: TimelineState,
+    pub project_metadata: Option<ProjectMetadata>,
+    /// Preferences at save time, for reference; opening a project doesn't apply them.
+    #[serde(default)]
+    pub preferences: Option<UserPreferences>,
+}
+
+/// Summary returned by `get_project_file_info`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ProjectFileInfo {
+    pub version: u32,
+    pub app_version: String,
+    pub created_at: i64,
+    pub clip_count: usize,
+}
+
+// Just the fields `ProjectFileInfo` needs; clips are counted, not parsed.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ProjectFileHeader {
+    project_version: u32,
+    app_version: Option<String>,
+    created_at: i64,
+    timeline: Option<ClipsHeader>,
+    // Version 0 files are the timeline itself
+    clips: Option<Vec<IgnoredAny>>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ClipsHeader {
+    clips: Vec<IgnoredAny>,
+}
+
+/// Read the summary fields of a project file's JSON without building the timeline.
+pub fn read_project_file_info(json: &str) -> Result<ProjectFileInfo, String> {
+    let header: ProjectFileHeader =
+        serde_json::from_str(json).map_err(|e| format!("Invalid project file: {}", e))?;
+    let clip_count = match (&header.timeline, &header.clips) {
+        (Some(timeline), _) => timeline.clips.len(),
+        (None, Some(clips)) => clips.len(),
+        (None, None) => 0,
+    };
+    Ok(ProjectFileInfo {
+        version: header.project_version,
+        app_version: header.app_version.unwrap_or_else(|| "unknown".to_string()),
+        created_at: header.created_at,
+        clip_count,
+    })
+}
+
+/// `project_version` of raw project JSON; files without one predate versioning.
+pub fn detect_project_version(raw: &Value) -> u32 {
+    raw.get("project_version")
+        .and_then(Value::as_u64)
+        .map_or(0, |v| v as u32)
+}
+
+/// Upgrade `raw` by exactly one version, from `from_version` to `from_version + 1`.
+pub fn migrate_project(raw: Value, from_version: u32) -> Result<Value, String> {
+    match from_version {
+        0 => Ok(serde_json::json!({
+            "project_version": 1,
+            "app_version": "unknown",
+            "created_at": 0,
+            "last_saved_at": 0,
+            "timeline": raw,
+            "project_metadata": null,
+        })),
+        v => Err(format!("No migration from project version {}", v)),
+    }
+}
+
+/// Apply every migration from the file's version up to `CURRENT_PROJECT_VERSION`.
+pub fn upgrade_project(mut raw: Value) -> Result<ProjectFile, String> {
+    let version = detect_project_version(&raw);
+    if version > CURRENT_PROJECT_VERSION {
+        return Err(format!(
+            "Project version {} is newer than this app supports ({})",
+            version, CURRENT_PROJECT_VERSION
+        ));
+    }
+    for from in version..CURRENT_PROJECT_VERSION {
+        raw = migrate_project(raw, from)?;
+    }
+    serde_json::from_value(raw).map_err(|e| format!("Invalid project file: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEGACY_PROJECT: &str = r#"{
+        "clips": [{"id": "a", "track_id": "v1", "start": 0.0, "duration": 4.0, "source_file": "/a.mp4"}],
+        "duration": 4.0,
+        "playhead_time": 1.0,
+        "version": 7
+    }"#;
+
+    #[test]
+    fn test_upgrade_legacy_project() {
+        let raw: Value = serde_json::from_str(LEGACY_PROJECT).unwrap();
+        assert_eq!(detect_project_version(&raw), 0);
+
+        let project = upgrade_project(raw).unwrap();
+        assert_eq!(project.project_version, CURRENT_PROJECT_VERSION);
+        assert_eq!(project.timeline.clips.len(), 1);
+        assert_eq!(project.timeline.version, 7);
+        assert!(project.preferences.is_none());
+
+        let newer = serde_json::json!({ "project_version": CURRENT_PROJECT_VERSION + 1 });
+        assert!(upgrade_project(newer).is_err());
+    }
+
+    #[test]
+    fn test_read_project_file_info() {
+        let legacy = read_project_file_info(LEGACY_PROJECT).unwrap();
+        assert_eq!((legacy.version, legacy.clip_count), (0, 1));
+
+        let current = serde_json::json!({
+            "project_version": 1,
+            "app_version": "0.1.0",
+            "created_at": 1700000000000i64,
+            "last_saved_at": 1700000000000i64,
+            "timeline": serde_json::from_str::<Value>(LEGACY_PROJECT).unwrap(),
+            "project_metadata": null,
+        });
+        let info = read_project_file_info(&current.to_string()).unwrap();
+        assert_eq!(
+            info,
+            ProjectFileInfo {
+                version: 1,
+                app_version: "0.1.0".to_string(),
+                created_at: 1700000000000,
+                clip_count: 1,
+            }
+        );
+    }
+}
@@ -0,0 +1,173 @@
+// src-tauri/src/render_cache.rs
+// Splits the rendered output into fixed-length segments that are kept between
+// exports, so re-exporting after a small edit only re-encodes what changed.
+use crate::ffmpeg::RenderSegment;
+use crate::timeline::Clip;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Length of one cached output segment.
+pub const CACHE_SEGMENT_SECS: f64 = 10.0;
+
+/// One `CACHE_SEGMENT_SECS` slice of the output and the clip pieces that fill it.
+#[derive(Debug, Clone)]
+pub struct CacheSegment {
+    pub index: usize,
+    /// Hash over the contributing clip pieces; changes whenever the segment would.
+    pub hash: String,
+    pub pieces: Vec<RenderSegment>,
+}
+
+impl CacheSegment {
+    pub fn file_name(&self) -> String {
+        format!("segment_{:04}_{}.mp4", self.index, &self.hash[..16])
+    }
+}
+
+/// Where cached segments are stored.
+pub fn cache_dir() -> PathBuf {
+    std::env::temp_dir().join("ghost_render_cache")
+}
+
+/// Slice `clips` (sorted, concatenated back to back like the render) into cache segments.
+pub fn plan_cache_segments(clips: &[Clip]) -> Vec<CacheSegment> {
+    // Output time at which each clip starts
+    let mut placed = Vec::with_capacity(clips.len());
+    let mut total = 0.0;
+    for clip in clips {
+        placed.push((total, clip));
+        total += clip.effective_duration();
+    }
+
+    let mut segments = Vec::new();
+    let mut index = 0;
+    while (index as f64) * CACHE_SEGMENT_SECS < total - 0.001 {
+        let seg_start = index as f64 * CACHE_SEGMENT_SECS;
+        let seg_end = (seg_start + CACHE_SEGMENT_SECS).min(total);
+
+        let pieces: Vec<RenderSegment> = placed
+            .iter()
+            .filter_map(|&(out_start, clip)| {
+                let out_end = out_start + clip.effective_duration();
+                let duration = out_end.min(seg_end) - out_start.max(seg_start);
+                if duration <= 0.001 {
+                    return None;
+                }
+                Some(RenderSegment {
+                    offset: clip.source_in()
+                        + (seg_start - out_start).max(0.0) * clip.speed_multiplier,
+                    duration,
+                    clip: clip.clone(),
+                })
+            })
+            .collect();
+
+        segments.push(CacheSegment {
+            index,
+            hash: hash_pieces(&pieces),
+            pieces,
+        });
+        index += 1;
+    }
+    segments
+}
+
+fn hash_pieces(pieces: &[RenderSegment]) -> String {
+    let mut hasher = Sha256::new();
+    // Segments cached before audio was rendered must not be reused
+    hasher.update(b"av|");
+    for piece in pieces {
+        hasher.update(piece.clip.source_file.as_bytes());
+        hasher.update(piece.clip.source_hash.as_deref().unwrap_or("").as_bytes());
+        hasher.update(
+            format!(
+                "|{:.4}|{:.4}|{:.4}|{:.2};",
+                piece.offset, piece.duration, piece.clip.speed_multiplier, piece.clip.volume_db
+            )
+            .as_bytes(),
+        );
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Which cached files each clip contributed to, so edits can drop them.
+#[derive(Debug, Default)]
+pub struct RenderCache {
+    by_clip: HashMap<String, Vec<PathBuf>>,
+}
+
+impl RenderCache {
+    pub fn record(&mut self, clip_id: &str, path: &Path) {
+        let paths = self.by_clip.entry(clip_id.to_string()).or_default();
+        if !paths.iter().any(|p| p == path) {
+            paths.push(path.to_path_buf());
+        }
+    }
+
+    /// Delete every cached segment `clip_id` appears in. Returns how many were removed.
+    pub fn invalidate_clip(&mut self, clip_id: &str) -> usize {
+        let Some(paths) = self.by_clip.remove(clip_id) else {
+            return 0;
+        };
+        paths
+            .iter()
+            .filter(|p| std::fs::remove_file(p).is_ok())
+            .count()
+    }
+
+    pub fn clear(&mut self) -> Result<(), String> {
+        self.by_clip.clear();
+        let dir = cache_dir();
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir)
+                .map_err(|e| format!("Failed to clear render cache: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clip(id: &str, source: &str, duration: f64) -> Clip {
+        Clip {
+            id: id.to_string(),
+            source_file: source.to_string(),
+            duration,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_plan_cache_segments_only_changed_segments_rehash() {
+        let clips = vec![
+            clip("a", "/a.mp4", 12.0),
+            clip("b", "/b.mp4", 6.0),
+            clip("c", "/c.mp4", 7.0),
+        ];
+        let segments = plan_cache_segments(&clips);
+        assert_eq!(segments.len(), 3);
+
+        // Segment 1 covers 10..20: the tail of "a", all of "b" and the head of "c"
+        let pieces: Vec<_> = segments[1]
+            .pieces
+            .iter()
+            .map(|p| (p.clip.id.as_str(), p.offset, p.duration))
+            .collect();
+        assert_eq!(
+            pieces,
+            vec![("a", 10.0, 2.0), ("b", 0.0, 6.0), ("c", 0.0, 2.0)]
+        );
+        assert!((segments[2].pieces[0].offset - 2.0).abs() < 1e-9);
+
+        // Trimming the end of "c" leaves the first two segments cached
+        let mut edited = clips.clone();
+        edited[2].edit_out = Some(5.0);
+        let replanned = plan_cache_segments(&edited);
+        assert_eq!(replanned[0].hash, segments[0].hash);
+        assert_eq!(replanned[1].hash, segments[1].hash);
+        assert_ne!(replanned[2].hash, segments[2].hash);
+    }
+}
@@ -0,0 +1,171 @@
+// src-tauri/src/source_watcher.rs
+// Watches the source files referenced by clips so the UI learns about edited or
+// deleted media immediately instead of at export time.
+use crate::timeline::TimelineState;
+use notify::event::ModifyKind;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter};
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SourceFileEvent {
+    pub clip_ids: Vec<String>,
+    pub path: String,
+}
+
+type WatchMap = Arc<Mutex<HashMap<PathBuf, Vec<String>>>>;
+
+pub struct SourceFileWatcher {
+    watcher: Mutex<RecommendedWatcher>,
+    /// Watched path -> ids of the clips using it.
+    watched: WatchMap,
+    /// Paths whose watch failed. They are retried on every sync, but only
+    /// reported once.
+    failed: Mutex<HashSet<PathBuf>>,
+    app: AppHandle,
+}
+
+impl SourceFileWatcher {
+    pub fn new(app: AppHandle) -> Result<Self, String> {
+        let watched: WatchMap = Arc::new(Mutex::new(HashMap::new()));
+        let map = watched.clone();
+        let event_app = app.clone();
+
+        let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            let event = match res {
+                Ok(event) => event,
+                Err(e) => {
+                    println!("⚠️ [SourceWatcher] {}", e);
+                    return;
+                }
+            };
+            for path in &event.paths {
+                let Some(name) = event_name(&event.kind, path) else {
+                    continue;
+                };
+                let clip_ids = match map.lock().unwrap().get(path) {
+                    Some(ids) => ids.clone(),
+                    None => continue,
+                };
+                let payload = SourceFileEvent {
+                    clip_ids,
+                    path: path.to_string_lossy().to_string(),
+                };
+                let _ = event_app.emit(name, payload);
+            }
+        })
+        .map_err(|e| format!("Failed to create file watcher: {}", e))?;
+
+        Ok(Self {
+            watcher: Mutex::new(watcher),
+            watched,
+            failed: Mutex::new(HashSet::new()),
+            app,
+        })
+    }
+
+    /// Bring the watch list in line with the clips in `state`.
+    pub fn sync(&self, state: &TimelineState) {
+        let wanted = clip_ids_by_source(state);
+        let mut watcher = self.watcher.lock().unwrap();
+        let mut watched = self.watched.lock().unwrap();
+        let mut failed = self.failed.lock().unwrap();
+
+        for path in watched.keys() {
+            if !wanted.contains_key(path) {
+                let _ = watcher.unwatch(path);
+            }
+        }
+        failed.retain(|path| wanted.contains_key(path));
+
+        let mut now_watched = HashMap::new();
+        for (path, clip_ids) in wanted {
+            if watched.contains_key(&path) {
+                now_watched.insert(path, clip_ids);
+                continue;
+            }
+            match watcher.watch(&path, RecursiveMode::NonRecursive) {
+                Ok(()) => {
+                    failed.remove(&path);
+                    now_watched.insert(path, clip_ids);
+                }
+                // Already reported; try again on the next sync
+                Err(_) if failed.contains(&path) => {}
+                Err(e) => {
+                    println!("⚠️ [SourceWatcher] Cannot watch {:?}: {}", path, e);
+                    if !path.exists() {
+                        let _ = self.app.emit(
+                            "SOURCE_FILE_MISSING",
+                            SourceFileEvent {
+                                clip_ids,
+                                path: path.to_string_lossy().to_string(),
+                            },
+                        );
+                    }
+                    failed.insert(path);
+                }
+            }
+        }
+
+        *watched = now_watched;
+    }
+}
+
+// Map a notify event to the frontend event it should raise, if any.
+fn event_name(kind: &EventKind, path: &Path) -> Option<&'static str> {
+    match kind {
+        EventKind::Remove(_) => Some("SOURCE_FILE_MISSING"),
+        // Renamed away from the watched path
+        EventKind::Modify(ModifyKind::Name(_)) if !path.exists() => Some("SOURCE_FILE_MISSING"),
+        EventKind::Modify(ModifyKind::Data(_)) | EventKind::Modify(ModifyKind::Any) => {
+            Some("SOURCE_FILE_CHANGED")
+        }
+        _ => None,
+    }
+}
+
+/// Unique source paths in the timeline with the clips that reference them.
+pub fn clip_ids_by_source(state: &TimelineState) -> HashMap<PathBuf, Vec<String>> {
+    let mut map: HashMap<PathBuf, Vec<String>> = HashMap::new();
+    for clip in &state.clips {
+        if clip.source_file.is_empty() {
+            continue;
+        }
+        map.entry(PathBuf::from(&clip.source_file))
+            .or_default()
+            .push(clip.id.clone());
+    }
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timeline::Clip;
+
+    #[test]
+    fn test_clip_ids_by_source_groups_shared_files() {
+        let clip = |id: &str, source: &str| Clip {
+            id: id.to_string(),
+            source_file: source.to_string(),
+            ..Default::default()
+        };
+        let state = TimelineState {
+            clips: vec![
+                clip("a", "/v/one.mp4"),
+                clip("b", "/v/two.mp4"),
+                clip("c", "/v/one.mp4"),
+                clip("d", ""),
+            ],
+            ..Default::default()
+        };
+
+        let map = clip_ids_by_source(&state);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map[Path::new("/v/one.mp4")], vec!["a", "c"]);
+        assert_eq!(map[Path::new("/v/two.mp4")], vec!["b"]);
+    }
+}
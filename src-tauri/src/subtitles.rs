@@ -0,0 +1,145 @@
+// src-tauri/src/subtitles.rs
+// SRT subtitle files attached to the timeline, and the FFmpeg arguments that
+// burn them into the picture or add them as soft subtitle streams.
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// How exports carry the timeline's subtitle tracks.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SubtitleMode {
+    /// Drawn into the video with the `subtitles` filter; always visible.
+    BurnIn,
+    /// Added as `mov_text` streams the player can toggle.
+    #[default]
+    Soft,
+}
+
+/// Number of cues in an SRT file, or an error if it has none.
+pub fn count_srt_cues(content: &str) -> Result<usize, String> {
+    let cues = content
+        .lines()
+        .filter(|line| parse_cue_times(line).is_some())
+        .count();
+    if cues == 0 {
+        return Err("No subtitle cues found (expected SRT)".to_string());
+    }
+    Ok(cues)
+}
+
+/// `content` with every cue moved by `offset_s`. Cues pushed entirely before
+/// zero are dropped; ones straddling it start at zero.
+pub fn shift_srt(content: &str, offset_s: f64) -> String {
+    let mut out = Vec::new();
+    let mut cue: Vec<String> = Vec::new();
+    let mut keep = true;
+    fn flush(cue: &mut Vec<String>, keep: bool, out: &mut Vec<String>) {
+        if keep && !cue.is_empty() {
+            out.push(cue.join("\n"));
+        }
+        cue.clear();
+    }
+
+    for line in content.lines() {
+        if line.trim().is_empty() {
+            flush(&mut cue, keep, &mut out);
+            keep = true;
+            continue;
+        }
+        match parse_cue_times(line) {
+            Some((start, end)) => {
+                let (start, end) = (start + offset_s, end + offset_s);
+                keep = end > 0.0;
+                cue.push(format!(
+                    "{} --> {}",
+                    format_srt_time(start.max(0.0)),
+                    format_srt_time(end.max(0.0))
+                ));
+            }
+            None => cue.push(line.to_string()),
+        }
+    }
+    flush(&mut cue, keep, &mut out);
+
+    // Renumber, since dropped cues leave holes
+    out.iter()
+        .enumerate()
+        .map(|(i, cue)| {
+            let body = match cue.split_once('\n') {
+                Some((first, rest)) if first.trim().parse::<u32>().is_ok() => rest,
+                _ => cue.as_str(),
+            };
+            format!("{}\n{}\n\n", i + 1, body)
+        })
+        .collect()
+}
+
+/// One `subtitles=` filter per file, chained for the `-vf` of a burn-in pass.
+pub fn burn_in_filter(srt_paths: &[&Path]) -> String {
+    srt_paths
+        .iter()
+        .map(|path| {
+            format!(
+                "subtitles='{}'",
+                escape_filter_path(&path.to_string_lossy())
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Filter arguments are quoted with '...', inside which ':' and '\' still need escaping.
+pub fn escape_filter_path(path: &str) -> String {
+    path.replace('\\', "\\\\")
+        .replace(':', "\\:")
+        .replace('\'', "\\'")
+}
+
+// "00:01:02,500 --> 00:01:04,000" -> (62.5, 64.0)
+fn parse_cue_times(line: &str) -> Option<(f64, f64)> {
+    let (start, end) = line.split_once("-->")?;
+    Some((parse_srt_time(start.trim())?, parse_srt_time(end.trim())?))
+}
+
+fn parse_srt_time(stamp: &str) -> Option<f64> {
+    let (hms, millis) = stamp.split_once([',', '.'])?;
+    let mut parts = hms.split(':').map(|p| p.parse::<u64>().ok());
+    let (h, m, s) = (parts.next()??, parts.next()??, parts.next()??);
+    let millis: u64 = millis.get(..3)?.parse().ok()?;
+    Some((h * 3600 + m * 60 + s) as f64 + millis as f64 / 1000.0)
+}
+
+fn format_srt_time(secs: f64) -> String {
+    let ms = (secs * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        ms / 3_600_000,
+        ms % 3_600_000 / 60_000,
+        ms % 60_000 / 1000,
+        ms % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shift_srt_moves_drops_and_renumbers() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nFirst\n\n\
+                   2\n00:00:03,000 --> 00:00:05,500\nSecond\nline two\n\n\
+                   3\n00:01:00,000 --> 00:01:01,000\nThird\n";
+        assert_eq!(count_srt_cues(srt).unwrap(), 3);
+        assert!(count_srt_cues("just some text").is_err());
+
+        assert_eq!(
+            shift_srt(srt, -3.5),
+            "1\n00:00:00,000 --> 00:00:02,000\nSecond\nline two\n\n\
+             2\n00:00:56,500 --> 00:00:57,500\nThird\n\n"
+        );
+        assert!(shift_srt(srt, 3600.0).contains("01:00:01,000 --> 01:00:02,000"));
+
+        let filter = burn_in_filter(&[Path::new("C:\\subs\\it's.srt")]);
+        assert_eq!(filter, "subtitles='C\\:\\\\subs\\\\it\\'s.srt'");
+    }
+}
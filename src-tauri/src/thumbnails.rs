@@ -0,0 +1,159 @@
+// src-tauri/src/thumbnails.rs
+// Progress of background thumbnail extraction, polled by the frontend, plus the
+// flag that lets it stop a batch between clips.
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Width of generated clip thumbnails; height keeps the aspect ratio.
+pub const THUMBNAIL_WIDTH: u32 = 320;
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct ThumbnailGenerationProgress {
+    pub total_requested: usize,
+    pub completed: usize,
+    /// Clip IDs currently being extracted.
+    pub in_progress: Vec<String>,
+    /// (clip_id, error)
+    pub failed: Vec<(String, String)>,
+}
+
+#[derive(Default)]
+pub struct ThumbnailGenerationState {
+    progress: Mutex<ThumbnailGenerationProgress>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl ThumbnailGenerationState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reset the progress for a new batch of `total` clips and clear any cancellation.
+    pub fn begin_batch(&self, total: usize) {
+        *self.progress.lock().unwrap() = ThumbnailGenerationProgress {
+            total_requested: total,
+            ..Default::default()
+        };
+        self.cancel.store(false, Ordering::SeqCst);
+    }
+
+    /// Count a single extraction outside a batch.
+    pub fn add_request(&self) {
+        self.progress.lock().unwrap().total_requested += 1;
+    }
+
+    pub fn start(&self, clip_id: &str) {
+        self.progress
+            .lock()
+            .unwrap()
+            .in_progress
+            .push(clip_id.to_string());
+    }
+
+    pub fn finish(&self, clip_id: &str, result: &Result<String, String>) {
+        let mut progress = self.progress.lock().unwrap();
+        progress.in_progress.retain(|id| id != clip_id);
+        match result {
+            Ok(_) => progress.completed += 1,
+            Err(e) => progress.failed.push((clip_id.to_string(), e.clone())),
+        }
+    }
+
+    pub fn progress(&self) -> ThumbnailGenerationProgress {
+        self.progress.lock().unwrap().clone()
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::SeqCst)
+    }
+}
+
+/// Where thumbnails are written, one `{clip_id}.jpg` per clip.
+pub fn thumbnail_dir() -> PathBuf {
+    std::env::temp_dir().join("ghost_thumbnails")
+}
+
+/// Seeked thumbnails sit next to `uploads/` so they survive restarts.
+pub fn clip_thumbnail_dir(uploads_dir: &Path) -> PathBuf {
+    uploads_dir
+        .parent()
+        .unwrap_or(uploads_dir)
+        .join("thumbnails")
+}
+
+/// `{clip_id}_{offset_ms}_{width}.jpg`
+pub fn clip_thumbnail_file_name(clip_id: &str, offset_secs: f64, width: u32) -> String {
+    format!(
+        "{}_{}_{}.jpg",
+        clip_id,
+        (offset_secs * 1000.0).round() as u64,
+        width
+    )
+}
+
+/// Keep the seek inside the clip, away from the final frames that some
+/// containers cannot decode.
+pub fn clamp_thumbnail_offset(offset_secs: f64, clip_duration: f64) -> f64 {
+    offset_secs.min(clip_duration * 0.9).max(0.0)
+}
+
+/// A thumbnail is reusable when it was written after the source last changed.
+pub fn is_thumbnail_fresh(thumbnail: &Path, source: &Path) -> bool {
+    let modified = |p: &Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+    match (modified(thumbnail), modified(source)) {
+        (Some(thumb), Some(src)) => thumb >= src,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_tracks_batch_and_cancellation() {
+        let state = ThumbnailGenerationState::new();
+        state.begin_batch(2);
+        state.start("a");
+        assert_eq!(state.progress().in_progress, vec!["a".to_string()]);
+        state.finish("a", &Ok("/tmp/a.jpg".to_string()));
+        state.start("b");
+        state.finish("b", &Err("no video stream".to_string()));
+
+        let progress = state.progress();
+        assert_eq!(progress.completed, 1);
+        assert!(progress.in_progress.is_empty());
+        assert_eq!(
+            progress.failed,
+            vec![("b".to_string(), "no video stream".to_string())]
+        );
+
+        state.cancel();
+        assert!(state.is_cancelled());
+        state.begin_batch(1);
+        assert!(!state.is_cancelled());
+        assert_eq!(state.progress().completed, 0);
+    }
+
+    #[test]
+    fn test_clip_thumbnail_naming_and_clamping() {
+        assert_eq!(clip_thumbnail_file_name("c1", 2.5, 160), "c1_2500_160.jpg");
+        assert_eq!(clamp_thumbnail_offset(-1.0, 10.0), 0.0);
+        assert_eq!(clamp_thumbnail_offset(12.0, 10.0), 9.0);
+        assert_eq!(clamp_thumbnail_offset(4.0, 10.0), 4.0);
+        assert_eq!(
+            clip_thumbnail_dir(Path::new("/videos/uploads")),
+            PathBuf::from("/videos/thumbnails")
+        );
+        assert!(!is_thumbnail_fresh(
+            Path::new("/nonexistent/t.jpg"),
+            Path::new("/nonexistent/s.mp4")
+        ));
+    }
+}
@@ -1,6 +1,8 @@
 // src-tauri/src/timeline.rs
+use crate::ffmpeg::LoudnormStats;
 use serde::{Deserialize, Serialize};
-use std::sync::Mutex;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
 
 // 1. THE DATA STRUCTURES (The Lego Blocks)
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -10,6 +12,246 @@ pub struct Clip {
     pub start: f64,    // Start time on timeline (seconds)
     pub duration: f64, // Length of clip (seconds)
     pub source_file: String,
+    /// SHA256 of the first 1MB of the original source, used for duplicate detection.
+    #[serde(default)]
+    pub source_hash: Option<String>,
+    /// Virtual trim handles (seconds into the source). When set, only
+    /// `[edit_in, edit_out]` plays; `duration` keeps the full source length.
+    #[serde(default)]
+    pub edit_in: Option<f64>,
+    #[serde(default)]
+    pub edit_out: Option<f64>,
+    /// Seconds of source before the usable part of the clip: where it plays
+    /// from without an `edit_in`. Set when a trim is made permanent, so
+    /// resetting the trim no longer brings the head back.
+    #[serde(default)]
+    pub source_offset: f64,
+    /// `start` and the timeline length as whole frames at `fps`. Edits do
+    /// their arithmetic on these and derive the seconds from them, so repeated
+    /// trims don't drift. Zero for clips without a probed fps.
+    #[serde(default)]
+    pub start_frames: u64,
+    #[serde(default)]
+    pub duration_frames: u64,
+    /// Audio-track clip whose audio ducks this clip's audio while both play.
+    #[serde(default)]
+    pub duck_when_active: Option<String>,
+    /// Gain reduction applied while ducked.
+    #[serde(default = "default_ducking_amount_db")]
+    pub ducking_amount_db: f64,
+    #[serde(default = "default_duck_attack_ms")]
+    pub duck_attack_ms: u32,
+    #[serde(default = "default_duck_release_ms")]
+    pub duck_release_ms: u32,
+    /// Low-resolution copy of the source for preview playback.
+    #[serde(default)]
+    pub proxy_path: Option<String>,
+    /// Stream properties probed at import; unset for clips added some other way.
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    #[serde(default)]
+    pub fps: Option<f64>,
+    #[serde(default)]
+    pub video_codec: Option<String>,
+    #[serde(default)]
+    pub has_audio: bool,
+    /// Playback rate; 2.0 plays the edit range in half the time. Trim handles
+    /// stay in source seconds.
+    #[serde(default = "default_speed_multiplier")]
+    pub speed_multiplier: f64,
+    /// Gain applied to the clip's audio when rendering.
+    #[serde(default)]
+    pub volume_db: f64,
+    /// User-facing name and "#RRGGBB" color for grouping clips.
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub color_hex: Option<String>,
+    /// Lowercase keywords for filtering the media pool, in the order added.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Seconds the audio is shifted against the picture: negative plays it
+    /// early (J-cut), positive late (L-cut).
+    #[serde(default)]
+    pub audio_offset_s: f64,
+    /// Measured by `normalize_audio_levels`; renders apply loudnorm with it.
+    #[serde(default)]
+    pub audio_loudness: Option<AudioLoudness>,
+    /// vidstabdetect transforms written by `stabilize_clip`, the source range
+    /// they were analyzed over, and the smoothing window (frames) to render
+    /// them with.
+    #[serde(default)]
+    pub stabilization_trf_path: Option<String>,
+    #[serde(default)]
+    pub stabilization_range: Option<(f64, f64)>,
+    #[serde(default = "default_stabilization_smoothing")]
+    pub stabilization_smoothing: u32,
+}
+
+/// A clip's first-pass loudnorm measurements and the target they were taken for.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AudioLoudness {
+    pub target_lufs: f64,
+    pub stats: LoudnormStats,
+}
+
+impl Default for Clip {
+    fn default() -> Self {
+        Self {
+            id: String::new(),
+            track_id: String::new(),
+            start: 0.0,
+            duration: 0.0,
+            source_file: String::new(),
+            source_hash: None,
+            edit_in: None,
+            edit_out: None,
+            source_offset: 0.0,
+            start_frames: 0,
+            duration_frames: 0,
+            duck_when_active: None,
+            ducking_amount_db: default_ducking_amount_db(),
+            duck_attack_ms: default_duck_attack_ms(),
+            duck_release_ms: default_duck_release_ms(),
+            proxy_path: None,
+            width: None,
+            height: None,
+            fps: None,
+            video_codec: None,
+            has_audio: false,
+            speed_multiplier: default_speed_multiplier(),
+            volume_db: 0.0,
+            label: None,
+            color_hex: None,
+            tags: Vec::new(),
+            audio_offset_s: 0.0,
+            audio_loudness: None,
+            stabilization_trf_path: None,
+            stabilization_range: None,
+            stabilization_smoothing: default_stabilization_smoothing(),
+        }
+    }
+}
+
+fn default_ducking_amount_db() -> f64 {
+    -6.0
+}
+
+fn default_duck_attack_ms() -> u32 {
+    50
+}
+
+fn default_duck_release_ms() -> u32 {
+    200
+}
+
+fn default_speed_multiplier() -> f64 {
+    1.0
+}
+
+fn default_stabilization_smoothing() -> u32 {
+    10
+}
+
+impl Clip {
+    /// Probed with an audio stream and no video stream.
+    pub fn is_audio_only(&self) -> bool {
+        self.has_audio && self.video_codec.is_none()
+    }
+
+    /// Where playback starts in the source.
+    pub fn source_in(&self) -> f64 {
+        self.edit_in.unwrap_or(self.source_offset)
+    }
+
+    /// Where playback stops in the source.
+    pub fn source_out(&self) -> f64 {
+        self.edit_out.unwrap_or(self.duration)
+    }
+
+    /// Seconds of source between the trim handles.
+    pub fn source_length(&self) -> f64 {
+        self.source_out() - self.source_in()
+    }
+
+    /// Length the clip occupies on the timeline.
+    pub fn effective_duration(&self) -> f64 {
+        self.source_length() / self.speed_multiplier
+    }
+
+    /// Timeline time at which the clip ends.
+    pub fn end(&self) -> f64 {
+        self.start + self.effective_duration()
+    }
+
+    /// The probed frame rate, when there is a usable one.
+    pub fn frame_rate(&self) -> Option<f64> {
+        self.fps.filter(|fps| *fps > 0.0)
+    }
+
+    /// Recount `start_frames` and `duration_frames` from the seconds fields.
+    pub fn sync_frames(&mut self) {
+        if let Some(fps) = self.frame_rate() {
+            self.start_frames = (self.start * fps).round().max(0.0) as u64;
+            self.duration_frames = (self.effective_duration() * fps).round().max(0.0) as u64;
+        }
+    }
+
+    /// Start on frame `frames`, with `start` derived from the count.
+    pub fn set_start_frames(&mut self, frames: u64) {
+        if let Some(fps) = self.frame_rate() {
+            self.start_frames = frames;
+            self.start = frames as f64 / fps;
+        }
+    }
+
+    /// Last `frames` frames on the timeline by moving the out point (never
+    /// past the end of the source).
+    pub fn set_duration_frames(&mut self, frames: u64) {
+        if let Some(fps) = self.frame_rate() {
+            let out = self.source_in() + frames as f64 / fps * self.speed_multiplier;
+            self.edit_out = Some(out.min(self.duration));
+            self.duration_frames = frames;
+        }
+    }
+
+    pub fn has_virtual_trim(&self) -> bool {
+        self.edit_in.is_some() || self.edit_out.is_some()
+    }
+
+    /// Change the timeline length by moving the out point (or `duration` when
+    /// there is no virtual trim). The out point never goes past the end of the
+    /// source.
+    pub fn set_effective_duration(&mut self, length: f64) {
+        let out = (self.source_in() + length * self.speed_multiplier).min(self.duration);
+        if self.has_virtual_trim() {
+            self.edit_out = Some(out);
+        } else {
+            self.duration = out;
+        }
+    }
+
+    /// The transforms file, if it was analyzed for `length` source seconds
+    /// starting `offset` into the source. Transforms are numbered from the
+    /// start of the analyzed range, so a split, head trim or slip since the
+    /// analysis makes them stale.
+    pub fn stabilization_for(&self, offset: f64, length: f64) -> Option<&str> {
+        let (from, to) = self.stabilization_range?;
+        let lines_up = (offset - from).abs() < 1e-6 && offset + length <= to + 1e-6;
+        self.stabilization_trf_path.as_deref().filter(|_| lines_up)
+    }
+
+    /// Make the trim permanent: the in point becomes `source_offset`, the out
+    /// point `duration`, and both handles are cleared. The clip still plays the
+    /// same source frames.
+    pub fn apply_virtual_trim(&mut self) {
+        self.source_offset = self.source_in();
+        self.duration = self.source_out();
+        self.edit_in = None;
+        self.edit_out = None;
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -20,6 +262,164 @@ pub struct TimelineState {
     pub playhead_time: f64,
     /// Version counter, incremented on every state mutation. Used for change detection.
     pub version: u64,
+    /// Frontend player preview speed. Never affects rendering, which is always real time.
+    #[serde(default = "default_playback_rate")]
+    pub preview_playback_rate: f64,
+    /// Frame rate used for frame-number seeking and frame snapping.
+    #[serde(default = "default_output_fps")]
+    pub output_fps: f64,
+    /// Crossfades between clips; registered pairs may overlap by up to `duration`.
+    #[serde(default)]
+    pub transitions: Vec<TransitionPoint>,
+    /// `(start, end)` range the preview repeats. Always within `[0, duration]`.
+    #[serde(default)]
+    pub loop_region: Option<(f64, f64)>,
+    /// Tracks clips are placed on. Empty for timelines saved before tracks
+    /// existed, which skip the track invariants until `backfill_tracks` runs.
+    #[serde(default)]
+    pub tracks: Vec<Track>,
+    /// Mute and solo per track; `muted` mirrors the track's own flag.
+    /// Tracks without an entry are neither.
+    #[serde(default)]
+    pub track_settings: Vec<TrackSettings>,
+    /// Named positions (chapters, beats, notes), ordered by time.
+    #[serde(default)]
+    pub markers: Vec<ChapterMarker>,
+    /// SRT files added to exports, in import order.
+    #[serde(default)]
+    pub subtitle_tracks: Vec<SubtitleTrack>,
+    /// Clips from the last copy or cut, earliest first. Undo and redo leave it alone.
+    #[serde(default)]
+    pub clipboard: Option<Vec<Clip>>,
+    /// Mirrors the engine's undo history so every STATE_UPDATE carries it.
+    #[serde(default)]
+    pub can_undo: bool,
+    #[serde(default)]
+    pub can_redo: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackType {
+    Video,
+    Audio,
+    Overlay,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Track {
+    pub id: String,
+    pub label: String,
+    pub track_type: TrackType,
+    /// Muted tracks are left out of the audio mix when rendering.
+    pub muted: bool,
+    /// Clips on locked tracks can't be changed by edit plans.
+    pub locked: bool,
+    /// Display position, top to bottom.
+    pub order: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct TrackSettings {
+    pub track_id: String,
+    pub muted: bool,
+    /// While any track is soloed, only soloed tracks are heard.
+    pub soloed: bool,
+}
+
+impl Track {
+    pub fn new(id: &str, label: &str, track_type: TrackType, order: u32) -> Self {
+        Self {
+            id: id.to_string(),
+            label: label.to_string(),
+            track_type,
+            muted: false,
+            locked: false,
+            order,
+        }
+    }
+
+    /// Type implied by a legacy track ID like "audio_track_1".
+    pub fn type_for_id(id: &str) -> TrackType {
+        if id.starts_with("audio") {
+            TrackType::Audio
+        } else if id.starts_with("overlay") {
+            TrackType::Overlay
+        } else {
+            TrackType::Video
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChapterMarker {
+    pub id: String,
+    pub time_s: f64,
+    pub label: String,
+    /// "#RRGGBB"; the frontend picks a color when unset.
+    pub color_hex: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SubtitleTrack {
+    pub id: String,
+    pub srt_path: String,
+    /// Seconds added to every cue time; negative values cut the start.
+    pub offset_s: f64,
+}
+
+/// Cheap snapshot of the timeline for high-frequency polling.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TimelineSummary {
+    pub version: u64,
+    pub clip_count: usize,
+    pub duration: f64,
+    pub playhead_time: f64,
+    pub track_count: usize,
+}
+
+/// Editorial totals for the whole timeline, computed from the clips alone.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TimelineStatistics {
+    pub total_duration_s: f64,
+    pub clip_count: usize,
+    pub clips_per_track: HashMap<String, usize>,
+    pub gap_count: usize,
+    pub total_gap_s: f64,
+    /// Average, shortest and longest effective duration; zero with no clips.
+    pub average_clip_duration_s: f64,
+    pub shortest_clip_s: f64,
+    pub longest_clip_s: f64,
+    /// `total_duration_s` as non-drop timecode at the output fps.
+    pub duration_timecode: String,
+}
+
+/// Shortest loop region `set_loop_region` accepts.
+pub const MIN_LOOP_REGION_SECS: f64 = 0.1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TransitionPoint {
+    pub clip_a_id: String,
+    pub clip_b_id: String,
+    pub duration: f64,
+}
+
+impl TransitionPoint {
+    /// True when this transition joins `a` and `b`, in either order.
+    pub fn connects(&self, a: &str, b: &str) -> bool {
+        (self.clip_a_id == a && self.clip_b_id == b) || (self.clip_a_id == b && self.clip_b_id == a)
+    }
+}
+
+/// Preview speeds the frontend player supports.
+pub const PREVIEW_PLAYBACK_RATES: [f64; 7] = [0.25, 0.5, 0.75, 1.0, 1.5, 2.0, 4.0];
+
+fn default_playback_rate() -> f64 {
+    1.0
+}
+
+fn default_output_fps() -> f64 {
+    30.0
 }
 
 impl Default for TimelineState {
@@ -29,23 +429,392 @@ impl Default for TimelineState {
             duration: 0.0,
             playhead_time: 0.0,
             version: 0,
+            preview_playback_rate: default_playback_rate(),
+            output_fps: default_output_fps(),
+            transitions: vec![],
+            loop_region: None,
+            tracks: vec![],
+            track_settings: vec![],
+            markers: vec![],
+            subtitle_tracks: vec![],
+            clipboard: None,
+            can_undo: false,
+            can_redo: false,
+        }
+    }
+}
+
+impl TimelineState {
+    pub fn summary(&self) -> TimelineSummary {
+        // Listed tracks count even when empty; clips can still name a track
+        // that isn't listed before `backfill_tracks` runs
+        let tracks: std::collections::HashSet<&str> = self
+            .tracks
+            .iter()
+            .map(|t| t.id.as_str())
+            .chain(self.clips.iter().map(|c| c.track_id.as_str()))
+            .collect();
+        TimelineSummary {
+            version: self.version,
+            clip_count: self.clips.len(),
+            duration: self.duration,
+            playhead_time: self.playhead_time,
+            track_count: tracks.len(),
         }
     }
+
+    pub fn track(&self, id: &str) -> Option<&Track> {
+        self.tracks.iter().find(|t| t.id == id)
+    }
+
+    /// Tracks left out of the audio mix: muted ones, and while anything is
+    /// soloed, everything that isn't.
+    pub fn silenced_track_ids(&self) -> Vec<String> {
+        let soloed = |id: &str| {
+            self.track_settings
+                .iter()
+                .any(|s| s.track_id == id && s.soloed)
+        };
+        let any_soloed = self.tracks.iter().any(|t| soloed(&t.id));
+        self.tracks
+            .iter()
+            .filter(|t| t.muted || (any_soloed && !soloed(&t.id)))
+            .map(|t| t.id.clone())
+            .collect()
+    }
+
+    /// The settings entry for `track_id`, added when the track has none yet.
+    pub fn track_settings_mut(&mut self, track_id: &str) -> &mut TrackSettings {
+        let index = match self
+            .track_settings
+            .iter()
+            .position(|s| s.track_id == track_id)
+        {
+            Some(index) => index,
+            None => {
+                let muted = self.track(track_id).is_some_and(|t| t.muted);
+                self.track_settings.push(TrackSettings {
+                    track_id: track_id.to_string(),
+                    muted,
+                    soloed: false,
+                });
+                self.track_settings.len() - 1
+            }
+        };
+        &mut self.track_settings[index]
+    }
+
+    /// The clip start or end nearest `time` if it's within `threshold`, else
+    /// `time`. Edges of `ignore_clip_id` (usually the clip being moved) don't count.
+    pub fn snap_to_clip_edge(
+        &self,
+        time: f64,
+        threshold: f64,
+        ignore_clip_id: Option<&str>,
+    ) -> f64 {
+        self.clips
+            .iter()
+            .filter(|c| Some(c.id.as_str()) != ignore_clip_id)
+            .flat_map(|c| [c.start, c.end()])
+            .filter(|edge| (edge - time).abs() <= threshold)
+            .min_by(|a, b| (a - time).abs().total_cmp(&(b - time).abs()))
+            .unwrap_or(time)
+    }
+
+    /// Whether `track_id` holds audio, guessing from the ID when tracks aren't listed.
+    pub fn is_audio_track(&self, track_id: &str) -> bool {
+        self.track(track_id)
+            .map_or_else(|| Track::type_for_id(track_id), |t| t.track_type)
+            == TrackType::Audio
+    }
+
+    /// Create the track `id` if the timeline lists tracks and doesn't have it yet.
+    pub fn ensure_track(&mut self, id: &str) {
+        if self.tracks.is_empty() || self.track(id).is_some() {
+            return;
+        }
+        let order = self.tracks.iter().map(|t| t.order + 1).max().unwrap_or(0);
+        self.tracks
+            .push(Track::new(id, id, Track::type_for_id(id), order));
+    }
+
+    /// Give a timeline saved before tracks existed a track for each clip track ID.
+    pub fn backfill_tracks(&mut self) {
+        if !self.tracks.is_empty() {
+            return;
+        }
+        for clip in &self.clips {
+            if self.tracks.iter().all(|t| t.id != clip.track_id) {
+                let order = self.tracks.len() as u32;
+                self.tracks.push(Track::new(
+                    &clip.track_id,
+                    &clip.track_id,
+                    Track::type_for_id(&clip.track_id),
+                    order,
+                ));
+            }
+        }
+    }
+
+    /// The transition registered between two clips, in either order.
+    pub fn transition_between(&self, a: &str, b: &str) -> Option<&TransitionPoint> {
+        self.transitions.iter().find(|t| t.connects(a, b))
+    }
+
+    /// Recompute `duration` from the clips and clamp the playhead into range.
+    pub fn recalculate_duration(&mut self) {
+        self.duration = self.clips.iter().map(|c| c.end()).fold(0.0, f64::max);
+        self.playhead_time = self.playhead_time.clamp(0.0, self.duration);
+        self.clamp_loop_region();
+        self.clamp_markers();
+    }
+
+    /// Pull the loop region back inside the timeline after it shrinks, dropping
+    /// it once it gets shorter than `MIN_LOOP_REGION_SECS`.
+    pub fn clamp_loop_region(&mut self) {
+        if let Some((start, end)) = self.loop_region {
+            let end = end.min(self.duration);
+            self.loop_region = (end - start >= MIN_LOOP_REGION_SECS).then_some((start, end));
+        }
+    }
+
+    /// Pull markers past the end back onto it after the timeline shrinks.
+    pub fn clamp_markers(&mut self) {
+        for marker in &mut self.markers {
+            marker.time_s = marker.time_s.min(self.duration);
+        }
+    }
+
+    /// Keep `markers` ordered by time after adding or moving one.
+    pub fn sort_markers(&mut self) {
+        self.markers
+            .sort_by(|a, b| a.time_s.partial_cmp(&b.time_s).unwrap());
+    }
+}
+
+/// "HH:MM:SS:FF" for `seconds` at `fps`, rounded to the nearest frame.
+/// Drop-frame ("HH:MM:SS;FF") only applies at 29.97 and 59.94 fps, where it
+/// skips frame numbers to keep the timecode on the wall clock.
+pub fn seconds_to_timecode(seconds: f64, fps: f64, drop_frame: bool) -> String {
+    let nominal = (fps.round() as u64).max(1);
+    let mut frames = (seconds.max(0.0) * fps).round() as u64;
+    let dropped = dropped_frames_per_minute(fps, drop_frame);
+    if dropped > 0 {
+        // Add back the frame numbers skipped in each minute not divisible by ten
+        let per_ten_minutes = nominal * 600 - dropped * 9;
+        let per_minute = nominal * 60 - dropped;
+        let (tens, rest) = (frames / per_ten_minutes, frames % per_ten_minutes);
+        frames += dropped * 9 * tens;
+        if rest > dropped {
+            frames += dropped * ((rest - dropped) / per_minute);
+        }
+    }
+    let separator = if dropped > 0 { ';' } else { ':' };
+    format!(
+        "{:02}:{:02}:{:02}{}{:02}",
+        frames / (nominal * 3600),
+        frames / (nominal * 60) % 60,
+        frames / nominal % 60,
+        separator,
+        frames % nominal
+    )
+}
+
+/// Seconds for a "HH:MM:SS:FF" (or drop-frame "HH:MM:SS;FF") timecode at `fps`.
+pub fn timecode_to_seconds(tc: &str, fps: f64, drop_frame: bool) -> Result<f64, String> {
+    if fps.is_nan() || fps <= 0.0 {
+        return Err(format!("Invalid fps {}", fps));
+    }
+    let parts: Vec<u64> = tc
+        .trim()
+        .split([':', ';'])
+        .map(|p| p.parse::<u64>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("Invalid timecode '{}' (expected HH:MM:SS:FF)", tc))?;
+    let &[hours, minutes, secs, frame] = parts.as_slice() else {
+        return Err(format!("Invalid timecode '{}' (expected HH:MM:SS:FF)", tc));
+    };
+    let nominal = (fps.round() as u64).max(1);
+    if minutes >= 60 || secs >= 60 || frame >= nominal {
+        return Err(format!("Timecode '{}' is out of range at {} fps", tc, fps));
+    }
+
+    let dropped = dropped_frames_per_minute(fps, drop_frame);
+    let total_minutes = hours * 60 + minutes;
+    if dropped > 0 && secs == 0 && frame < dropped && minutes % 10 != 0 {
+        return Err(format!("Timecode '{}' is skipped in drop-frame", tc));
+    }
+    let frames = (total_minutes * 60 + secs) * nominal + frame
+        - dropped * (total_minutes - total_minutes / 10);
+    Ok(frames as f64 / fps)
+}
+
+// Frame numbers drop-frame timecode skips each minute: 2 at 29.97, 4 at 59.94
+fn dropped_frames_per_minute(fps: f64, drop_frame: bool) -> u64 {
+    let nominal = fps.round();
+    if drop_frame && (nominal == 30.0 || nominal == 60.0) && (fps - nominal).abs() > 1e-3 {
+        nominal as u64 / 15
+    } else {
+        0
+    }
+}
+
+/// Undo history kept by default.
+pub const DEFAULT_HISTORY_DEPTH: usize = 50;
+
+/// Snapshots taken before each edit plan; the oldest drop off past `depth`.
+#[derive(Debug, Clone)]
+struct History {
+    undo: VecDeque<TimelineState>,
+    redo: Vec<TimelineState>,
+    depth: usize,
+}
+
+/// Saved by `TimelineEngine::checkpoint`.
+pub struct TimelineCheckpoint {
+    state: TimelineState,
+    history: History,
 }
 
 // 2. THE ENGINE (Holds the State safely)
+// Clones share the same state.
+#[derive(Clone)]
 pub struct TimelineEngine {
     // Mutex allows safe access from multiple threads (UI + AI)
-    pub state: Mutex<TimelineState>,
+    pub state: Arc<Mutex<TimelineState>>,
+    // Always locked after `state` when both are needed
+    history: Arc<Mutex<History>>,
+}
+
+impl Default for TimelineEngine {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TimelineEngine {
     pub fn new() -> Self {
+        let state = TimelineState {
+            tracks: vec![
+                Track::new("video_track_1", "Video 1", TrackType::Video, 0),
+                Track::new("audio_track_1", "Audio 1", TrackType::Audio, 1),
+            ],
+            ..Default::default()
+        };
         Self {
-            state: Mutex::new(TimelineState::default()),
+            state: Arc::new(Mutex::new(state)),
+            history: Arc::new(Mutex::new(History {
+                undo: VecDeque::new(),
+                redo: Vec::new(),
+                depth: DEFAULT_HISTORY_DEPTH,
+            })),
+        }
+    }
+
+    /// Change how many undo steps are kept, dropping the oldest beyond it.
+    pub fn set_history_depth(&self, depth: usize) {
+        let mut history = self.history.lock().unwrap();
+        history.depth = depth.max(1);
+        while history.undo.len() > history.depth {
+            history.undo.pop_front();
+        }
+    }
+
+    /// Record `before` as the undo point for the edit that produced `state`,
+    /// discarding anything that could have been redone. `state` is the locked
+    /// current state.
+    pub fn push_undo_snapshot(&self, state: &mut TimelineState, before: TimelineState) {
+        let mut history = self.history.lock().unwrap();
+        history.undo.push_back(before);
+        if history.undo.len() > history.depth {
+            history.undo.pop_front();
+        }
+        history.redo.clear();
+        Self::sync_flags(state, &history);
+    }
+
+    /// Run `edit` on the state as one undoable step. When it succeeds the
+    /// version is bumped and the prior state becomes the undo point; when it
+    /// fails the state is put back as it was. Returns the new state.
+    pub fn apply_edit(
+        &self,
+        edit: impl FnOnce(&mut TimelineState) -> Result<(), String>,
+    ) -> Result<TimelineState, String> {
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|_| "Failed to lock state".to_string())?;
+        let before = state.clone();
+        if let Err(e) = edit(&mut state) {
+            *state = before;
+            return Err(e);
+        }
+        state.version += 1;
+        self.push_undo_snapshot(&mut state, before);
+        Ok(state.clone())
+    }
+
+    /// Forget all history, e.g. after loading a different project.
+    pub fn clear_history(&self, state: &mut TimelineState) {
+        let mut history = self.history.lock().unwrap();
+        history.undo.clear();
+        history.redo.clear();
+        Self::sync_flags(state, &history);
+    }
+
+    pub fn undo(&self) -> Result<TimelineState, String> {
+        let mut state = self.state.lock().map_err(|_| "Failed to lock state")?;
+        let mut history = self.history.lock().unwrap();
+        let previous = history.undo.pop_back().ok_or("Nothing to undo")?;
+        let current = std::mem::replace(&mut *state, previous);
+        state.version = current.version + 1;
+        state.clipboard = current.clipboard.clone();
+        history.redo.push(current);
+        Self::sync_flags(&mut state, &history);
+        Ok(state.clone())
+    }
+
+    pub fn redo(&self) -> Result<TimelineState, String> {
+        let mut state = self.state.lock().map_err(|_| "Failed to lock state")?;
+        let mut history = self.history.lock().unwrap();
+        let next = history.redo.pop().ok_or("Nothing to redo")?;
+        let current = std::mem::replace(&mut *state, next);
+        state.version = current.version + 1;
+        state.clipboard = current.clipboard.clone();
+        history.undo.push_back(current);
+        Self::sync_flags(&mut state, &history);
+        Ok(state.clone())
+    }
+
+    /// The current state and undo history, to return to with `restore_checkpoint`.
+    pub fn checkpoint(&self) -> TimelineCheckpoint {
+        let state = self.state.lock().unwrap();
+        let history = self.history.lock().unwrap();
+        TimelineCheckpoint {
+            state: state.clone(),
+            history: history.clone(),
         }
     }
 
+    /// Drop every edit made since `checkpoint`, including their undo steps.
+    pub fn restore_checkpoint(&self, checkpoint: TimelineCheckpoint) -> TimelineState {
+        let mut state = self.state.lock().unwrap();
+        let mut history = self.history.lock().unwrap();
+        let current = std::mem::replace(&mut *state, checkpoint.state);
+        state.version = current.version + 1;
+        state.clipboard = current.clipboard;
+        let depth = history.depth;
+        *history = checkpoint.history;
+        history.depth = depth;
+        Self::sync_flags(&mut state, &history);
+        state.clone()
+    }
+
+    fn sync_flags(state: &mut TimelineState, history: &History) {
+        state.can_undo = !history.undo.is_empty();
+        state.can_redo = !history.redo.is_empty();
+    }
+
     /// Seek to a specific time on the timeline.
     /// Clamps to valid range [0, duration].
     pub fn seek(&self, time: f64) -> f64 {
@@ -56,6 +825,48 @@ impl TimelineEngine {
         clamped
     }
 
+    /// Seek to a frame number at the timeline's output fps. Returns the clamped time.
+    pub fn seek_to_frame(&self, frame_number: u64) -> f64 {
+        let fps = self.state.lock().unwrap().output_fps;
+        self.seek(frame_number as f64 / fps)
+    }
+
+    /// Current playhead position as a frame number at the output fps.
+    pub fn playhead_frame(&self) -> u64 {
+        let state = self.state.lock().unwrap();
+        (state.playhead_time * state.output_fps).round() as u64
+    }
+
+    /// Round a time to the nearest frame boundary at the output fps.
+    pub fn snap_to_frame(&self, time: f64) -> f64 {
+        let fps = self.state.lock().unwrap().output_fps;
+        (time * fps).round() / fps
+    }
+
+    pub fn set_output_fps(&self, fps: f64) -> Result<(), String> {
+        if !(fps > 0.0 && fps <= 240.0) {
+            return Err(format!("Invalid output fps {} (must be in (0, 240])", fps));
+        }
+        let mut state = self.state.lock().unwrap();
+        state.output_fps = fps;
+        state.version += 1;
+        Ok(())
+    }
+
+    /// Set the preview playback rate. Only the rates in `PREVIEW_PLAYBACK_RATES` are accepted.
+    pub fn set_preview_playback_rate(&self, rate: f64) -> Result<(), String> {
+        if !PREVIEW_PLAYBACK_RATES.contains(&rate) {
+            return Err(format!(
+                "Unsupported playback rate {}. Allowed: {:?}",
+                rate, PREVIEW_PLAYBACK_RATES
+            ));
+        }
+        let mut state = self.state.lock().unwrap();
+        state.preview_playback_rate = rate;
+        state.version += 1;
+        Ok(())
+    }
+
     /// Get the clip that is active at the given time.
     /// Returns None if no clip exists at that time (gap or empty timeline).
     pub fn get_active_clip(&self, time: f64) -> Option<Clip> {
@@ -63,7 +874,7 @@ impl TimelineEngine {
         state
             .clips
             .iter()
-            .find(|clip| clip.start <= time && time < clip.start + clip.duration)
+            .find(|clip| clip.start <= time && time < clip.end())
             .cloned()
     }
 
@@ -74,7 +885,7 @@ impl TimelineEngine {
         state
             .clips
             .iter()
-            .find(|clip| clip.start <= time && time < clip.start + clip.duration)
+            .find(|clip| clip.start <= time && time < clip.end())
             .cloned()
     }
 
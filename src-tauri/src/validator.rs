@@ -1,5 +1,5 @@
 // src-tauri/src/validator.rs
-use crate::edit_plan::EditPlan;
+use crate::edit_plan::{ActionType, EditPlan};
 use crate::timeline::TimelineEngine;
 use serde::Serialize;
 use tauri::State;
@@ -21,30 +21,170 @@ pub enum Action {
     // Add more actions as needed
 }
 
-pub fn validate_plan(plan: &EditPlan, engine: &State<'_, TimelineEngine>) -> Result<(), String> {
-    if plan.actions.is_empty() {
-        return Err("Plan Validation Rejected: Plan contains no actions.".to_string());
+/// Fastest playback rate `SetSpeed` accepts (exclusive).
+pub const MAX_SPEED_MULTIPLIER: f64 = 10.0;
+
+pub fn check_speed_multiplier(speed: f64) -> Result<(), String> {
+    if speed > 0.0 && speed < MAX_SPEED_MULTIPLIER {
+        Ok(())
+    } else {
+        Err(format!(
+            "Speed multiplier {} is outside (0, {})",
+            speed, MAX_SPEED_MULTIPLIER
+        ))
     }
+}
+
+/// Range `SetVolume` gains are clamped to.
+pub const MIN_VOLUME_DB: f64 = -60.0;
+pub const MAX_VOLUME_DB: f64 = 20.0;
 
+pub fn validate_plan(plan: &EditPlan, engine: &State<'_, TimelineEngine>) -> Result<(), String> {
     // Lock the state to check against current clips
     let state = engine
         .state
         .lock()
         .map_err(|_| "Failed to acquire state lock".to_string())?;
 
+    validate_plan_with_state(plan, &state)
+}
+
+/// Same checks as `validate_plan`, against a state the caller already holds.
+pub fn validate_plan_with_state(
+    plan: &EditPlan,
+    state: &crate::timeline::TimelineState,
+) -> Result<(), String> {
+    if plan.actions.is_empty() {
+        return Err("Plan Validation Rejected: Plan contains no actions.".to_string());
+    }
+
+    validate_split_dependencies(plan, state)
+        .map_err(|e| format!("Validation Failed [{}]: {}", e.code, e.message))?;
+
     for action in &plan.actions {
         // Rule: Target clip must exist
-        if !state.clips.iter().any(|c| c.id == action.target_clip_id) {
+        let Some(clip) = state.clips.iter().find(|c| c.id == action.target_clip_id) else {
             return Err(format!(
                 "Validation Failed: Target clip ID '{}' not found in timeline.",
                 action.target_clip_id
             ));
+        };
+        // Rule: Locked tracks can't be edited (markers belong to the timeline)
+        if action.action_type != ActionType::AddMarker
+            && state.track(&clip.track_id).is_some_and(|t| t.locked)
+        {
+            return Err(format!(
+                "Validation Failed: track '{}' is locked.",
+                clip.track_id
+            ));
+        }
+        if action.action_type == ActionType::SetSpeed {
+            let speed = action
+                .parameters
+                .as_ref()
+                .and_then(|p| p.speed_multiplier)
+                .ok_or("Validation Failed: SET_SPEED requires speed_multiplier.")?;
+            check_speed_multiplier(speed).map_err(|e| format!("Validation Failed: {}", e))?;
+        }
+        if action.action_type == ActionType::RollingTrim
+            && action
+                .parameters
+                .as_ref()
+                .and_then(|p| p.roll_delta)
+                .is_none()
+        {
+            return Err("Validation Failed: ROLLING_TRIM requires roll_delta.".to_string());
+        }
+        if action.action_type == ActionType::SlipClip
+            && action
+                .parameters
+                .as_ref()
+                .and_then(|p| p.slip_delta)
+                .is_none()
+        {
+            return Err("Validation Failed: SLIP_CLIP requires slip_delta.".to_string());
+        }
+        if action.action_type == ActionType::SlideClip
+            && action
+                .parameters
+                .as_ref()
+                .and_then(|p| p.slide_delta)
+                .is_none()
+        {
+            return Err("Validation Failed: SLIDE_CLIP requires slide_delta.".to_string());
+        }
+        if action.action_type == ActionType::AudioOffset
+            && action
+                .parameters
+                .as_ref()
+                .and_then(|p| p.audio_offset_delta)
+                .is_none()
+        {
+            return Err("Validation Failed: AUDIO_OFFSET requires audio_offset_delta.".to_string());
+        }
+        if action.action_type == ActionType::SetVolume {
+            let volume_db = action
+                .parameters
+                .as_ref()
+                .and_then(|p| p.volume_db)
+                .ok_or("Validation Failed: SET_VOLUME requires volume_db.")?;
+            if !(MIN_VOLUME_DB..=MAX_VOLUME_DB).contains(&volume_db) {
+                return Err(format!(
+                    "Validation Failed: volume_db {} is outside [{}, {}].",
+                    volume_db, MIN_VOLUME_DB, MAX_VOLUME_DB
+                ));
+            }
         }
     }
 
     Ok(())
 }
 
+/// Enforce the prompt's SPLIT rule: clips created by a Split don't exist until the
+/// plan runs, so later actions can't target them. Split results get fresh IDs, so
+/// after the first Split any target that isn't already on the timeline is treated
+/// as one of its products.
+pub fn validate_split_dependencies(
+    plan: &EditPlan,
+    state: &crate::timeline::TimelineState,
+) -> Result<(), ValidationError> {
+    let mut split_targets: Vec<&str> = Vec::new();
+    for action in &plan.actions {
+        let exists = state.clips.iter().any(|c| c.id == action.target_clip_id);
+        if !exists && !split_targets.is_empty() {
+            return Err(ValidationError {
+                code: "SPLIT_DEPENDENCY_VIOLATION".to_string(),
+                message: format!(
+                    "Clip '{}' would be created by splitting {:?} in this plan. \
+                     New clips from a Split cannot be targeted in the same plan.",
+                    action.target_clip_id, split_targets
+                ),
+                offending_action: Some(format!(
+                    "{:?}({})",
+                    action.action_type, action.target_clip_id
+                )),
+            });
+        }
+        if action.action_type == ActionType::Split {
+            split_targets.push(&action.target_clip_id);
+        }
+    }
+    Ok(())
+}
+
+/// Clips shorter than this can trip FFmpeg's concat filter with timestamp errors.
+pub const MIN_SEGMENT_FOR_CONCAT_SECS: f64 = 0.1;
+
+/// IDs of clips too short to concat reliably at render time.
+pub fn validate_minimum_segment_duration(state: &crate::timeline::TimelineState) -> Vec<String> {
+    state
+        .clips
+        .iter()
+        .filter(|c| c.effective_duration() < MIN_SEGMENT_FOR_CONCAT_SECS)
+        .map(|c| c.id.clone())
+        .collect()
+}
+
 #[allow(dead_code)]
 pub fn validate_actions_against_state(
     actions: &[Action],
@@ -80,6 +220,7 @@ mod tests {
             duration: 0.0,
             playhead_time: 0.0,
             version: 0,
+            ..Default::default()
         };
         let actions = vec![Action::DeleteClip {
             id: "missing".to_string(),
@@ -98,12 +239,14 @@ mod tests {
             start: 0.0,
             duration: 5.0,
             source_file: "test.mp4".to_string(),
+            ..Default::default()
         };
         let state = TimelineState {
             clips: vec![clip],
             duration: 5.0,
             playhead_time: 0.0,
             version: 0,
+            ..Default::default()
         };
         let actions = vec![Action::DeleteClip {
             id: "existing".to_string(),
@@ -112,4 +255,19 @@ mod tests {
         let result = validate_actions_against_state(&actions, &state);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_validate_minimum_segment_duration() {
+        let clip = |id: &str, duration: f64| Clip {
+            id: id.to_string(),
+            track_id: "v1".to_string(),
+            duration,
+            ..Default::default()
+        };
+        let state = TimelineState {
+            clips: vec![clip("ok", 2.0), clip("tiny", 0.05), clip("edge", 0.1)],
+            ..Default::default()
+        };
+        assert_eq!(validate_minimum_segment_duration(&state), vec!["tiny"]);
+    }
 }
@@ -1,10 +1,37 @@
 #[cfg(test)]
 mod tests {
-    use ghost_lib::action_router::validate_state_invariants;
-    use ghost_lib::edit_plan::{ActionType, EditPlan};
-    use ghost_lib::llm::parse_edit_plan;
-    use ghost_lib::timeline::{Clip, TimelineState};
-    use ghost_lib::validator::{validate_actions_against_state, Action};
+    use ghost_lib::action_router::{
+        apply_plan_to_state, apply_plan_to_state_with, delete_clips_in_range,
+        describe_state_changes, downgrade_ripple_deletes, enforce_gap_policy, find_gaps,
+        is_noop_action, preview_edit_plan, reorder_clip, validate_state_invariants,
+        validate_state_invariants_with,
+    };
+    use ghost_lib::edit_plan::{
+        generate_plan_for_move, generate_plan_for_scene_splits, generate_plan_for_silence_removal,
+        generate_plan_for_split, generate_plan_for_trim, ActionType, EditPlan,
+        CURRENT_SCHEMA_VERSION,
+    };
+    use ghost_lib::llm::{migrate_edit_plan, parse_edit_plan, LlmParseError};
+    use ghost_lib::preferences::{EditingPreferences, GeneralPreferences};
+    use ghost_lib::timeline::{
+        seconds_to_timecode, timecode_to_seconds, Clip, TimelineEngine, TimelineState, Track,
+        TrackType, TransitionPoint,
+    };
+    use ghost_lib::validator::{
+        validate_actions_against_state, validate_plan_with_state, validate_split_dependencies,
+        Action,
+    };
+
+    fn clip(id: &str, track: &str, start: f64, duration: f64) -> Clip {
+        Clip {
+            id: id.to_string(),
+            track_id: track.to_string(),
+            start,
+            duration,
+            source_file: format!("/{}.mp4", id),
+            ..Default::default()
+        }
+    }
 
     // Mocking State is hard in integration tests without full app setup.
     // We will test the components that *would* be called by the command.
@@ -28,10 +55,33 @@ mod tests {
         assert_eq!(plan.actions[0].target_clip_id, "123");
     }
 
+    #[test]
+    fn test_edit_plan_schema_version() {
+        let unversioned = r#"{"actions": [{"type": "DELETE", "target_clip_id": "a"}]}"#;
+        assert_eq!(parse_edit_plan(unversioned).unwrap().schema_version, 1);
+
+        let migrated: serde_json::Value =
+            serde_json::from_str(&migrate_edit_plan(unversioned).unwrap()).unwrap();
+        assert_eq!(migrated["schema_version"], CURRENT_SCHEMA_VERSION);
+        assert_eq!(migrated["actions"][0]["target_clip_id"], "a");
+        assert!(migrate_edit_plan("[1, 2]").is_err());
+
+        let future = format!(
+            r#"{{"schema_version": {}, "actions": [{{"type": "TELEPORT", "target_clip_id": "a"}}]}}"#,
+            CURRENT_SCHEMA_VERSION + 1
+        );
+        assert!(matches!(
+            parse_edit_plan(&future),
+            Err(LlmParseError::UnsupportedSchemaVersion(v)) if v == CURRENT_SCHEMA_VERSION + 1
+        ));
+        assert!(migrate_edit_plan(&future).is_err());
+    }
+
     #[test]
     fn test_validation_logic() {
         // Test Empty Plan
         let _empty_plan = EditPlan {
+            schema_version: CURRENT_SCHEMA_VERSION,
             thought_process: Some("Nothing".to_string()),
             actions: vec![],
             confidence: None,
@@ -42,6 +92,7 @@ mod tests {
             duration: 0.0,
             playhead_time: 0.0,
             version: 0,
+            ..Default::default()
         };
         let actions = vec![Action::DeleteClip {
             id: "missing".to_string(),
@@ -65,10 +116,12 @@ mod tests {
                 start: 0.0,
                 duration: -5.0, // INVALID: negative duration
                 source_file: "/test.mp4".to_string(),
+                ..Default::default()
             }],
             duration: 0.0,
             playhead_time: 0.0,
             version: 0,
+            ..Default::default()
         };
         let result = validate_state_invariants(&state);
         assert!(result.is_err(), "Should reject negative duration clip");
@@ -83,10 +136,12 @@ mod tests {
                 start: 0.0,
                 duration: 0.0, // INVALID: zero duration
                 source_file: "/test.mp4".to_string(),
+                ..Default::default()
             }],
             duration: 0.0,
             playhead_time: 0.0,
             version: 0,
+            ..Default::default()
         };
         let result = validate_state_invariants(&state);
         assert!(result.is_err(), "Should reject zero duration clip");
@@ -101,10 +156,12 @@ mod tests {
                 start: -1.0, // INVALID: negative start
                 duration: 5.0,
                 source_file: "/test.mp4".to_string(),
+                ..Default::default()
             }],
             duration: 4.0,
             playhead_time: 0.0,
             version: 0,
+            ..Default::default()
         };
         let result = validate_state_invariants(&state);
         assert!(result.is_err(), "Should reject negative start time");
@@ -120,6 +177,7 @@ mod tests {
                     start: 0.0,
                     duration: 10.0, // Ends at 10s
                     source_file: "/test.mp4".to_string(),
+                    ..Default::default()
                 },
                 Clip {
                     id: "clip2".to_string(),
@@ -127,11 +185,13 @@ mod tests {
                     start: 5.0,                 // INVALID: Starts at 5s, overlaps clip1
                     duration: 10.0,
                     source_file: "/test2.mp4".to_string(),
+                    ..Default::default()
                 },
             ],
             duration: 15.0,
             playhead_time: 0.0,
             version: 0,
+            ..Default::default()
         };
         let result = validate_state_invariants(&state);
         assert!(
@@ -149,10 +209,12 @@ mod tests {
                 start: 0.0,
                 duration: 10.0,
                 source_file: "/test.mp4".to_string(),
+                ..Default::default()
             }],
             duration: 10.0,
             playhead_time: 15.0, // INVALID: beyond duration
             version: 0,
+            ..Default::default()
         };
         let result = validate_state_invariants(&state);
         assert!(result.is_err(), "Should reject playhead beyond duration");
@@ -167,10 +229,12 @@ mod tests {
                 start: 0.0,
                 duration: 10.0,
                 source_file: "/test.mp4".to_string(),
+                ..Default::default()
             }],
             duration: 10.0,
             playhead_time: -5.0, // INVALID: negative playhead
             version: 0,
+            ..Default::default()
         };
         let result = validate_state_invariants(&state);
         assert!(result.is_err(), "Should reject negative playhead");
@@ -185,10 +249,12 @@ mod tests {
                 start: 0.0,
                 duration: 10.0, // Clip ends at 10s
                 source_file: "/test.mp4".to_string(),
+                ..Default::default()
             }],
             duration: 5.0, // INVALID: should be 10.0
             playhead_time: 0.0,
             version: 0,
+            ..Default::default()
         };
         let result = validate_state_invariants(&state);
         assert!(result.is_err(), "Should reject duration mismatch");
@@ -204,6 +270,7 @@ mod tests {
                     start: 0.0,
                     duration: 5.0,
                     source_file: "/test.mp4".to_string(),
+                    ..Default::default()
                 },
                 Clip {
                     id: "clip2".to_string(),
@@ -211,11 +278,13 @@ mod tests {
                     start: 5.0, // Starts exactly where clip1 ends
                     duration: 5.0,
                     source_file: "/test2.mp4".to_string(),
+                    ..Default::default()
                 },
             ],
             duration: 10.0,
             playhead_time: 3.0, // Valid: within [0, 10]
             version: 0,
+            ..Default::default()
         };
         let result = validate_state_invariants(&state);
         assert!(result.is_ok(), "Valid state should pass all invariants");
@@ -228,8 +297,886 @@ mod tests {
             duration: 0.0,
             playhead_time: 0.0,
             version: 0,
+            ..Default::default()
         };
         let result = validate_state_invariants(&state);
         assert!(result.is_ok(), "Empty timeline should be valid");
     }
+
+    #[test]
+    fn test_preview_edit_plan_leaves_state_untouched() {
+        let state = TimelineState {
+            clips: vec![clip("clip-1", "v1", 0.0, 10.0)],
+            duration: 10.0,
+            ..Default::default()
+        };
+        let plan = parse_edit_plan(
+            r#"{"actions": [{"type": "SPLIT", "target_clip_id": "clip-1", "parameters": {"split_time": 4.0}}]}"#,
+        )
+        .unwrap();
+
+        let preview = preview_edit_plan(&state, &plan, &EditingPreferences::default())
+            .expect("split should apply");
+        assert_eq!(preview.clips.len(), 2);
+        assert_eq!(state.clips.len(), 1);
+        assert_eq!(state.clips[0].duration, 10.0);
+
+        let new_id = &preview.clips.iter().find(|c| c.id != "clip-1").unwrap().id;
+        assert_eq!(
+            describe_state_changes(&state, &preview),
+            vec![
+                "clip clip-1: duration 10.0 → 4.0s".to_string(),
+                format!("clip {}: added at 4.0s (6.0s long)", new_id),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_saved_plan_round_trips_into_apply() {
+        let state = TimelineState {
+            clips: vec![clip("clip-1", "v1", 0.0, 10.0)],
+            duration: 10.0,
+            ..Default::default()
+        };
+        let plan = parse_edit_plan(
+            r#"{"actions": [{"type": "SPLIT", "target_clip_id": "clip-1", "parameters": {"split_time": 4.0}}]}"#,
+        )
+        .unwrap();
+
+        // `export_edit_plan` writes this, `import_edit_plan` reads it back and
+        // the frontend passes it to `apply_edit_plan`
+        let saved = serde_json::to_string_pretty(&plan).unwrap();
+        let imported = parse_edit_plan(&saved).expect("saved plan should parse");
+        let reapplied = parse_edit_plan(&serde_json::to_string(&imported).unwrap())
+            .expect("imported plan should parse");
+        validate_plan_with_state(&reapplied, &state).expect("saved plan should validate");
+
+        let mut saved_state = state.clone();
+        let mut fresh_state = state;
+        apply_plan_to_state(&mut saved_state, &reapplied).unwrap();
+        apply_plan_to_state(&mut fresh_state, &plan).unwrap();
+        let durations = |s: &TimelineState| s.clips.iter().map(|c| c.duration).collect::<Vec<_>>();
+        assert_eq!(saved_state.clips.len(), 2);
+        assert_eq!(durations(&saved_state), durations(&fresh_state));
+    }
+
+    #[test]
+    fn test_rejected_plan_error_describes_attempted_changes() {
+        let mut state = TimelineState {
+            clips: vec![clip("a", "v1", 0.0, 5.0), clip("b", "v1", 5.0, 5.0)],
+            duration: 10.0,
+            ..Default::default()
+        };
+        let plan = parse_edit_plan(
+            r#"{"actions": [{"type": "MOVE", "target_clip_id": "a", "parameters": {"new_start_time": 3.0}}]}"#,
+        )
+        .unwrap();
+
+        let err = apply_plan_to_state(&mut state, &plan).unwrap_err();
+        assert!(err.contains("overlap"), "{}", err);
+        assert!(
+            err.contains("(attempted: clip a: start 0.0 → 3.0s via MOVE"),
+            "{}",
+            err
+        );
+    }
+
+    fn range_state() -> TimelineState {
+        // v1: a [0,8) b [8,12) c [12,20) d [20,25); a1: music [0,25)
+        TimelineState {
+            clips: vec![
+                clip("a", "v1", 0.0, 8.0),
+                clip("b", "v1", 8.0, 4.0),
+                clip("c", "v1", 12.0, 8.0),
+                clip("d", "v1", 20.0, 5.0),
+                clip("music", "a1", 0.0, 25.0),
+            ],
+            duration: 25.0,
+            ..Default::default()
+        }
+    }
+
+    fn clip_span(state: &TimelineState, id: &str) -> (f64, f64, f64, f64) {
+        let c = state.clips.iter().find(|c| c.id == id).unwrap();
+        (c.start, c.end(), c.source_in(), c.source_out())
+    }
+
+    #[test]
+    fn test_delete_clips_in_range_trims_edges() {
+        let mut state = range_state();
+        let removed = delete_clips_in_range(&mut state, 5.0, 15.0, Some("v1"), false).unwrap();
+        assert_eq!(removed, 1); // only "b" lies fully inside
+
+        // "a" keeps its head, "c" its tail, both through edit points
+        assert_eq!(clip_span(&state, "a"), (0.0, 5.0, 0.0, 5.0));
+        assert_eq!(clip_span(&state, "c"), (15.0, 20.0, 3.0, 8.0));
+        assert_eq!(clip_span(&state, "d"), (20.0, 25.0, 0.0, 5.0));
+        assert_eq!(clip_span(&state, "music"), (0.0, 25.0, 0.0, 25.0));
+        assert!(validate_state_invariants(&state).is_ok());
+    }
+
+    #[test]
+    fn test_delete_clips_in_range_ripples_and_splits() {
+        let mut state = range_state();
+        delete_clips_in_range(&mut state, 5.0, 15.0, None, true).unwrap();
+
+        // v1 closes up; the music spanning the range is split and closed up too
+        assert_eq!(clip_span(&state, "a"), (0.0, 5.0, 0.0, 5.0));
+        assert_eq!(clip_span(&state, "c"), (5.0, 10.0, 3.0, 8.0));
+        assert_eq!(clip_span(&state, "d"), (10.0, 15.0, 0.0, 5.0));
+        let music: Vec<_> = state
+            .clips
+            .iter()
+            .filter(|c| c.track_id == "a1")
+            .map(|c| (c.start, c.end(), c.source_in(), c.source_out()))
+            .collect();
+        assert_eq!(music, vec![(5.0, 15.0, 15.0, 25.0), (0.0, 5.0, 0.0, 5.0)]);
+        assert_eq!(state.duration, 15.0);
+        assert!(validate_state_invariants(&state).is_ok());
+
+        assert!(delete_clips_in_range(&mut state, 4.0, 4.0, None, true).is_err());
+    }
+
+    #[test]
+    fn test_find_gaps_and_gap_free_policy() {
+        let state = TimelineState {
+            clips: vec![
+                clip("a", "v1", 2.0, 3.0),
+                clip("b", "v1", 5.0, 2.0),
+                clip("c", "v1", 9.0, 1.0),
+                clip("d", "a1", 0.0, 10.0),
+            ],
+            duration: 10.0,
+            ..Default::default()
+        };
+
+        let gaps = find_gaps(&state);
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].track_id, "v1");
+        assert_eq!((gaps[0].gap_start, gaps[0].gap_end), (7.0, 9.0));
+
+        // Gaps are only rejected when the preference asks for it
+        assert!(validate_state_invariants(&state).is_ok());
+        assert!(enforce_gap_policy(&state, &GeneralPreferences::default()).is_ok());
+        let strict = GeneralPreferences {
+            require_no_gaps: true,
+            ..Default::default()
+        };
+        let err = enforce_gap_policy(&state, &strict).unwrap_err();
+        assert!(
+            err.contains("Gap on track 'v1' from 7.00s to 9.00s"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_trim_sets_virtual_edit_points() {
+        let mut state = TimelineState {
+            clips: vec![clip("clip-1", "v1", 0.0, 10.0)],
+            duration: 10.0,
+            ..Default::default()
+        };
+        let plan = parse_edit_plan(
+            r#"{"actions": [{"type": "TRIM", "target_clip_id": "clip-1", "parameters": {"trim_start_delta": 2.0, "trim_end_delta": -3.0}}]}"#,
+        )
+        .unwrap();
+
+        apply_plan_to_state(&mut state, &plan).expect("trim should apply");
+        let clip = &state.clips[0];
+        assert_eq!(clip.duration, 10.0, "source duration is untouched");
+        assert_eq!((clip.edit_in, clip.edit_out), (Some(2.0), Some(7.0)));
+        assert_eq!(clip.start, 2.0);
+        assert_eq!(clip.effective_duration(), 5.0);
+        assert_eq!(state.duration, 7.0);
+    }
+
+    #[test]
+    fn test_trims_and_splits_count_frames() {
+        let mut state = TimelineState {
+            clips: vec![Clip {
+                fps: Some(30.0),
+                ..clip("clip-1", "v1", 0.0, 10.0)
+            }],
+            duration: 10.0,
+            ..Default::default()
+        };
+        let trim = parse_edit_plan(
+            r#"{"actions": [{"type": "TRIM", "target_clip_id": "clip-1", "parameters": {"trim_start_delta": 0.1}}]}"#,
+        )
+        .unwrap();
+
+        // Adding 0.1s ten times would drift; ten 3-frame trims don't
+        for _ in 0..10 {
+            apply_plan_to_state(&mut state, &trim).unwrap();
+        }
+        let clip = &state.clips[0];
+        assert_eq!((clip.start_frames, clip.duration_frames), (30, 270));
+        assert_eq!((clip.start, clip.end()), (1.0, 10.0));
+
+        // Splits land on the nearest frame
+        let split = parse_edit_plan(
+            r#"{"actions": [{"type": "SPLIT", "target_clip_id": "clip-1", "parameters": {"split_time": 2.51}}]}"#,
+        )
+        .unwrap();
+        apply_plan_to_state(&mut state, &split).unwrap();
+        let (first, second) = (&state.clips[0], &state.clips[1]);
+        assert_eq!((first.duration_frames, second.start_frames), (45, 75));
+        assert_eq!((first.end(), second.start), (2.5, 2.5));
+        assert_eq!(second.duration_frames, 225);
+    }
+
+    #[test]
+    fn test_noop_actions_are_skipped() {
+        let mut state = TimelineState {
+            clips: vec![clip("clip-1", "v1", 2.0, 10.0)],
+            duration: 12.0,
+            ..Default::default()
+        };
+        let plan = parse_edit_plan(
+            r#"{"actions": [
+                {"type": "MOVE", "target_clip_id": "clip-1", "parameters": {"new_start_time": 2.0}},
+                {"type": "TRIM", "target_clip_id": "clip-1", "parameters": {"trim_start_delta": 0.0}},
+                {"type": "MOVE", "target_clip_id": "clip-1", "parameters": {"new_start_time": 0.0}}
+            ]}"#,
+        )
+        .unwrap();
+
+        assert!(is_noop_action(&plan.actions[0], &state));
+        assert!(is_noop_action(&plan.actions[1], &state));
+        assert!(!is_noop_action(&plan.actions[2], &state));
+
+        let skipped = apply_plan_to_state(&mut state, &plan).unwrap();
+        assert_eq!(skipped, vec![0, 1]);
+        assert_eq!(state.clips[0].start, 0.0);
+    }
+
+    #[test]
+    fn test_reorder_clip_with_and_without_gaps() {
+        // a [0,2)  gap  b [3,6)  gap  c [8,9)
+        let state = TimelineState {
+            clips: vec![
+                clip("a", "v1", 0.0, 2.0),
+                clip("b", "v1", 3.0, 3.0),
+                clip("c", "v1", 8.0, 1.0),
+            ],
+            duration: 9.0,
+            ..Default::default()
+        };
+        let starts = |s: &TimelineState| -> Vec<(String, f64)> {
+            let mut v: Vec<_> = s.clips.iter().map(|c| (c.id.clone(), c.start)).collect();
+            v.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            v
+        };
+
+        let mut packed = state.clone();
+        reorder_clip(&mut packed, "a", 2, false).unwrap();
+        assert_eq!(
+            starts(&packed),
+            vec![("b".into(), 0.0), ("c".into(), 3.0), ("a".into(), 4.0)]
+        );
+
+        let mut gapped = state.clone();
+        reorder_clip(&mut gapped, "a", 2, true).unwrap();
+        assert_eq!(
+            starts(&gapped),
+            vec![("b".into(), 1.0), ("c".into(), 6.0), ("a".into(), 7.0)]
+        );
+        assert!(validate_state_invariants(&gapped).is_ok());
+
+        let mut back = state.clone();
+        reorder_clip(&mut back, "c", 0, true).unwrap();
+        assert_eq!(
+            starts(&back),
+            vec![("c".into(), 0.0), ("a".into(), 1.0), ("b".into(), 4.0)]
+        );
+
+        assert!(reorder_clip(&mut state.clone(), "a", 3, true).is_err());
+    }
+
+    #[test]
+    fn test_registered_transition_allows_overlap() {
+        let mut state = TimelineState {
+            clips: vec![
+                clip("a", "v1", 0.0, 5.0),
+                Clip {
+                    id: "b".to_string(),
+                    track_id: "v1".to_string(),
+                    start: 4.0, // Overlaps "a" by 1s
+                    duration: 5.0,
+                    source_file: "/b.mp4".to_string(),
+                    ..Default::default()
+                },
+            ],
+            duration: 9.0,
+            ..Default::default()
+        };
+        assert!(validate_state_invariants(&state).is_err());
+
+        state.transitions.push(TransitionPoint {
+            clip_a_id: "a".to_string(),
+            clip_b_id: "b".to_string(),
+            duration: 1.0,
+        });
+        assert!(validate_state_invariants(&state).is_ok());
+
+        state.clips[1].start = 3.0; // More overlap than the crossfade covers
+        state.duration = 8.0;
+        assert!(validate_state_invariants(&state).is_err());
+    }
+
+    #[test]
+    fn test_split_dependency_violation() {
+        let state = TimelineState {
+            clips: vec![clip("clip-1", "v1", 0.0, 10.0)],
+            duration: 10.0,
+            ..Default::default()
+        };
+        let plan = parse_edit_plan(
+            r#"{"actions": [
+                {"type": "SPLIT", "target_clip_id": "clip-1", "parameters": {"split_time": 4.0}},
+                {"type": "TRIM", "target_clip_id": "clip-1-part-2", "parameters": {"trim_end_delta": -1.0}}
+            ]}"#,
+        )
+        .unwrap();
+
+        let err = validate_split_dependencies(&plan, &state).unwrap_err();
+        assert_eq!(err.code, "SPLIT_DEPENDENCY_VIOLATION");
+        assert_eq!(err.offending_action.as_deref(), Some("Trim(clip-1-part-2)"));
+        assert!(validate_plan_with_state(&plan, &state)
+            .unwrap_err()
+            .contains("SPLIT_DEPENDENCY_VIOLATION"));
+
+        // Targeting the original clip again is fine
+        let plan = parse_edit_plan(
+            r#"{"actions": [
+                {"type": "SPLIT", "target_clip_id": "clip-1", "parameters": {"split_time": 4.0}},
+                {"type": "TRIM", "target_clip_id": "clip-1", "parameters": {"trim_start_delta": 1.0}}
+            ]}"#,
+        )
+        .unwrap();
+        assert!(validate_split_dependencies(&plan, &state).is_ok());
+    }
+
+    #[test]
+    fn test_set_loop_region_action_and_clamping() {
+        let mut state = TimelineState {
+            clips: vec![clip("a", "v1", 0.0, 4.0), clip("b", "v1", 4.0, 6.0)],
+            duration: 10.0,
+            ..Default::default()
+        };
+        let plan = parse_edit_plan(
+            r#"{"actions": [{"type": "SET_LOOP_REGION", "target_clip_id": "b", "parameters": {"loop_start": 5.0}}]}"#,
+        )
+        .unwrap();
+        apply_plan_to_state(&mut state, &plan).unwrap();
+        assert_eq!(state.loop_region, Some((5.0, 10.0)));
+        assert!(is_noop_action(&plan.actions[0], &state));
+
+        // Deleting "b" would leave the region past the end, so it is dropped
+        let plan =
+            parse_edit_plan(r#"{"actions": [{"type": "DELETE", "target_clip_id": "b"}]}"#).unwrap();
+        apply_plan_to_state(&mut state, &plan).unwrap();
+        assert_eq!(state.loop_region, None);
+
+        state.loop_region = Some((1.0, 6.0));
+        assert!(validate_state_invariants(&state).is_err());
+    }
+
+    #[test]
+    fn test_add_marker_action_and_marker_invariants() {
+        let mut state = TimelineState {
+            clips: vec![clip("a", "v1", 0.0, 8.0)],
+            duration: 8.0,
+            ..Default::default()
+        };
+        let plan = parse_edit_plan(
+            r#"{"actions": [
+                {"type": "ADD_MARKER", "target_clip_id": "a", "parameters": {"marker_time": 5.0, "marker_label": "Scene 2"}},
+                {"type": "ADD_MARKER", "target_clip_id": "a"}
+            ]}"#,
+        )
+        .unwrap();
+        apply_plan_to_state(&mut state, &plan).unwrap();
+        let markers: Vec<_> = state
+            .markers
+            .iter()
+            .map(|m| (m.time_s, m.label.as_str()))
+            .collect();
+        assert_eq!(markers, vec![(0.0, "Chapter 2"), (5.0, "Scene 2")]);
+
+        state.markers[1].id = state.markers[0].id.clone();
+        assert!(validate_state_invariants(&state).is_err());
+        state.markers[1].id = "other".to_string();
+        state.markers[1].time_s = 9.0;
+        assert!(validate_state_invariants(&state).is_err());
+    }
+
+    fn ripple_state() -> TimelineState {
+        TimelineState {
+            clips: vec![
+                clip("a", "v1", 0.0, 4.0),
+                clip("b", "v1", 4.0, 3.0),
+                clip("c", "v1", 7.0, 5.0),
+                clip("d", "v1", 14.0, 2.0),
+                clip("music", "a1", 10.0, 6.0),
+            ],
+            duration: 16.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_ripple_delete_closes_gap_on_track() {
+        let mut state = ripple_state();
+        let plan =
+            parse_edit_plan(r#"{"actions": [{"type": "RIPPLE_DELETE", "target_clip_id": "b"}]}"#)
+                .unwrap();
+        apply_plan_to_state(&mut state, &plan).unwrap();
+
+        let starts: Vec<_> = state
+            .clips
+            .iter()
+            .map(|c| (c.id.as_str(), c.start))
+            .collect();
+        assert_eq!(
+            starts,
+            vec![("a", 0.0), ("c", 4.0), ("d", 11.0), ("music", 10.0)]
+        );
+        assert_eq!(state.duration, 16.0);
+    }
+
+    #[test]
+    fn test_ripple_delete_downgrades_to_delete() {
+        let mut state = ripple_state();
+        let mut plan =
+            parse_edit_plan(r#"{"actions": [{"type": "RIPPLE_DELETE", "target_clip_id": "b"}]}"#)
+                .unwrap();
+        downgrade_ripple_deletes(&mut plan);
+        assert_eq!(plan.actions[0].action_type, ActionType::Delete);
+        apply_plan_to_state(&mut state, &plan).unwrap();
+
+        let starts: Vec<_> = state
+            .clips
+            .iter()
+            .map(|c| (c.id.as_str(), c.start))
+            .collect();
+        assert_eq!(
+            starts,
+            vec![("a", 0.0), ("c", 7.0), ("d", 14.0), ("music", 10.0)]
+        );
+    }
+
+    #[test]
+    fn test_set_speed_rescales_clip_and_shifts_track() {
+        let speed_plan = |speed: f64| {
+            parse_edit_plan(&format!(
+                r#"{{"actions": [{{"type": "SET_SPEED", "target_clip_id": "b", "parameters": {{"speed_multiplier": {}}}}}]}}"#,
+                speed
+            ))
+            .unwrap()
+        };
+        let mut state = ripple_state();
+        assert!(validate_plan_with_state(&speed_plan(10.0), &state).is_err());
+        assert!(validate_plan_with_state(&speed_plan(0.0), &state).is_err());
+
+        // Slowing "b" down pushes the clips after it on v1, but not the music
+        let plan = speed_plan(0.5);
+        validate_plan_with_state(&plan, &state).unwrap();
+        apply_plan_to_state(&mut state, &plan).unwrap();
+        let starts: Vec<_> = state
+            .clips
+            .iter()
+            .map(|c| (c.id.as_str(), c.start))
+            .collect();
+        assert_eq!(
+            starts,
+            vec![
+                ("a", 0.0),
+                ("b", 4.0),
+                ("c", 10.0),
+                ("d", 17.0),
+                ("music", 10.0)
+            ]
+        );
+        assert_eq!(state.clips[1].effective_duration(), 6.0);
+        assert_eq!(state.duration, 19.0);
+
+        // Splitting the slowed clip halfway splits its source halfway
+        apply_plan_to_state(&mut state, &generate_plan_for_split("b", 7.0)).unwrap();
+        assert_eq!(state.clips[1].edit_out, Some(1.5));
+        assert_eq!(state.clips[2].effective_duration(), 3.0);
+    }
+
+    #[test]
+    fn test_set_volume_is_validated_and_clamped() {
+        let mut state = ripple_state();
+        let plan = parse_edit_plan(
+            r#"{"actions": [{"type": "SET_VOLUME", "target_clip_id": "a", "parameters": {"volume_db": 30.0}}]}"#,
+        )
+        .unwrap();
+        assert!(validate_plan_with_state(&plan, &state).is_err());
+
+        // Plans that skip validation still can't store more than +20 dB
+        apply_plan_to_state(&mut state, &plan).unwrap();
+        assert_eq!(state.clips[0].volume_db, 20.0);
+        assert!(is_noop_action(&plan.actions[0], &state));
+    }
+
+    #[test]
+    fn test_rolling_trim_moves_cut_and_keeps_duration() {
+        // Both clips have 2s of unused source on the side of the cut
+        let mut state = TimelineState {
+            clips: vec![
+                Clip {
+                    edit_out: Some(4.0),
+                    ..clip("a", "v1", 0.0, 6.0)
+                },
+                Clip {
+                    edit_in: Some(2.0),
+                    ..clip("b", "v1", 4.0, 6.0)
+                },
+            ],
+            duration: 8.0,
+            ..Default::default()
+        };
+        let roll_plan = |delta: f64| {
+            parse_edit_plan(&format!(
+                r#"{{"actions": [{{"type": "ROLLING_TRIM", "target_clip_id": "a", "parameters": {{"roll_delta": {}}}}}]}}"#,
+                delta
+            ))
+            .unwrap()
+        };
+        let missing = parse_edit_plan(
+            r#"{"actions": [{"type": "ROLLING_TRIM", "target_clip_id": "a", "parameters": {}}]}"#,
+        )
+        .unwrap();
+        assert!(validate_plan_with_state(&missing, &state).is_err());
+
+        let plan = roll_plan(1.5);
+        validate_plan_with_state(&plan, &state).unwrap();
+        apply_plan_to_state(&mut state, &plan).unwrap();
+        assert_eq!(state.clips[0].effective_duration(), 5.5);
+        assert_eq!(state.clips[1].start, 5.5);
+        assert_eq!(state.clips[1].edit_in, Some(3.5));
+        assert_eq!(state.duration, 8.0);
+
+        // Past the target's source, too short a clip, or no clip after the target
+        for (target, delta) in [("a", 1.0), ("a", -5.45), ("b", 1.0)] {
+            let mut plan = roll_plan(delta);
+            plan.actions[0].target_clip_id = target.to_string();
+            assert!(apply_plan_to_state(&mut state.clone(), &plan).is_err());
+        }
+    }
+
+    #[test]
+    fn test_slide_clip_trims_neighbours() {
+        let trimmed = |id: &str, start: f64, edit_in: f64, edit_out: f64| Clip {
+            edit_in: Some(edit_in),
+            edit_out: Some(edit_out),
+            ..clip(id, "v1", start, 10.0)
+        };
+        let mut state = TimelineState {
+            clips: vec![
+                trimmed("a", 0.0, 0.0, 4.0),
+                trimmed("b", 4.0, 0.0, 3.0),
+                trimmed("c", 7.0, 2.0, 7.0),
+            ],
+            duration: 12.0,
+            ..Default::default()
+        };
+        let slide_plan = |target: &str, delta: f64| {
+            parse_edit_plan(&format!(
+                r#"{{"actions": [{{"type": "SLIDE_CLIP", "target_clip_id": "{}", "parameters": {{"slide_delta": {}}}}}]}}"#,
+                target, delta
+            ))
+            .unwrap()
+        };
+
+        apply_plan_to_state(&mut state, &slide_plan("b", 1.5)).unwrap();
+        let spans: Vec<_> = state
+            .clips
+            .iter()
+            .map(|c| (c.id.as_str(), c.start, c.effective_duration()))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![("a", 0.0, 5.5), ("b", 5.5, 3.0), ("c", 8.5, 3.5)]
+        );
+        assert_eq!(state.duration, 12.0);
+
+        // "c" can't lose its last 3.5s, "a" has no clip before it
+        assert!(apply_plan_to_state(&mut state.clone(), &slide_plan("b", 3.5)).is_err());
+        assert!(apply_plan_to_state(&mut state.clone(), &slide_plan("a", 1.0)).is_err());
+    }
+
+    #[test]
+    fn test_slip_clip_moves_source_range_only() {
+        let mut state = TimelineState {
+            clips: vec![Clip {
+                id: "a".to_string(),
+                track_id: "v1".to_string(),
+                start: 3.0,
+                duration: 10.0,
+                edit_in: Some(1.0),
+                edit_out: Some(5.0),
+                source_file: "/a.mp4".to_string(),
+                ..Default::default()
+            }],
+            duration: 7.0,
+            ..Default::default()
+        };
+        let slip_plan = |delta: f64| {
+            parse_edit_plan(&format!(
+                r#"{{"actions": [{{"type": "SLIP_CLIP", "target_clip_id": "a", "parameters": {{"slip_delta": {}}}}}]}}"#,
+                delta
+            ))
+            .unwrap()
+        };
+
+        apply_plan_to_state(&mut state, &slip_plan(2.5)).unwrap();
+        let clip = &state.clips[0];
+        assert_eq!((clip.source_in(), clip.source_out()), (3.5, 7.5));
+        assert_eq!((clip.start, clip.effective_duration()), (3.0, 4.0));
+
+        // Neither handle can leave the source
+        assert!(apply_plan_to_state(&mut state.clone(), &slip_plan(-4.0)).is_err());
+        assert!(apply_plan_to_state(&mut state.clone(), &slip_plan(3.0)).is_err());
+    }
+
+    #[test]
+    fn test_timecode_round_trips() {
+        assert_eq!(seconds_to_timecode(3630.5, 30.0, false), "01:00:30:15");
+        assert_eq!(timecode_to_seconds("01:00:30:15", 30.0, false), Ok(3630.5));
+        assert!(timecode_to_seconds("00:00:01:30", 30.0, false).is_err());
+        assert!(timecode_to_seconds("00:01:02", 30.0, false).is_err());
+
+        // 29.97 drop-frame skips ;00 and ;01 at every minute but each tenth
+        let ntsc = 30000.0 / 1001.0;
+        assert_eq!(
+            seconds_to_timecode(1800.0 / ntsc, ntsc, true),
+            "00:01:00;02"
+        );
+        assert_eq!(
+            seconds_to_timecode(17982.0 / ntsc, ntsc, true),
+            "00:10:00;00"
+        );
+        assert!(timecode_to_seconds("00:01:00;00", ntsc, true).is_err());
+        for frame in [0u64, 1799, 1800, 17981, 17982, 107892, 215783] {
+            let seconds = frame as f64 / ntsc;
+            for drop_frame in [false, true] {
+                let tc = seconds_to_timecode(seconds, ntsc, drop_frame);
+                let back = timecode_to_seconds(&tc, ntsc, drop_frame).unwrap();
+                assert!((back - seconds).abs() < 1e-9, "{} -> {}", frame, tc);
+            }
+        }
+    }
+
+    #[test]
+    fn test_move_snaps_to_nearby_clip_edge() {
+        let mut state = ripple_state();
+        assert_eq!(state.snap_to_clip_edge(6.95, 0.1, None), 7.0);
+        assert_eq!(state.snap_to_clip_edge(6.8, 0.1, None), 6.8);
+
+        // "d" lands on the end of "c" (12.0), not on its own old edges
+        apply_plan_to_state(&mut state, &generate_plan_for_move("d", 12.06)).unwrap();
+        assert_eq!(state.clips[3].start, 12.0);
+
+        let editing = EditingPreferences {
+            snap_threshold_s: 0.0,
+            ..Default::default()
+        };
+        apply_plan_to_state_with(&mut state, &generate_plan_for_move("d", 12.06), &editing)
+            .unwrap();
+        assert!((state.clips[3].start - 12.06).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_audio_offset_stays_within_clip() {
+        let mut state = ripple_state();
+        let offset_plan = |delta: f64| {
+            parse_edit_plan(&format!(
+                r#"{{"actions": [{{"type": "AUDIO_OFFSET", "target_clip_id": "b", "parameters": {{"audio_offset_delta": {}}}}}]}}"#,
+                delta
+            ))
+            .unwrap()
+        };
+
+        apply_plan_to_state(&mut state, &offset_plan(-1.0)).unwrap();
+        apply_plan_to_state(&mut state, &offset_plan(-0.5)).unwrap();
+        assert_eq!(state.clips[1].audio_offset_s, -1.5);
+        assert_eq!(state.clips[1].start, 4.0);
+
+        // "b" is 3s long, so a 3s offset leaves none of its audio under it
+        assert!(apply_plan_to_state(&mut state.clone(), &offset_plan(-1.5)).is_err());
+        assert!(apply_plan_to_state(&mut state.clone(), &offset_plan(4.5)).is_err());
+    }
+
+    #[test]
+    fn test_generated_manual_plans_apply_and_round_trip() {
+        let mut state = TimelineState {
+            clips: vec![clip("a", "v1", 0.0, 10.0)],
+            duration: 10.0,
+            ..Default::default()
+        };
+
+        let trim = generate_plan_for_trim("a", Some(1.0), Some(-2.0));
+        assert_eq!(trim.actions[0].action_type, ActionType::Trim);
+        apply_plan_to_state(&mut state, &trim).unwrap();
+        assert!((state.clips[0].effective_duration() - 7.0).abs() < 1e-9);
+
+        let moved = generate_plan_for_move("a", 2.0);
+        apply_plan_to_state(&mut state, &moved).unwrap();
+        assert_eq!(state.clips[0].start, 2.0);
+
+        let split = generate_plan_for_split("a", 5.0);
+        apply_plan_to_state(&mut state, &split).unwrap();
+        assert_eq!(state.clips.len(), 2);
+
+        // Scene splits arrive in any order; each must still hit the original clip
+        let mut scenes = state.clone();
+        let first_half = scenes.clips[0].id.clone();
+        let splits = generate_plan_for_scene_splits(&first_half, &[2.5, 4.0, 3.0]);
+        apply_plan_to_state(&mut scenes, &splits).unwrap();
+        let starts: Vec<f64> = scenes.clips.iter().map(|c| c.start).collect();
+        assert_eq!(starts, vec![2.0, 2.5, 3.0, 4.0, 5.0]);
+
+        // Silence at the head, middle and tail of a 2..5 clip
+        let mut silent = state.clone();
+        let ranges = [(2.0, 2.5), (3.0, 3.5), (4.5, 5.0)];
+        let removal = generate_plan_for_silence_removal(&first_half, 2.0, 5.0, &ranges);
+        apply_plan_to_state(&mut silent, &removal).unwrap();
+        let kept: Vec<(f64, f64)> = silent.clips[..2]
+            .iter()
+            .map(|c| (c.start, c.end()))
+            .collect();
+        assert_eq!(kept, vec![(2.5, 3.0), (3.5, 4.5)]);
+        assert_eq!(silent.clips.len(), 3);
+
+        // The artifact stores the plan as JSON; it must parse back unchanged
+        let json = serde_json::to_string(&trim).unwrap();
+        let parsed = parse_edit_plan(&json).unwrap();
+        assert_eq!(parsed.actions[0].target_clip_id, "a");
+        let params = parsed.actions[0].parameters.as_ref().unwrap();
+        assert_eq!(params.trim_end_delta, Some(-2.0));
+    }
+
+    #[test]
+    fn test_editing_preferences_drive_trim_and_clip_limit() {
+        let mut state = TimelineState {
+            clips: vec![clip("a", "v1", 0.0, 10.0)],
+            duration: 10.0,
+            ..Default::default()
+        };
+        let editing = EditingPreferences {
+            min_clip_duration_secs: 2.0,
+            min_trim_delta_secs: 0.5,
+            max_timeline_clips: Some(1),
+            ..Default::default()
+        };
+
+        // The start delta is below the threshold; the end trim is clamped to 2s
+        let plan = generate_plan_for_trim("a", Some(0.2), Some(-9.5));
+        apply_plan_to_state_with(&mut state, &plan, &editing).unwrap();
+        assert_eq!(state.clips[0].source_in(), 0.0);
+        assert!((state.clips[0].effective_duration() - 2.0).abs() < 1e-9);
+
+        // Splitting would exceed the one-clip limit
+        let split = generate_plan_for_split("a", 1.0);
+        assert!(apply_plan_to_state_with(&mut state.clone(), &split, &editing).is_err());
+        assert!(apply_plan_to_state(&mut state, &split).is_ok());
+        assert!(validate_state_invariants(&state).is_ok());
+        assert!(validate_state_invariants_with(&state, &editing).is_err());
+    }
+
+    #[test]
+    fn test_locked_tracks_and_track_invariant() {
+        let mut locked = Track::new("v1", "Video 1", TrackType::Video, 0);
+        locked.locked = true;
+        let mut state = TimelineState {
+            clips: vec![clip("a", "v1", 0.0, 10.0)],
+            duration: 10.0,
+            tracks: vec![locked],
+            ..Default::default()
+        };
+        assert!(validate_state_invariants(&state).is_ok());
+
+        let plan = generate_plan_for_move("a", 2.0);
+        let err = validate_plan_with_state(&plan, &state).unwrap_err();
+        assert!(err.contains("track 'v1' is locked"), "{}", err);
+        let err = apply_plan_to_state(&mut state.clone(), &plan).unwrap_err();
+        assert!(err.contains("locked track v1"), "{}", err);
+
+        state.tracks[0].locked = false;
+        apply_plan_to_state(&mut state, &plan).unwrap();
+        assert_eq!(state.clips[0].start, 2.0);
+
+        // A clip on a track that isn't listed breaks the invariant
+        state.clips[0].track_id = "v2".to_string();
+        assert!(validate_state_invariants(&state).is_err());
+        state.ensure_track("v2");
+        assert!(validate_state_invariants(&state).is_ok());
+
+        // Audio-only clips can't sit on a video track
+        state.clips[0].has_audio = true;
+        assert!(validate_state_invariants(&state).is_err());
+        state.clips[0].video_codec = Some("h264".to_string());
+        assert!(validate_state_invariants(&state).is_ok());
+    }
+
+    #[test]
+    fn test_undo_redo_history() {
+        let engine = TimelineEngine::new();
+        engine.set_history_depth(2);
+        let edit = |duration: f64| {
+            let mut state = engine.state.lock().unwrap();
+            let before = state.clone();
+            state.duration = duration;
+            state.version += 1;
+            engine.push_undo_snapshot(&mut state, before);
+        };
+        assert!(engine.undo().is_err());
+
+        edit(1.0);
+        edit(2.0);
+        edit(3.0); // drops the snapshot at 0.0
+        assert_eq!(engine.undo().unwrap().duration, 2.0);
+        let state = engine.undo().unwrap();
+        assert_eq!(state.duration, 1.0);
+        assert!(!state.can_undo && state.can_redo);
+        assert!(engine.undo().is_err());
+
+        let state = engine.redo().unwrap();
+        assert_eq!(state.duration, 2.0);
+        assert!(state.can_undo && state.can_redo);
+        assert!(state.version > 3);
+
+        // A fresh edit discards what could have been redone
+        edit(5.0);
+        assert!(engine.redo().is_err());
+        assert_eq!(engine.undo().unwrap().duration, 2.0);
+    }
+
+    #[test]
+    fn test_restore_checkpoint_drops_edits_and_their_undo_steps() {
+        let engine = TimelineEngine::new();
+        let edit = |duration: f64| {
+            let mut state = engine.state.lock().unwrap();
+            let before = state.clone();
+            state.duration = duration;
+            state.version += 1;
+            engine.push_undo_snapshot(&mut state, before);
+        };
+        edit(1.0);
+        let checkpoint = engine.checkpoint();
+        edit(2.0);
+        edit(3.0);
+
+        let state = engine.restore_checkpoint(checkpoint);
+        assert_eq!(state.duration, 1.0);
+        assert!(state.can_undo && !state.can_redo);
+        assert_eq!(state.version, 4);
+        assert_eq!(engine.undo().unwrap().duration, 0.0);
+        assert!(engine.undo().is_err());
+    }
 }
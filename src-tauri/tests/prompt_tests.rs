@@ -1,10 +1,38 @@
 #[cfg(test)]
 mod tests {
-    use ghost_lib::llm::is_valid_uuid;
-    use ghost_lib::prompt::{build_prompt, simplify_timeline_for_prompt};
+    use ghost_lib::llm::{
+        chat_messages, is_valid_uuid, parse_chat_completion, ConversationHistory, RetryPolicy,
+    };
+    use ghost_lib::preferences::{AiPreferences, ContextPriority, PromptExample, PromptTemplate};
+    use ghost_lib::project::ProjectMetadata;
+    use ghost_lib::prompt::{
+        build_context_block, build_explanation_prompt, build_prompt, select_clips_for_prompt,
+        simplify_timeline_for_prompt,
+    };
     use ghost_lib::timeline::{Clip, TimelineEngine};
     use uuid::Uuid;
 
+    #[test]
+    fn test_conversation_history_precedes_prompt() {
+        let history = ConversationHistory::default();
+        history.push("user", "cut the intro");
+        history.push("assistant", "{\"actions\": []}");
+        history.push("user", "now trim 1 more second");
+        history.push("assistant", "{\"actions\": []}");
+        let recent = history.recent(2);
+        assert_eq!(recent[0].content, "now trim 1 more second");
+        assert_eq!(recent[1].role, "assistant");
+        assert_eq!(history.recent(10).len(), 4);
+
+        let messages = chat_messages(&recent, "PROMPT");
+        assert_eq!(messages.as_array().unwrap().len(), 3);
+        assert_eq!(messages[2]["content"], "PROMPT");
+        assert_eq!(messages[2]["role"], "user");
+
+        history.clear();
+        assert!(history.messages().is_empty());
+    }
+
     #[test]
     fn test_simplify_timeline_structure() {
         let engine = TimelineEngine::new();
@@ -20,6 +48,7 @@ mod tests {
                 start: 0.0,
                 duration: 5.5,
                 source_file: "/path/1.mp4".to_string(),
+                ..Default::default()
             });
             state.clips.push(Clip {
                 id: id2.clone(),
@@ -27,6 +56,10 @@ mod tests {
                 start: 5.5,
                 duration: 3.2,
                 source_file: "/path/2.mp4".to_string(),
+                label: Some("B-roll".to_string()),
+                color_hex: Some("#00FF00".to_string()),
+                tags: vec!["broll".to_string()],
+                ..Default::default()
             });
             state.clips.push(Clip {
                 id: id3.clone(),
@@ -34,6 +67,7 @@ mod tests {
                 start: 0.0,
                 duration: 10.0,
                 source_file: "/path/3.mp3".to_string(),
+                ..Default::default()
             });
         }
 
@@ -50,11 +84,107 @@ mod tests {
 
         assert_eq!(simplified[1].id, id2);
         assert_eq!(simplified[1].timeline_start, 5.5);
+        assert_eq!(simplified[1].color_hex.as_deref(), Some("#00FF00"));
+
+        // Unset labels are left out of the JSON the model sees
+        let json = serde_json::to_value(&simplified).unwrap();
+        assert_eq!(json[1]["label"], "B-roll");
+        assert!(json[0].get("label").is_none());
+        assert_eq!(json[1]["tags"], serde_json::json!(["broll"]));
+        assert!(json[0].get("tags").is_none());
 
         assert_eq!(simplified[2].id, id3);
         assert_eq!(simplified[2].duration, 10.0);
     }
 
+    #[test]
+    fn test_select_clips_for_prompt_near_playhead_and_budget() {
+        let mut state = ghost_lib::timeline::TimelineState::default();
+        for i in 0..80 {
+            state.clips.push(Clip {
+                id: format!("clip-{:02}", i),
+                track_id: "v1".to_string(),
+                start: i as f64 * 5.0,
+                duration: 5.0,
+                ..Default::default()
+            });
+        }
+        state.playhead_time = 302.0;
+
+        let ids = |clips: &[ghost_lib::prompt::SimplifiedClip]| {
+            clips.iter().map(|c| c.id.clone()).collect::<Vec<_>>()
+        };
+        let mut ai = AiPreferences {
+            context_priority: ContextPriority::Chronological,
+            context_token_budget: 10_000,
+            ..Default::default()
+        };
+        let chronological = select_clips_for_prompt(&state, &ai);
+        assert_eq!(chronological.len(), 50);
+        assert_eq!(chronological[49].id, "clip-49");
+
+        // 272..332 is within 30s of the playhead: clips 54..=66, then 0.. fills the rest
+        ai.context_priority = ContextPriority::NearPlayhead;
+        let near = ids(&select_clips_for_prompt(&state, &ai));
+        assert_eq!(near.len(), 50);
+        assert_eq!(near[36], "clip-36");
+        assert_eq!(near[37], "clip-54");
+        assert_eq!(near[49], "clip-66");
+
+        // A tight budget keeps the clips closest to the playhead
+        ai.context_token_budget = 60;
+        let tight = ids(&select_clips_for_prompt(&state, &ai));
+        assert!(!tight.is_empty() && tight.len() < 13);
+        assert!(tight.contains(&"clip-60".to_string()));
+        assert!(!tight.contains(&"clip-00".to_string()));
+
+        // The clip limit is exact here, and tags and labels can be left out
+        state.clips[60].label = Some("Toast".to_string());
+        state.clips[60].tags = vec!["speech".to_string()];
+        ai.context_token_budget = 10_000;
+        ai.context_max_clips = 5;
+        let few = select_clips_for_prompt(&state, &ai);
+        assert_eq!(few.len(), 5);
+        assert_eq!(few[2].label.as_deref(), Some("Toast"));
+        ai.context_include_tags = false;
+        ai.context_include_labels = false;
+        let bare = select_clips_for_prompt(&state, &ai);
+        assert!(bare[2].label.is_none() && bare[2].tags.is_empty());
+        assert_eq!(simplify_timeline_for_prompt(&state, 2).len(), 2);
+    }
+
+    #[test]
+    fn test_context_block_follows_llm_preferences() {
+        let engine = TimelineEngine::new();
+        {
+            let mut state = engine.state.lock().unwrap();
+            for i in 0..20 {
+                state.clips.push(Clip {
+                    id: format!("clip-{:02}", i),
+                    track_id: "v1".to_string(),
+                    start: i as f64 * 5.0,
+                    duration: 5.0,
+                    label: Some(format!("Shot {}", i)),
+                    ..Default::default()
+                });
+            }
+        }
+        let project = ProjectMetadata::default();
+
+        // The preference is clamped to at least MIN_CONTEXT_CLIPS
+        let ai = AiPreferences {
+            context_max_clips: 1,
+            context_include_labels: false,
+            ..Default::default()
+        };
+        let block = build_context_block(&engine, &project, &ai);
+        assert!(block.contains("NOTE: 15 clips omitted."));
+        assert!(!block.contains("Shot 0"));
+
+        let prompt = build_explanation_prompt(&engine, &project, &AiPreferences::default(), None);
+        assert!(prompt.contains("Shot 0"));
+    }
+
     #[test]
     fn test_build_prompt_contains_json_context() {
         let engine = TimelineEngine::new();
@@ -67,11 +197,18 @@ mod tests {
                 start: 10.0,
                 duration: 4.0,
                 source_file: "foo.mp4".to_string(),
+                ..Default::default()
             });
         }
 
         let prefs = ghost_lib::preferences::PreferenceManager::new_in_memory();
-        let prompt = build_prompt(&engine, &prefs, "Trim the clip");
+        let prompt = build_prompt(
+            &engine,
+            &prefs,
+            &ProjectMetadata::default(),
+            "Trim the clip",
+            None,
+        );
 
         // Check for JSON structure
         assert!(prompt.contains("\"timeline_context\""));
@@ -94,11 +231,137 @@ mod tests {
         assert!(!is_valid_uuid("12345"));
     }
 
+    #[test]
+    fn test_parse_chat_completion() {
+        let body = r#"{
+            "choices": [{"index": 0, "message": {"role": "assistant", "content": "{\"actions\": []}"}}],
+            "usage": {"prompt_tokens": 812, "completion_tokens": 9}
+        }"#;
+        let (text, input, output) = parse_chat_completion(body).unwrap();
+        assert_eq!(text, r#"{"actions": []}"#);
+        assert_eq!((input, output), (Some(812), Some(9)));
+
+        assert!(parse_chat_completion(r#"{"choices": []}"#).is_err());
+        assert!(parse_chat_completion("not json").is_err());
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_up_to_cap() {
+        let policy = RetryPolicy {
+            max_retries: 6,
+            base_delay_ms: 500,
+        };
+        let delays: Vec<u64> = (0..6).map(|attempt| policy.delay_ms(attempt)).collect();
+        assert_eq!(delays, vec![500, 1000, 2000, 4000, 8000, 8000]);
+    }
+
     #[test]
     fn test_empty_timeline_prompt() {
         let engine = TimelineEngine::new();
         let prefs = ghost_lib::preferences::PreferenceManager::new_in_memory();
-        let prompt = build_prompt(&engine, &prefs, "Hello");
+        let prompt = build_prompt(
+            &engine,
+            &prefs,
+            &ProjectMetadata::default(),
+            "Hello",
+            Some(0.87),
+        );
+        assert!(prompt.contains("- AI success rate: 87%"));
         assert!(prompt.contains("NOTE: timeline contains 0 clips."));
     }
+
+    #[test]
+    fn test_prompt_includes_project_title() {
+        let engine = TimelineEngine::new();
+        let prefs = ghost_lib::preferences::PreferenceManager::new_in_memory();
+        let project = ProjectMetadata {
+            title: Some("Wedding 2024".to_string()),
+            ..Default::default()
+        };
+        let prompt = build_prompt(&engine, &prefs, &project, "Hello", None);
+        assert!(prompt.contains("Project: Wedding 2024."));
+    }
+
+    #[test]
+    fn test_prompt_template_replaces_fixed_text() {
+        let engine = TimelineEngine::new();
+        let prefs = ghost_lib::preferences::PreferenceManager::new_in_memory();
+        let project = ProjectMetadata::default();
+        let prompt = build_prompt(&engine, &prefs, &project, "Hello", None);
+        assert!(prompt.contains("TIMELINE_CONTEXT:\nNOTE: timeline contains 0 clips."));
+        assert!(prompt.ends_with("USER:\n\"Hello\"\n"));
+
+        prefs.update(|p| {
+            p.prompt_template = PromptTemplate {
+                system_prompt: "Be brief.\n{{PREFERENCE_CONTEXT}}".to_string(),
+                context_header: String::new(),
+                user_prefix: "REQUEST:".to_string(),
+            }
+        });
+        let prompt = build_prompt(&engine, &prefs, &project, "Hello", None);
+        assert!(prompt.starts_with("Be brief.\n"));
+        assert!(!prompt.contains("{{PREFERENCE_CONTEXT}}"));
+        assert!(!prompt.contains("TIMELINE_CONTEXT:"));
+        assert!(!prompt.contains("You are \"Ghost\""));
+        assert!(prompt.ends_with("NOTE: timeline contains 0 clips.\n\nREQUEST:\n\"Hello\"\n"));
+    }
+
+    #[test]
+    fn test_custom_examples_follow_builtin_ones() {
+        let engine = TimelineEngine::new();
+        let prefs = ghost_lib::preferences::PreferenceManager::new_in_memory();
+        prefs.update(|p| {
+            p.ai.custom_examples.push(PromptExample {
+                user_input: "Cut the ums".to_string(),
+                context_description: "A single podcast clip".to_string(),
+                expected_output_json: r#"{"actions": []}"#.to_string(),
+            })
+        });
+        let prompt = build_prompt(&engine, &prefs, &ProjectMetadata::default(), "Hi", None);
+        let custom = prompt
+            .find("Input: \"Cut the ums\"\nContext: A single podcast clip\nOutput:\n{\"actions\": []}\n")
+            .expect("custom example missing");
+        assert!(custom > prompt.find("Input: \"Delete the first clip\"").unwrap());
+        assert!(custom < prompt.find("TIMELINE_CONTEXT:").unwrap());
+    }
+
+    #[test]
+    fn test_prompt_includes_loop_region() {
+        let engine = TimelineEngine::new();
+        let prefs = ghost_lib::preferences::PreferenceManager::new_in_memory();
+        {
+            let mut state = engine.state.lock().unwrap();
+            state.clips.push(Clip {
+                id: "a".to_string(),
+                track_id: "v1".to_string(),
+                duration: 8.0,
+                source_file: "/a.mp4".to_string(),
+                ..Default::default()
+            });
+            state.duration = 8.0;
+            state.loop_region = Some((2.0, 6.5));
+        }
+        let prompt = build_prompt(&engine, &prefs, &ProjectMetadata::default(), "Hello", None);
+        assert!(prompt.contains(r#""loop_region":{"end":6.5,"start":2.0}"#));
+    }
+
+    #[test]
+    fn test_explanation_prompt() {
+        let engine = TimelineEngine::new();
+        let project = ProjectMetadata::default();
+
+        let ai = AiPreferences::default();
+        let prompt = build_explanation_prompt(&engine, &project, &ai, None);
+        assert!(prompt.contains("Describe the current timeline in plain English."));
+        assert!(!prompt.contains("OUTPUT FORMAT:"));
+        assert!(!prompt.contains("QUESTION:"));
+
+        let prompt = build_explanation_prompt(
+            &engine,
+            &project,
+            &ai,
+            Some("How many clips will be in the final video?"),
+        );
+        assert!(prompt.contains("QUESTION:\n\"How many clips will be in the final video?\""));
+    }
 }
@@ -13,6 +13,23 @@ interface Clip {
   start: number;
   duration: number;
   track_id: string;
+  edit_in?: number | null;
+  edit_out?: number | null;
+  source_offset?: number;
+  proxy_path?: string | null;
+  width?: number | null;
+  height?: number | null;
+  fps?: number | null;
+  video_codec?: string | null;
+  has_audio?: boolean;
+  speed_multiplier?: number;
+}
+
+interface ChapterMarker {
+  id: string;
+  time_s: number;
+  label: string;
+  color_hex?: string | null;
 }
 
 interface TimelineState {
@@ -20,6 +37,9 @@ interface TimelineState {
   duration: number;
   playhead_time: number;
   version: number;
+  can_undo?: boolean;
+  can_redo?: boolean;
+  markers?: ChapterMarker[];
 }
 
 function App() {
@@ -41,8 +41,12 @@ export default function MissionControl() {
         setIsExporting(true);
         setStatus("Exporting timeline...");
         try {
-            const path = await invoke<string>("export_timeline");
-            setStatus(`Export complete: ${path.split('/').pop()}`);
+            const result = await invoke<{ output_path: string; verification: { success: boolean; warnings: string[] } | null }>("export_timeline");
+            const path = result.output_path;
+            const warning = result.verification && !result.verification.success
+                ? ` (verification: ${result.verification.warnings.join("; ")})`
+                : "";
+            setStatus(`Export complete: ${path.split('/').pop()}${warning}`);
             console.log("✅ [Frontend] Export saved to:", path);
         } catch (error) {
             console.error("Export Error:", error);
@@ -7,6 +7,30 @@ interface Clip {
     start: number;
     duration: number;
     track_id: string;
+    edit_in?: number | null;
+    edit_out?: number | null;
+    source_offset?: number;
+    width?: number | null;
+    height?: number | null;
+    fps?: number | null;
+    video_codec?: string | null;
+    has_audio?: boolean;
+    speed_multiplier?: number;
+}
+
+// Tooltip line for the probed stream properties, when the clip has them
+function mediaInfo(clip: Clip): string {
+    const parts: string[] = [];
+    if (clip.width && clip.height) parts.push(`${clip.width}x${clip.height}`);
+    if (clip.fps) parts.push(`${clip.fps.toFixed(2)} fps`);
+    if (clip.video_codec) parts.push(clip.video_codec);
+    if (clip.has_audio) parts.push('audio');
+    return parts.length ? `\nMedia: ${parts.join(', ')}` : '';
+}
+
+// Length the clip occupies on the timeline (honours virtual trim points)
+function clipLength(clip: Clip): number {
+    return ((clip.edit_out ?? clip.duration) - (clip.edit_in ?? clip.source_offset ?? 0)) / (clip.speed_multiplier ?? 1);
 }
 
 interface TimelineState {
@@ -109,16 +133,16 @@ const Timeline: React.FC<TimelineProps> = ({ timelineState, playheadTime, onSeek
                                 className="timeline-clip"
                                 style={{
                                     left: `${clip.start * PIXELS_PER_SECOND}px`,
-                                    width: `${clip.duration * PIXELS_PER_SECOND - 1}px`, // -1 for gap
+                                    width: `${clipLength(clip) * PIXELS_PER_SECOND - 1}px`, // -1 for gap
                                     backgroundColor: stringToColor(clip.id)
                                 }}
-                                title={`ID: ${clip.id}\nSource: ${clip.source_file}\nStart: ${clip.start.toFixed(2)}s\nDur: ${clip.duration.toFixed(2)}s`}
+                                title={`ID: ${clip.id}\nSource: ${clip.source_file}\nStart: ${clip.start.toFixed(2)}s\nDur: ${clipLength(clip).toFixed(2)}s${mediaInfo(clip)}`}
                             >
                                 <div style={{ fontWeight: 'bold', overflow: 'hidden', textOverflow: 'ellipsis' }}>
                                     {clip.source_file.split(/[/\\]/).pop()}
                                 </div>
                                 <div style={{ fontSize: '0.7rem', opacity: 0.8 }}>
-                                    {clip.duration.toFixed(1)}s
+                                    {clipLength(clip).toFixed(1)}s
                                 </div>
                             </div>
                         ))}
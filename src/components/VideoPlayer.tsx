@@ -7,6 +7,11 @@ interface Clip {
     start: number;
     duration: number;
     track_id: string;
+    edit_in?: number | null;
+    edit_out?: number | null;
+    source_offset?: number;
+    proxy_path?: string | null;
+    speed_multiplier?: number;
 }
 
 interface VideoPlayerProps {
@@ -17,15 +22,30 @@ interface VideoPlayerProps {
     onPlayingChange: (playing: boolean) => void;
 }
 
+// Where playback starts in the source (virtual trim in point)
+function sourceIn(clip: Clip): number {
+    return clip.edit_in ?? clip.source_offset ?? 0;
+}
+
+// Playback rate; trim points stay in source seconds
+function speedOf(clip: Clip): number {
+    return clip.speed_multiplier ?? 1;
+}
+
+// Length the clip occupies on the timeline
+function clipLength(clip: Clip): number {
+    return ((clip.edit_out ?? clip.duration) - sourceIn(clip)) / speedOf(clip);
+}
+
 /**
  * Get the clip that should be active at a given timeline time.
- * Returns the clip where: clip.start <= time < clip.start + clip.duration
+ * Returns the clip where: clip.start <= time < clip.start + clipLength(clip)
  */
 function getActiveClip(clips: Clip[], time: number): Clip | null {
     // Sort by start time to ensure correct order
     const sorted = [...clips].sort((a, b) => a.start - b.start);
     for (const clip of sorted) {
-        if (clip.start <= time && time < clip.start + clip.duration) {
+        if (clip.start <= time && time < clip.start + clipLength(clip)) {
             return clip;
         }
     }
@@ -55,12 +75,11 @@ function getActiveClip(clips: Clip[], time: number): Clip | null {
  *   - Smooth clip-to-clip handoff (requires pre-render)
  *   - Sub-frame accurate seeking (HTML5 video limitation)
  * 
- * KNOWN V1 LIMITATION - SOURCE OFFSET:
- *   Trimmed/split clips do NOT play from correct source offset.
- *   Clip struct lacks `source_offset` field - would require data model change.
- *   Workaround: Export renders correctly; preview shows wrong segment.
+ * SOURCE OFFSET:
+ *   Trimmed/split clips carry virtual trim points (edit_in/edit_out);
+ *   the source is seeked by edit_in so the preview shows the kept segment.
  * 
- * V2 FUTURE: Add source_offset to Clip, backend-rendered preview segments.
+ * V2 FUTURE: Backend-rendered preview segments.
  */
 export default function VideoPlayer({
     clips,
@@ -91,16 +110,16 @@ export default function VideoPlayer({
             setError(null);
 
             if (videoRef.current) {
-                const url = convertFileSrc(activeClip.source_file);
+                // Proxies play smoothly where 4K sources stutter
+                const url = convertFileSrc(activeClip.proxy_path ?? activeClip.source_file);
                 console.log("🔗 [VideoPlayer] Loading:", url);
                 videoRef.current.src = url;
+                videoRef.current.playbackRate = speedOf(activeClip);
 
-                // V1: Compute offset based on timeline position
-                // NOTE: This does NOT account for source_offset (trimmed clips)
-                // Trimmed/split clips will play from wrong source position
+                // Compute source offset from timeline position and the trim in point
                 const offsetInClip = playheadTime - activeClip.start;
-                const clampedOffset = Math.max(0, offsetInClip);
-                console.log(`📍 [VideoPlayer] Seeking to ${clampedOffset.toFixed(2)}s in source (V1: no source_offset)`);
+                const clampedOffset = sourceIn(activeClip) + Math.max(0, offsetInClip) * speedOf(activeClip);
+                console.log(`📍 [VideoPlayer] Seeking to ${clampedOffset.toFixed(2)}s in source`);
                 videoRef.current.currentTime = clampedOffset;
 
                 if (isPlaying) {
@@ -114,26 +133,27 @@ export default function VideoPlayer({
     useEffect(() => {
         if (!activeClip || !videoRef.current) return;
 
-        const offsetInClip = playheadTime - activeClip.start;
+        const sourceTime = sourceIn(activeClip) + Math.max(0, playheadTime - activeClip.start) * speedOf(activeClip);
         const currentVideoTime = videoRef.current.currentTime;
 
         // Only seek if there's a significant difference (avoid micro-corrections during playback)
-        if (Math.abs(currentVideoTime - offsetInClip) > 0.1) {
-            videoRef.current.currentTime = Math.max(0, offsetInClip);
+        if (Math.abs(currentVideoTime - sourceTime) > 0.1) {
+            videoRef.current.currentTime = sourceTime;
         }
-    }, [playheadTime, activeClip?.start]);
+        videoRef.current.playbackRate = speedOf(activeClip);
+    }, [playheadTime, activeClip?.start, activeClip?.edit_in, activeClip?.speed_multiplier]);
 
     // Update playhead during video playback
     const updatePlayhead = useCallback(() => {
         if (!videoRef.current || !activeClip || !isPlaying) return;
 
-        const videoTime = videoRef.current.currentTime;
+        const videoTime = (videoRef.current.currentTime - sourceIn(activeClip)) / speedOf(activeClip);
         const newPlayheadTime = activeClip.start + videoTime;
 
         // Check if we've reached the end of the current clip
-        if (videoTime >= activeClip.duration) {
+        if (videoTime >= clipLength(activeClip)) {
             // Find next clip
-            const nextClip = getActiveClip(clips, activeClip.start + activeClip.duration + 0.01);
+            const nextClip = getActiveClip(clips, activeClip.start + clipLength(activeClip) + 0.01);
             if (nextClip) {
                 console.log("🎬 [VideoPlayer] Auto-advancing to next clip");
                 onPlayheadChange(nextClip.start);
@@ -177,7 +197,7 @@ export default function VideoPlayer({
         if (clips.length === 0) return;
 
         // If at end of timeline, restart
-        const totalDuration = clips.reduce((max, c) => Math.max(max, c.start + c.duration), 0);
+        const totalDuration = clips.reduce((max, c) => Math.max(max, c.start + clipLength(c)), 0);
         if (!isPlaying && playheadTime >= totalDuration - 0.1) {
             onPlayheadChange(0);
         }
@@ -240,6 +260,17 @@ export default function VideoPlayer({
                 className="video-element"
                 onError={(e) => {
                     const target = e.target as HTMLVideoElement;
+                    // The proxy may have been deleted since it was built; play the source instead
+                    if (activeClip?.proxy_path && target.src === convertFileSrc(activeClip.proxy_path)) {
+                        console.warn("⚠️ [VideoPlayer] Proxy failed to load, using source:", activeClip.proxy_path);
+                        target.src = convertFileSrc(activeClip.source_file);
+                        target.playbackRate = speedOf(activeClip);
+                        target.currentTime = sourceIn(activeClip) + Math.max(0, playheadTime - activeClip.start) * speedOf(activeClip);
+                        if (isPlaying) {
+                            target.play().catch(e => console.error("Autoplay failed:", e));
+                        }
+                        return;
+                    }
                     const mediaError = target.error;
                     console.error("Video Error Event:", e);
                     let msg = "Playback failed.";
@@ -277,7 +308,7 @@ export default function VideoPlayer({
                     {isPlaying ? "⏸" : "▶"}
                 </button>
                 <span className="time-display">
-                    {playheadTime.toFixed(1)}s / {clips.reduce((max, c) => Math.max(max, c.start + c.duration), 0).toFixed(1)}s
+                    {playheadTime.toFixed(1)}s / {clips.reduce((max, c) => Math.max(max, c.start + clipLength(c)), 0).toFixed(1)}s
                 </span>
             </div>
         </div>